@@ -0,0 +1,104 @@
+//! Throughput of the block and convolutional decoders over clean and corrupted words.
+
+#[macro_use]
+extern crate criterion;
+extern crate p25;
+
+use criterion::{black_box, Benchmark, Criterion, Throughput};
+
+use p25::bits::Dibit;
+use p25::coding::{cyclic, hamming, trellis};
+
+/// Number of words decoded in each iteration.
+const WORDS: usize = 1024;
+
+/// Generate a set of (15, 11, 3) Hamming codewords, with a single-bit error in each if
+/// `errors` is set.
+fn hamming_words(errors: bool) -> Vec<u16> {
+    (0..WORDS).map(|i| {
+        let word = hamming::standard::encode(i as u16 & 0x7FF);
+        if errors { word ^ 1 << (i % 15) } else { word }
+    }).collect()
+}
+
+/// Generate a set of (16, 8, 5) cyclic codewords, with a double-bit error in each if
+/// `errors` is set.
+fn cyclic_words(errors: bool) -> Vec<u16> {
+    (0..WORDS).map(|i| {
+        let word = cyclic::encode(i as u8);
+        if errors { word ^ 1 << (i % 16) ^ 1 << ((i + 5) % 16) } else { word }
+    }).collect()
+}
+
+/// Generate a trellis coded TSBK-sized dibit stream, with every tenth dibit corrupted if
+/// `errors` is set.
+fn trellis_dibits(errors: bool) -> Vec<Dibit> {
+    let mut fsm = trellis::DibitFSM::new();
+    let mut dibits = vec![];
+
+    for i in 0..48 {
+        let (hi, lo) = fsm.feed(Dibit::new(i as u8 % 4));
+        dibits.push(hi);
+        dibits.push(lo);
+    }
+
+    let (hi, lo) = fsm.finish();
+    dibits.push(hi);
+    dibits.push(lo);
+
+    if errors {
+        for (_, d) in dibits.iter_mut().enumerate().filter(|&(i, _)| i % 10 == 0) {
+            *d = Dibit::new(d.bits() ^ 0b11);
+        }
+    }
+
+    dibits
+}
+
+fn bench_hamming(c: &mut Criterion) {
+    let clean = hamming_words(false);
+    let errors = hamming_words(true);
+
+    c.bench("hamming::standard::decode", Benchmark::new("clean", move |b| b.iter(|| {
+        for &w in clean.iter() {
+            black_box(hamming::standard::decode(w));
+        }
+    })).with_function("errors", move |b| b.iter(|| {
+        for &w in errors.iter() {
+            black_box(hamming::standard::decode(w));
+        }
+    })).throughput(Throughput::Elements(WORDS as u32)));
+}
+
+fn bench_cyclic(c: &mut Criterion) {
+    let clean = cyclic_words(false);
+    let errors = cyclic_words(true);
+
+    c.bench("cyclic::decode", Benchmark::new("clean", move |b| b.iter(|| {
+        for &w in clean.iter() {
+            black_box(cyclic::decode(w));
+        }
+    })).with_function("errors", move |b| b.iter(|| {
+        for &w in errors.iter() {
+            black_box(cyclic::decode(w));
+        }
+    })).throughput(Throughput::Elements(WORDS as u32)));
+}
+
+fn bench_trellis(c: &mut Criterion) {
+    let clean = trellis_dibits(false);
+    let errors = trellis_dibits(true);
+
+    c.bench("trellis::DibitDecoder", Benchmark::new("clean", move |b| b.iter(|| {
+        for d in trellis::DibitDecoder::new(clean.iter().cloned()) {
+            black_box(d.ok());
+        }
+    })).with_function("errors", move |b| b.iter(|| {
+        for d in trellis::DibitDecoder::new(errors.iter().cloned()) {
+            black_box(d.ok());
+        }
+    })).throughput(Throughput::Elements(1)));
+}
+
+criterion_group!(benches, bench_hamming, bench_cyclic, bench_trellis);
+criterion_main!(benches);
@@ -0,0 +1,40 @@
+//! Feed arbitrary bytes through the parsers and decoders that handle received data,
+//! which should reject malformed input rather than panic.
+
+#![no_main]
+
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate p25;
+
+use p25::bits::{Dibit, Dibits};
+use p25::coding::{cyclic, golay, hamming, trellis};
+use p25::data::{self, ConfirmedBlocks, UnconfirmedBlocks};
+
+fuzz_target!(|buf: &[u8]| {
+    data::header::parse(buf);
+
+    ConfirmedBlocks::new(buf).count();
+    UnconfirmedBlocks::new(buf).count();
+
+    for word in buf.chunks(4) {
+        let word = word.iter().fold(0u32, |w, &b| w << 8 | b as u32);
+
+        hamming::standard::decode(word as u16);
+        hamming::standard::decode_checked(word as u16);
+        hamming::shortened::decode(word as u16);
+        hamming::shortened::decode_detect(word as u16);
+        cyclic::decode(word as u16);
+        golay::standard::decode(word);
+        golay::standard::decode_cyclic(word);
+        golay::extended::decode(word);
+        golay::shortened::decode(word);
+    }
+
+    cyclic::decode_slice(buf).count();
+
+    let dibits = Dibits::new(buf.iter().cloned()).collect::<Vec<Dibit>>();
+
+    trellis::DibitDecoder::new(dibits.iter().cloned()).count();
+    trellis::TribitDecoder::new(dibits.iter().cloned()).count();
+});
@@ -39,6 +39,43 @@ impl Decoder {
     }
 }
 
+/// Maps the four deviation levels to dibits.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Mapping([u8; 4]);
+
+/// Standard P25 mapping, where +3 is `01`, +1 is `00`, -1 is `10`, and -3 is `11`.
+pub const STANDARD_MAPPING: Mapping = Mapping([0b01, 0b00, 0b10, 0b11]);
+
+impl Mapping {
+    /// Create a new `Mapping` from the dibits for each level, ordered from the highest
+    /// (+3) to the lowest (-3) deviation.
+    pub fn new(dibits: [bits::Dibit; 4]) -> Mapping {
+        let mut map = [0; 4];
+
+        for (m, d) in map.iter_mut().zip(dibits.iter()) {
+            *m = d.bits();
+        }
+
+        Mapping(map)
+    }
+
+    /// Create the mapping for a demodulator with inverted polarity, so the highest level
+    /// takes the dibit of the lowest and so on.
+    pub fn inverted(&self) -> Mapping {
+        Mapping([self.0[3], self.0[2], self.0[1], self.0[0]])
+    }
+
+    /// Get the dibit for the given level, where 0 is the highest (+3) and 3 is the lowest
+    /// (-3) deviation.
+    pub fn dibit(&self, level: usize) -> bits::Dibit {
+        bits::Dibit::new(self.0[level])
+    }
+}
+
+impl Default for Mapping {
+    fn default() -> Mapping { STANDARD_MAPPING }
+}
+
 /// Decides which symbol a sample represents with a threshold method.
 #[derive(Copy, Clone)]
 pub struct Decider {
@@ -48,30 +85,40 @@ pub struct Decider {
     mthresh: f32,
     /// Lower threshold.
     nthresh: f32,
+    /// Dibit for each level.
+    mapping: Mapping,
 }
 
 impl Decider {
     /// Create a new Decider with the given positive threshold, mid threshold, and
-    /// negative threshold.
+    /// negative threshold, using the standard symbol mapping.
     pub fn new(pthresh: f32, mthresh: f32, nthresh: f32) -> Decider {
+        Decider::with_mapping(pthresh, mthresh, nthresh, STANDARD_MAPPING)
+    }
+
+    /// Create a new Decider with the given thresholds and symbol mapping.
+    pub fn with_mapping(pthresh: f32, mthresh: f32, nthresh: f32, mapping: Mapping)
+        -> Decider
+    {
         Decider {
             pthresh: pthresh,
             mthresh: mthresh,
             nthresh: nthresh,
+            mapping: mapping,
         }
     }
 
     /// Decide which symbol the given sample looks closest to.
     pub fn decide(&self, sample: f32) -> bits::Dibit {
-        if sample > self.pthresh {
-            bits::Dibit::new(0b01)
+        self.mapping.dibit(if sample > self.pthresh {
+            0
         } else if sample > self.mthresh && sample <= self.pthresh {
-            bits::Dibit::new(0b00)
+            1
         } else if sample <= self.mthresh && sample > self.nthresh {
-            bits::Dibit::new(0b10)
+            2
         } else {
-            bits::Dibit::new(0b11)
-        }
+            3
+        })
     }
 }
 
@@ -89,6 +136,30 @@ mod test {
         assert_eq!(d.decide(-0.244).bits(), 0b11);
     }
 
+    #[test]
+    fn test_mapping() {
+        let samples = [0.044, -0.052, -0.148, -0.244];
+
+        let std = Decider::new(-0.004, -0.1, -0.196);
+        let inv = Decider::with_mapping(-0.004, -0.1, -0.196, STANDARD_MAPPING.inverted());
+
+        let custom = Decider::with_mapping(-0.004, -0.1, -0.196, Mapping::new([
+            bits::Dibit::new(0b00),
+            bits::Dibit::new(0b01),
+            bits::Dibit::new(0b10),
+            bits::Dibit::new(0b11),
+        ]));
+
+        let decode = |d: &Decider| samples.iter().map(|&s| d.decide(s).bits())
+            .collect::<Vec<u8>>();
+
+        assert_eq!(decode(&std), vec![0b01, 0b00, 0b10, 0b11]);
+        assert_eq!(decode(&inv), vec![0b11, 0b10, 0b00, 0b01]);
+        assert_eq!(decode(&custom), vec![0b00, 0b01, 0b10, 0b11]);
+        assert_eq!(STANDARD_MAPPING.inverted().inverted(), STANDARD_MAPPING);
+        assert_eq!(Mapping::default(), STANDARD_MAPPING);
+    }
+
     #[test]
     fn test_decoder() {
         let mut d = Decoder::new(Decider::new(0.0, 0.0, 0.0));
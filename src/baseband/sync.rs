@@ -5,12 +5,24 @@ use collect_slice::CollectSlice;
 use static_ewma::{MovingAverageWeight, MovingAverage};
 use static_fir::FIRFilter;
 
+use std;
+
+use bits::Dibit;
+use consts::SYNC_SYMBOLS;
+use util::hamming_distance;
+
 /// Empirically-determined power threshold for detecting correlation power with
 /// fingerprint, scaled by average power of signal under test.
 const THRESH_FACTOR: f32 = 0.1506734989540087;
 /// Number of samples in the frame sync fingerprint, from first impulse to last, at 48kHz
 /// sample rate.
 const FINGERPRINT_SAMPS: usize = 231;
+/// Maximum number of bit errors allowed when matching the frame sync sequence in
+/// hard-decision dibits.
+pub const MAX_SYNC_ERRORS: u32 = 4;
+/// Nominal number of symbols from one frame sync to the next during a voice call, the
+/// length of a voice frame group.
+pub const FRAME_GROUP_SYMBOLS: usize = 864;
 
 /// Smoothing factor for signal power EWMA.
 struct PowerSmoothing;
@@ -135,6 +147,321 @@ impl SyncDetector {
     }
 }
 
+/// Detects the frame sync sequence in a stream of hard-decision dibits.
+#[derive(Copy, Clone, Debug)]
+pub struct DibitSyncDetector {
+    /// Most recent dibits, with the latest in the LSBs.
+    history: u64,
+    /// Number of dibits seen, saturating at the length of the sequence.
+    count: usize,
+}
+
+impl DibitSyncDetector {
+    /// Create a new `DibitSyncDetector` with no dibits seen.
+    pub fn new() -> DibitSyncDetector {
+        DibitSyncDetector {
+            history: 0,
+            count: 0,
+        }
+    }
+
+    /// Feed in a dibit, returning whether it ends a frame sync sequence with at most
+    /// `MAX_SYNC_ERRORS` bit errors.
+    pub fn feed(&mut self, dibit: Dibit) -> bool {
+        self.history = (self.history << 2 | dibit.bits() as u64) &
+            ((1 << SYNC_SYMBOLS * 2) - 1);
+
+        if self.count < SYNC_SYMBOLS {
+            self.count += 1;
+        }
+
+        self.count == SYNC_SYMBOLS &&
+            hamming_distance(self.history, patterns::PHASE1) <= MAX_SYNC_ERRORS
+    }
+}
+
+/// Count the bit errors between the given hard-decision dibits, as received in place of
+/// the frame sync, and the frame sync sequence. Only the first `SYNC_SYMBOLS` dibits are
+/// considered.
+pub fn sync_errors(dibits: &[Dibit]) -> u32 {
+    patterns::PHASE1_DIBITS.iter()
+        .zip(dibits.iter())
+        .fold(0, |s, (&sync, d)| s + hamming_distance(sync as u64, d.bits() as u64))
+}
+
+/// Smoothing factor for sync bit error rate EWMA.
+struct BerSmoothing;
+
+impl MovingAverageWeight for BerSmoothing {
+    fn weight() -> f32 {
+        // Settles within a few tens of syncs, which is a fraction of a second of voice.
+        0.1
+    }
+}
+
+/// Tracks a running bit error rate estimate from the bit errors in each received frame
+/// sync, as a cheap signal quality measure available before any frame is decoded.
+pub struct SyncQuality {
+    /// Number of bits in each sync sequence.
+    bits: usize,
+    /// Moving average bit error rate.
+    avg: MovingAverage<BerSmoothing>,
+    /// Current bit error rate estimate.
+    ber: f32,
+    /// Number of syncs seen.
+    syncs: usize,
+}
+
+impl SyncQuality {
+    /// Create a new `SyncQuality` for sync sequences of the given number of bits, such as
+    /// `SYNC_SYMBOLS * 2` for Phase 1.
+    pub fn new(bits: usize) -> SyncQuality {
+        assert!(bits > 0);
+
+        SyncQuality {
+            bits: bits,
+            avg: MovingAverage::new(0.0),
+            ber: 0.0,
+            syncs: 0,
+        }
+    }
+
+    /// Update the estimate with the number of bit errors in a received sync.
+    pub fn feed(&mut self, errors: u32) {
+        let rate = errors as f32 / self.bits as f32;
+
+        // Start from the first measurement rather than biasing towards zero.
+        self.ber = if self.syncs == 0 {
+            self.avg = MovingAverage::new(rate);
+            rate
+        } else {
+            self.avg.add(rate)
+        };
+
+        self.syncs += 1;
+    }
+
+    /// Current bit error rate estimate, from 0 to 1.
+    pub fn ber(&self) -> f32 { self.ber }
+
+    /// Number of syncs the estimate is based on.
+    pub fn syncs(&self) -> usize { self.syncs }
+}
+
+/// Location and strength of a frame sync found by soft correlation.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct SoftSyncPeak {
+    /// Symbol index where the frame sync starts.
+    pub offset: usize,
+    /// Correlation value at the offset.
+    pub corr: i32,
+}
+
+/// Correlates soft symbol values with the frame sync symbols.
+///
+/// Each soft symbol is a signed value, with positive values tending towards symbol 01
+/// (+3 deviation) and negative towards symbol 11 (-3 deviation), and the magnitude
+/// giving the confidence. Since every frame sync symbol is one of these two, summing the
+/// soft values with the sign of each sync symbol lets confident symbols outweigh weak
+/// ones that would be wrong under a hard decision.
+#[derive(Copy, Clone, Debug)]
+pub struct SoftSyncCorrelator {
+    /// Minimum correlation value to declare frame sync.
+    thresh: i32,
+}
+
+impl SoftSyncCorrelator {
+    /// Create a new `SoftSyncCorrelator` that declares frame sync when the correlation is
+    /// at least the given threshold.
+    pub fn new(thresh: i32) -> SoftSyncCorrelator {
+        SoftSyncCorrelator {
+            thresh: thresh,
+        }
+    }
+
+    /// Find the offset in the given soft symbols with the highest correlation to the
+    /// frame sync. Return `Some(peak)` if the peak reaches the threshold and `None`
+    /// otherwise.
+    pub fn correlate_soft(&self, symbols: &[i8]) -> Option<SoftSyncPeak> {
+        let signs = sync_signs();
+
+        self.find_peak(symbols.len(), |offset| {
+            signs.iter().zip(&symbols[offset..])
+                .fold(0, |s, (&sign, &sym)| s + sign * sym as i32)
+        })
+    }
+
+    /// Find the frame sync peak in the same way as `correlate_soft`, but over symbol
+    /// amplitudes scaled to the standard +3/+1/-1/-3 deviation levels.
+    ///
+    /// The correlation is rounded to the nearest integer, so a clean sync correlates to
+    /// 72 and the threshold should be chosen on that scale.
+    pub fn correlate_f32(&self, symbols: &[f32]) -> Option<SoftSyncPeak> {
+        let signs = sync_signs();
+
+        self.find_peak(symbols.len(), |offset| {
+            signs.iter().zip(&symbols[offset..])
+                .fold(0.0, |s, (&sign, &sym)| s + sign as f32 * sym)
+                .round() as i32
+        })
+    }
+
+    /// Find the highest of the correlations computed by `corr` at each offset into
+    /// symbols of the given length.
+    fn find_peak<F>(&self, len: usize, corr: F) -> Option<SoftSyncPeak> where
+        F: Fn(usize) -> i32
+    {
+        if len < SYNC_SYMBOLS {
+            return None;
+        }
+
+        let peak = (0..len - SYNC_SYMBOLS + 1).map(|offset| {
+            let corr = corr(offset);
+
+            SoftSyncPeak {
+                offset: offset,
+                corr: corr,
+            }
+        }).fold(None, |max: Option<SoftSyncPeak>, cur| match max {
+            Some(m) if m.corr >= cur.corr => Some(m),
+            _ => Some(cur),
+        });
+
+        match peak {
+            Some(p) if p.corr >= self.thresh => Some(p),
+            _ => None,
+        }
+    }
+}
+
+/// Searches chunks of soft symbols for frame sync, carrying state between chunks so a
+/// sync split across two chunks is still found.
+///
+/// The last `SYNC_SYMBOLS - 1` symbols of each chunk are kept and searched again at the
+/// start of the next chunk. A sync can't fit entirely within these held symbols, so no
+/// sync is reported twice.
+///
+/// The offsets of found syncs are also tracked to estimate drift of the symbol clock
+/// against the nominal spacing between syncs.
+pub struct FrameSync {
+    /// Correlator applied to each window.
+    corr: SoftSyncCorrelator,
+    /// Trailing symbols of the previous chunk that could start a sync.
+    tail: Vec<i8>,
+    /// Number of symbols pushed so far.
+    pos: usize,
+    /// Nominal number of symbols between syncs.
+    spacing: usize,
+    /// Offset of the first and latest found sync.
+    syncs: Option<(usize, usize)>,
+    /// Number of nominal spacings between the first and latest found sync.
+    periods: usize,
+}
+
+impl FrameSync {
+    /// Create a new `FrameSync` that declares frame sync when the correlation is at
+    /// least the given threshold.
+    pub fn new(thresh: i32) -> FrameSync {
+        FrameSync {
+            corr: SoftSyncCorrelator::new(thresh),
+            tail: Vec::with_capacity(SYNC_SYMBOLS),
+            pos: 0,
+            spacing: FRAME_GROUP_SYMBOLS,
+            syncs: None,
+            periods: 0,
+        }
+    }
+
+    /// Measure drift against the given nominal number of symbols between syncs, rather
+    /// than the length of a voice frame group.
+    pub fn with_spacing(mut self, symbols: usize) -> Self {
+        assert!(symbols > 0);
+        self.spacing = symbols;
+        self
+    }
+
+    /// Search the given chunk of soft symbols, along with the held symbols of the
+    /// previous chunk, for frame sync. Return `Some(peak)`, with the offset counted from
+    /// the first symbol ever pushed, if a peak reaches the threshold and `None`
+    /// otherwise.
+    pub fn push(&mut self, symbols: &[i8]) -> Option<SoftSyncPeak> {
+        let start = self.pos - self.tail.len();
+
+        let mut window = std::mem::replace(&mut self.tail, vec![]);
+        window.extend_from_slice(symbols);
+
+        let peak = self.corr.correlate_soft(&window[..]).map(|p| SoftSyncPeak {
+            offset: start + p.offset,
+            corr: p.corr,
+        });
+
+        let keep = std::cmp::min(window.len(), SYNC_SYMBOLS - 1);
+        let split = window.len() - keep;
+
+        self.tail = window.split_off(split);
+        self.pos += symbols.len();
+
+        if let Some(p) = peak {
+            self.track(p.offset);
+        }
+
+        peak
+    }
+
+    /// Record a sync found at the given offset. Gaps spanning several nominal spacings,
+    /// as when frames are missed, count as that many spacings.
+    fn track(&mut self, offset: usize) {
+        let (first, last) = match self.syncs {
+            Some(s) => s,
+            None => {
+                self.syncs = Some((offset, offset));
+                return;
+            },
+        };
+
+        let periods = (offset - last + self.spacing / 2) / self.spacing;
+
+        if periods > 0 {
+            self.periods += periods;
+            self.syncs = Some((first, offset));
+        }
+    }
+
+    /// Estimate the drift of the symbol clock in parts per million, from the spacing of
+    /// all syncs found so far against the nominal spacing. The drift is positive when
+    /// syncs arrive further apart than nominal, as when the sample rate is higher than
+    /// configured. Return `None` if fewer than two syncs have been found.
+    pub fn drift_ppm(&self) -> Option<f32> {
+        let (first, last) = match self.syncs {
+            Some(s) if self.periods > 0 => s,
+            _ => return None,
+        };
+
+        let expected = (self.periods * self.spacing) as f32;
+
+        Some(((last - first) as f32 - expected) / expected * 1.0e6)
+    }
+
+    /// Clear any held symbols and restart the symbol count and drift estimate.
+    pub fn reset(&mut self) {
+        self.tail.clear();
+        self.pos = 0;
+        self.syncs = None;
+        self.periods = 0;
+    }
+}
+
+/// Get the sign of each frame sync symbol, +1 for symbol 01 and -1 for symbol 11.
+fn sync_signs() -> [i32; SYNC_SYMBOLS] {
+    let mut signs = [0; SYNC_SYMBOLS];
+
+    patterns::PHASE1_DIBITS.iter()
+        .map(|&d| if d == 0b01 { 1 } else { -1 })
+        .collect_slice_checked(&mut signs[..]);
+
+    signs
+}
+
 /// Fingerprint of 24-symbol frame sync waveform in "volts".
 impl_fir!(SyncFingerprint, f32, FINGERPRINT_SAMPS, [
     0.1800000071525574,
@@ -370,6 +697,38 @@ impl_fir!(SyncFingerprint, f32, FINGERPRINT_SAMPS, [
     -0.1800000071525574,
 ]);
 
+/// Frame sync sequences, each given as a word with the first dibit in the MSBs and as an
+/// array of dibit values in the order transmitted.
+pub mod patterns {
+    /// Phase 1 frame sync sequence.
+    pub const PHASE1: u64 = 0x5575F5FF77FF;
+
+    /// Phase 1 frame sync sequence, as 24 dibits.
+    pub const PHASE1_DIBITS: [u8; 24] = [
+        0b01, 0b01, 0b01, 0b01, 0b01, 0b11, 0b01, 0b01, 0b11, 0b11, 0b01, 0b01,
+        0b11, 0b11, 0b11, 0b11, 0b01, 0b11, 0b01, 0b11, 0b11, 0b11, 0b11, 0b11,
+    ];
+
+    /// Phase 1 frame sync sequence as received with inverted polarity, where each symbol
+    /// is negated.
+    pub const PHASE1_INVERTED: u64 = 0xFFDF5F55DD55;
+
+    /// Inverted Phase 1 frame sync sequence, as 24 dibits.
+    pub const PHASE1_INVERTED_DIBITS: [u8; 24] = [
+        0b11, 0b11, 0b11, 0b11, 0b11, 0b01, 0b11, 0b11, 0b01, 0b01, 0b11, 0b11,
+        0b01, 0b01, 0b01, 0b01, 0b11, 0b01, 0b11, 0b01, 0b01, 0b01, 0b01, 0b01,
+    ];
+
+    /// Phase 2 frame sync sequence carried in the S-ISCH.
+    pub const PHASE2: u64 = 0x575D57F7FF;
+
+    /// Phase 2 frame sync sequence, as 20 dibits.
+    pub const PHASE2_DIBITS: [u8; 20] = [
+        0b01, 0b01, 0b01, 0b11, 0b01, 0b01, 0b11, 0b01, 0b01, 0b01,
+        0b01, 0b11, 0b11, 0b11, 0b01, 0b11, 0b11, 0b11, 0b11, 0b11,
+    ];
+}
+
 /// Symbols that make up the frame sync fingerprint.
 pub const SYNC_GENERATOR: &'static [u8] = &[
     0b01010101,
@@ -383,8 +742,236 @@ pub const SYNC_GENERATOR: &'static [u8] = &[
 #[cfg(test)]
 mod test {
     use super::{SyncFingerprint, calc_averages, calc_thresholds, SyncDetector};
+    use super::{SoftSyncCorrelator, SYNC_GENERATOR, SyncQuality, sync_errors};
+    use super::{FrameSync, SoftSyncPeak, DibitSyncDetector};
+    use super::patterns;
+    use bits::{Dibit, Dibits};
+    use consts::SYNC_SYMBOLS;
     use static_fir::FIRFilter;
 
+    #[test]
+    fn test_soft_sync() {
+        let mut symbols = vec![10i8, -20, 5, 0, -3, 7, 12];
+
+        symbols.extend(Dibits::new(SYNC_GENERATOR.iter().cloned()).map(|d| {
+            if d.bits() == 0b01 { 40 } else { -40 }
+        }));
+
+        symbols.extend(&[-30, 25, 0]);
+
+        // Bury the sync in noise so that several symbols flip sign.
+        let noise = [
+            -45, 10, -50, 5, 0, 12, -44, -8, 3, 15, 0, -2,
+            41, 0, 7, -3, 6, 48, -11, 9, 0, 43, -6, 2,
+        ];
+
+        for (s, &n) in symbols[7..].iter_mut().zip(noise.iter()) {
+            *s = (*s as i32 + n) as i8;
+        }
+
+        // A hard decision sees 6 wrong sync symbols.
+        let errors = Dibits::new(SYNC_GENERATOR.iter().cloned())
+            .zip(&symbols[7..])
+            .filter(|&(d, &s)| (d.bits() == 0b01) != (s > 0))
+            .count();
+
+        assert_eq!(errors, 6);
+
+        let peak = SoftSyncCorrelator::new(500).correlate_soft(&symbols[..]).unwrap();
+        assert_eq!(peak.offset, 7);
+        assert_eq!(peak.corr, 650);
+
+        assert!(SoftSyncCorrelator::new(1000).correlate_soft(&symbols[..]).is_none());
+        assert!(SoftSyncCorrelator::new(0).correlate_soft(&symbols[..23]).is_none());
+
+        // The same symbols as floats find the same peak.
+        let floats: Vec<f32> = symbols.iter().map(|&s| s as f32).collect();
+        let peak = SoftSyncCorrelator::new(500).correlate_f32(&floats[..]).unwrap();
+        assert_eq!(peak.offset, 7);
+        assert_eq!(peak.corr, 650);
+
+        // Clean sync at the standard levels.
+        let levels: Vec<f32> = Dibits::new(SYNC_GENERATOR.iter().cloned())
+            .map(|d| if d.bits() == 0b01 { 3.0 } else { -3.0 })
+            .collect();
+        let peak = SoftSyncCorrelator::new(60).correlate_f32(&levels[..]).unwrap();
+        assert_eq!(peak, super::SoftSyncPeak { offset: 0, corr: 72 });
+        assert!(SoftSyncCorrelator::new(0).correlate_f32(&levels[..23]).is_none());
+    }
+
+    #[test]
+    fn test_frame_sync() {
+        let mut symbols = vec![0i8; 10];
+
+        symbols.extend(Dibits::new(SYNC_GENERATOR.iter().cloned()).map(|d| {
+            if d.bits() == 0b01 { 40 } else { -40 }
+        }));
+
+        symbols.extend(&[0; 6]);
+
+        // Split the sync at its midpoint.
+        let (first, second) = symbols.split_at(10 + SYNC_SYMBOLS / 2);
+
+        // Searching each chunk alone misses the sync.
+        let c = SoftSyncCorrelator::new(900);
+        assert!(c.correlate_soft(first).is_none());
+        assert!(c.correlate_soft(second).is_none());
+
+        let mut s = FrameSync::new(900);
+        assert_eq!(s.push(first), None);
+        assert_eq!(s.push(second), Some(SoftSyncPeak { offset: 10, corr: 960 }));
+
+        // Held symbols don't report the sync again.
+        assert_eq!(s.push(&[0; 4]), None);
+
+        // Chunks shorter than the sync are held until enough arrive.
+        let mut s = FrameSync::new(900);
+        let found: Vec<SoftSyncPeak> = symbols.chunks(5)
+            .filter_map(|c| s.push(c))
+            .collect();
+        assert_eq!(found, vec![SoftSyncPeak { offset: 10, corr: 960 }]);
+
+        s.reset();
+        assert_eq!(s.push(&symbols[10..]), Some(SoftSyncPeak { offset: 0, corr: 960 }));
+    }
+
+    #[test]
+    fn test_drift() {
+        /// Build a sync followed by quiet symbols, the given number of symbols long.
+        fn frame(len: usize) -> Vec<i8> {
+            let mut symbols = Dibits::new(SYNC_GENERATOR.iter().cloned())
+                .map(|d| if d.bits() == 0b01 { 40 } else { -40 })
+                .collect::<Vec<i8>>();
+
+            symbols.resize(len, 0);
+            symbols
+        }
+
+        let mut s = FrameSync::new(900);
+        assert_eq!(s.drift_ppm(), None);
+
+        for _ in 0..10 {
+            s.push(&frame(864));
+        }
+
+        assert_eq!(s.drift_ppm(), Some(0.0));
+
+        // One extra symbol every frame group is about 1157ppm fast.
+        let mut s = FrameSync::new(900);
+
+        for _ in 0..10 {
+            s.push(&frame(865));
+        }
+
+        let drift = s.drift_ppm().unwrap();
+        assert!((drift - 1157.4).abs() < 0.1);
+
+        // Missed syncs count as whole frame groups, and short spacing drifts negative.
+        let mut s = FrameSync::new(900);
+        s.push(&frame(863));
+        s.push(&vec![0; 863]);
+        s.push(&frame(863));
+
+        let drift = s.drift_ppm().unwrap();
+        assert!((drift + 1157.4).abs() < 0.1);
+
+        s.reset();
+        assert_eq!(s.drift_ppm(), None);
+
+        let mut s = FrameSync::new(900).with_spacing(100);
+        s.push(&frame(101));
+        s.push(&frame(101));
+        assert!((s.drift_ppm().unwrap() - 10000.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_patterns() {
+        /// Pack the given dibit values into a word, first dibit in the MSBs.
+        fn word(dibits: &[u8]) -> u64 {
+            dibits.iter().fold(0, |w, &d| w << 2 | d as u64)
+        }
+
+        assert_eq!(word(&patterns::PHASE1_DIBITS), patterns::PHASE1);
+        assert_eq!(word(&patterns::PHASE1_INVERTED_DIBITS), patterns::PHASE1_INVERTED);
+        assert_eq!(word(&patterns::PHASE2_DIBITS), patterns::PHASE2);
+
+        assert_eq!(patterns::PHASE1_DIBITS.len(), SYNC_SYMBOLS);
+        assert_eq!(patterns::PHASE1 ^ patterns::PHASE1_INVERTED, 0xAAAAAAAAAAAA);
+
+        let gen: Vec<u8> = Dibits::new(SYNC_GENERATOR.iter().cloned())
+            .map(|d| d.bits())
+            .collect();
+        assert_eq!(&gen[..], &patterns::PHASE1_DIBITS[..]);
+    }
+
+    #[test]
+    fn test_sync_errors() {
+        let mut sync = Dibits::new(SYNC_GENERATOR.iter().cloned())
+            .collect::<Vec<Dibit>>();
+        assert_eq!(sync_errors(&sync[..]), 0);
+
+        sync[0] = Dibit::new(sync[0].bits() ^ 0b11);
+        sync[23] = Dibit::new(sync[23].bits() ^ 0b10);
+        assert_eq!(sync_errors(&sync[..]), 3);
+    }
+
+    #[test]
+    fn test_dibit_detector() {
+        let mut sync = Dibits::new(SYNC_GENERATOR.iter().cloned())
+            .collect::<Vec<Dibit>>();
+
+        let mut d = DibitSyncDetector::new();
+        let hits = sync.iter().map(|&s| d.feed(s)).collect::<Vec<bool>>();
+        assert_eq!(hits.iter().position(|&h| h), Some(SYNC_SYMBOLS - 1));
+
+        // Detected after leading noise, with up to 4 bit errors.
+        sync[0] = Dibit::new(sync[0].bits() ^ 0b11);
+        sync[10] = Dibit::new(sync[10].bits() ^ 0b11);
+
+        let mut d = DibitSyncDetector::new();
+        assert!((0..30).all(|i| !d.feed(Dibit::new(i as u8 & 0b11))));
+        assert!(!sync[..SYNC_SYMBOLS - 1].iter().any(|&s| d.feed(s)));
+        assert!(d.feed(sync[SYNC_SYMBOLS - 1]));
+
+        sync[20] = Dibit::new(sync[20].bits() ^ 0b01);
+
+        let mut d = DibitSyncDetector::new();
+        assert!(!sync.iter().any(|&s| d.feed(s)));
+
+        // A partial sequence isn't detected, even against a zeroed history.
+        let mut d = DibitSyncDetector::new();
+        assert!(!d.feed(Dibit::new(0b11)));
+    }
+
+    #[test]
+    fn test_sync_quality() {
+        let mut q = SyncQuality::new(SYNC_SYMBOLS * 2);
+        assert_eq!(q.syncs(), 0);
+        assert_eq!(q.ber(), 0.0);
+
+        q.feed(12);
+        assert_eq!(q.ber(), 0.25);
+
+        // Clean syncs trend towards zero.
+        for _ in 0..50 {
+            q.feed(0);
+        }
+
+        assert!(q.ber() < 0.002);
+        assert_eq!(q.syncs(), 51);
+
+        // Noisy syncs raise the estimate towards their error rate.
+        let mut prev = q.ber();
+
+        for _ in 0..50 {
+            q.feed(6);
+            assert!(q.ber() > prev);
+            prev = q.ber();
+        }
+
+        assert!((q.ber() - 0.125).abs() < 0.001);
+    }
+
     #[test]
     fn test_calc_averages() {
         let (pavg, navg) = calc_averages(&[
@@ -0,0 +1,64 @@
+//! Diagnostics for checking the parameters of a linear block code by enumerating its
+//! codewords.
+//!
+//! These are meant for validating a transcribed generator matrix, and enumerate all
+//! `2^data_bits` codewords, so they're only practical for the smaller codes. The module
+//! is only built for tests or with the `testutil` feature.
+
+/// Compute the number of codewords of each weight, where the entry at index `w` is the
+/// number of codewords with `w` bits set.
+pub fn weight_distribution<F: Fn(u64) -> u64>(encode: F, data_bits: usize) -> Vec<usize> {
+    assert!(data_bits < 32);
+
+    let mut dist = vec![0; 65];
+
+    for data in 0..1u64 << data_bits {
+        dist[encode(data).count_ones() as usize] += 1;
+    }
+
+    // Trim weights beyond the heaviest codeword.
+    let len = dist.iter().rposition(|&n| n > 0).map_or(0, |w| w + 1);
+    dist.truncate(len);
+
+    dist
+}
+
+/// Compute the minimum Hamming distance of the linear code with the given encoder, which
+/// is the minimum weight of its nonzero codewords.
+pub fn min_distance<F: Fn(u64) -> u64>(encode: F, data_bits: usize) -> usize {
+    weight_distribution(encode, data_bits).iter()
+        .skip(1)
+        .position(|&n| n > 0)
+        .map_or(0, |w| w + 1)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use coding::{bch, cyclic, golay, hamming};
+
+    #[test]
+    fn test_min_distance() {
+        assert_eq!(min_distance(|d| hamming::standard::encode(d as u16) as u64, 11), 3);
+        assert_eq!(min_distance(|d| hamming::shortened::encode(d as u8) as u64, 6), 3);
+        assert_eq!(min_distance(|d| cyclic::encode(d as u8) as u64, 8), 5);
+        assert_eq!(min_distance(|d| golay::standard::encode(d as u16) as u64, 12), 7);
+        assert_eq!(min_distance(|d| golay::extended::encode(d as u16) as u64, 12), 8);
+        assert_eq!(min_distance(|d| golay::shortened::encode(d as u8) as u64, 6), 8);
+        assert_eq!(min_distance(|d| bch::encode(d as u16), 16), 23);
+    }
+
+    #[test]
+    fn test_weight_distribution() {
+        // Known weight enumerator of the extended Golay code.
+        let dist = weight_distribution(|d| golay::extended::encode(d as u16) as u64, 12);
+
+        assert_eq!(dist.len(), 25);
+        assert_eq!(dist[0], 1);
+        assert_eq!(dist[8], 759);
+        assert_eq!(dist[12], 2576);
+        assert_eq!(dist[16], 759);
+        assert_eq!(dist[24], 1);
+        assert_eq!(dist.iter().sum::<usize>(), 4096);
+    }
+}
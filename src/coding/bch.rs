@@ -6,7 +6,7 @@
 use std;
 
 use coding::galois::{GaloisField, P25Field, P25Codeword, Polynomial, PolynomialCoefs};
-use coding::bmcf;
+use coding::{bmcf, bytes};
 
 /// Encode the given 16 data bits into a 64-bit codeword.
 pub fn encode(word: u16) -> u64 {
@@ -35,6 +35,31 @@ pub fn decode(bits: u64) -> Option<(u16, usize)> {
     })
 }
 
+/// Encode the given 16 data bits in the same way as `encode`, packing the 64-bit
+/// codeword MSB first into bytes.
+pub fn encode_bytes(data: u16) -> [u8; 8] {
+    let mut buf = [0; 8];
+    bytes::unpack(encode(data), 64, &mut buf);
+    buf
+}
+
+/// Decode the 64-bit word packed MSB first in the given bytes in the same way as
+/// `decode`.
+pub fn decode_bytes(buf: &[u8; 8]) -> Option<(u16, usize)> {
+    decode(bytes::pack(buf, 64))
+}
+
+/// Compute the error syndrome of the given 64-bit word, which is the difference between
+/// its 48 check bits and those of the codeword with the same data bits.
+///
+/// This isn't the syndrome of the (63, 16) BCH code alone: the check bits are the 47
+/// BCH parity bits followed by the P25 parity bit in bit 0, so an error in only the
+/// P25 parity bit gives a syndrome of 1. The result is zero exactly when the full
+/// 64-bit word is a valid codeword.
+pub fn syndrome(bits: u64) -> u64 {
+    (bits ^ encode((bits >> 48) as u16)) & 0xFFFFFFFFFFFF
+}
+
 /// Generator matrix from P25, transformed for more efficient codeword generation.
 const GEN: &'static [u16] = &[
     0b1110110001000111,
@@ -222,6 +247,16 @@ mod test {
         assert_eq!(encode(0b1111)&1, 0);
     }
 
+    #[test]
+    fn test_syndrome() {
+        let w = encode(0b1111111100000000);
+
+        assert_eq!(syndrome(w), 0);
+        assert_eq!(syndrome(w ^ 1), 1);
+        assert_eq!(syndrome(w ^ 1 << 20), 1 << 20);
+        assert!(syndrome(w ^ 1 << 60) != 0);
+    }
+
     #[test]
     fn test_syndromes() {
         let w = encode(0b1111111100000000)>>1;
@@ -265,4 +300,21 @@ mod test {
             assert_eq!(decode(encode(i as u16)).unwrap().0, i as u16);
         }
     }
+
+    #[test]
+    fn test_bytes() {
+        let w = encode(0xBEEF) ^ 1 << 40 ^ 1 << 3;
+        let mut buf = [0; 8];
+
+        for (i, b) in buf.iter_mut().enumerate() {
+            *b = (w >> (56 - i * 8)) as u8;
+        }
+
+        assert_eq!(decode_bytes(&buf), decode(w));
+        assert_eq!(decode_bytes(&buf), Some((0xBEEF, 2)));
+
+        buf[2] ^= 1;
+        buf[7] ^= 1 << 3;
+        assert_eq!(encode_bytes(0xBEEF), buf);
+    }
 }
@@ -0,0 +1,127 @@
+//! Common interface over the binary block codes, for code-agnostic tooling.
+//!
+//! Each code is represented by a unit struct implementing `BlockCode`, which delegates to
+//! the code's `encode` and `decode` functions. Data and codewords are carried in the LSBs
+//! of a `u64`.
+//!
+//! The delegation runs from the trait to the free functions rather than the other way
+//! around: the free functions take and return the code's own word types and are called
+//! per word by the frame decoders, so they're kept free of the `u64` conversions and
+//! width checks the trait needs.
+
+use coding::{bch, cyclic, golay, hamming};
+
+/// A binary block code.
+pub trait BlockCode {
+    /// Number of data bits in each word.
+    fn data_bits(&self) -> usize;
+
+    /// Number of bits in each codeword.
+    fn code_bits(&self) -> usize;
+
+    /// Encode the given data bits into a codeword.
+    ///
+    /// Panics if `data` has any bits set above the low `data_bits()` bits.
+    fn encode(&self, data: u64) -> u64;
+
+    /// Try to decode the given word to the nearest codeword.
+    ///
+    /// If decoding was successful, return `Some((data, err))`, where `err` is the number
+    /// of corrected bits, in the same sense as the code's `decode` function. Otherwise,
+    /// return `None` to indicate an unrecoverable error, which includes a word with any
+    /// bits set above the low `code_bits()` bits.
+    fn decode(&self, word: u64) -> Option<(u64, usize)>;
+}
+
+/// Implement `BlockCode` for the given unit struct using the given encode and decode
+/// functions and their data and word types.
+macro_rules! impl_block_code {
+    ($name:ident, $data:expr, $code:expr, $enc:path, $dec:path, $dty:ty, $wty:ty) => {
+        impl BlockCode for $name {
+            fn data_bits(&self) -> usize { $data }
+            fn code_bits(&self) -> usize { $code }
+
+            fn encode(&self, data: u64) -> u64 {
+                assert!(data >> $data == 0);
+                $enc(data as $dty) as u64
+            }
+
+            fn decode(&self, word: u64) -> Option<(u64, usize)> {
+                if word.checked_shr($code).unwrap_or(0) != 0 {
+                    return None;
+                }
+
+                $dec(word as $wty).map(|(data, err)| (data as u64, err))
+            }
+        }
+    };
+}
+
+/// The (15, 11, 3) standard Hamming code.
+pub struct StandardHamming;
+
+/// The (10, 6, 3) shortened Hamming code.
+pub struct ShortHamming;
+
+/// The (16, 8, 5) shortened cyclic code.
+pub struct Cyclic;
+
+/// The (23, 12, 7) standard Golay code.
+pub struct StandardGolay;
+
+/// The (24, 12, 8) extended Golay code.
+pub struct ExtendedGolay;
+
+/// The (18, 6, 8) shortened Golay code.
+pub struct ShortGolay;
+
+/// The (63, 16, 23) BCH code, with the extra P25 parity bit making 64-bit words.
+pub struct Bch;
+
+impl_block_code!(StandardHamming, 11, 15, hamming::standard::encode,
+                 hamming::standard::decode, u16, u16);
+impl_block_code!(ShortHamming, 6, 10, hamming::shortened::encode,
+                 hamming::shortened::decode, u8, u16);
+impl_block_code!(Cyclic, 8, 16, cyclic::encode, cyclic::decode, u8, u16);
+impl_block_code!(StandardGolay, 12, 23, golay::standard::encode,
+                 golay::standard::decode, u16, u32);
+impl_block_code!(ExtendedGolay, 12, 24, golay::extended::encode,
+                 golay::extended::decode, u16, u32);
+impl_block_code!(ShortGolay, 6, 18, golay::shortened::encode,
+                 golay::shortened::decode, u8, u32);
+impl_block_code!(Bch, 16, 64, bch::encode, bch::decode, u16, u64);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_block_codes() {
+        let codes: Vec<Box<BlockCode>> = vec![
+            Box::new(StandardHamming),
+            Box::new(ShortHamming),
+            Box::new(Cyclic),
+            Box::new(StandardGolay),
+            Box::new(ExtendedGolay),
+            Box::new(ShortGolay),
+            Box::new(Bch),
+        ];
+
+        for code in codes.iter() {
+            let mask = (1 << code.data_bits()) - 1;
+
+            for data in [0, 1, 0b101010101010101, 0xFFFF].iter().map(|&d| d & mask) {
+                let word = code.encode(data);
+                assert!(word.checked_shr(code.code_bits() as u32).unwrap_or(0) == 0);
+
+                assert_eq!(code.decode(word), Some((data, 0)));
+                assert_eq!(code.decode(word ^ 1 << (code.code_bits() - 1)),
+                           Some((data, 1)));
+
+                if code.code_bits() < 64 {
+                    assert_eq!(code.decode(word | 1 << code.code_bits()), None);
+                }
+            }
+        }
+    }
+}
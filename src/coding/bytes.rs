@@ -0,0 +1,69 @@
+//! Pack codewords to and from bytes.
+//!
+//! Codewords are packed MSB first, in the same order their bits are transmitted, so the
+//! first bit of a word is the MSB of the first byte. Words that don't fill a whole number
+//! of bytes are padded with zero bits at the end of the last byte.
+
+/// Compute the number of bytes needed to hold a word of the given size.
+pub fn bytes_for(bits: usize) -> usize { (bits + 7) / 8 }
+
+/// Pack the first `bits` bits of the given bytes, MSB first, into the low bits of a
+/// word. Any bits beyond those in the last byte are ignored.
+pub fn pack(buf: &[u8], bits: usize) -> u64 {
+    assert!(bits <= 64);
+    assert!(buf.len() >= bytes_for(bits));
+
+    let word = buf[..bytes_for(bits)].iter().fold(0, |word, &b| word << 8 | b as u64);
+
+    word >> (bytes_for(bits) * 8 - bits)
+}
+
+/// Unpack the low `bits` bits of the given word, MSB first, into the given bytes,
+/// padding the last byte with zero bits.
+pub fn unpack(word: u64, bits: usize, buf: &mut [u8]) {
+    assert!(bits <= 64);
+    assert!(bits == 64 || word >> bits == 0);
+    assert!(buf.len() >= bytes_for(bits));
+
+    let len = bytes_for(bits);
+    let word = word << (len * 8 - bits);
+
+    for (i, b) in buf[..len].iter_mut().enumerate() {
+        *b = (word >> ((len - i - 1) * 8)) as u8;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_bytes_for() {
+        assert_eq!(bytes_for(8), 1);
+        assert_eq!(bytes_for(10), 2);
+        assert_eq!(bytes_for(16), 2);
+        assert_eq!(bytes_for(23), 3);
+        assert_eq!(bytes_for(64), 8);
+    }
+
+    #[test]
+    fn test_pack() {
+        assert_eq!(pack(&[0xAB, 0xCD], 16), 0xABCD);
+        assert_eq!(pack(&[0b10110011, 0b01000000], 10), 0b1011001101);
+        assert_eq!(pack(&[0b10110011, 0b01111111], 10), 0b1011001101);
+        assert_eq!(pack(&[0x12, 0x34, 0x56, 0xFF], 24), 0x123456);
+        assert_eq!(pack(&[0xFF; 8], 64), 0xFFFFFFFFFFFFFFFF);
+    }
+
+    #[test]
+    fn test_unpack() {
+        let mut buf = [0xFF; 3];
+        unpack(0b1011001101, 10, &mut buf);
+        assert_eq!(buf, [0b10110011, 0b01000000, 0xFF]);
+
+        let mut buf = [0; 8];
+        unpack(0x0123456789ABCDEF, 64, &mut buf);
+        assert_eq!(buf, [0x01, 0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD, 0xEF]);
+        assert_eq!(pack(&buf, 64), 0x0123456789ABCDEF);
+    }
+}
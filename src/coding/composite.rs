@@ -0,0 +1,76 @@
+//! Decoding of fields protected by a block code followed by a check over the decoded
+//! data bits, such as a parity bit or short CRC carried inside the data.
+//!
+//! The block code corrects the word, then the check validates the corrected data bits,
+//! so a miscorrection the block code can't detect is still caught when the check fails.
+
+use coding::block::BlockCode;
+
+/// Outer block code chained with a check over its decoded data bits.
+pub struct Composite<C: BlockCode, F: Fn(u64) -> bool> {
+    /// Outer code applied to the received word.
+    code: C,
+    /// Check run on the decoded data bits, returning whether they're valid.
+    check: F,
+}
+
+impl<C: BlockCode, F: Fn(u64) -> bool> Composite<C, F> {
+    /// Create a new `Composite` that decodes with the given code and validates the data
+    /// bits with the given check.
+    pub fn new(code: C, check: F) -> Composite<C, F> {
+        Composite {
+            code: code,
+            check: check,
+        }
+    }
+
+    /// Try to decode the given word through both stages.
+    ///
+    /// If decoding was successful, return `Some((data, err))`, where `data` is the data
+    /// bits of the outer code, including any check bits, and `err` is the number of
+    /// corrected bits in the same sense as the outer code. Otherwise, return `None` if
+    /// either the outer code couldn't correct the word or the check failed.
+    pub fn decode(&self, word: u64) -> Option<(u64, usize)> {
+        match self.code.decode(word) {
+            Some((data, err)) if (self.check)(data) => Some((data, err)),
+            _ => None,
+        }
+    }
+
+    /// Get the outer code.
+    pub fn code(&self) -> &C { &self.code }
+}
+
+/// Check that the given data bits have even parity, for use as a `Composite` check.
+pub fn even_parity(data: u64) -> bool { data.count_ones() % 2 == 0 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use coding::block::{StandardGolay, ExtendedGolay};
+
+    #[test]
+    fn test_composite() {
+        let c = Composite::new(StandardGolay, even_parity);
+
+        // 11 field bits followed by an even parity bit.
+        let data = 0b10110100110;
+        let e = StandardGolay.encode(data << 1 | 0);
+
+        assert_eq!(c.decode(e), Some((data << 1, 0)));
+        assert_eq!(c.decode(e ^ 0b10000000000000000000001), Some((data << 1, 1)));
+        assert_eq!(c.decode(e ^ 0b10000000010000000000010), Some((data << 1, 2)));
+
+        // Odd parity fails the check even though the Golay word is clean.
+        let bad = StandardGolay.encode(data << 1 | 1);
+        assert_eq!(StandardGolay.decode(bad), Some((data << 1 | 1, 0)));
+        assert_eq!(c.decode(bad), None);
+
+        // Uncorrectable Golay word fails the first stage.
+        let c = Composite::new(ExtendedGolay, even_parity);
+        let e = ExtendedGolay.encode(data << 1);
+        assert_eq!(c.decode(e), Some((data << 1, 0)));
+        assert_eq!(c.decode(e ^ 0b111100000000000000000000), None);
+        assert_eq!(c.code().code_bits(), 24);
+    }
+}
@@ -6,6 +6,11 @@
 //! that this code is shortened from a (17, 8, 5) code came from "Standard APCO25 Physical
 //! Layer of the Radio Transmission Chain", Simon, 2014.
 
+use std;
+
+use coding::{bytes, Decoded, Verbose};
+use error::Result;
+
 /// Encode the given 8 data bits into a 16-bit codeword.
 pub fn encode(data: u8) -> u16 {
     matrix_mul_systematic!(data, GEN, u16)
@@ -14,30 +19,188 @@ pub fn encode(data: u8) -> u16 {
 /// Try to decode the given 16-bit word to the nearest codeword, correcting up to 2
 /// errors.
 ///
-/// If decoding was successful, return `Some((data, err))`, where `data` is the 12 data
+/// If decoding was successful, return `Some((data, err))`, where `data` is the 8 data
 /// bits and `err` is the number of corrected bits. Otherwise, return `None` to indicate
 /// an unrecoverable error.
 pub fn decode(word: u16) -> Option<(u8, usize)> {
+    match decode_checked(word) {
+        Ok(d) => d.ok(),
+        Err(_) => None,
+    }
+}
+
+/// Encode the given 8 data bits in the same way as `encode`, packing the 16-bit
+/// codeword MSB first into bytes.
+pub fn encode_bytes(data: u8) -> [u8; 2] {
+    let mut buf = [0; 2];
+    bytes::unpack(encode(data) as u64, 16, &mut buf);
+    buf
+}
+
+/// Decode the 16-bit word packed MSB first in the given bytes in the same way as
+/// `decode`.
+pub fn decode_bytes(buf: &[u8; 2]) -> Option<(u8, usize)> {
+    decode(bytes::pack(buf, 16) as u16)
+}
+
+/// Decode the back-to-back 16-bit codewords in the given buffer, where each byte holds a
+/// single bit, MSB first.
+///
+/// The returned iterator yields the result of `decode` for each complete codeword, or
+/// `None` for a word containing a byte other than 0 or 1. Any trailing partial word is
+/// skipped and can be retrieved with `remainder`.
+pub fn decode_slice<'a>(bits: &'a [u8]) -> SliceDecoder<'a> {
+    SliceDecoder(bits.chunks(16))
+}
+
+/// Decodes the codewords in a buffer of bits.
+pub struct SliceDecoder<'a>(std::slice::Chunks<'a, u8>);
+
+impl<'a> SliceDecoder<'a> {
+    /// Get the bits of a trailing partial word, which is empty if the buffer was a
+    /// multiple of 16 bits.
+    pub fn remainder(&self) -> &'a [u8] {
+        match self.0.clone().last() {
+            Some(c) if c.len() < 16 => c,
+            _ => &[],
+        }
+    }
+}
+
+impl<'a> Iterator for SliceDecoder<'a> {
+    type Item = Option<(u8, usize)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let chunk = match self.0.next() {
+            Some(c) if c.len() == 16 => c,
+            _ => return None,
+        };
+
+        if chunk.iter().any(|&bit| bit >> 1 != 0) {
+            return Some(None);
+        }
+
+        Some(decode(chunk.iter().fold(0, |word, &bit| word << 1 | bit as u16)))
+    }
+}
+
+/// Decode the given 16-bit word, correcting up to 2 errors and detecting words that
+/// can't be corrected.
+///
+/// This has the same signature as the Hamming `decode_checked` functions, but since every
+/// `u16` is a 16-bit word, it never fails.
+pub fn decode_checked(word: u16) -> Result<Decoded<u8>> {
+    Ok(match correct(word) {
+        (code, Some(err)) => Decoded::new((code >> 8) as u8, err),
+        (_, None) => Decoded::detected((word >> 8) as u8),
+    })
+}
+
+/// Try to decode the given 16-bit word to the nearest codeword, correcting up to 2
+/// errors.
+///
+/// If decoding was successful, return `Some((code, data, err))`, where `code` is the
+/// corrected 16-bit codeword, `data` is the 8 data bits, and `err` is the number of
+/// corrected bits. Otherwise, return `None` to indicate an unrecoverable error.
+pub fn decode_full(word: u16) -> Option<(u16, u8, usize)> {
+    match correct(word) {
+        (code, Some(err)) => Some((code, (code >> 8) as u8, err)),
+        (_, None) => None,
+    }
+}
+
+/// Decode the given 16-bit word in the same way as `decode`, also reporting the received
+/// and corrected codewords and the bit positions that were flipped.
+pub fn decode_verbose(word: u16) -> Option<Verbose<u8>> {
+    decode_full(word).map(|(code, data, _)| Verbose::new(data, word as u32, code as u32))
+}
+
+/// Decode the given 16-bit word in the same way as `decode`, but map each syndrome to an
+/// error pattern with the given function rather than the built-in table.
+///
+/// The function is given syndromes of the word as it's rotated through the full
+/// 17-bit cycle, and should return a 17-bit pattern to flip, or `None` if the syndrome
+/// is uncorrectable. Passing `pattern` gives the same results as `decode`.
+pub fn decode_with_patterns<F>(word: u16, patterns: F) -> Option<(u8, usize)> where
+    F: Fn(u8) -> Option<u32>
+{
+    match correct_17(word as u32, &patterns) {
+        (code, Some(err)) => Some(((code >> 8) as u8, err)),
+        (_, None) => None,
+    }
+}
+
+/// Encode the given 9 data bits into a 17-bit codeword of the full, unshortened code.
+///
+/// The shortened code is the subset of these codewords with the MSB data bit cleared,
+/// so `encode_17(data as u16) == encode(data) as u32`.
+pub fn encode_17(data: u16) -> u32 {
+    assert!(data >> 9 == 0);
+
+    // The low 9 bits of each parity-check row are the generator row extended with the
+    // parity contribution of the extra data bit.
+    matrix_mul_systematic!(data as u32, PAR, u32)
+}
+
+/// Try to decode the given 17-bit word of the full, unshortened code to the nearest
+/// codeword, correcting up to 2 errors.
+///
+/// If decoding was successful, return `Some((data, err))`, where `data` is the 9 data
+/// bits and `err` is the number of corrected bits. Otherwise, return `None` to indicate
+/// an unrecoverable error.
+pub fn decode_17(word: u32) -> Option<(u16, usize)> {
+    if word >> 17 != 0 {
+        return None;
+    }
+
+    match correct_17(word, &pattern) {
+        (code, Some(err)) => Some(((code >> 8) as u16, err)),
+        (_, None) => None,
+    }
+}
+
+/// Correct the given 16-bit word, returning the corrected codeword and the number of
+/// corrected bits, or `None` for an unrecoverable error.
+fn correct(word: u16) -> (u16, Option<usize>) {
+    // The word is expanded so it can be treated as the 17-bit word the shortened code is
+    // derived from.
+    let (code, fixed) = correct_17(word as u32, &pattern);
+    (code as u16, fixed)
+}
+
+/// Correct the given 17-bit word using the given syndrome-to-pattern map, returning the
+/// corrected codeword and the number of corrected bits, or `None` for an unrecoverable
+/// error.
+fn correct_17<F>(word: u32, patterns: &F) -> (u32, Option<usize>) where
+    F: Fn(u8) -> Option<u32>
+{
     // Go through a full cycle of the codeword, so the data bits end up in their original
-    // position. The word is expanded to 32 bits so it can be treated as the 17-bit word
-    // the shortened code is derived from.
-    let (fixed, word) = (0..17).fold((Some(0), word as u32), |(fixed, word), _| {
+    // position.
+    let mut word = word;
+    let mut fixed = Some(0);
+
+    for step in 0..17 {
         let syndrome = matrix_mul!(word, PAR, u8);
 
+        // Every rotation of a codeword is also a codeword, so the rest of the cycle
+        // would only rotate the word back into its original alignment.
         if syndrome == 0 {
-            return (fixed, rotate_17(word));
+            return (rotate_17_by(word, 17 - step), fixed);
         }
 
-        match pattern(syndrome) {
-            Some(pat) => (Some(pat.count_ones()), rotate_17(word ^ pat)),
-            None => (None, rotate_17(word)),
-        }
-    });
+        fixed = match patterns(syndrome) {
+            Some(pat) => {
+                word ^= pat;
+                Some(pat.count_ones() as usize)
+            },
+            None => None,
+        };
 
-    match fixed {
-        Some(err) => Some(((word >> 8) as u8, err as usize)),
-        None => None,
+        word = rotate_17(word);
     }
+
+    // After the full cycle the word is back in its original alignment.
+    (word, fixed)
 }
 
 /// Transposed generator matrix.
@@ -52,8 +215,11 @@ const GEN: [u8; 8] = [
     0b01111001,
 ];
 
-/// Transposed parity-check matrix, where the rows of the original are generated from x^i
-/// mod g(x).
+/// Generator polynomial g(x) = x^8 + x^5 + x^4 + x^3 + 1 of the (17, 9, 5) code.
+pub const GEN_POLY: u32 = 0b100111001;
+
+/// Transposed parity-check matrix, where the column of the original for bit i is
+/// x^(i+8) mod g(x), MSB first.
 const PAR: [u32; 8] = [
     0b10000000100111100,
     0b01000000010011110,
@@ -69,7 +235,7 @@ const PAR: [u32; 8] = [
 ///
 /// One of the benefits of the cyclic algorithm is we only have to store error patterns
 /// with the LSB set.
-fn pattern(syn: u8) -> Option<u32> {
+pub fn pattern(syn: u8) -> Option<u32> {
     match syn {
         0b00011001 => Some(0b00100000000000001),
         0b00011110 => Some(0b00000000001000001),
@@ -98,10 +264,17 @@ fn rotate_17(word: u32) -> u32 {
     word >> 1 | lsb << 16
 }
 
+/// Cyclically rotate the 17-bit word right by the given number of bits, up to 17.
+fn rotate_17_by(word: u32, bits: usize) -> u32 {
+    (word >> bits | word << (17 - bits)) & 0x1FFFF
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
-    use super::rotate_17;
+    use super::{rotate_17, rotate_17_by, GEN_POLY, PAR};
+    use coding::{galois, patterns};
+    use coding::Decoded;
 
     #[test]
     fn test_decode() {
@@ -138,6 +311,160 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_decode_verbose() {
+        let w = 0b10101011;
+        let e = encode(w);
+
+        let v = decode_verbose(e).unwrap();
+        assert_eq!(v.data, w);
+        assert_eq!(v.flipped_positions, vec![]);
+        assert_eq!(v.errors, 0);
+
+        let v = decode_verbose(e ^ 1 << 5).unwrap();
+        assert_eq!(v.data, w);
+        assert_eq!(v.received, (e ^ 1 << 5) as u32);
+        assert_eq!(v.corrected_word, e as u32);
+        assert_eq!(v.flipped_positions, vec![5]);
+        assert_eq!(v.errors, 1);
+
+        let v = decode_verbose(e ^ (1 << 3 | 1 << 12)).unwrap();
+        assert_eq!(v.flipped_positions, vec![3, 12]);
+        assert_eq!(v.errors, 2);
+    }
+
+    #[test]
+    fn test_unshortened() {
+        for i in 0..1u16<<8 {
+            assert_eq!(encode_17(i), encode(i as u8) as u32);
+        }
+
+        let w = 0b110010110;
+        let e = encode_17(w);
+
+        assert_eq!(e >> 16, 1);
+        assert_eq!(decode_17(e), Some((w, 0)));
+        // Error in the bit position that's always zero in the shortened code.
+        assert_eq!(decode_17(e ^ 1 << 16), Some((w, 1)));
+        assert_eq!(decode_17(e ^ (1 << 16 | 1 << 3)), Some((w, 2)));
+        assert_eq!(decode_17(e ^ 0b1011), None);
+        assert_eq!(decode_17(1 << 17), None);
+
+        // Every rotation of a codeword is a codeword.
+        for i in 0..1u16<<9 {
+            let e = encode_17(i);
+            assert_eq!(decode_17(e).unwrap().1, 0);
+            assert_eq!(decode_17(rotate_17(e)).unwrap().1, 0);
+        }
+
+        // The shortened decoder is the unshortened one restricted to 16 bits.
+        for w in 0...0xFFFFu32 {
+            assert_eq!(decode(w as u16),
+                       decode_17(w).map(|(data, err)| (data as u8, err)));
+        }
+    }
+
+    #[test]
+    fn test_decode_full() {
+        let w = 0b01100101;
+        let e = encode(w);
+
+        assert_eq!(Some((e, w, 0)), decode_full(e));
+        assert_eq!(Some((e, w, 1)), decode_full(e^0b0000000000100000));
+        assert_eq!(Some((e, w, 2)), decode_full(e^0b1000000000000001));
+        assert_eq!(Some((e, w, 2)), decode_full(e^0b0000010000001000));
+        assert_eq!(Some((e, w, 2)), decode_full(e^0b0000000011000000));
+    }
+
+    #[test]
+    fn test_decode_checked() {
+        let w = 0b10101011;
+        let e = encode(w);
+
+        assert_eq!(decode_checked(e), Ok(Decoded::new(w, 0)));
+        assert_eq!(decode_checked(e^0b0000100000000100), Ok(Decoded::new(w, 2)));
+
+        // Three errors exceed the correction radius but are still detected.
+        let d = decode_checked(e^0b0000000000001011).unwrap();
+        assert_eq!(d, Decoded::detected(w));
+        assert_eq!(d.ok(), None);
+        assert_eq!(decode(e^0b0000000000001011), None);
+    }
+
+    #[test]
+    fn test_decode_slice() {
+        let words = [0b00000001u8, 0b10101011, 0b11111111, 0b01100101];
+        let errs = [
+            0b0000000000000000u16,
+            0b0100000000000100,
+            0b0000000000001011,
+            0b0010000000000000,
+        ];
+
+        let mut bits = vec![];
+
+        for (&w, &e) in words.iter().zip(errs.iter()) {
+            let code = encode(w) ^ e;
+
+            for i in (0..16).rev() {
+                bits.push((code >> i) as u8 & 1);
+            }
+        }
+
+        let dec = decode_slice(&bits[..]).collect::<Vec<_>>();
+        assert_eq!(dec, vec![
+            Some((words[0], 0)),
+            Some((words[1], 2)),
+            None,
+            Some((words[3], 1)),
+        ]);
+        assert!(decode_slice(&bits[..]).remainder().is_empty());
+
+        // Trailing partial word is skipped.
+        bits.extend_from_slice(&[1, 0, 1]);
+
+        let mut dec = decode_slice(&bits[..]);
+        assert_eq!(dec.remainder(), &[1, 0, 1]);
+        assert_eq!(dec.by_ref().count(), 4);
+        assert!(dec.next().is_none());
+
+        // Non-bit values are rejected.
+        bits[5] = 2;
+        assert_eq!(decode_slice(&bits[..]).next(), Some(None));
+    }
+
+    #[test]
+    fn test_par() {
+        let mut par = [0; 8];
+
+        for i in 0..17 {
+            let col = galois::poly_mod(1 << (i + 8), GEN_POLY);
+
+            for (r, row) in par.iter_mut().enumerate() {
+                *row |= (col >> (7 - r) & 1) << i;
+            }
+        }
+
+        assert_eq!(par, PAR);
+
+        // The generator divides x^17 - 1, so the code is cyclic.
+        assert_eq!(galois::poly_mod(1 << 17 | 1, GEN_POLY), 0);
+    }
+
+    #[test]
+    fn test_patterns() {
+        let table = patterns::coset_leaders(&PAR, 17, 2);
+
+        assert_eq!(pattern(0), None);
+
+        // Only patterns with the LSB set are stored.
+        for s in 1..1u32<<8 {
+            assert_eq!(pattern(s as u8), table[s as usize].and_then(|p| {
+                if p & 1 == 1 { Some(p) } else { None }
+            }));
+        }
+    }
+
     #[test]
     fn test_rotate_17() {
         assert_eq!(rotate_17(0b00000000000000000), 0b00000000000000000);
@@ -167,5 +494,54 @@ mod test {
         }
 
         assert_eq!(word, 0b11100011001010101);
+
+        for bits in 0...17 {
+            let word = 0b11100011001010101;
+            assert_eq!(rotate_17_by(word, bits), (0..bits).fold(word, |w, _| rotate_17(w)));
+        }
+    }
+
+    #[test]
+    fn test_decode_with_patterns() {
+        for i in 0..1u32<<16 {
+            let w = i as u16;
+            assert_eq!(decode_with_patterns(w, pattern), decode(w));
+        }
+
+        let e = encode(0x5C);
+
+        // Without any correctable patterns, only codewords decode.
+        assert_eq!(decode_with_patterns(e, |_| None), Some((0x5C, 0)));
+        assert_eq!(decode_with_patterns(e ^ 1 << 3, |_| None), None);
+
+        // Only single-bit errors are corrected.
+        let single = |s| pattern(s).and_then(|p| if p == 1 { Some(p) } else { None });
+        assert_eq!(decode_with_patterns(e ^ 1 << 3, &single), Some((0x5C, 1)));
+        assert_eq!(decode_with_patterns(e ^ 1 << 3 ^ 1 << 9, &single), None);
+    }
+
+    #[test]
+    fn test_data_width() {
+        // The 8 data bits are the MSBs of the systematic codeword.
+        assert_eq!(encode(0xA5), 0xA51C);
+        assert_eq!(decode(0xA51C), Some((0xA5, 0)));
+        assert_eq!(decode(0xA51C ^ 1 << 15 ^ 1), Some((0xA5, 2)));
+
+        for i in 0..1u16<<8 {
+            assert_eq!(encode(i as u8) >> 8, i);
+        }
+    }
+
+    #[test]
+    fn test_bytes() {
+        assert_eq!(encode_bytes(0xA5), [0xA5, encode(0xA5) as u8]);
+
+        for data in 0..256 {
+            let w = encode(data as u8) ^ 0b0100000000010000;
+            let buf = [(w >> 8) as u8, w as u8];
+
+            assert_eq!(decode_bytes(&buf), decode(w));
+            assert_eq!(decode_bytes(&buf), Some((data as u8, 2)));
+        }
     }
 }
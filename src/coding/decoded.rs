@@ -0,0 +1,77 @@
+//! Common result type for the block decoders.
+
+/// Result of decoding a word, including how much the decoder trusts it.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Decoded<T> {
+    /// Decoded data bits. If `detected` is set, these are the uncorrected data bits as
+    /// received.
+    pub value: T,
+    /// Number of bits corrected.
+    pub corrected: usize,
+    /// Whether an error was detected that couldn't be corrected.
+    pub detected: bool,
+}
+
+impl<T> Decoded<T> {
+    /// Create a new `Decoded` for data bits that were recovered after correcting the
+    /// given number of bits.
+    pub fn new(value: T, corrected: usize) -> Decoded<T> {
+        Decoded {
+            value: value,
+            corrected: corrected,
+            detected: false,
+        }
+    }
+
+    /// Create a new `Decoded` for uncorrectable data bits.
+    pub fn detected(value: T) -> Decoded<T> {
+        Decoded {
+            value: value,
+            corrected: 0,
+            detected: true,
+        }
+    }
+
+    /// Convert into the `Some((data, err))` form used by the `decode` functions, or
+    /// `None` if the word was uncorrectable.
+    pub fn ok(self) -> Option<(T, usize)> {
+        if self.detected {
+            None
+        } else {
+            Some((self.value, self.corrected))
+        }
+    }
+}
+
+/// Result of decoding a word along with the received and corrected codewords, for
+/// inspecting exactly which bits the decoder flipped.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Verbose<T> {
+    /// Decoded data bits.
+    pub data: T,
+    /// Word as received.
+    pub received: u32,
+    /// Codeword the received word was corrected to.
+    pub corrected_word: u32,
+    /// Positions of the flipped bits, counted from the LSB of the word.
+    pub flipped_positions: Vec<u8>,
+    /// Number of bits corrected.
+    pub errors: usize,
+}
+
+impl<T> Verbose<T> {
+    /// Create a new `Verbose` for the given data bits recovered by correcting the given
+    /// received word to the given codeword.
+    pub fn new(data: T, received: u32, corrected_word: u32) -> Verbose<T> {
+        let diff = received ^ corrected_word;
+        let flipped: Vec<u8> = (0..32).filter(|&i| diff >> i & 1 == 1).collect();
+
+        Verbose {
+            data: data,
+            received: received,
+            corrected_word: corrected_word,
+            errors: flipped.len(),
+            flipped_positions: flipped,
+        }
+    }
+}
@@ -159,6 +159,26 @@ impl GaloisField for P25Field {
     }
 }
 
+/// Compute the remainder of dividing the given GF(2) polynomials, where bit i of each
+/// word is the coefficient of x<sup>i</sup>.
+///
+/// This is used to derive the parity-check matrices of cyclic codes, where the column
+/// for each bit position is x<sup>i</sup> mod g(x) for the code's generator polynomial
+/// g(x).
+pub fn poly_mod(dividend: u32, generator: u32) -> u32 {
+    assert!(generator != 0);
+
+    let deg = 31 - generator.leading_zeros();
+    let mut rem = dividend;
+
+    // Cancel the leading term until the remainder has a lower degree than the generator.
+    while rem != 0 && 31 - rem.leading_zeros() >= deg {
+        rem ^= generator << (31 - rem.leading_zeros() - deg);
+    }
+
+    rem
+}
+
 /// A GF(2<sup>r</sup>) Galois field.
 pub trait GaloisField {
     /// Number of unique codewords in the field: 2<sup>r</sup> - 1.
@@ -597,6 +617,23 @@ mod test {
         assert_eq!(TestCoefs::syndromes(), 22);
     }
 
+    #[test]
+    fn test_poly_mod() {
+        // x^3 + x + 1
+        let g = 0b1011;
+
+        assert_eq!(poly_mod(0, g), 0);
+        assert_eq!(poly_mod(0b101, g), 0b101);
+        assert_eq!(poly_mod(0b1000, g), 0b011);
+        assert_eq!(poly_mod(0b10000, g), 0b110);
+        assert_eq!(poly_mod(g, g), 0);
+        // (x + 1)g(x) + x^2
+        assert_eq!(poly_mod(0b11101 ^ 0b100, g), 0b100);
+        // g(x) divides x^7 - 1 since it's primitive.
+        assert_eq!(poly_mod(0b10000001, g), 0);
+        assert_eq!(poly_mod(1 << 31, 1), 0);
+    }
+
     #[test]
     fn test_for_power() {
         assert!(P25Codeword::for_power(0) == 0b000001);
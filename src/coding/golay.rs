@@ -3,9 +3,17 @@
 //!
 //! These algorithms are sourced from *Coding Theory and Cryptography: The Essentials*,
 //! Hankerson, Hoffman, et al, 2000.
+//!
+//! Each code also has an alternative `decode_cyclic` decoder, which uses error trapping
+//! over the cyclic structure of the (23, 12, 7) code, as described in *Error Control
+//! Coding*, Lin and Costello, 2004.
+//!
+//! The decoders reject words wider than the code as unrecoverable.
 
 /// Encoding and decoding of the (23, 12, 7) code.
 pub mod standard {
+    use coding::bytes;
+
     /// Encode the given 12 data bits into a 23-bit codeword.
     pub fn encode(data: u16) -> u32 {
         assert!(data >> 12 == 0);
@@ -19,7 +27,9 @@ pub mod standard {
     /// data bits and `err` is the number of bits corrected in the data bits. Otherwise,
     /// return `None` to indicate an unrecoverable error.
     pub fn decode(word: u32) -> Option<(u16, usize)> {
-        assert!(word >> 23 == 0);
+        if word >> 23 != 0 {
+            return None;
+        }
 
         // Create a 24-bit codeword with odd weight.
         let expanded = if word.count_ones() % 2 == 0 {
@@ -37,10 +47,38 @@ pub mod standard {
             super::decode_syndrome(data, s)
         }
     }
+
+    /// Encode the given 12 data bits in the same way as `encode`, packing the 23-bit
+    /// codeword MSB first into bytes.
+    pub fn encode_bytes(data: u16) -> [u8; 3] {
+        let mut buf = [0; 3];
+        bytes::unpack(encode(data) as u64, 23, &mut buf);
+        buf
+    }
+
+    /// Decode the 23-bit word packed MSB first in the given bytes in the same way as
+    /// `decode`.
+    pub fn decode_bytes(buf: &[u8; 3]) -> Option<(u16, usize)> {
+        decode(bytes::pack(buf, 23) as u32)
+    }
+
+    /// Decode the given 23-bit word in the same way as `decode`, but using error
+    /// trapping rather than the syndrome decoding algorithm.
+    pub fn decode_cyclic(word: u32) -> Option<(u16, usize)> {
+        if word >> 23 != 0 {
+            return None;
+        }
+
+        let e = super::trap_errors(word);
+
+        Some((((word ^ e) >> 11) as u16, (e >> 11).count_ones() as usize))
+    }
 }
 
 /// Encoding and decoding of the (24, 12, 8) code.
 pub mod extended {
+    use coding::bytes;
+
     /// Encode the given 12 data bits into a 24-bit codeword.
     pub fn encode(data: u16) -> u32 {
         assert!(data >> 12 == 0);
@@ -54,13 +92,51 @@ pub mod extended {
     /// data bits and `err` is the number of bits corrected in the data bits. Otherwise,
     /// return `None` to indicate an unrecoverable error.
     pub fn decode(word: u32) -> Option<(u16, usize)> {
-        assert!(word >> 24 == 0);
+        if word >> 24 != 0 {
+            return None;
+        }
+
         super::decode_syndrome(super::word_data(word), super::syndrome_24(word))
     }
+
+    /// Encode the given 12 data bits in the same way as `encode`, packing the 24-bit
+    /// codeword MSB first into bytes.
+    pub fn encode_bytes(data: u16) -> [u8; 3] {
+        let mut buf = [0; 3];
+        bytes::unpack(encode(data) as u64, 24, &mut buf);
+        buf
+    }
+
+    /// Decode the 24-bit word packed MSB first in the given bytes in the same way as
+    /// `decode`.
+    pub fn decode_bytes(buf: &[u8; 3]) -> Option<(u16, usize)> {
+        decode(bytes::pack(buf, 24) as u32)
+    }
+
+    /// Decode the given 24-bit word in the same way as `decode`, but using error
+    /// trapping rather than the syndrome decoding algorithm.
+    pub fn decode_cyclic(word: u32) -> Option<(u16, usize)> {
+        if word >> 24 != 0 {
+            return None;
+        }
+
+        // The first 23 bits form a codeword in the standard code, and the last bit is
+        // an overall parity bit.
+        let e = super::trap_errors(word >> 1);
+        let fixed = word ^ e << 1;
+
+        // An odd-weight word after correction means the parity bit is also in error.
+        if e.count_ones() + fixed.count_ones() % 2 > 3 {
+            return None;
+        }
+
+        Some((super::word_data(fixed), (e >> 11).count_ones() as usize))
+    }
 }
 
 /// Encoding and decoding of the (18, 6, 8) code.
 pub mod shortened {
+    use coding::bytes;
     use super::extended;
 
     /// Encode the given 6 data bits to an 18-bit codeword.
@@ -76,9 +152,40 @@ pub mod shortened {
     /// data bits and `err` is the number of bits corrected in the data bits. Otherwise,
     /// return `None` to indicate an unrecoverable error.
     pub fn decode(word: u32) -> Option<(u8, usize)> {
-        assert!(word >> 18 == 0);
+        if word >> 18 != 0 {
+            return None;
+        }
+
+        check_data(extended::decode(word))
+    }
+
+    /// Encode the given 6 data bits in the same way as `encode`, packing the 18-bit
+    /// codeword MSB first into bytes.
+    pub fn encode_bytes(data: u8) -> [u8; 3] {
+        let mut buf = [0; 3];
+        bytes::unpack(encode(data) as u64, 18, &mut buf);
+        buf
+    }
+
+    /// Decode the 18-bit word packed MSB first in the given bytes in the same way as
+    /// `decode`.
+    pub fn decode_bytes(buf: &[u8; 3]) -> Option<(u8, usize)> {
+        decode(bytes::pack(buf, 18) as u32)
+    }
+
+    /// Decode the given 18-bit word in the same way as `decode`, but using error
+    /// trapping rather than the syndrome decoding algorithm.
+    pub fn decode_cyclic(word: u32) -> Option<(u8, usize)> {
+        if word >> 18 != 0 {
+            return None;
+        }
+
+        check_data(extended::decode_cyclic(word))
+    }
 
-        match extended::decode(word) {
+    /// Verify the decoded data lies within the shortened code.
+    fn check_data(decoded: Option<(u16, usize)>) -> Option<(u8, usize)> {
+        match decoded {
             Some((data, err)) => if data >> 6 != 0 {
                 None
             } else {
@@ -154,6 +261,10 @@ const PAR: [u32; 12] = [
     0b000000000001100011101011,
 ];
 
+/// Generator polynomial of the cyclic (23, 12, 7) code, x^11 + x^10 + x^6 + x^5 + x^4 +
+/// x^2 + 1, with the MSB being the coefficient of highest degree.
+const GEN_POLY: u32 = 0b110001110101;
+
 /// Try to correct errors in the given data bits using the given first-level syndrome.
 fn decode_syndrome(data: u16, s: u16) -> Option<(u16, usize)> {
     decode_parity(s, &CORE).map(|(a, _)| {
@@ -179,6 +290,51 @@ fn decode_parity(s: u16, matrix: &[u16; 12]) -> Option<(u16, u16)> {
     None
 }
 
+/// Find the error pattern of up to 3 bits in the given 23-bit word.
+///
+/// Since the code is perfect, every word is within 3 bits of exactly one codeword, and
+/// since any 3 errors have a cyclic shift with at most 1 error in the data bits, this
+/// always finds the pattern.
+fn trap_errors(word: u32) -> u32 {
+    for r in 0..23 {
+        let s = poly_syndrome(rotate_23(word, r));
+
+        // All errors are in the parity bits.
+        if s.count_ones() <= 3 {
+            return rotate_23(s, 23 - r);
+        }
+
+        // Try each single error in the data bits.
+        for i in 11..23 {
+            let t = s ^ poly_syndrome(1 << i);
+
+            if t.count_ones() <= 2 {
+                return rotate_23(t | 1 << i, 23 - r);
+            }
+        }
+    }
+
+    unreachable!()
+}
+
+/// Calculate the remainder of the given 23-bit word divided by the generator
+/// polynomial.
+fn poly_syndrome(word: u32) -> u32 {
+    (11..23).rev().fold(word, |rem, i| {
+        if rem >> i & 1 == 1 {
+            rem ^ GEN_POLY << (i - 11)
+        } else {
+            rem
+        }
+    })
+}
+
+/// Cyclically rotate the given 23-bit word left by the given amount.
+fn rotate_23(word: u32, n: usize) -> u32 {
+    let n = n % 23;
+    (word << n | word >> (23 - n)) & 0x7FFFFF
+}
+
 /// Calculate the first-level syndrome.
 fn syndrome_24(word: u32) -> u16 {
     matrix_mul!(word, PAR, u16)
@@ -388,4 +544,76 @@ mod test {
             assert_eq!(extended::decode(extended::encode(i)).unwrap().0, i);
         }
     }
+
+    /// Generate pseudorandom numbers for tests.
+    fn xorshift(state: &mut u32) -> u32 {
+        *state ^= *state << 13;
+        *state ^= *state >> 17;
+        *state ^= *state << 5;
+        *state
+    }
+
+    /// Generate a pseudorandom error pattern of the given weight within the given
+    /// number of bits.
+    fn error_pattern(state: &mut u32, weight: u32, bits: u32) -> u32 {
+        let mut e = 0u32;
+
+        while e.count_ones() < weight {
+            e |= 1 << (xorshift(state) % bits);
+        }
+
+        e
+    }
+
+    #[test]
+    fn test_decode_cyclic() {
+        let mut state = 0xDEADBEEF;
+
+        for _ in 0..10000 {
+            let data = (xorshift(&mut state) & 0xFFF) as u16;
+            let weight = xorshift(&mut state) % 4;
+
+            let w = standard::encode(data) ^ error_pattern(&mut state, weight, 23);
+            assert_eq!(standard::decode_cyclic(w), standard::decode(w));
+            assert_eq!(standard::decode_cyclic(w).unwrap().0, data);
+
+            let w = extended::encode(data) ^ error_pattern(&mut state, weight, 24);
+            assert_eq!(extended::decode_cyclic(w), extended::decode(w));
+            assert_eq!(extended::decode_cyclic(w).unwrap().0, data);
+
+            let w = shortened::encode(data as u8 & 0x3F) ^
+                error_pattern(&mut state, weight, 18);
+            assert_eq!(shortened::decode_cyclic(w), shortened::decode(w));
+            assert_eq!(shortened::decode_cyclic(w).unwrap().0, data as u8 & 0x3F);
+        }
+
+        for i in 0..1<<12 {
+            assert_eq!(standard::decode_cyclic(standard::encode(i)), Some((i, 0)));
+            assert_eq!(extended::decode_cyclic(extended::encode(i)), Some((i, 0)));
+        }
+
+        // 4 errors are detected but not corrected by the extended code.
+        let e = extended::encode(0b101010101010);
+        assert_eq!(extended::decode_cyclic(e ^ 0b111100000000000000000000), None);
+        assert_eq!(extended::decode_cyclic(e ^ 0b100000001000000000100001), None);
+    }
+
+    #[test]
+    fn test_bytes() {
+        let w = standard::encode(0xABC) ^ 0b101;
+        let buf = [(w >> 15) as u8, (w >> 7) as u8, (w << 1) as u8];
+        assert_eq!(standard::decode_bytes(&buf), standard::decode(w));
+        assert_eq!(standard::decode_bytes(&buf), Some((0xABC, 0)));
+        assert_eq!(standard::encode_bytes(0xABC), [buf[0], buf[1], buf[2] ^ 0b1010]);
+
+        let w = extended::encode(0x123) ^ 1 << 20;
+        let buf = [(w >> 16) as u8, (w >> 8) as u8, w as u8];
+        assert_eq!(extended::decode_bytes(&buf), extended::decode(w));
+        assert_eq!(extended::decode_bytes(&buf), Some((0x123, 1)));
+
+        let w = shortened::encode(0b110011) ^ 1 << 17;
+        let buf = [(w >> 10) as u8, (w >> 2) as u8, (w << 6) as u8];
+        assert_eq!(shortened::decode_bytes(&buf), shortened::decode(w));
+        assert_eq!(shortened::decode_bytes(&buf), Some((0b110011, 1)));
+    }
 }
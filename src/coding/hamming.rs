@@ -3,10 +3,16 @@
 //!
 //! Both codes can correct up to 1 error. These algorithms are sourced from *Coding Theory
 //! and Cryptography: The Essentials*, Hankerson, Hoffman, et al, 2000.
+//!
+//! Neither code has the overall parity bit of an extended (SECDED) Hamming code, so a
+//! double error can't be told apart from a single error in general. The `decode_detect`
+//! functions report this ambiguity rather than hiding it.
 
 /// Encoding and decoding of the (15, 11, 3) code.
 pub mod standard {
-    use super::HammingDecoder;
+    use coding::{bytes, Decoded, Verbose};
+    use error::{P25Error, Result};
+    use super::{HammingDecoder, HammingResult};
 
     /// Encode the given 11 bits of data into a 15-bit codeword.
     pub fn encode(data: u16) -> u16 {
@@ -19,12 +25,103 @@ pub mod standard {
     ///
     /// If decoding was successful, return `Some((data, err))`, where `data` is the 11
     /// data bits and `err` is the number of corrected bits. Otherwise, return `None` to
-    /// indicate an unrecoverable error.
+    /// indicate an unrecoverable error or a word wider than 15 bits.
     pub fn decode(word: u16) -> Option<(u16, usize)> {
-        assert!(word >> 15 == 0);
+        if word >> 15 != 0 {
+            return None;
+        }
+
         StandardHamming::decode(word)
     }
 
+    /// Encode the given 11 data bits in the same way as `encode`, packing the 15-bit
+    /// codeword MSB first into bytes.
+    pub fn encode_bytes(data: u16) -> [u8; 2] {
+        let mut buf = [0; 2];
+        bytes::unpack(encode(data) as u64, 15, &mut buf);
+        buf
+    }
+
+    /// Decode the 15-bit word packed MSB first in the given bytes in the same way as
+    /// `decode`.
+    pub fn decode_bytes(buf: &[u8; 2]) -> Option<(u16, usize)> {
+        decode(bytes::pack(buf, 15) as u16)
+    }
+
+    /// Decode the given 15-bit word, correcting up to 1 error. Since this code is
+    /// perfect, no error is ever detected, and a word wider than 15 bits fails with
+    /// `WordTooWide`.
+    pub fn decode_checked(word: u16) -> Result<Decoded<u16>> {
+        if word >> 15 != 0 {
+            return Err(P25Error::WordTooWide);
+        }
+
+        Ok(StandardHamming::decode_checked(word))
+    }
+
+    /// Decode the given 15-bit word in the same way as `decode`, also reporting the
+    /// received and corrected codewords and the bit positions that were flipped.
+    pub fn decode_verbose(word: u16) -> Option<Verbose<u16>> {
+        if word >> 15 != 0 {
+            return None;
+        }
+
+        StandardHamming::decode_verbose(word)
+    }
+
+    /// Decode the given 15-bit word, reporting whether any correction could have been
+    /// a miscorrected double error.
+    ///
+    /// This uses the same syndrome classification as the shortened code, but since this
+    /// code is perfect, every nonzero syndrome maps to a single-bit error, so a double
+    /// error is always miscorrected into a different codeword. Any correction is
+    /// therefore reported as `Uncertain`, and `DoubleDetected` is never returned. Return
+    /// `Err(WordTooWide)` for a word wider than 15 bits.
+    pub fn decode_detect(word: u16) -> Result<HammingResult<u16>> {
+        if word >> 15 != 0 {
+            return Err(P25Error::WordTooWide);
+        }
+
+        Ok(StandardHamming::decode_detect(word))
+    }
+
+    /// Decode each 15-bit word in `words` into the corresponding entry of `out`.
+    ///
+    /// This gives the same results as calling `decode` on each word, including `None`
+    /// for words wider than 15 bits.
+    pub fn decode_batch(words: &[u16], out: &mut [Option<(u16, usize)>]) {
+        assert!(words.len() == out.len());
+        StandardHamming::decode_batch(words, out);
+
+        for (o, &w) in out.iter_mut().zip(words.iter()) {
+            if w >> 15 != 0 {
+                *o = None;
+            }
+        }
+    }
+
+    /// Decode the given 15 soft bits, MSB first, to the most likely codeword.
+    ///
+    /// Each soft bit is positive when favoring a 1 and negative when favoring a 0, as
+    /// produced by `coding::soft`. Return `(data, err)`, where `err` is the number of
+    /// hard decisions that differ from the chosen codeword, which may be more than the
+    /// single error `decode` can correct.
+    pub fn decode_soft(bits: &[f32; 15]) -> (u16, usize) {
+        super::decode_soft(&bits[..], 11, encode)
+    }
+
+    /// Retrieve the table that maps each 4-bit syndrome to the single-bit error pattern
+    /// it corrects, where the zero syndrome maps to no error.
+    ///
+    /// ```
+    /// use p25::coding::hamming::standard;
+    ///
+    /// // Syndrome 1 corrects bit position 0.
+    /// assert_eq!(standard::error_locations()[1], 1 << 0);
+    /// assert_eq!(standard::error_locations()[0], 0);
+    /// ```
+    pub fn error_locations() -> &'static [u16; 16] { &LOCATIONS }
+
     /// Generator patterns for 4 parity bits.
     const GEN: [u16; 4] = [
         0b11111110000,
@@ -34,7 +131,7 @@ pub mod standard {
     ];
 
     /// Parity-check patterns for 4 syndromes.
-    const PAR: [u16; 4] = [
+    pub(super) const PAR: [u16; 4] = [
         0b111111100001000,
         0b111100011100100,
         0b110011011010010,
@@ -42,7 +139,7 @@ pub mod standard {
     ];
 
     /// Maps 4-bit syndrome values to bit error locations.
-    const LOCATIONS: [u16; 16] = [
+    pub(super) static LOCATIONS: [u16; 16] = [
         0,
         0b0000000000000001,
         0b0000000000000010,
@@ -74,7 +171,9 @@ pub mod standard {
 
 /// Encoding and decoding of the (10, 6, 3) code.
 pub mod shortened {
-    use super::HammingDecoder;
+    use coding::{bytes, Decoded, Verbose};
+    use error::{P25Error, Result};
+    use super::{HammingDecoder, HammingResult};
 
     /// Encode the given 6 data bits into a 10-bit codeword.
     pub fn encode(data: u8) -> u16 {
@@ -87,12 +186,84 @@ pub mod shortened {
     ///
     /// If decoding was successful, return `Some((data, err))`, where `data` is the 6
     /// data bits and `err` is the number of corrected bits. Otherwise, return `None` to
-    /// indicate an unrecoverable error.
+    /// indicate an unrecoverable error or a word wider than 10 bits. Use `decode_checked`
+    /// to tell a detected error apart from a clean decode while keeping the data bits.
     pub fn decode(word: u16) -> Option<(u8, usize)> {
-        assert!(word >> 10 == 0);
+        if word >> 10 != 0 {
+            return None;
+        }
+
         ShortHamming::decode(word)
     }
 
+    /// Encode the given 6 data bits in the same way as `encode`, packing the 10-bit
+    /// codeword MSB first into bytes.
+    pub fn encode_bytes(data: u8) -> [u8; 2] {
+        let mut buf = [0; 2];
+        bytes::unpack(encode(data) as u64, 10, &mut buf);
+        buf
+    }
+
+    /// Decode the 10-bit word packed MSB first in the given bytes in the same way as
+    /// `decode`.
+    pub fn decode_bytes(buf: &[u8; 2]) -> Option<(u8, usize)> {
+        decode(bytes::pack(buf, 10) as u16)
+    }
+
+    /// Decode the given 10-bit word, correcting up to 1 error and detecting syndromes
+    /// that don't correspond to any single-bit error. A word wider than 10 bits fails
+    /// with `WordTooWide`.
+    pub fn decode_checked(word: u16) -> Result<Decoded<u8>> {
+        if word >> 10 != 0 {
+            return Err(P25Error::WordTooWide);
+        }
+
+        Ok(ShortHamming::decode_checked(word))
+    }
+
+    /// Decode the given 10-bit word in the same way as `decode`, also reporting the
+    /// received and corrected codewords and the bit positions that were flipped.
+    pub fn decode_verbose(word: u16) -> Option<Verbose<u8>> {
+        if word >> 10 != 0 {
+            return None;
+        }
+
+        ShortHamming::decode_verbose(word)
+    }
+
+    /// Decode the given 10-bit word, reporting whether any correction could have been
+    /// a miscorrected double error.
+    ///
+    /// Syndromes that don't correspond to any single-bit error are reported as
+    /// `DoubleDetected`, but other double errors look like single errors and are
+    /// reported as `Uncertain`. Return `Err(WordTooWide)` for a word wider than 10 bits.
+    pub fn decode_detect(word: u16) -> Result<HammingResult<u8>> {
+        if word >> 10 != 0 {
+            return Err(P25Error::WordTooWide);
+        }
+
+        Ok(ShortHamming::decode_detect(word))
+    }
+
+    /// Decode the given 10 soft bits, MSB first, to the most likely codeword in the same
+    /// way as `standard::decode_soft`.
+    pub fn decode_soft(bits: &[f32; 10]) -> (u8, usize) {
+        let (data, err) = super::decode_soft(&bits[..], 6, |d| encode(d as u8));
+        (data as u8, err)
+    }
+
+    /// Retrieve the table that maps each 4-bit syndrome to the single-bit error pattern
+    /// it corrects, where the zero syndrome and syndromes that don't correspond to any
+    /// single-bit error map to no error.
+    ///
+    /// ```
+    /// use p25::coding::hamming::shortened;
+    ///
+    /// // Syndrome 1 corrects bit position 0.
+    /// assert_eq!(shortened::error_locations()[1], 1 << 0);
+    /// ```
+    pub fn error_locations() -> &'static [u16; 16] { &LOCATIONS }
+
     const GEN: [u8; 4] = [
         0b111001,
         0b110101,
@@ -100,14 +271,16 @@ pub mod shortened {
         0b011110,
     ];
 
-    const PAR: [u16; 4] = [
+    pub(super) const PAR: [u16; 4] = [
         0b1110011000,
         0b1101010100,
         0b1011100010,
         0b0111100001,
     ];
 
-    const LOCATIONS: [u16; 16] = [
+    /// Maps 4-bit syndrome values to bit error locations. Shortening the code leaves
+    /// syndromes that no single-bit error produces, and these map to 0.
+    pub(super) static LOCATIONS: [u16; 16] = [
         0,
         0b0000000000000001,
         0b0000000000000010,
@@ -137,6 +310,42 @@ pub mod shortened {
     }
 }
 
+use std;
+
+use coding::{Decoded, Verbose};
+use util::hamming_distance;
+
+/// Find the codeword with the highest correlation to the given soft bits by trying every
+/// codeword of the code with the given encoder.
+fn decode_soft<F: Fn(u16) -> u16>(bits: &[f32], data_bits: usize, encode: F)
+    -> (u16, usize)
+{
+    let hard = bits.iter().fold(0u16, |w, &b| w << 1 | (b > 0.0) as u16);
+
+    let corr = |word: u16| bits.iter().enumerate().fold(0.0, |s, (i, &b)| {
+        if word >> (bits.len() - 1 - i) & 1 == 1 { s + b } else { s - b }
+    });
+
+    let (data, _) = (0..1 << data_bits).fold((0, std::f32::NEG_INFINITY), |best, d| {
+        let c = corr(encode(d));
+        if c > best.1 { (d, c) } else { best }
+    });
+
+    (data, hamming_distance(encode(data) as u64, hard as u64) as usize)
+}
+
+/// Result of decoding with error detection.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum HammingResult<T> {
+    /// The word was a valid codeword, with the given data bits.
+    Clean(T),
+    /// A single-bit error was corrected, giving the given data bits, but the word may
+    /// actually have had a double error that was corrected to the wrong codeword.
+    Uncertain(T),
+    /// The syndrome could only have been caused by multiple errors.
+    DoubleDetected,
+}
+
 /// Defines code-specific decoding functions.
 trait HammingDecoder {
     /// The type of the data bit output.
@@ -153,20 +362,68 @@ trait HammingDecoder {
 
     /// Use the current decoder to decode the given word.
     fn decode(word: u16) -> Option<(Self::Data, usize)> {
-        // Compute the 4-bit syndrome.
-        let s = matrix_mul!(word, Self::par(), u8);
+        Self::decode_checked(word).ok()
+    }
 
+    /// Use the current decoder to decode the given word, reporting detected errors.
+    fn decode_checked(word: u16) -> Decoded<Self::Data> {
+        Self::correct(word, matrix_mul!(word, Self::par(), u8))
+    }
+
+    /// Correct the given word using its previously computed 4-bit syndrome, following
+    /// the classification of `classify`.
+    fn correct(word: u16, s: u8) -> Decoded<Self::Data> {
+        match Self::classify(word, s) {
+            HammingResult::Clean(d) => Decoded::new(d, 0),
+            HammingResult::Uncertain(d) => Decoded::new(d, 1),
+            HammingResult::DoubleDetected => Decoded::detected(Self::data(word)),
+        }
+    }
+
+    /// Classify the given word using its previously computed 4-bit syndrome.
+    fn classify(word: u16, s: u8) -> HammingResult<Self::Data> {
         // A zero syndrome means it's a valid codeword (possibly different from the
         // transmitted codeword.)
         if s == 0 {
-            return Some((Self::data(word), 0));
+            return HammingResult::Clean(Self::data(word));
         }
 
         match Self::locs().get(s as usize) {
             // More than one error/unrecoverable error.
-            Some(&0) | None => None,
-            // Valid location means the error can be corrected.
-            Some(&loc) => Some((Self::data(word ^ loc), 1)),
+            Some(&0) | None => HammingResult::DoubleDetected,
+            // Valid location means the error can be corrected, though a double error
+            // could have produced the same syndrome.
+            Some(&loc) => HammingResult::Uncertain(Self::data(word ^ loc)),
+        }
+    }
+
+    /// Use the current decoder to decode the given word, reporting the corrected
+    /// codeword and flipped bits.
+    fn decode_verbose(word: u16) -> Option<Verbose<Self::Data>> {
+        let s = matrix_mul!(word, Self::par(), u8);
+
+        let loc = if s == 0 {
+            0
+        } else {
+            match Self::locs().get(s as usize) {
+                Some(&0) | None => return None,
+                Some(&loc) => loc,
+            }
+        };
+
+        Some(Verbose::new(Self::data(word ^ loc), word as u32, (word ^ loc) as u32))
+    }
+
+    /// Use the current decoder to decode the given word, reporting ambiguous
+    /// corrections.
+    fn decode_detect(word: u16) -> HammingResult<Self::Data> {
+        Self::classify(word, matrix_mul!(word, Self::par(), u8))
+    }
+
+    /// Decode each word in `words` into the corresponding entry of `out`.
+    fn decode_batch(words: &[u16], out: &mut [Option<(Self::Data, usize)>]) {
+        for (o, &w) in out.iter_mut().zip(words.iter()) {
+            *o = Self::decode(w);
         }
     }
 }
@@ -174,6 +431,51 @@ trait HammingDecoder {
 #[cfg(test)]
 mod test {
     use super::*;
+    use coding::{patterns, soft, Decoded};
+    use bits::Dibit;
+    use error::P25Error;
+
+    /// Get the soft bits for the given word at full confidence.
+    fn soft_word(word: u16, bits: usize) -> Vec<f32> {
+        (0..bits).rev().map(|i| if word >> i & 1 == 1 { 2.0 } else { -2.0 }).collect()
+    }
+
+    #[test]
+    fn test_decode_soft() {
+        let mut buf = [0.0; 15];
+
+        for &d in [0, 0b10101010101, 0x7FF, 0x123].iter() {
+            let w = standard::encode(d);
+            buf.copy_from_slice(&soft_word(w, 15)[..]);
+            assert_eq!(standard::decode_soft(&buf), (d, 0));
+
+            // Single confident error matches the hard decoder.
+            buf[3] = -buf[3];
+            assert_eq!(standard::decode_soft(&buf),
+                       standard::decode(w ^ 1 << 11).unwrap());
+
+            // Two weak errors that would miscorrect under a hard decision.
+            buf[3] = -buf[3];
+            buf[5] = -buf[5] * 0.1;
+            buf[9] = -buf[9] * 0.1;
+            assert!(standard::decode(w ^ 1 << 9 ^ 1 << 5).unwrap().0 != d);
+            assert_eq!(standard::decode_soft(&buf), (d, 2));
+        }
+
+        // Soft bits from symbol amplitudes.
+        let w = shortened::encode(0b101101);
+        let syms: Vec<f32> = (0..5).map(|i| {
+            soft::level(Dibit::new((w >> (8 - 2 * i) & 0b11) as u8))
+        }).collect();
+
+        let mut buf = [0.0; 10];
+        buf.copy_from_slice(&soft::soft_bits_all(&syms[..])[..]);
+        assert_eq!(shortened::decode_soft(&buf), (0b101101, 0));
+
+        buf[0] = -0.5;
+        buf[7] = -buf[7] * 0.2;
+        assert_eq!(shortened::decode_soft(&buf).0, 0b101101);
+    }
 
     #[test]
     fn test_standard() {
@@ -201,6 +503,39 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_standard_batch() {
+        let words = (0..1<<15).collect::<Vec<u16>>();
+        let mut out = vec![None; words.len()];
+
+        standard::decode_batch(&words[..], &mut out[..]);
+
+        for (&w, &o) in words.iter().zip(out.iter()) {
+            assert_eq!(o, standard::decode(w));
+        }
+
+        // Check a batch that isn't a multiple of the lane count.
+        let mut out = [None; 11];
+        standard::decode_batch(&words[100..111], &mut out);
+
+        for (&w, &o) in words[100..111].iter().zip(out.iter()) {
+            assert_eq!(o, standard::decode(w));
+        }
+
+        // Words wider than the code are rejected the same as the scalar decoder.
+        let words = [1 << 15, 0xFFFF, standard::encode(0x123) | 1 << 15, 0x7FFF, 0x8001];
+        let mut out = [Some((0, 0)); 5];
+        standard::decode_batch(&words, &mut out);
+
+        for (&w, &o) in words.iter().zip(out.iter()) {
+            assert_eq!(o, standard::decode(w));
+        }
+
+        assert_eq!(out[0], None);
+        assert_eq!(out[2], None);
+        assert!(out[3].is_some());
+    }
+
     #[test]
     fn test_shortened() {
         let w = 0b110011;
@@ -221,4 +556,203 @@ mod test {
             assert_eq!(shortened::decode(shortened::encode(i)).unwrap().0, i);
         }
     }
+
+    #[test]
+    fn test_locations() {
+        fn generate(par: &[u16], bits: usize) -> Vec<u16> {
+            let par = par.iter().map(|&r| r as u32).collect::<Vec<u32>>();
+
+            patterns::coset_leaders(&par[..], bits, 1).iter()
+                .map(|p| p.unwrap_or(0) as u16)
+                .collect()
+        }
+
+        assert_eq!(generate(&standard::PAR, 15), standard::LOCATIONS.to_vec());
+        assert_eq!(generate(&shortened::PAR, 10), shortened::LOCATIONS.to_vec());
+
+        // Derive the tables directly, placing each single-bit error at its syndrome.
+        fn derive(par: &[u16], bits: usize) -> Vec<u16> {
+            let par = par.iter().map(|&r| r as u32).collect::<Vec<u32>>();
+            let mut locs = vec![0; 1 << par.len()];
+
+            for i in 0..bits {
+                let s = patterns::syndrome(1 << i, &par[..]) as usize;
+                assert_eq!(locs[s], 0);
+                locs[s] = 1 << i;
+            }
+
+            locs
+        }
+
+        assert_eq!(derive(&standard::PAR, 15), standard::LOCATIONS.to_vec());
+        assert_eq!(derive(&shortened::PAR, 10), shortened::LOCATIONS.to_vec());
+    }
+
+    #[test]
+    fn test_decode_verbose() {
+        let w = 0b10110101011;
+        let e = standard::encode(w);
+
+        let v = standard::decode_verbose(e ^ 1 << 9).unwrap();
+        assert_eq!(v.data, w);
+        assert_eq!(v.received, (e ^ 1 << 9) as u32);
+        assert_eq!(v.corrected_word, e as u32);
+        assert_eq!(v.flipped_positions, vec![9]);
+        assert_eq!(v.errors, 1);
+
+        let v = standard::decode_verbose(e).unwrap();
+        assert_eq!(v.flipped_positions, vec![]);
+        assert_eq!(v.errors, 0);
+        assert_eq!(standard::decode_verbose(1 << 15), None);
+
+        let w = 0b101011;
+        let e = shortened::encode(w);
+
+        let v = shortened::decode_verbose(e ^ 1 << 4).unwrap();
+        assert_eq!(v.data, w);
+        assert_eq!(v.corrected_word, e as u32);
+        assert_eq!(v.flipped_positions, vec![4]);
+        assert_eq!(shortened::decode_verbose(e ^ 0b0000000101), None);
+    }
+
+    #[test]
+    fn test_decode_checked() {
+        let w = 0b110011;
+        let e = shortened::encode(w);
+
+        assert_eq!(shortened::decode_checked(e).unwrap(), Decoded::new(w, 0));
+        assert_eq!(shortened::decode_checked(e ^ 0b0000100000).unwrap(),
+                   Decoded::new(w, 1));
+
+        let d = shortened::decode_checked(e ^ 0b0000000101).unwrap();
+        assert!(d.detected);
+        assert_eq!(d.corrected, 0);
+        assert_eq!(d.value, w);
+        assert_eq!(d.ok(), None);
+
+        let w = 0b10101010101;
+        let e = standard::encode(w);
+
+        assert_eq!(standard::decode_checked(e ^ 0b000000001000000).unwrap(),
+                   Decoded::new(w, 1));
+
+        // Words wider than the code fail rather than decoding truncated bits.
+        assert_eq!(standard::decode_checked(e | 1 << 15), Err(P25Error::WordTooWide));
+        assert_eq!(shortened::decode_checked(1 << 10), Err(P25Error::WordTooWide));
+    }
+
+    #[test]
+    fn test_decode_detect() {
+        let w = 0b10101010101;
+        let e = standard::encode(w);
+
+        assert_eq!(standard::decode_detect(e), Ok(HammingResult::Clean(w)));
+        assert_eq!(standard::decode_detect(e ^ 0b000000000100000).unwrap(),
+                   HammingResult::Uncertain(w));
+
+        // Double errors are flagged instead of being reported as a clean correction.
+        for &pat in [0b000000000000011, 0b100000000000001, 0b010000100000000].iter() {
+            match standard::decode_detect(e ^ pat).unwrap() {
+                HammingResult::Uncertain(d) => assert!(d != w),
+                _ => panic!(),
+            }
+        }
+
+        let w = 0b110011;
+        let e = shortened::encode(w);
+
+        assert_eq!(shortened::decode_detect(e), Ok(HammingResult::Clean(w)));
+        assert_eq!(shortened::decode_detect(e ^ 0b0001000000).unwrap(),
+                   HammingResult::Uncertain(w));
+
+        // Every double error is either detected or flagged as uncertain.
+        let mut detected = 0;
+
+        for i in 0..10 {
+            for j in i+1..10 {
+                match shortened::decode_detect(e ^ (1 << i | 1 << j)).unwrap() {
+                    HammingResult::DoubleDetected => detected += 1,
+                    HammingResult::Uncertain(_) => {},
+                    HammingResult::Clean(_) => panic!(),
+                }
+            }
+        }
+
+        assert!(detected > 0);
+        assert_eq!(shortened::decode_detect(e ^ 0b0000000101).unwrap(),
+                   HammingResult::DoubleDetected);
+
+        // Words wider than the code are an input error rather than a detected error.
+        assert_eq!(shortened::decode_detect(e | 1 << 10), Err(P25Error::WordTooWide));
+        assert_eq!(standard::decode_detect(1 << 15), Err(P25Error::WordTooWide));
+
+        // Both codes classify every syndrome the same way as the corrected decode.
+        for word in 0..1 << 15 {
+            let d = standard::decode_checked(word).unwrap();
+            assert_eq!(standard::decode_detect(word).unwrap(), if d.corrected == 0 {
+                HammingResult::Clean(d.value)
+            } else {
+                HammingResult::Uncertain(d.value)
+            });
+        }
+
+        for word in 0..1 << 10 {
+            let d = shortened::decode_checked(word).unwrap();
+            assert_eq!(shortened::decode_detect(word).unwrap(), if d.detected {
+                HammingResult::DoubleDetected
+            } else if d.corrected == 0 {
+                HammingResult::Clean(d.value)
+            } else {
+                HammingResult::Uncertain(d.value)
+            });
+        }
+    }
+
+    #[test]
+    fn test_shortened_invalid_syndromes() {
+        let par = shortened::PAR.iter().map(|&r| r as u32).collect::<Vec<u32>>();
+        let w = 0b011010;
+        let e = shortened::encode(w);
+
+        // Syndromes with no single-bit error location.
+        let invalid = (1..16).filter(|&s| shortened::LOCATIONS[s] == 0)
+            .collect::<Vec<usize>>();
+
+        assert_eq!(invalid, vec![5, 6, 9, 10, 15]);
+
+        for &s in invalid.iter() {
+            // Find a double error that produces the syndrome.
+            let pat = (0..10).flat_map(|i| (i + 1..10).map(move |j| 1 << i | 1 << j))
+                .find(|&p| patterns::syndrome(p, &par[..]) as usize == s)
+                .unwrap() as u16;
+
+            let d = shortened::decode_checked(e ^ pat).unwrap();
+            assert!(d.detected);
+            assert_eq!(d.corrected, 0);
+
+            assert_eq!(shortened::decode_detect(e ^ pat),
+                       Ok(HammingResult::DoubleDetected));
+            assert_eq!(shortened::decode(e ^ pat), None);
+        }
+    }
+
+    #[test]
+    fn test_bytes() {
+        let w = standard::encode(0b10110011101);
+        assert_eq!(standard::encode_bytes(0b10110011101),
+                   [(w >> 7) as u8, (w << 1) as u8]);
+
+        let w = w ^ 1 << 3;
+        let buf = [(w >> 7) as u8, (w << 1) as u8];
+        assert_eq!(standard::decode_bytes(&buf), standard::decode(w));
+        assert_eq!(standard::decode_bytes(&buf), Some((0b10110011101, 1)));
+
+        let w = shortened::encode(0b101101);
+        assert_eq!(shortened::encode_bytes(0b101101), [(w >> 2) as u8, (w << 6) as u8]);
+
+        let w = w ^ 1 << 9;
+        let buf = [(w >> 2) as u8, (w << 6) as u8];
+        assert_eq!(shortened::decode_bytes(&buf), shortened::decode(w));
+        assert_eq!(shortened::decode_bytes(&buf), Some((0b101101, 1)));
+    }
 }
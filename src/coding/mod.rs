@@ -38,10 +38,21 @@ mod macros;
 #[macro_use]
 pub mod galois;
 
+#[cfg(any(test, feature = "testutil"))]
+pub mod analysis;
 pub mod bch;
+pub mod block;
+pub mod bytes;
 pub mod bmcf;
+pub mod composite;
 pub mod cyclic;
+pub mod decoded;
 pub mod golay;
 pub mod hamming;
+pub mod patterns;
+pub mod profile;
 pub mod reed_solomon;
+pub mod soft;
 pub mod trellis;
+
+pub use self::decoded::{Decoded, Verbose};
@@ -0,0 +1,126 @@
+//! Generate syndrome decoding tables from a parity-check matrix.
+//!
+//! The decoders for the smaller codes use hand-written tables that map each syndrome to
+//! the error pattern that caused it. These helpers enumerate error patterns in order of
+//! increasing weight to build the same tables from a code's transposed parity-check
+//! matrix, for checking the existing tables and deriving tables for new codes.
+
+/// Compute the syndrome of the given word using the given transposed parity-check matrix.
+pub fn syndrome(word: u32, par: &[u32]) -> u32 {
+    matrix_mul!(word, par, u32)
+}
+
+/// Build a table that maps each syndrome to its coset leader, the lowest-weight error
+/// pattern over a word of the given size that produces it, considering patterns of up
+/// to `max_errors` bits.
+///
+/// Syndromes that aren't produced by any considered pattern, or that are produced by more
+/// than one pattern of the lowest weight, map to `None`.
+pub fn coset_leaders(par: &[u32], bits: usize, max_errors: usize) -> Vec<Option<u32>> {
+    assert!(par.len() < 32 && bits < 32);
+
+    let size = 1 << par.len();
+
+    let mut table = vec![None; size];
+    // Whether the syndrome's leader has been settled at a lower weight.
+    let mut done = vec![false; size];
+
+    table[0] = Some(0);
+    done[0] = true;
+
+    for weight in 1...max_errors {
+        let mut ambiguous = vec![false; size];
+
+        for pat in Patterns::new(bits, weight) {
+            let s = syndrome(pat, par) as usize;
+
+            if done[s] {
+                continue;
+            }
+
+            if table[s].is_some() {
+                ambiguous[s] = true;
+            } else {
+                table[s] = Some(pat);
+            }
+        }
+
+        for s in 0..size {
+            if ambiguous[s] {
+                table[s] = None;
+            }
+
+            done[s] = done[s] || ambiguous[s] || table[s].is_some();
+        }
+    }
+
+    table
+}
+
+/// Iterates over all words of a given size with a given number of bits set, in
+/// increasing order.
+struct Patterns {
+    /// Next pattern to yield.
+    next: u32,
+    /// Bound on patterns.
+    end: u32,
+}
+
+impl Patterns {
+    /// Create a new `Patterns` over words of `bits` bits with `weight` bits set.
+    pub fn new(bits: usize, weight: usize) -> Patterns {
+        assert!(weight >= 1 && weight <= bits);
+
+        Patterns {
+            next: (1 << weight) - 1,
+            end: 1 << bits,
+        }
+    }
+}
+
+impl Iterator for Patterns {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<u32> {
+        let cur = self.next;
+
+        if cur >= self.end {
+            return None;
+        }
+
+        // Step to the next word with the same number of bits set (Gosper's hack.)
+        let low = cur & cur.wrapping_neg();
+        let ripple = cur + low;
+        self.next = (((ripple ^ cur) >> 2) / low) | ripple;
+
+        Some(cur)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::Patterns;
+
+    #[test]
+    fn test_patterns() {
+        assert_eq!(Patterns::new(4, 1).collect::<Vec<u32>>(), vec![1, 2, 4, 8]);
+        assert_eq!(Patterns::new(4, 2).collect::<Vec<u32>>(),
+                   vec![0b0011, 0b0101, 0b0110, 0b1001, 0b1010, 0b1100]);
+        assert_eq!(Patterns::new(4, 4).collect::<Vec<u32>>(), vec![0b1111]);
+        assert_eq!(Patterns::new(17, 2).count(), 136);
+    }
+
+    #[test]
+    fn test_coset_leaders() {
+        // (3, 1, 3) repetition code.
+        let par = [0b110, 0b101];
+        let table = coset_leaders(&par, 3, 3);
+
+        assert_eq!(table, vec![Some(0), Some(0b001), Some(0b010), Some(0b100)]);
+
+        // Single parity bit can't locate errors.
+        let table = coset_leaders(&[0b111], 3, 1);
+        assert_eq!(table, vec![Some(0), None]);
+    }
+}
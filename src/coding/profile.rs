@@ -0,0 +1,222 @@
+//! Table of the error correcting codes that protect each P25 field.
+//!
+//! Fields protected by a concatenated code list each stage in the order it's applied
+//! when decoding, so the inner binary code comes before the outer Reed-Solomon code.
+//!
+//! The field decoders look up their binary codes here with `inner_code`, so this table
+//! is the one place the code for each field is chosen.
+
+use coding::block::{self, BlockCode};
+
+/// Error correcting code applied to a field.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Code {
+    /// (63, 16, 23) BCH code with an extra parity bit.
+    Bch,
+    /// (23, 12, 7) standard Golay code.
+    StandardGolay,
+    /// (24, 12, 8) extended Golay code.
+    ExtendedGolay,
+    /// (18, 6, 8) shortened Golay code.
+    ShortGolay,
+    /// (15, 11, 3) standard Hamming code.
+    StandardHamming,
+    /// (10, 6, 3) shortened Hamming code.
+    ShortHamming,
+    /// (16, 8, 5) shortened cyclic code.
+    Cyclic,
+    /// (24, 12, 13) Reed-Solomon code over hexbits.
+    ShortReedSolomon,
+    /// (24, 16, 9) Reed-Solomon code over hexbits.
+    MediumReedSolomon,
+    /// (36, 20, 17) Reed-Solomon code over hexbits.
+    LongReedSolomon,
+    /// 1/2-rate trellis code over dibits.
+    DibitTrellis,
+    /// 3/4-rate trellis code over tribits.
+    TribitTrellis,
+}
+
+impl Code {
+    /// Get the `(n, k, d)` parameters of the block code, counted in bits for binary
+    /// codes and hexbits for Reed-Solomon codes, or `None` for a trellis code.
+    pub fn params(&self) -> Option<(usize, usize, usize)> {
+        use self::Code::*;
+
+        match *self {
+            Bch => Some((64, 16, 23)),
+            StandardGolay => Some((23, 12, 7)),
+            ExtendedGolay => Some((24, 12, 8)),
+            ShortGolay => Some((18, 6, 8)),
+            StandardHamming => Some((15, 11, 3)),
+            ShortHamming => Some((10, 6, 3)),
+            Cyclic => Some((16, 8, 5)),
+            ShortReedSolomon => Some((24, 12, 13)),
+            MediumReedSolomon => Some((24, 16, 9)),
+            LongReedSolomon => Some((36, 20, 17)),
+            DibitTrellis | TribitTrellis => None,
+        }
+    }
+
+    /// Get the binary block code implementation, or `None` for a Reed-Solomon or
+    /// trellis code.
+    pub fn block_code(&self) -> Option<&'static BlockCode> {
+        use self::Code::*;
+
+        static BCH: block::Bch = block::Bch;
+        static STANDARD_GOLAY: block::StandardGolay = block::StandardGolay;
+        static EXTENDED_GOLAY: block::ExtendedGolay = block::ExtendedGolay;
+        static SHORT_GOLAY: block::ShortGolay = block::ShortGolay;
+        static STANDARD_HAMMING: block::StandardHamming = block::StandardHamming;
+        static SHORT_HAMMING: block::ShortHamming = block::ShortHamming;
+        static CYCLIC: block::Cyclic = block::Cyclic;
+
+        match *self {
+            Bch => Some(&BCH),
+            StandardGolay => Some(&STANDARD_GOLAY),
+            ExtendedGolay => Some(&EXTENDED_GOLAY),
+            ShortGolay => Some(&SHORT_GOLAY),
+            StandardHamming => Some(&STANDARD_HAMMING),
+            ShortHamming => Some(&SHORT_HAMMING),
+            Cyclic => Some(&CYCLIC),
+            _ => None,
+        }
+    }
+}
+
+/// A field of a P25 data unit.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Field {
+    /// Network ID following frame sync.
+    Nid,
+    /// Voice header message.
+    Header,
+    /// Link control word in an LDU1.
+    LinkControl,
+    /// Crypto control word in an LDU2.
+    CryptoControl,
+    /// Low-speed data byte in an LDU.
+    LowSpeedData,
+    /// Voice frame word u<sub>i</sub>, from 0 to 7.
+    VoiceWord(usize),
+    /// Link control word in a voice terminator.
+    TerminatorLinkControl,
+    /// Trunking signalling block.
+    Tsbk,
+    /// Data packet header block.
+    DataHeader,
+    /// Confirmed data packet block.
+    ConfirmedData,
+    /// Unconfirmed data packet block.
+    UnconfirmedData,
+}
+
+/// Get the codes that protect the given field, in the order they're applied when
+/// decoding. The list is empty for a field sent uncoded, such as voice word
+/// u<sub>7</sub>. Return `None` for a voice word index past 7.
+pub fn codes(field: Field) -> Option<&'static [Code]> {
+    use self::Code::*;
+    use self::Field::*;
+
+    Some(match field {
+        Nid => &[Bch],
+        Header => &[ShortGolay, LongReedSolomon],
+        LinkControl => &[ShortHamming, ShortReedSolomon],
+        CryptoControl => &[ShortHamming, MediumReedSolomon],
+        LowSpeedData => &[Cyclic],
+        VoiceWord(0...3) => &[StandardGolay],
+        VoiceWord(4...6) => &[StandardHamming],
+        VoiceWord(7) => &[],
+        VoiceWord(_) => return None,
+        TerminatorLinkControl => &[ExtendedGolay, ShortReedSolomon],
+        Tsbk | DataHeader | UnconfirmedData => &[DibitTrellis],
+        ConfirmedData => &[TribitTrellis],
+    })
+}
+
+/// Get the binary block code applied first when decoding the given field.
+///
+/// Panics if the field isn't protected by a binary block code, such as a trellis-coded
+/// field or voice word u<sub>7</sub>.
+pub fn inner_code(field: Field) -> &'static BlockCode {
+    match codes(field).and_then(|c| c.first()).and_then(|c| c.block_code()) {
+        Some(code) => code,
+        None => panic!("field has no binary block code"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::Code::*;
+    use super::Field::*;
+    use consts;
+
+    #[test]
+    fn test_codes() {
+        assert_eq!(codes(Nid).unwrap(), &[Bch]);
+        assert_eq!(Bch.params(), Some((64, 16, 23)));
+        assert_eq!(codes(Header).unwrap()[1].params(), Some((36, 20, 17)));
+        assert_eq!(codes(LinkControl).unwrap()[1].params(), Some((24, 12, 13)));
+        assert_eq!(codes(TerminatorLinkControl).unwrap()[1],
+                   codes(LinkControl).unwrap()[1]);
+        assert_eq!(codes(VoiceWord(0)).unwrap(), &[StandardGolay]);
+        assert_eq!(codes(VoiceWord(6)).unwrap(), &[StandardHamming]);
+        assert_eq!(codes(VoiceWord(7)).unwrap(), &[]);
+        assert_eq!(codes(VoiceWord(8)), None);
+        assert_eq!(codes(ConfirmedData).unwrap(), &[TribitTrellis]);
+        assert_eq!(TribitTrellis.params(), None);
+        assert!(LongReedSolomon.block_code().is_none());
+    }
+
+    #[test]
+    fn test_block_codes() {
+        let fields = [Nid, Header, LinkControl, CryptoControl, LowSpeedData,
+                      VoiceWord(0), VoiceWord(4), TerminatorLinkControl];
+
+        // Binary codes match their implementations.
+        for &f in fields.iter() {
+            let code = codes(f).unwrap()[0];
+            let (n, k, _) = code.params().unwrap();
+            let imp = code.block_code().unwrap();
+
+            assert_eq!(imp.code_bits(), n);
+            assert_eq!(imp.data_bits(), k);
+            assert_eq!(inner_code(f).code_bits(), n);
+        }
+
+        assert_eq!(inner_code(VoiceWord(3)).code_bits(), 23);
+        assert_eq!(inner_code(VoiceWord(6)).code_bits(), 15);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_inner_code_trellis() {
+        inner_code(Tsbk);
+    }
+
+    #[test]
+    fn test_sizes() {
+        // Each outer codeword hexbit is carried by one inner codeword.
+        let hexbits = |f| codes(f).unwrap()[1].params().unwrap().0;
+        let dibits = |f| codes(f).unwrap()[0].params().unwrap().0 / 2;
+
+        assert_eq!(hexbits(Header), consts::HEADER_HEXBITS);
+        assert_eq!(dibits(Header), consts::HEADER_WORD_DIBITS);
+        assert_eq!(hexbits(LinkControl), consts::EXTRA_HEXBITS);
+        assert_eq!(hexbits(CryptoControl), consts::EXTRA_HEXBITS);
+        assert_eq!(dibits(LinkControl), consts::EXTRA_WORD_DIBITS);
+        assert_eq!(dibits(TerminatorLinkControl), consts::LC_TERM_WORD_DIBITS);
+        assert_eq!(codes(Nid).unwrap()[0].params().unwrap().0 / 2, consts::NID_DIBITS);
+        assert_eq!(codes(LowSpeedData).unwrap()[0].params().unwrap().0 / 2,
+                   consts::DATA_FRAG_DIBITS);
+
+        // Reed-Solomon data carries the field's bytes.
+        assert_eq!(codes(Header).unwrap()[1].params().unwrap().1 * 6 / 8,
+                   consts::HEADER_BYTES);
+        assert_eq!(codes(LinkControl).unwrap()[1].params().unwrap().1 * 6 / 8,
+                   consts::LINK_CONTROL_BYTES);
+        assert_eq!(codes(CryptoControl).unwrap()[1].params().unwrap().1 * 6 / 8,
+                   consts::CRYPTO_CONTROL_BYTES);
+    }
+}
@@ -0,0 +1,79 @@
+//! Soft-decision metrics for received 4-level symbol amplitudes.
+//!
+//! Amplitudes are expected to be scaled so that the standard deviation levels fall at
+//! +3, +1, -1, and -3, which carry the dibits `01`, `00`, `10`, and `11`. Keeping the
+//! amplitudes rather than deciding each symbol up front lets the decoders weigh each
+//! symbol by how close it came to a level.
+//!
+//! A soft bit is positive when it favors a 1 and negative when it favors a 0, with its
+//! magnitude giving the confidence.
+
+use bits::Dibit;
+
+/// Get the nominal amplitude of the given dibit.
+pub fn level(dibit: Dibit) -> f32 {
+    match dibit.bits() {
+        0b01 => 3.0,
+        0b00 => 1.0,
+        0b10 => -1.0,
+        0b11 => -3.0,
+        _ => unreachable!(),
+    }
+}
+
+/// Decide the dibit with the nearest level to the given amplitude.
+pub fn hard_dibit(sym: f32) -> Dibit {
+    Dibit::new(if sym >= 2.0 {
+        0b01
+    } else if sym >= 0.0 {
+        0b00
+    } else if sym >= -2.0 {
+        0b10
+    } else {
+        0b11
+    })
+}
+
+/// Compute the squared distance between the given amplitude and the level of the given
+/// dibit, where a smaller distance means a more likely dibit.
+pub fn distance(sym: f32, dibit: Dibit) -> f32 {
+    let diff = sym - level(dibit);
+    diff * diff
+}
+
+/// Split the given amplitude into soft bits for the high and low bits of its dibit.
+pub fn soft_bits(sym: f32) -> [f32; 2] {
+    // The high bit is set for negative deviations and the low bit for the outer levels.
+    [-sym, sym.abs() - 2.0]
+}
+
+/// Convert the given amplitudes into a soft bit for each bit of their dibits, MSB first.
+pub fn soft_bits_all(syms: &[f32]) -> Vec<f32> {
+    syms.iter().flat_map(|&s| soft_bits(s).to_vec()).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_levels() {
+        for &bits in [0b00, 0b01, 0b10, 0b11].iter() {
+            let d = Dibit::new(bits);
+            let s = level(d);
+
+            assert_eq!(hard_dibit(s), d);
+            assert_eq!(hard_dibit(s + 0.9), d);
+            assert_eq!(hard_dibit(s - 0.9), d);
+            assert_eq!(distance(s, d), 0.0);
+
+            // Soft bits agree with the dibit's bits.
+            let b = soft_bits(s);
+            assert_eq!(b[0] > 0.0, d.hi() == 1);
+            assert_eq!(b[1] > 0.0, d.lo() == 1);
+        }
+
+        assert_eq!(soft_bits_all(&[3.0, -1.0]), vec![-3.0, 1.0, 1.0, -1.0]);
+        assert_eq!(distance(2.5, Dibit::new(0b00)), 2.25);
+    }
+}
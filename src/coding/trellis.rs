@@ -4,13 +4,19 @@
 //! Encoding is done with a state machine and decoding is done with the Viterbi algorithm,
 //! adapted from *Coding Theory and Cryptography: The Essentials*, Hankerson, Hoffman, et
 //! al, 2000.
+//!
+//! Decoding many streams with `decode_soft` allocates its buffers each time, so a
+//! `TrellisDecoder` can be kept around instead to reuse them across calls.
 
 use std;
+use std::collections::VecDeque;
 use std::ops::{Deref, DerefMut};
 
 use collect_slice::CollectSlice;
 
 use bits;
+use coding::soft;
+use util::hamming_distance;
 
 use self::Decision::*;
 
@@ -249,6 +255,10 @@ pub struct ViterbiDecoder<S, H, W, T> where
 impl<S, H, W, T> ViterbiDecoder<S, H, W, T> where
     S: States, H: WalkHistory, W: Walks<H>, T: Iterator<Item = bits::Dibit>
 {
+    /// Get the traceback depth of the decoder, which is fixed by its walk history type.
+    /// For a configurable depth, use a `TrellisDecoder`.
+    pub fn traceback_depth() -> usize { H::history() }
+
     /// Construct a new `ViterbiDecoder` over the given dibit source.
     pub fn new(src: T) -> ViterbiDecoder<S, H, W, T> {
         debug_assert!(S::size() == W::states());
@@ -279,8 +289,8 @@ impl<S, H, W, T> ViterbiDecoder<S, H, W, T> where
     fn step(&mut self) -> bool {
         let input = Edge::new(match (self.src.next(), self.src.next()) {
             (Some(hi), Some(lo)) => (hi, lo),
-            (None, None) => return false,
-            _ => panic!("dibits ended on boundary"),
+            // A dangling dibit can't form a symbol pair, so treat it as the end.
+            _ => return false,
         });
 
         self.remain += 1;
@@ -330,7 +340,7 @@ impl<S, H, W, T> Iterator for ViterbiDecoder<S, H, W, T> where
     fn next(&mut self) -> Option<Self::Item> {
         // Stop on the symbol before last since the final symbol is always a dummy symbol
         // used for flushing.
-        if !self.step() && self.remain == 1 {
+        if !self.step() && self.remain <= 1 {
             return None;
         }
 
@@ -343,6 +353,167 @@ impl<S, H, W, T> Iterator for ViterbiDecoder<S, H, W, T> where
     }
 }
 
+/// Decode the given symbol amplitudes, scaled to the standard +3/+1/-1/-3 deviation
+/// levels, using the Viterbi algorithm with soft branch metrics.
+///
+/// Each branch is weighed by the squared distance of its two symbols from their nominal
+/// levels, so a symbol near a decision boundary counts for less than a confident one.
+/// Like `ViterbiDecoder`, this yields every symbol except the final flushing symbol, and
+/// a dangling amplitude at the end is ignored.
+///
+/// This allocates a fresh decoding context for each call, so hot loops should reuse a
+/// `TrellisDecoder` instead.
+pub fn decode_soft<S: States>(symbols: &[f32]) -> Vec<S::Symbol> {
+    let mut out = vec![];
+    TrellisDecoder::<S>::new().decode_soft_into(symbols, &mut out);
+    out
+}
+
+/// Reusable Viterbi decoding context over whole streams, which keeps its path metric and
+/// survivor history buffers between calls so repeated decodes don't reallocate them.
+///
+/// By default, the survivor history covers the whole stream, which suits the short
+/// streams of P25. A traceback depth can be set to bound the history for longer streams.
+pub struct TrellisDecoder<S: States> {
+    states: std::marker::PhantomData<S>,
+    /// Number of steps of history kept before the oldest symbol is decided, or `None` to
+    /// keep the history of the whole stream.
+    depth: Option<usize>,
+    /// Accumulated metric of the best path into each state.
+    metrics: Vec<f32>,
+    /// Path metrics being computed for the next step.
+    next: Vec<f32>,
+    /// Previous state on the best path into each state, `S::size()` entries for each
+    /// step, oldest first.
+    history: VecDeque<usize>,
+}
+
+impl<S: States> TrellisDecoder<S> {
+    /// Create a new `TrellisDecoder` with empty buffers.
+    pub fn new() -> TrellisDecoder<S> {
+        TrellisDecoder {
+            states: std::marker::PhantomData,
+            depth: None,
+            metrics: vec![],
+            next: vec![],
+            history: VecDeque::new(),
+        }
+    }
+
+    /// Bound the survivor history to the given number of steps, which must be at least 1.
+    ///
+    /// Once the history exceeds the depth, its oldest symbol is decided by tracing back
+    /// from the current best state. The symbols remaining at the end of the stream are
+    /// traced back from the final best state, as with a full history. A depth at least
+    /// the length of the stream gives the same output as no depth.
+    pub fn with_traceback_depth(mut self, depth: usize) -> Self {
+        assert!(depth > 0);
+        self.depth = Some(depth);
+        self
+    }
+
+    /// Decode the given dibits, replacing the contents of `out` with the decoded symbols.
+    ///
+    /// Each branch is weighed by the number of bits its dibit pair differs from the
+    /// received pair. Like `ViterbiDecoder`, every symbol except the final flushing
+    /// symbol is output, and a dangling dibit at the end is ignored. Unlike
+    /// `ViterbiDecoder`, ties are broken toward the lowest state rather than reported.
+    pub fn decode_into(&mut self, dibits: &[bits::Dibit], out: &mut Vec<S::Symbol>) {
+        self.run(dibits.len() / 2, |step, prev, next| {
+            Edge::new((dibits[step * 2], dibits[step * 2 + 1]))
+                .distance(Edge::new(S::pair(prev, next))) as f32
+        }, out)
+    }
+
+    /// Decode the given symbol amplitudes in the same way as `decode_soft`, replacing the
+    /// contents of `out` with the decoded symbols.
+    pub fn decode_soft_into(&mut self, symbols: &[f32], out: &mut Vec<S::Symbol>) {
+        self.run(symbols.len() / 2, |step, prev, next| {
+            let (hi, lo) = S::pair(prev, next);
+            soft::distance(symbols[step * 2], hi) +
+                soft::distance(symbols[step * 2 + 1], lo)
+        }, out)
+    }
+
+    /// Run the Viterbi algorithm over the given number of steps, using the given metric
+    /// for the branch from one state to the next at each step.
+    fn run<F>(&mut self, steps: usize, metric: F, out: &mut Vec<S::Symbol>) where
+        F: Fn(usize, usize, usize) -> f32
+    {
+        out.clear();
+
+        if steps == 0 {
+            return;
+        }
+
+        // Start every path from state 0.
+        self.metrics.clear();
+        self.metrics.resize(S::size(), std::f32::INFINITY);
+        self.metrics[0] = 0.0;
+        self.history.clear();
+
+        for step in 0..steps {
+            self.next.clear();
+            self.next.resize(S::size(), std::f32::INFINITY);
+
+            for s in 0..S::size() {
+                let mut prev = 0;
+
+                for p in 0..S::size() {
+                    let m = self.metrics[p] + metric(step, p, s);
+
+                    if m < self.next[s] {
+                        self.next[s] = m;
+                        prev = p;
+                    }
+                }
+
+                self.history.push_back(prev);
+            }
+
+            std::mem::swap(&mut self.metrics, &mut self.next);
+
+            let depth = match self.depth {
+                Some(depth) => depth,
+                None => continue,
+            };
+
+            if self.history.len() <= depth * S::size() {
+                continue;
+            }
+
+            // Decide the oldest step along the current best path.
+            let state = (1..self.history.len() / S::size()).rev()
+                .fold(self.best(), |s, step| self.history[step * S::size() + s]);
+
+            out.push(S::symbol(state));
+
+            for _ in 0..S::size() {
+                self.history.pop_front();
+            }
+        }
+
+        // Trace back the states along the best path, which are the decoded symbols.
+        let start = out.len();
+        let mut state = self.best();
+
+        for step in (0..self.history.len() / S::size()).rev() {
+            out.push(S::symbol(state));
+            state = self.history[step * S::size() + state];
+        }
+
+        out[start..].reverse();
+        out.pop();
+    }
+
+    /// Get the state with the lowest path metric.
+    fn best(&self) -> usize {
+        (0..S::size()).fold(0, |best, s| {
+            if self.metrics[s] < self.metrics[best] { s } else { best }
+        })
+    }
+}
+
 /// Decoding decision.
 enum Decision {
     Definite(usize, Option<usize>),
@@ -419,7 +590,7 @@ impl Edge {
     }
 
     pub fn distance(&self, other: Edge) -> usize {
-        (self.0 ^ other.0).count_ones() as usize
+        hamming_distance(self.0 as u64, other.0 as u64) as usize
     }
 }
 
@@ -507,6 +678,195 @@ mod test {
         assert_eq!(dec.next().unwrap().unwrap().bits(), 2);
     }
 
+    #[test]
+    fn test_decode_soft() {
+        let bits = [1, 2, 2, 2, 2, 1, 3, 3, 0, 2];
+
+        let mut dibits = vec![];
+        let mut fsm = DibitFSM::new();
+
+        for &b in bits.iter() {
+            let (hi, lo) = fsm.feed(Dibit::new(b));
+            dibits.push(hi);
+            dibits.push(lo);
+        }
+
+        let (hi, lo) = fsm.finish();
+        dibits.push(hi);
+        dibits.push(lo);
+
+        let mut symbols: Vec<f32> = dibits.iter().map(|&d| soft::level(d)).collect();
+
+        // Clean symbols decode the same as hard decisions.
+        let hard: Vec<u8> = DibitDecoder::new(dibits.iter().cloned())
+            .map(|d| d.unwrap().bits())
+            .collect();
+        let soft: Vec<u8> = decode_soft::<DibitStates>(&symbols[..]).iter()
+            .map(|d| d.bits())
+            .collect();
+
+        assert_eq!(hard, &bits[..]);
+        assert_eq!(soft, &bits[..]);
+
+        // Push several symbols across decision boundaries.
+        for &i in [3, 10, 17].iter() {
+            symbols[i] += if symbols[i] > 0.0 { -1.2 } else { 1.2 };
+            assert!(soft::hard_dibit(symbols[i]) != dibits[i]);
+        }
+
+        let soft: Vec<u8> = decode_soft::<DibitStates>(&symbols[..]).iter()
+            .map(|d| d.bits())
+            .collect();
+
+        assert_eq!(soft, &bits[..]);
+
+        // Dangling and missing symbols.
+        assert_eq!(decode_soft::<DibitStates>(&[]).len(), 0);
+        assert_eq!(decode_soft::<DibitStates>(&[1.0]).len(), 0);
+        assert_eq!(decode_soft::<DibitStates>(&symbols[..5]).len(), 1);
+
+        let mut tribits = vec![];
+        let mut fsm = TribitFSM::new();
+
+        for &b in [5, 0, 7, 3, 6].iter() {
+            let (hi, lo) = fsm.feed(Tribit::new(b));
+            tribits.push(soft::level(hi));
+            tribits.push(soft::level(lo));
+        }
+
+        let (hi, lo) = fsm.finish();
+        tribits.push(soft::level(hi));
+        tribits.push(soft::level(lo));
+        tribits[3] *= 0.3;
+
+        let soft: Vec<u8> = decode_soft::<TribitStates>(&tribits[..]).iter()
+            .map(|t| t.bits())
+            .collect();
+
+        assert_eq!(soft, vec![5, 0, 7, 3, 6]);
+    }
+
+    #[test]
+    fn test_decode_into() {
+        let streams: Vec<Vec<u8>> = vec![
+            vec![1, 2, 2, 2, 2, 1, 3, 3, 0, 2],
+            vec![3, 0],
+            vec![],
+            (0..40).map(|i| (i * 7 % 5) as u8 & 0b11).collect(),
+        ];
+
+        let mut dec = TrellisDecoder::<DibitStates>::new();
+        let mut out = vec![];
+
+        // Decode each stream twice to check nothing carries over between calls.
+        for bits in streams.iter().chain(streams.iter()) {
+            let mut dibits = vec![];
+            let mut fsm = DibitFSM::new();
+
+            for &b in bits.iter() {
+                let (hi, lo) = fsm.feed(Dibit::new(b));
+                dibits.push(hi);
+                dibits.push(lo);
+            }
+
+            let (hi, lo) = fsm.finish();
+            dibits.push(hi);
+            dibits.push(lo);
+
+            let symbols: Vec<f32> = dibits.iter().map(|&d| soft::level(d)).collect();
+
+            if dibits.len() > 4 {
+                dibits[2] = Dibit::new(dibits[2].bits() ^ 0b10);
+            }
+
+            dec.decode_soft_into(&symbols[..], &mut out);
+            assert_eq!(out, decode_soft::<DibitStates>(&symbols[..]));
+            assert_eq!(out.iter().map(|d| d.bits()).collect::<Vec<_>>(), &bits[..]);
+
+            dec.decode_into(&dibits[..], &mut out);
+            assert_eq!(out.iter().map(|d| d.bits()).collect::<Vec<_>>(), &bits[..]);
+
+            let hard: Vec<u8> = DibitDecoder::new(dibits.iter().cloned())
+                .map(|d| d.unwrap().bits())
+                .collect();
+            assert_eq!(hard, &bits[..]);
+        }
+
+        // A dangling dibit is ignored.
+        dec.decode_into(&[Dibit::new(0b01)], &mut out);
+        assert_eq!(out.len(), 0);
+
+        let mut dibits = vec![];
+        let mut fsm = TribitFSM::new();
+
+        for &b in [5, 0, 7, 3, 6].iter() {
+            let (hi, lo) = fsm.feed(Tribit::new(b));
+            dibits.push(hi);
+            dibits.push(lo);
+        }
+
+        let (hi, lo) = fsm.finish();
+        dibits.push(hi);
+        dibits.push(lo);
+        dibits[4] = Dibit::new(dibits[4].bits() ^ 0b01);
+
+        let mut out = vec![];
+        TrellisDecoder::<TribitStates>::new().decode_into(&dibits[..], &mut out);
+        assert_eq!(out.iter().map(|t| t.bits()).collect::<Vec<_>>(), vec![5, 0, 7, 3, 6]);
+    }
+
+    #[test]
+    fn test_traceback_depth() {
+        let bits: Vec<u8> = (0..200).map(|i| (i * 13 % 7) as u8 & 0b11).collect();
+
+        let mut dibits = vec![];
+        let mut fsm = DibitFSM::new();
+
+        for &b in bits.iter() {
+            let (hi, lo) = fsm.feed(Dibit::new(b));
+            dibits.push(hi);
+            dibits.push(lo);
+        }
+
+        let (hi, lo) = fsm.finish();
+        dibits.push(hi);
+        dibits.push(lo);
+
+        // Spread single errors out through the stream, including the tail.
+        for i in (5..dibits.len()).filter(|i| i % 37 == 5) {
+            dibits[i] = Dibit::new(dibits[i].bits() ^ 0b10);
+        }
+
+        let mut full = vec![];
+        TrellisDecoder::<DibitStates>::new().decode_into(&dibits[..], &mut full);
+        assert_eq!(full.iter().map(|d| d.bits()).collect::<Vec<_>>(), bits);
+
+        for &depth in [16, 32, 201, 500].iter() {
+            let mut dec = TrellisDecoder::<DibitStates>::new()
+                .with_traceback_depth(depth);
+            let mut out = vec![];
+
+            dec.decode_into(&dibits[..], &mut out);
+            assert_eq!(out, full);
+
+            // The bounded history is reset between calls.
+            dec.decode_into(&dibits[..20], &mut out);
+            assert_eq!(out.iter().map(|d| d.bits()).collect::<Vec<_>>(), &bits[..9]);
+        }
+
+        assert_eq!(DibitDecoder::<std::vec::IntoIter<Dibit>>::traceback_depth(), 4);
+    }
+
+    #[test]
+    fn test_short_input() {
+        let dibits = [Dibit::new(0b01); 5];
+
+        for len in 0..dibits.len() {
+            let dec = DibitDecoder::new(dibits[..len].iter().cloned());
+            assert!(dec.count() <= len / 2);
+        }
+    }
+
     #[test]
     fn test_tribit_decoder() {
         let bits = [
@@ -28,6 +28,10 @@ pub const EXTRA_HEXBITS: usize = 24;
 pub const LINK_CONTROL_BYTES: usize = 9;
 /// Number of bytes in a crypto control word.
 pub const CRYPTO_CONTROL_BYTES: usize = 12;
+/// Number of bytes in a crypto message indicator.
+pub const MI_BYTES: usize = 9;
+/// Number of hexbits that carry a crypto message indicator.
+pub const MI_HEXBITS: usize = MI_BYTES * 8 / 6;
 /// Number of dibits in an LC/CC piece. An LC/CC word is spread over 6 equal-sized pieces
 /// in each frame group, for a total of 120 dibits.
 pub const EXTRA_PIECE_DIBITS: usize = 20;
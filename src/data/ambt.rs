@@ -0,0 +1,246 @@
+//! Decode alternate multiple block trunking (AMBT) packets, which carry trunking
+//! messages too long to fit in a single TSBK.
+//!
+//! An AMBT packet is a data packet header followed by unconfirmed data blocks, all using
+//! the same interleaving and 1/2-rate trellis code as a TSBK. The header holds the
+//! message's TSBK opcode, manufacturer ID, and subscriber address, along with the first
+//! 2 bytes of opcode-defined fields. The remaining fields follow in the data blocks, the
+//! last of which ends with a 32-bit checksum over all the data block bytes.
+
+use collect_slice::CollectSlice;
+
+use bits::{Dibit, DibitBytes};
+use coding::trellis;
+use consts::{CODING_DIBITS, TSBK_DIBITS, TSBK_BYTES};
+use data::{crc, interleave};
+use data::fields::{DataPacketOpcode, ServiceAccessPoint};
+use data::header::Manufacturer;
+use error::{Result, P25Error};
+use trunking::tsbk::TsbkOpcode;
+use util::{check_len, slice_u16, slice_u24, slice_u32};
+
+/// Buffer of bytes that represents an AMBT header or data block.
+pub type Buf = [u8; TSBK_BYTES];
+
+/// Deinterleave and decode the given block of dibits into the bytes of an AMBT header or
+/// data block.
+pub fn decode_block(dibits: &[Dibit; CODING_DIBITS]) -> Result<Buf> {
+    let mut decoded = [Dibit::default(); TSBK_DIBITS];
+
+    let count = trellis::DibitDecoder::new(interleave::Deinterleaver::new(dibits))
+        .filter_map(|x| x.ok())
+        .collect_slice_exhaust(&mut decoded[..]);
+
+    if count != decoded.len() {
+        return Err(P25Error::ViterbiUnrecoverable);
+    }
+
+    let mut bytes = [0; TSBK_BYTES];
+    DibitBytes::new(decoded.iter().cloned()).collect_slice_checked(&mut bytes[..]);
+
+    Ok(bytes)
+}
+
+/// Header block of an AMBT packet.
+#[derive(Copy, Clone)]
+pub struct AmbtHeader(Buf);
+
+impl AmbtHeader {
+    /// Parse an AMBT header from the given 10 field bytes followed by 2 checksum bytes.
+    /// Return an error if the buffer is too short, the packet format isn't AMBT, or the
+    /// checksum doesn't match.
+    pub fn parse(buf: &[u8]) -> Result<AmbtHeader> {
+        if let Err(e) = check_len(buf, TSBK_BYTES) {
+            return Err(e);
+        }
+
+        if DataPacketOpcode::from_bits(buf[0] & 0x1F) !=
+            Some(DataPacketOpcode::TrunkingPacket)
+        {
+            return Err(P25Error::InvalidHeader);
+        }
+
+        let checksum = crc::CRC16::new()
+            .feed_bytes(buf[..10].iter().cloned())
+            .finish() as u16;
+
+        if checksum != slice_u16(&buf[10..12]) {
+            return Err(P25Error::HeaderCrcMismatch);
+        }
+
+        let mut bytes = [0; TSBK_BYTES];
+        bytes.copy_from_slice(&buf[..TSBK_BYTES]);
+
+        Ok(AmbtHeader(bytes))
+    }
+
+    /// Whether the packet is an outbound message.
+    pub fn outbound(&self) -> bool { self.0[0] >> 5 & 1 == 1 }
+    /// Destination service of the packet, normally trunking control.
+    pub fn sap(&self) -> Option<ServiceAccessPoint> {
+        ServiceAccessPoint::from_bits(self.0[1] & 0x3F)
+    }
+    /// Manufacturer ID, which determines if the message is standardized.
+    pub fn mfg(&self) -> u8 { self.0[2] }
+    /// Manufacturer identified by the manufacturer ID.
+    pub fn manufacturer(&self) -> Manufacturer { Manufacturer::from_id(self.mfg()) }
+    /// Subscriber the message is addressed to, or sent from for inbound messages.
+    pub fn addr(&self) -> u32 { slice_u24(&self.0[3...5]) }
+    /// Number of data blocks following the header.
+    pub fn blocks(&self) -> usize { (self.0[6] & 0x7F) as usize }
+    /// Type of message carried by the packet.
+    pub fn opcode(&self) -> Option<TsbkOpcode> { TsbkOpcode::from_bits(self.0[7] & 0x3F) }
+    /// Opcode-defined fields carried in the header.
+    pub fn fields(&self) -> &[u8] { &self.0[8...9] }
+}
+
+/// Complete AMBT message made up of a header and its data blocks.
+pub struct AmbtMessage {
+    /// Header of the packet.
+    header: AmbtHeader,
+    /// Data block bytes, without the trailing checksum.
+    body: Vec<u8>,
+}
+
+impl AmbtMessage {
+    /// Parse a message from the given header and the data blocks that follow it. Return
+    /// an error if the number of blocks doesn't match the header or the message
+    /// checksum doesn't match.
+    pub fn parse(header: AmbtHeader, blocks: &[Buf]) -> Result<AmbtMessage> {
+        if blocks.is_empty() || blocks.len() != header.blocks() {
+            return Err(P25Error::AmbtBlockCountMismatch);
+        }
+
+        let mut body: Vec<u8> = blocks.iter().flat_map(|b| b.iter().cloned()).collect();
+
+        let split = body.len() - 4;
+        let checksum = slice_u32(&body[split..]);
+        body.truncate(split);
+
+        if crc::crc32(&body) != checksum {
+            return Err(P25Error::AmbtCrcMismatch);
+        }
+
+        Ok(AmbtMessage {
+            header: header,
+            body: body,
+        })
+    }
+
+    /// Header of the packet.
+    pub fn header(&self) -> &AmbtHeader { &self.header }
+    /// Type of message carried by the packet.
+    pub fn opcode(&self) -> Option<TsbkOpcode> { self.header.opcode() }
+    /// Opcode-defined fields carried in the data blocks, without the trailing checksum.
+    pub fn body(&self) -> &[u8] { &self.body[..] }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use data::coder::DibitCoder;
+
+    /// Encode the given header fields into a checksummed header block.
+    fn header(fields: [u8; 10]) -> Buf {
+        let checksum = crc::CRC16::new()
+            .feed_bytes(fields.iter().cloned())
+            .finish();
+
+        let mut buf = [0; TSBK_BYTES];
+        buf[..10].copy_from_slice(&fields);
+        buf[10] = (checksum >> 8) as u8;
+        buf[11] = checksum as u8;
+
+        buf
+    }
+
+    /// Encode and interleave the given block bytes.
+    fn encode(bytes: Buf) -> [Dibit; CODING_DIBITS] {
+        let coded = DibitCoder::new()
+            .feed_bytes(bytes.iter().cloned())
+            .finish();
+
+        let mut dibits = [Dibit::default(); CODING_DIBITS];
+        interleave::Interleaver::new(coded).collect_slice_checked(&mut dibits[..]);
+
+        dibits
+    }
+
+    #[test]
+    fn test_ambt() {
+        // Outbound group voice grant update (explicit) with two data blocks.
+        let hdr = header([
+            0b00110111, 0b11111101, 0x00, 0x12, 0x34, 0x56, 0b10000010, 0x03, 0xAB, 0xCD,
+        ]);
+
+        let mut first = [0; TSBK_BYTES];
+        for (i, b) in first.iter_mut().enumerate() {
+            *b = i as u8;
+        }
+
+        let mut second = [0; TSBK_BYTES];
+        second[0] = 0xEE;
+
+        let checksum = {
+            let mut data = first.to_vec();
+            data.extend(&second[..8]);
+            crc::crc32(&data)
+        };
+
+        second[8] = (checksum >> 24) as u8;
+        second[9] = (checksum >> 16) as u8;
+        second[10] = (checksum >> 8) as u8;
+        second[11] = checksum as u8;
+
+        let mut dibits = encode(hdr);
+        dibits[20] = Dibit::new(dibits[20].bits() ^ 0b11);
+
+        let h = AmbtHeader::parse(&decode_block(&dibits).unwrap()).unwrap();
+        assert!(h.outbound());
+        assert_eq!(h.sap(), Some(ServiceAccessPoint::TrunkingControl));
+        assert!(h.manufacturer().is_standard());
+        assert_eq!(h.addr(), 0x123456);
+        assert_eq!(h.blocks(), 2);
+        assert_eq!(h.opcode(), Some(TsbkOpcode::GroupVoiceUpdateExplicit));
+        assert_eq!(h.fields(), &[0xAB, 0xCD]);
+
+        let blocks = [
+            decode_block(&encode(first)).unwrap(),
+            decode_block(&encode(second)).unwrap(),
+        ];
+
+        let msg = AmbtMessage::parse(h, &blocks).unwrap();
+        assert_eq!(msg.opcode(), Some(TsbkOpcode::GroupVoiceUpdateExplicit));
+        assert_eq!(msg.body().len(), 20);
+        assert_eq!(&msg.body()[..12], &first[..]);
+        assert_eq!(msg.body()[12], 0xEE);
+
+        assert_eq!(AmbtMessage::parse(h, &blocks[..1]).err(),
+                   Some(P25Error::AmbtBlockCountMismatch));
+
+        let mut bad = blocks;
+        bad[0][3] ^= 0x10;
+        assert_eq!(AmbtMessage::parse(h, &bad).err(), Some(P25Error::AmbtCrcMismatch));
+    }
+
+    #[test]
+    fn test_header() {
+        let mut hdr = header([
+            0b00110111, 0b11111101, 0x90, 0, 0, 1, 0b10000001, 0x00, 0, 0,
+        ]);
+
+        assert!(AmbtHeader::parse(&hdr).is_ok());
+        assert_eq!(AmbtHeader::parse(&hdr[..11]).err(),
+                   Some(P25Error::TooShort { needed: 12, got: 11 }));
+
+        // Checksum mismatch.
+        hdr[5] ^= 1;
+        assert_eq!(AmbtHeader::parse(&hdr).err(), Some(P25Error::HeaderCrcMismatch));
+
+        // Unconfirmed packet rather than AMBT.
+        let hdr = header([
+            0b00110101, 0b11111101, 0x90, 0, 0, 1, 0b10000001, 0x00, 0, 0,
+        ]);
+        assert_eq!(AmbtHeader::parse(&hdr).err(), Some(P25Error::InvalidHeader));
+    }
+}
@@ -0,0 +1,599 @@
+//! Decode data packets from the symbols of a 9600 baud data channel frame.
+//!
+//! A data frame starts with the frame sync and NID, followed by the coded header block
+//! and then the coded data blocks, each `CODING_DIBITS` long. A status symbol follows
+//! every 35 dibits, counted from the start of the frame sync, so blocks generally
+//! straddle status symbols and these must be removed before decoding.
+//!
+//! The header block uses the 1/2-rate trellis code. Data blocks use the 3/4-rate code
+//! for confirmed packets and the 1/2-rate code for unconfirmed packets, and are
+//! interleaved. Confirmed blocks each carry a serial number and CRC-9, and the tail block
+//! of either type ends with a CRC-32 over all data and pad bytes in the packet.
+
+use collect_slice::CollectSlice;
+use std;
+
+use baseband::sync::{sync_errors, MAX_SYNC_ERRORS};
+use bits::{Dibit, DibitBytes, Tribit, TribitBytes};
+use coding::trellis;
+use consts::{CODING_DIBITS, SYNC_SYMBOLS, TSBK_DIBITS};
+use data::{crc, interleave};
+use data::fragment::{BlockParams, Blocks};
+use data::header::{self, DataHeader};
+use data::params::{ConfirmedParams, PacketParams, UnconfirmedParams};
+use error::{P25Error, Result};
+use message::nid::{self, DataUnit};
+use message::status::{StatusDeinterleaver, StreamSymbol};
+use util::{check_len, slice_u32};
+
+/// Number of dibits in the coded NID.
+const NID_DIBITS: usize = 32;
+
+/// Data packet reassembled from a data channel frame.
+pub struct DataChannelPacket {
+    /// Header of the packet.
+    header: DataHeader,
+    /// Serial number of each data block, in the order received.
+    seqs: Vec<u8>,
+    /// User data carried by the packet, without pads or extended address fields.
+    payload: Vec<u8>,
+}
+
+impl DataChannelPacket {
+    /// Header of the packet.
+    pub fn header(&self) -> &DataHeader { &self.header }
+    /// Serial number of each data block, in the order received. Unconfirmed blocks have
+    /// no serial number, so each is numbered by its position.
+    pub fn seqs(&self) -> &[u8] { &self.seqs[..] }
+    /// User data carried by the packet.
+    pub fn payload(&self) -> &[u8] { &self.payload[..] }
+}
+
+/// Decodes data channel frames, rejecting packets whose header claims more blocks or
+/// bytes than the configured limits before any block is decoded.
+#[derive(Copy, Clone, Debug)]
+pub struct ChannelDecoder {
+    /// Maximum number of data blocks in a packet.
+    max_blocks: usize,
+    /// Maximum number of data bytes, including pads, in a packet.
+    max_bytes: usize,
+}
+
+impl ChannelDecoder {
+    /// Create a new `ChannelDecoder` that accepts any packet the header fields can
+    /// describe.
+    pub fn new() -> ChannelDecoder {
+        ChannelDecoder {
+            max_blocks: ConfirmedParams::max_blocks(),
+            max_bytes: ConfirmedParams::max_blocks() * ConfirmedParams::block_bytes(),
+        }
+    }
+
+    /// Reject packets with more than the given number of data blocks.
+    pub fn with_max_blocks(mut self, max_blocks: usize) -> Self {
+        self.max_blocks = max_blocks;
+        self
+    }
+
+    /// Reject packets with more than the given number of data bytes, including pads.
+    pub fn with_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Decode the given frame in the same way as `decode_frame`, returning a `TooLarge`
+    /// error if the packet exceeds the limits.
+    pub fn decode_frame(&self, symbols: &[Dibit]) -> Result<DataChannelPacket> {
+        decode_frame_limited(symbols, self)
+    }
+
+    /// Decode the given packet in the same way as `decode_packet`, returning a
+    /// `TooLarge` error if the packet exceeds the limits.
+    pub fn decode_packet(&self, dibits: &[Dibit]) -> Result<DataChannelPacket> {
+        decode_packet_limited(dibits, self)
+    }
+
+    /// Check the size of the packet described by the given header against the limits.
+    fn check(&self, header: &DataHeader) -> Result<()> {
+        let blocks = header.blocks().count as usize;
+
+        let bytes = match *header {
+            DataHeader::Unconfirmed(_) => blocks * UnconfirmedParams::block_bytes(),
+            _ => blocks * ConfirmedParams::block_bytes(),
+        };
+
+        if blocks > self.max_blocks || bytes > self.max_bytes {
+            Err(P25Error::TooLarge)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Decode the data frame starting with the frame sync at the start of the given
+/// symbols, removing status symbols and checking the NID before decoding the packet as
+/// in `decode_packet`.
+///
+/// Return an error if the frame sync has too many errors, the NID can't be decoded or
+/// isn't for a data packet, or the packet can't be decoded.
+pub fn decode_frame(symbols: &[Dibit]) -> Result<DataChannelPacket> {
+    decode_frame_limited(symbols, &ChannelDecoder::new())
+}
+
+/// Decode the data frame in the given symbols using the given limits.
+fn decode_frame_limited(symbols: &[Dibit], limits: &ChannelDecoder)
+    -> Result<DataChannelPacket>
+{
+    if let Err(e) = check_len(symbols, SYNC_SYMBOLS) {
+        return Err(e);
+    }
+
+    if sync_errors(&symbols[..SYNC_SYMBOLS]) > MAX_SYNC_ERRORS {
+        return Err(P25Error::SyncLost);
+    }
+
+    let dibits = strip_status(&symbols[SYNC_SYMBOLS..]);
+
+    if let Err(e) = check_len(&dibits, NID_DIBITS) {
+        return Err(e);
+    }
+
+    let word = dibits[..NID_DIBITS].iter()
+        .fold(0u64, |w, d| w << 2 | d.bits() as u64);
+
+    match nid::decode_word(word) {
+        Ok((n, _)) => if n.data_unit != DataUnit::DataPacket {
+            return Err(P25Error::UnknownNid);
+        },
+        Err(e) => return Err(e),
+    }
+
+    decode_packet_limited(&dibits[NID_DIBITS..], limits)
+}
+
+/// Remove the status symbols from the given symbols, which start immediately after the
+/// frame sync.
+pub fn strip_status(symbols: &[Dibit]) -> Vec<Dibit> {
+    let mut status = StatusDeinterleaver::new();
+
+    symbols.iter().filter_map(|&d| match status.feed(d) {
+        StreamSymbol::Data(d) => Some(d),
+        StreamSymbol::Status(_) => None,
+    }).collect()
+}
+
+/// Decode the packet made up of the given coded header and data blocks, with status
+/// symbols removed, and reassemble its payload.
+///
+/// Confirmed blocks are put in order of their serial numbers before reassembly. Return
+/// an error if the header is invalid, there are fewer blocks than the header declares,
+/// any block can't be decoded, or any block or packet checksum doesn't match.
+pub fn decode_packet(dibits: &[Dibit]) -> Result<DataChannelPacket> {
+    decode_packet_limited(dibits, &ChannelDecoder::new())
+}
+
+/// Decode the packet in the given dibits using the given limits.
+fn decode_packet_limited(dibits: &[Dibit], limits: &ChannelDecoder)
+    -> Result<DataChannelPacket>
+{
+    if let Err(e) = check_len(dibits, CODING_DIBITS) {
+        return Err(e);
+    }
+
+    let (header, buf) = match decode_header(&dibits[..CODING_DIBITS], limits) {
+        Ok(x) => x,
+        Err(e) => return Err(e),
+    };
+
+    let count = header.blocks().count as usize;
+
+    if let Err(e) = check_len(dibits, CODING_DIBITS * (count + 1)) {
+        return Err(e);
+    }
+
+    let blocks = dibits[CODING_DIBITS..CODING_DIBITS * (count + 1)]
+        .chunks(CODING_DIBITS);
+
+    match header {
+        DataHeader::Unconfirmed(h) => {
+            let mut raw = vec![];
+
+            for block in blocks {
+                match decode_dibit_block(deinterleave(block)) {
+                    Some(b) => raw.extend_from_slice(&b[..]),
+                    None => return Err(P25Error::ViterbiUnrecoverable),
+                }
+            }
+
+            let pads = h.fields().pads.0 as usize;
+            let seqs = (0..count as u8).collect();
+
+            reassemble::<UnconfirmedParams>(DataHeader::Unconfirmed(h), seqs, raw, pads)
+        },
+        DataHeader::Confirmed(h) => {
+            let mut decoded = vec![];
+
+            for block in blocks {
+                match decode_tribit_block(deinterleave(block)) {
+                    Some(b) => decoded.push(b),
+                    None => return Err(P25Error::ViterbiUnrecoverable),
+                }
+            }
+
+            let seqs: Vec<u8> = decoded.iter().map(|b| b[0] >> 1).collect();
+            decoded.sort_by_key(|b| b[0] >> 1);
+
+            // Extended address fields take up the start of the first block's data.
+            let header = match header::parse_extended(&buf, &decoded[0][2..]) {
+                Ok(h) => h,
+                Err(e) => return Err(e),
+            };
+
+            let raw = decoded.iter().flat_map(|b| b.iter().cloned()).collect();
+            let pads = h.fields().pads.0 as usize;
+
+            reassemble::<ConfirmedParams>(header, seqs, raw, pads)
+        },
+        DataHeader::Extended(..) => unreachable!(),
+    }
+}
+
+/// Decode the given coded header block and check it against the given limits, returning
+/// the header and its raw bytes.
+fn decode_header(dibits: &[Dibit], limits: &ChannelDecoder)
+    -> Result<(DataHeader, [u8; 12])>
+{
+    let buf = match decode_dibit_block(dibits.iter().cloned()) {
+        Some(b) => b,
+        None => return Err(P25Error::ViterbiUnrecoverable),
+    };
+
+    let header = match header::parse(&buf) {
+        Ok(h) => h,
+        Err(e) => return Err(e),
+    };
+
+    if let Err(e) = limits.check(&header) {
+        return Err(e);
+    }
+
+    if header.blocks().count == 0 {
+        return Err(P25Error::InvalidHeader);
+    }
+
+    Ok((header, buf))
+}
+
+/// Reassembles a data packet from a stream of dibits, with status symbols removed,
+/// starting at the coded header block.
+///
+/// The header is decoded as soon as its block has been received, and a packet whose
+/// header exceeds the limits is rejected before any of its data blocks are buffered.
+pub struct PacketReceiver {
+    /// Limits on the size of the packet.
+    limits: ChannelDecoder,
+    /// Dibits of the current packet received so far.
+    dibits: Vec<Dibit>,
+    /// Number of dibits in the current packet, once known from the header.
+    size: Option<usize>,
+}
+
+impl PacketReceiver {
+    /// Create a new `PacketReceiver` that rejects packets exceeding the limits of the
+    /// given decoder.
+    pub fn new(limits: ChannelDecoder) -> PacketReceiver {
+        PacketReceiver {
+            limits: limits,
+            dibits: Vec::with_capacity(CODING_DIBITS),
+            size: None,
+        }
+    }
+
+    /// Feed in a data dibit, returning `Some(Ok(packet))` when a packet has been
+    /// reassembled, `Some(Err(err))` if the header is invalid or exceeds the limits or
+    /// the packet can't be decoded, and `None` otherwise.
+    ///
+    /// The receiver starts over with the next dibit after returning `Some`.
+    pub fn feed(&mut self, dibit: Dibit) -> Option<Result<DataChannelPacket>> {
+        self.dibits.push(dibit);
+
+        match self.size {
+            None if self.dibits.len() == CODING_DIBITS => {
+                match decode_header(&self.dibits[..], &self.limits) {
+                    Ok((h, _)) => {
+                        let size = CODING_DIBITS * (h.blocks().count as usize + 1);
+
+                        self.dibits.reserve_exact(size - CODING_DIBITS);
+                        self.size = Some(size);
+
+                        None
+                    },
+                    Err(e) => {
+                        self.reset();
+                        Some(Err(e))
+                    },
+                }
+            },
+            Some(size) if self.dibits.len() == size => {
+                let pkt = decode_packet_limited(&self.dibits[..], &self.limits);
+                self.reset();
+
+                Some(pkt)
+            },
+            _ => None,
+        }
+    }
+
+    /// Discard any partial packet and wait for the next header block.
+    pub fn reset(&mut self) {
+        self.dibits = Vec::with_capacity(CODING_DIBITS);
+        self.size = None;
+    }
+}
+
+/// Check the block and packet checksums of the given raw block bytes and extract the
+/// user data.
+fn reassemble<P: BlockParams>(header: DataHeader, seqs: Vec<u8>, raw: Vec<u8>,
+                              pads: usize)
+    -> Result<DataChannelPacket>
+{
+    let mut data = vec![];
+
+    for block in Blocks::<P>::new(&raw[..]) {
+        if !block.crc_valid() {
+            return Err(P25Error::DataCrcMismatch);
+        }
+
+        data.extend_from_slice(block.data());
+    }
+
+    let tail = raw.len() - 4;
+
+    if crc::CRC32::new().feed_bytes(data.iter().cloned()).finish() as u32 !=
+        slice_u32(&raw[tail..])
+    {
+        return Err(P25Error::DataCrcMismatch);
+    }
+
+    let offset = header.payload_offset();
+
+    if pads + offset > data.len() {
+        return Err(P25Error::DataOffsetOutOfRange);
+    }
+
+    let end = data.len() - pads;
+
+    Ok(DataChannelPacket {
+        header: header,
+        seqs: seqs,
+        payload: data[offset..end].to_vec(),
+    })
+}
+
+/// Deinterleave the given coded data block.
+fn deinterleave(block: &[Dibit]) -> std::vec::IntoIter<Dibit> {
+    let mut buf = [Dibit::default(); CODING_DIBITS];
+    buf.copy_from_slice(block);
+
+    interleave::Deinterleaver::new(&buf).collect::<Vec<_>>().into_iter()
+}
+
+/// Decode the given 1/2-rate coded block into its 12 bytes.
+fn decode_dibit_block<T: Iterator<Item = Dibit>>(dibits: T) -> Option<[u8; 12]> {
+    let mut decoded = [Dibit::default(); TSBK_DIBITS];
+
+    let count = trellis::DibitDecoder::new(dibits)
+        .filter_map(|x| x.ok())
+        .collect_slice_exhaust(&mut decoded[..]);
+
+    if count != decoded.len() {
+        return None;
+    }
+
+    let mut bytes = [0; 12];
+    DibitBytes::new(decoded.iter().cloned()).collect_slice_checked(&mut bytes[..]);
+
+    Some(bytes)
+}
+
+/// Decode the given 3/4-rate coded block into its 18 bytes.
+fn decode_tribit_block<T: Iterator<Item = Dibit>>(dibits: T) -> Option<[u8; 18]> {
+    let mut decoded = [Tribit::default(); CODING_DIBITS / 2 - 1];
+
+    let count = trellis::TribitDecoder::new(dibits)
+        .filter_map(|x| x.ok())
+        .collect_slice_exhaust(&mut decoded[..]);
+
+    if count != decoded.len() {
+        return None;
+    }
+
+    let mut bytes = [0; 18];
+    TribitBytes::new(decoded.iter().cloned()).collect_slice_checked(&mut bytes[..]);
+
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use baseband::sync::patterns;
+    use bits::Dibits;
+    use data::{self, coder, packet};
+    use data::header::*;
+    use message::nid::{NetworkAccessCode, NetworkId};
+    use message::status::{StatusCode, StatusInterleaver};
+
+    /// Build the symbols of a data frame carrying the given packet, starting with the
+    /// frame sync.
+    fn frame(pkt: Vec<Dibit>, data_unit: DataUnit) -> Vec<Dibit> {
+        let nid = NetworkId::new(NetworkAccessCode::Default, data_unit).encode();
+
+        let dibits = patterns::PHASE1_DIBITS.iter().map(|&d| Dibit::new(d))
+            .chain(Dibits::new(nid.iter().cloned()))
+            .chain(pkt.into_iter())
+            .collect::<Vec<_>>();
+
+        StatusInterleaver::new(dibits.into_iter(), StatusCode::InboundIdle).collect()
+    }
+
+    #[test]
+    fn test_unconfirmed() {
+        let bytes = (0..30).collect::<Vec<u8>>();
+        let payload = data::UnconfirmedPayload::new(&bytes);
+
+        let fields = UnconfirmedFields {
+            preamble: UnconfirmedPreamble::outbound(),
+            sap: ServiceAccessPoint(data::fields::ServiceAccessPoint::PacketData),
+            mfg: Manufacturer(0),
+            addr: LogicalLink(0x123456),
+            blocks: BlockCount { full_pkt: true, count: payload.blocks() as u8 },
+            pads: PadCount(payload.pads() as u8),
+            data_offset: DataOffset(0),
+        };
+
+        let pkt = packet::unconfirmed(data::UnconfirmedHeader::new(fields), payload);
+        let symbols = frame(pkt.clone(), DataUnit::DataPacket);
+
+        let p = decode_frame(&symbols).unwrap();
+        assert_eq!(p.payload(), &bytes[..]);
+        assert_eq!(p.seqs(), &[0, 1, 2]);
+        assert_eq!(p.header().dest(), LogicalLink(0x123456));
+
+        // Missing the last block.
+        assert_eq!(decode_packet(&pkt[..CODING_DIBITS * 3]).err(),
+                   Some(P25Error::TooShort { needed: CODING_DIBITS * 4,
+                                             got: CODING_DIBITS * 3 }));
+
+        // Header claiming the maximum block count with no blocks following.
+        let huge = packet::unconfirmed(data::UnconfirmedHeader::new(UnconfirmedFields {
+            blocks: BlockCount { full_pkt: true, count: 127 },
+            ..fields
+        }), data::UnconfirmedPayload::new(&[]));
+        let d = ChannelDecoder::new().with_max_blocks(16);
+        assert_eq!(d.decode_packet(&huge[..CODING_DIBITS]).err(),
+                   Some(P25Error::TooLarge));
+        assert_eq!(decode_packet(&huge[..CODING_DIBITS]).err(),
+                   Some(P25Error::TooShort { needed: CODING_DIBITS * 128,
+                                             got: CODING_DIBITS }));
+
+        // A block replaced with different data decodes cleanly but fails the packet
+        // checksum.
+        let mut bad = pkt.clone();
+        let block = interleave::Interleaver::new(coder::DibitCoder::new()
+            .feed_bytes((0..12).map(|_| 0xFF))
+            .finish()).collect::<Vec<_>>();
+        bad[CODING_DIBITS..CODING_DIBITS * 2].copy_from_slice(&block[..]);
+        assert_eq!(decode_packet(&bad).err(), Some(P25Error::DataCrcMismatch));
+    }
+
+    #[test]
+    fn test_confirmed() {
+        let bytes = (0..40).map(|b| b * 3).collect::<Vec<u8>>();
+        let payload = data::ConfirmedPayload::new(&bytes);
+
+        let header = data::ConfirmedHeader::new(ConfirmedFields {
+            preamble: ConfirmedPreamble::outbound(),
+            sap: ServiceAccessPoint(data::fields::ServiceAccessPoint::PacketData),
+            mfg: Manufacturer(0),
+            addr: LogicalLink(0xABCDEF),
+            blocks: BlockCount { full_pkt: true, count: payload.blocks() as u8 },
+            pads: PadCount(payload.pads() as u8),
+            seq: Sequencing { resync: false, pkt_seq: 0, frag_seq: 0 },
+            data_offset: DataOffset(0),
+        });
+
+        let pkt = packet::confirmed(header, payload, 0..127);
+        let symbols = frame(pkt.clone(), DataUnit::DataPacket);
+
+        let p = decode_frame(&symbols).unwrap();
+        assert_eq!(p.payload(), &bytes[..]);
+        assert_eq!(p.seqs(), &[0, 1, 2]);
+
+        // Limits are checked against the header before any block is decoded.
+        let d = ChannelDecoder::new().with_max_blocks(2);
+        assert_eq!(d.decode_frame(&symbols).err(), Some(P25Error::TooLarge));
+        assert_eq!(d.decode_packet(&pkt[..CODING_DIBITS]).err(),
+                   Some(P25Error::TooLarge));
+        let d = ChannelDecoder::new().with_max_bytes(47);
+        assert_eq!(d.decode_packet(&pkt[..CODING_DIBITS]).err(),
+                   Some(P25Error::TooLarge));
+        let d = ChannelDecoder::new().with_max_blocks(3).with_max_bytes(48);
+        assert_eq!(d.decode_packet(&pkt).unwrap().payload(), &bytes[..]);
+
+        // Blocks received out of order are put back in sequence.
+        let mut swapped = pkt[..CODING_DIBITS].to_vec();
+        swapped.extend_from_slice(&pkt[CODING_DIBITS * 2..CODING_DIBITS * 3]);
+        swapped.extend_from_slice(&pkt[CODING_DIBITS..CODING_DIBITS * 2]);
+        swapped.extend_from_slice(&pkt[CODING_DIBITS * 3..]);
+
+        let p = decode_packet(&swapped).unwrap();
+        assert_eq!(p.payload(), &bytes[..]);
+        assert_eq!(p.seqs(), &[1, 0, 2]);
+
+        // Wrong data unit.
+        let symbols = frame(pkt.clone(), DataUnit::TrunkingSignaling);
+        assert_eq!(decode_frame(&symbols).err(), Some(P25Error::UnknownNid));
+
+        // Missing frame sync.
+        let mut symbols = frame(pkt, DataUnit::DataPacket);
+        for d in symbols[..8].iter_mut() {
+            *d = Dibit::new(d.bits() ^ 0b10);
+        }
+        assert_eq!(decode_frame(&symbols).err(), Some(P25Error::SyncLost));
+    }
+
+    #[test]
+    fn test_receiver() {
+        let bytes = (0..40).collect::<Vec<u8>>();
+        let payload = data::ConfirmedPayload::new(&bytes);
+
+        let fields = ConfirmedFields {
+            preamble: ConfirmedPreamble::outbound(),
+            sap: ServiceAccessPoint(data::fields::ServiceAccessPoint::PacketData),
+            mfg: Manufacturer(0),
+            addr: LogicalLink(0xABCDEF),
+            blocks: BlockCount { full_pkt: true, count: payload.blocks() as u8 },
+            pads: PadCount(payload.pads() as u8),
+            seq: Sequencing { resync: false, pkt_seq: 0, frag_seq: 0 },
+            data_offset: DataOffset(0),
+        };
+
+        let pkt = packet::confirmed(data::ConfirmedHeader::new(fields), payload, 0..127);
+
+        // Header claiming the maximum block count is rejected as soon as the header
+        // block is received, without reserving room for the blocks.
+        let huge = packet::confirmed(data::ConfirmedHeader::new(ConfirmedFields {
+            blocks: BlockCount { full_pkt: true, count: 127 },
+            ..fields
+        }), data::ConfirmedPayload::new(&[]), 0..127);
+
+        let mut r = PacketReceiver::new(ChannelDecoder::new().with_max_blocks(16));
+
+        for &d in huge[..CODING_DIBITS - 1].iter() {
+            assert!(r.feed(d).is_none());
+        }
+
+        assert_eq!(r.feed(huge[CODING_DIBITS - 1]).unwrap().err(),
+                   Some(P25Error::TooLarge));
+        assert!(r.dibits.capacity() < CODING_DIBITS * 2);
+
+        let mut r = PacketReceiver::new(ChannelDecoder::new().with_max_bytes(32));
+        assert_eq!(pkt[..CODING_DIBITS].iter().filter_map(|&d| r.feed(d))
+                       .next().unwrap().err(),
+                   Some(P25Error::TooLarge));
+
+        // The receiver starts over after an error and reassembles the next packet.
+        let mut r = PacketReceiver::new(ChannelDecoder::new().with_max_blocks(3));
+
+        let out = huge[..CODING_DIBITS].iter().chain(pkt.iter())
+            .filter_map(|&d| r.feed(d))
+            .collect::<Vec<_>>();
+
+        assert_eq!(out.len(), 2);
+        assert_eq!(out[0].as_ref().err(), Some(&P25Error::TooLarge));
+        assert_eq!(out[1].as_ref().unwrap().payload(), &bytes[..]);
+        assert_eq!(out[1].as_ref().unwrap().seqs(), &[0, 1, 2]);
+        assert!(r.size.is_none());
+    }
+}
@@ -10,6 +10,50 @@ pub type DibitCoder = DataCoder<trellis::DibitStates>;
 /// 3/4-rate (tribit) convolutional coder.
 pub type TribitCoder = DataCoder<trellis::TribitStates>;
 
+/// Order in which a field's bits are packed into each octet.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum BitOrder {
+    /// First bit of the field in the MSB of the octet, as for most fields.
+    MsbFirst,
+    /// First bit of the field in the LSB of the octet.
+    LsbFirst,
+}
+
+impl BitOrder {
+    /// Pack the given bits, each 0 or 1 and in transmitted order, into an octet. Missing
+    /// trailing bits are taken as 0.
+    pub fn pack(self, bits: &[u8]) -> u8 {
+        assert!(bits.len() <= 8);
+
+        let byte = bits.iter().enumerate().fold(0, |byte, (i, &bit)| {
+            assert!(bit >> 1 == 0);
+            byte | bit << (7 - i)
+        });
+
+        self.to_msb(byte)
+    }
+
+    /// Unpack the given octet into its bits in transmitted order.
+    pub fn unpack(self, byte: u8) -> [u8; 8] {
+        let byte = self.to_msb(byte);
+        let mut bits = [0; 8];
+
+        for (i, bit) in bits.iter_mut().enumerate() {
+            *bit = byte >> (7 - i) & 1;
+        }
+
+        bits
+    }
+
+    /// Convert the given octet packed in this order to one packed MSB first, or back.
+    pub fn to_msb(self, byte: u8) -> u8 {
+        match self {
+            BitOrder::MsbFirst => byte,
+            BitOrder::LsbFirst => (0..8).fold(0, |b, i| b | (byte >> i & 1) << (7 - i)),
+        }
+    }
+}
+
 pub struct DataCoder<S: trellis::States> {
     /// Convolutional state machine.
     fsm: trellis::TrellisFSM<S>,
@@ -68,6 +112,11 @@ impl DibitCoder {
 
         self
     }
+
+    /// Code the given bytes of a field packed in the given bit order as dibits.
+    pub fn feed_field<T: Iterator<Item = u8>>(self, bytes: T, order: BitOrder) -> Self {
+        self.feed_bytes(bytes.map(move |b| order.to_msb(b)))
+    }
 }
 
 impl TribitCoder {
@@ -84,6 +133,11 @@ impl TribitCoder {
 
         self
     }
+
+    /// Code the given bytes of a field packed in the given bit order as tribits.
+    pub fn feed_field<T: Iterator<Item = u8>>(self, bytes: T, order: BitOrder) -> Self {
+        self.feed_bytes(bytes.map(move |b| order.to_msb(b)))
+    }
 }
 
 #[cfg(test)]
@@ -142,4 +196,41 @@ mod test {
         assert_eq!(buf[12].bits(), 0b11);
         assert_eq!(buf[13].bits(), 0b11);
     }
+
+    #[test]
+    fn test_bit_order() {
+        let bits = [1, 0, 0, 0, 0, 1, 1, 0];
+
+        assert_eq!(BitOrder::MsbFirst.pack(&bits), 0b10000110);
+        assert_eq!(BitOrder::LsbFirst.pack(&bits), 0b01100001);
+        assert_eq!(BitOrder::MsbFirst.pack(&[1, 1, 0]), 0b11000000);
+        assert_eq!(BitOrder::LsbFirst.pack(&[1, 1, 0]), 0b00000011);
+
+        assert_eq!(BitOrder::MsbFirst.unpack(0b10000110), bits);
+        assert_eq!(BitOrder::LsbFirst.unpack(0b01100001), bits);
+
+        assert_eq!(BitOrder::LsbFirst.to_msb(0b00101101), 0b10110100);
+
+        for b in 0..256 {
+            let b = b as u8;
+            assert_eq!(BitOrder::LsbFirst.to_msb(BitOrder::LsbFirst.to_msb(b)), b);
+            assert_eq!(BitOrder::MsbFirst.to_msb(b), b);
+        }
+
+        let bytes = (0..18).map(|i| i * 13 + 3).collect::<Vec<u8>>();
+        let rev = bytes.iter().map(|&b| BitOrder::LsbFirst.to_msb(b)).collect::<Vec<_>>();
+
+        let a = DibitCoder::new()
+            .feed_field(bytes[..12].iter().cloned(), BitOrder::LsbFirst);
+        let b = DibitCoder::new().feed_bytes(rev[..12].iter().cloned());
+        assert_eq!(&a.finish()[..], &b.finish()[..]);
+
+        let a = TribitCoder::new().feed_field(bytes.iter().cloned(), BitOrder::LsbFirst);
+        let b = TribitCoder::new().feed_bytes(rev.iter().cloned());
+        assert_eq!(&a.finish()[..], &b.finish()[..]);
+
+        let a = TribitCoder::new().feed_field(bytes.iter().cloned(), BitOrder::MsbFirst);
+        let b = TribitCoder::new().feed_bytes(bytes.iter().cloned());
+        assert_eq!(&a.finish()[..], &b.finish()[..]);
+    }
 }
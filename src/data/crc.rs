@@ -5,6 +5,10 @@
 
 use std;
 
+use data::fragment::{BlockParams, ConfirmedBlocks};
+use data::params::ConfirmedParams;
+use util::slice_u32;
+
 /// 9-bit CRC calculator.
 pub type CRC9 = CRC<CRC9Params>;
 
@@ -14,6 +18,85 @@ pub type CRC16 = CRC<CRC16Params>;
 /// 32-bit CRC calculator.
 pub type CRC32 = CRC<CRC32Params>;
 
+/// Calculate the 32-bit CRC of the given bytes in one shot.
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = CRC32::new();
+    crc.update(bytes);
+    crc.finalize() as u32
+}
+
+/// Type of CRC used by P25.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum CrcKind {
+    /// 9-bit CRC in the header of a confirmed data block.
+    Crc9,
+    /// 16-bit CCITT CRC at the end of a header block or TSBK.
+    CcittCrc16,
+    /// 32-bit CRC at the end of a packet's data.
+    Crc32,
+}
+
+/// Identify which CRC validates the given block, or the given data blocks of a whole
+/// packet, or `None` if none do.
+///
+/// Each CRC is tried at the position P25 places it: a 32-bit CRC in the last 4 bytes, a
+/// 16-bit CRC in the last 2 bytes, and a 9-bit CRC after the 7-bit serial number in the
+/// first 2 bytes of a block. Longer CRCs are tried first since they're less likely to
+/// validate a block by chance.
+///
+/// The 32-bit CRC is checked over all preceding bytes, as in unconfirmed blocks, and,
+/// when the bytes divide into confirmed blocks, over the data of each block as in
+/// `ConfirmedBlocks`. When there are several confirmed blocks, the 9-bit CRC must
+/// validate each of them.
+pub fn identify(bytes: &[u8]) -> Option<CrcKind> {
+    let block = ConfirmedParams::coded_bytes();
+    let confirmed = bytes.len() % block == 0;
+
+    if bytes.len() > 4 {
+        let checksum = slice_u32(&bytes[bytes.len() - 4..]);
+
+        if crc32(&bytes[..bytes.len() - 4]) == checksum {
+            return Some(CrcKind::Crc32);
+        }
+
+        if confirmed {
+            let data = ConfirmedBlocks::new(bytes)
+                .flat_map(|b| b.data().iter().cloned())
+                .collect::<Vec<u8>>();
+
+            if crc32(&data[..]) == checksum {
+                return Some(CrcKind::Crc32);
+            }
+        }
+    }
+
+    if bytes.len() > 2 {
+        let (data, tail) = bytes.split_at(bytes.len() - 2);
+        let checksum = (tail[0] as u16) << 8 | tail[1] as u16;
+
+        if CRC16::new().update(data).finish() as u16 == checksum {
+            return Some(CrcKind::CcittCrc16);
+        }
+
+        if confirmed && bytes.len() > block {
+            if ConfirmedBlocks::new(bytes).all(|b| b.crc_valid()) {
+                return Some(CrcKind::Crc9);
+            }
+        } else {
+            let sn = bytes[0] >> 1;
+            let checksum = ((bytes[0] & 1) as u16) << 8 | bytes[1] as u16;
+
+            if CRC9::new().feed_bits(sn, 7).update(&bytes[2..]).finish() as u16 ==
+                checksum
+            {
+                return Some(CrcKind::Crc9);
+            }
+        }
+    }
+
+    None
+}
+
 pub trait CRCParams {
     /// Generator polynomial, with the MSB being the coefficient of highest degree.
     fn gen() -> u64;
@@ -99,6 +182,18 @@ impl<P: CRCParams> CRC<P> {
         self
     }
 
+    /// Feed in the given chunk of bytes. Feeding a stream in chunks gives the same CRC as
+    /// feeding it all at once, regardless of where the chunks are split.
+    pub fn update(&mut self, bytes: &[u8]) -> &mut Self {
+        self.feed_bytes(bytes.iter().cloned())
+    }
+
+    /// Finish the CRC calculation, consuming the calculator, and return the resulting
+    /// CRC.
+    pub fn finalize(mut self) -> u64 {
+        self.finish()
+    }
+
     /// Finish the CRC calculation and return the resulting CRC.
     pub fn finish(&mut self) -> u64 {
         self.flush();
@@ -139,6 +234,7 @@ fn degree(x: u64) -> u32 {
 #[cfg(test)]
 mod test {
     use super::*;
+    use data::payload::*;
 
     struct CRCTest;
 
@@ -181,6 +277,97 @@ mod test {
         ].iter().cloned()).finish(), 0b011);
     }
 
+    #[test]
+    fn test_update() {
+        let data = (0..200).map(|i| (i * 37 + 11) as u8).collect::<Vec<u8>>();
+        let crc = crc32(&data[..]);
+
+        assert_eq!(crc, CRC32::new().feed_bytes(data.iter().cloned()).finish() as u32);
+
+        for &(a, b) in [(0, 0), (1, 2), (3, 100), (99, 199), (200, 200), (17, 18)].iter() {
+            let mut c = CRC32::new();
+            c.update(&data[..a]).update(&data[a..b]).update(&data[b..]);
+            assert_eq!(c.finalize() as u32, crc);
+        }
+
+        let mut c = CRC32::new();
+
+        for byte in data.chunks(7) {
+            c.update(byte);
+        }
+
+        assert_eq!(c.finalize() as u32, crc);
+    }
+
+    #[test]
+    fn test_identify() {
+        let data = (0..16).map(|i| (i * 37 + 11) as u8).collect::<Vec<u8>>();
+
+        let mut block = data[..10].to_vec();
+        let crc = CRC16::new().update(&block[..]).finish() as u16;
+        block.push((crc >> 8) as u8);
+        block.push(crc as u8);
+        assert_eq!(identify(&block[..]), Some(CrcKind::CcittCrc16));
+
+        let mut block = data[..12].to_vec();
+        let crc = crc32(&block[..]);
+        block.extend([(crc >> 24) as u8, (crc >> 16) as u8, (crc >> 8) as u8,
+                      crc as u8].iter().cloned());
+        assert_eq!(identify(&block[..]), Some(CrcKind::Crc32));
+
+        let crc = CRC9::new().feed_bits(0x2A, 7).update(&data[..]).finish() as u16;
+        let mut block = vec![0x2A << 1 | (crc >> 8) as u8, crc as u8];
+        block.extend(data.iter().cloned());
+        assert_eq!(identify(&block[..]), Some(CrcKind::Crc9));
+
+        assert_eq!(identify(&data[..]), None);
+        assert_eq!(identify(&[]), None);
+
+        // Packet CRC over several unconfirmed blocks.
+        let bytes = (0..40).map(|i| (i * 13 + 5) as u8).collect::<Vec<u8>>();
+        let p = UnconfirmedPayload::new(&bytes);
+        assert_eq!(p.blocks(), 4);
+
+        let mut raw = vec![];
+
+        for block in p.iter() {
+            let (data, pads) = block.build();
+            raw.extend(data.iter().cloned().chain(pads.map(|_| 0)));
+        }
+
+        let (data, pads, checksum) = p.tail().build();
+        raw.extend(data.iter().cloned().chain(pads.map(|_| 0)));
+        raw.extend(checksum.iter().cloned());
+        assert_eq!(identify(&raw[..]), Some(CrcKind::Crc32));
+
+        // Packet CRC over several confirmed blocks, skipping each block header.
+        let p = ConfirmedPayload::new(&bytes);
+        assert_eq!(p.blocks(), 3);
+
+        let mut raw = vec![];
+
+        for (sn, block) in p.iter().enumerate() {
+            let (data, pads) = block.build();
+            raw.extend(ConfirmedBlockHeader::new(sn as u8, data, pads.clone()).build()
+                .iter().cloned());
+            raw.extend(data.iter().cloned().chain(pads.map(|_| 0)));
+        }
+
+        let (data, pads, checksum) = p.tail().build();
+        raw.extend(ConfirmedBlockHeader::new(2, data, pads.clone()).build().iter()
+            .cloned());
+        raw.extend(data.iter().cloned().chain(pads.map(|_| 0)));
+        raw.extend(checksum.iter().cloned());
+        assert_eq!(identify(&raw[..]), Some(CrcKind::Crc32));
+
+        // With a bad packet CRC, only the block CRCs validate.
+        raw[53] ^= 1;
+        assert_eq!(identify(&raw[..]), Some(CrcKind::Crc9));
+
+        raw[20] ^= 1;
+        assert_eq!(identify(&raw[..]), None);
+    }
+
     #[test]
     fn test_crc32() {
         assert_eq!(CRC32::new().feed_bytes([
@@ -2,6 +2,7 @@
 
 /// Data packet type present in every header.
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "ser", derive(Serialize, Deserialize))]
 pub enum DataPacketOpcode {
     /// Confirmed packet that requires an acknowledgement response from the recipient.
     ConfirmedPacket,
@@ -45,6 +46,7 @@ impl DataPacketOpcode {
 
 /// Destination service for data packet.
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "ser", derive(Serialize, Deserialize))]
 pub enum ServiceAccessPoint {
     UnencryptedUserData,
     EncryptedUserData,
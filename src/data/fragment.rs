@@ -3,9 +3,13 @@
 //!
 //! A data message is made up of unlimited data fragments, with the FSNF field of the
 //! packet header determining how the message is reconstructed from the fragments.
+//!
+//! Also provides the `Blocks` iterator for walking the received payload blocks of a
+//! fragment.
 
 use std;
 
+use data::crc;
 use data::params::*;
 
 /// Fragments for confirmed data packets.
@@ -52,10 +56,184 @@ impl<'a, P: PacketParams> Iterator for Fragments<'a, P> {
     }
 }
 
+/// Blocks of a received confirmed data packet payload.
+pub type ConfirmedBlocks<'a> = Blocks<'a, ConfirmedParams>;
+
+/// Blocks of a received unconfirmed data packet payload.
+pub type UnconfirmedBlocks<'a> = Blocks<'a, UnconfirmedParams>;
+
+/// Describes how received payload blocks are laid out.
+pub trait BlockParams: PacketParams {
+    /// Number of header bytes preceding the data in each block.
+    fn header_bytes() -> usize;
+
+    /// Get the sequence number of the block at the given index and whether its block
+    /// checksum is valid, using the given header and data bytes.
+    fn check(idx: usize, header: &[u8], data: &[u8]) -> (u8, bool);
+
+    /// Total number of bytes in each block.
+    fn coded_bytes() -> usize { Self::header_bytes() + Self::block_bytes() }
+}
+
+impl BlockParams for ConfirmedParams {
+    fn header_bytes() -> usize { 2 }
+
+    fn check(_: usize, header: &[u8], data: &[u8]) -> (u8, bool) {
+        let sn = header[0] >> 1;
+        let checksum = ((header[0] & 1) as u16) << 8 | header[1] as u16;
+
+        let calc = crc::CRC9::new()
+            .feed_bits(sn, 7)
+            .feed_bytes(data.iter().cloned())
+            .finish() as u16;
+
+        (sn, calc == checksum)
+    }
+}
+
+impl BlockParams for UnconfirmedParams {
+    fn header_bytes() -> usize { 0 }
+
+    /// Unconfirmed blocks have no header, so the sequence number is the block's position
+    /// in the payload and there is no block checksum to fail.
+    fn check(idx: usize, _: &[u8], _: &[u8]) -> (u8, bool) { (idx as u8, true) }
+}
+
+/// Order of the bits within each payload byte, as expected by the application.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum BitOrder {
+    /// Bytes are kept as transmitted, with the first bit received as the MSB. This is
+    /// the order defined by the standard.
+    MsbFirst,
+    /// The bits of each byte are reversed, so the first bit received is the LSB.
+    LsbFirst,
+}
+
+impl BitOrder {
+    /// Convert the given received byte into this order.
+    pub fn apply(self, b: u8) -> u8 {
+        match self {
+            BitOrder::MsbFirst => b,
+            BitOrder::LsbFirst => (0..8).fold(0, |r, i| r | (b >> i & 1) << (7 - i)),
+        }
+    }
+}
+
+impl Default for BitOrder {
+    fn default() -> Self { BitOrder::MsbFirst }
+}
+
+/// Iterator over the blocks in a received payload, where the last block is taken to be
+/// the tail block. Any trailing partial block is ignored.
+pub struct Blocks<'a, P: BlockParams> {
+    params: std::marker::PhantomData<P>,
+    /// Raw block bytes, including any block headers.
+    data: &'a [u8],
+    /// Current block index.
+    block: std::ops::Range<usize>,
+    /// Bit order used when extracting payload bytes.
+    order: BitOrder,
+}
+
+impl<'a, P: BlockParams> Blocks<'a, P> {
+    /// Construct a new `Blocks` over the given raw block bytes.
+    pub fn new(data: &'a [u8]) -> Blocks<'a, P> {
+        Blocks {
+            params: std::marker::PhantomData,
+            block: 0..data.len() / P::coded_bytes(),
+            data: data,
+            order: BitOrder::default(),
+        }
+    }
+
+    /// Extract payload bytes from each block in the given bit order.
+    pub fn with_order(mut self, order: BitOrder) -> Self {
+        self.order = order;
+        self
+    }
+}
+
+impl<'a, P: BlockParams> Iterator for Blocks<'a, P> {
+    type Item = Block<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let idx = match self.block.next() {
+            Some(idx) => idx,
+            None => return None,
+        };
+
+        let start = idx * P::coded_bytes();
+        let block = &self.data[start..start + P::coded_bytes()];
+        let (header, data) = block.split_at(P::header_bytes());
+
+        // The tail block only carries `tail_bytes` of data, followed by the packet
+        // checksum.
+        let data = if self.block.len() == 0 {
+            &data[..P::tail_bytes()]
+        } else {
+            data
+        };
+
+        let (seq, valid) = P::check(idx, header, data);
+
+        if !valid {
+            log_debug!("data block {} (seq {}) checksum failed", idx, seq);
+        }
+
+        Some(Block::new(seq, data, valid).with_order(self.order))
+    }
+}
+
+/// A received payload block.
+pub struct Block<'a> {
+    /// Sequence number of the block.
+    seq: u8,
+    /// Data and pad bytes in the block.
+    data: &'a [u8],
+    /// Whether the block checksum was valid.
+    valid: bool,
+    /// Bit order used when extracting payload bytes.
+    order: BitOrder,
+}
+
+impl<'a> Block<'a> {
+    /// Construct a new `Block` from the given sequence number, data, and checksum flag.
+    pub fn new(seq: u8, data: &'a [u8], valid: bool) -> Block<'a> {
+        Block {
+            seq: seq,
+            data: data,
+            valid: valid,
+            order: BitOrder::default(),
+        }
+    }
+
+    /// Extract payload bytes in the given bit order.
+    pub fn with_order(mut self, order: BitOrder) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Sequence number of the block.
+    pub fn seq(&self) -> u8 { self.seq }
+
+    /// Data and pad bytes in the block (not including the packet checksum in the tail
+    /// block.)
+    pub fn data(&self) -> &'a [u8] { self.data }
+
+    /// Whether the block checksum was valid.
+    pub fn crc_valid(&self) -> bool { self.valid }
+
+    /// Data and pad bytes in the block, converted to the configured bit order.
+    pub fn payload(&self) -> Vec<u8> {
+        self.data.iter().map(|&b| self.order.apply(b)).collect()
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use super::Fragments;
+    use super::{Fragments, ConfirmedBlocks, UnconfirmedBlocks, BitOrder};
     use data::params::*;
+    use data::payload::*;
 
     #[test]
     fn test_fragments() {
@@ -85,4 +263,106 @@ mod test {
         assert_eq!(f.next().unwrap(), &[5]);
         assert!(f.next().is_none());
     }
+
+    #[test]
+    fn test_confirmed_blocks() {
+        let bytes = (0..40).collect::<Vec<u8>>();
+        let p = ConfirmedPayload::new(&bytes);
+        assert_eq!(p.blocks(), 3);
+
+        let mut raw = vec![];
+
+        for (sn, block) in p.iter().enumerate() {
+            let (data, pads) = block.build();
+            raw.extend(ConfirmedBlockHeader::new(sn as u8 + 5, data,
+                pads.clone()).build().iter().cloned());
+            raw.extend(data.iter().cloned().chain(pads.map(|_| 0)));
+        }
+
+        let (data, pads, checksum) = p.tail().build();
+        raw.extend(ConfirmedBlockHeader::new(7, data, pads.clone()).build().iter()
+            .cloned());
+        raw.extend(data.iter().cloned().chain(pads.map(|_| 0)));
+        raw.extend(checksum.iter().cloned());
+
+        assert_eq!(raw.len(), 3 * 18);
+
+        {
+            let mut b = ConfirmedBlocks::new(&raw[..]);
+
+            let block = b.next().unwrap();
+            assert_eq!(block.seq(), 5);
+            assert_eq!(block.data(), &bytes[0..16]);
+            assert!(block.crc_valid());
+
+            let block = b.next().unwrap();
+            assert_eq!(block.seq(), 6);
+            assert_eq!(block.data(), &bytes[16..32]);
+            assert!(block.crc_valid());
+
+            let block = b.next().unwrap();
+            assert_eq!(block.seq(), 7);
+            assert_eq!(&block.data()[..8], &bytes[32..40]);
+            assert_eq!(&block.data()[8..], &[0, 0, 0, 0]);
+            assert!(block.crc_valid());
+
+            assert!(b.next().is_none());
+        }
+
+        raw[20] ^= 0b100;
+
+        {
+            let mut b = ConfirmedBlocks::new(&raw[..]);
+            assert!(b.next().unwrap().crc_valid());
+            assert!(!b.next().unwrap().crc_valid());
+            assert!(b.next().unwrap().crc_valid());
+            assert!(b.next().is_none());
+        }
+
+        // Trailing partial block is skipped.
+        assert_eq!(ConfirmedBlocks::new(&raw[..40]).count(), 2);
+    }
+
+    #[test]
+    fn test_unconfirmed_blocks() {
+        let bytes = (0..36).collect::<Vec<u8>>();
+        let mut b = UnconfirmedBlocks::new(&bytes[..]);
+
+        let block = b.next().unwrap();
+        assert_eq!(block.seq(), 0);
+        assert_eq!(block.data(), &bytes[0..12]);
+        assert!(block.crc_valid());
+
+        let block = b.next().unwrap();
+        assert_eq!(block.seq(), 1);
+        assert_eq!(block.data(), &bytes[12..24]);
+
+        let block = b.next().unwrap();
+        assert_eq!(block.seq(), 2);
+        assert_eq!(block.data(), &bytes[24..32]);
+
+        assert!(b.next().is_none());
+    }
+
+    #[test]
+    fn test_bit_order() {
+        assert_eq!(BitOrder::MsbFirst.apply(0b11010010), 0b11010010);
+        assert_eq!(BitOrder::LsbFirst.apply(0b11010010), 0b01001011);
+        assert_eq!(BitOrder::LsbFirst.apply(0x01), 0x80);
+
+        // IPv4 header start.
+        let mut bytes = vec![0x45, 0x00, 0x00, 0x1C, 0xC0, 0xA8, 0x01, 0x01];
+        bytes.extend((0..16).map(|_| 0));
+
+        let msb = UnconfirmedBlocks::new(&bytes[..]).next().unwrap().payload();
+        let lsb = UnconfirmedBlocks::new(&bytes[..]).with_order(BitOrder::LsbFirst)
+            .next().unwrap().payload();
+
+        assert_eq!(&msb[..8], &[0x45, 0x00, 0x00, 0x1C, 0xC0, 0xA8, 0x01, 0x01]);
+        assert_eq!(&lsb[..8], &[0xA2, 0x00, 0x00, 0x38, 0x03, 0x15, 0x80, 0x80]);
+
+        for (&m, &l) in msb.iter().zip(lsb.iter()) {
+            assert_eq!(BitOrder::LsbFirst.apply(l), m);
+        }
+    }
 }
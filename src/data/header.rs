@@ -1,10 +1,25 @@
-//! Header generation for data packets.
+//! Header generation and parsing for data packets.
 //!
 //! A header has several user-filled fields followed by a 16-bit checksum over those
 //! fields.
 
+use std;
+
 use data::crc;
-use data::fields;
+use data::fields::{self, DataPacketOpcode};
+use error::{P25Error, Result};
+use trunking::mfg;
+use util::check_len;
+
+/// Unwrap the given `Option`, or return `None` from the current function if it's empty.
+macro_rules! try_opt {
+    ($e:expr) => {
+        match $e {
+            Some(x) => x,
+            None => return None,
+        }
+    };
+}
 
 /// Packet header block for confirmed data packet.
 pub type ConfirmedHeader = Header<ConfirmedFields>;
@@ -17,11 +32,21 @@ pub trait BufWrite {
     fn write<'a, 'b, T: Iterator<Item = &'a mut u8>>(&self, buf: &'b mut T);
 }
 
+/// Read some bytes from a buffer.
+pub trait BufRead: Sized {
+    fn read<'a, 'b, T: Iterator<Item = &'a u8>>(buf: &'b mut T) -> Option<Self>;
+}
+
 /// Field is only a single byte.
 pub trait ByteField {
     fn byte(&self) -> u8;
 }
 
+/// Field can be parsed from a single byte.
+pub trait FromByte: Sized {
+    fn from_byte(b: u8) -> Option<Self>;
+}
+
 /// Just write the single byte.
 impl<B: ByteField> BufWrite for B {
     fn write<'a, 'b, T: Iterator<Item = &'a mut u8>>(&self, mut buf: &'b mut T) {
@@ -29,7 +54,18 @@ impl<B: ByteField> BufWrite for B {
     }
 }
 
+/// Just read the single byte.
+impl<B: FromByte> BufRead for B {
+    fn read<'a, 'b, T: Iterator<Item = &'a u8>>(buf: &'b mut T) -> Option<Self> {
+        match buf.next() {
+            Some(&b) => B::from_byte(b),
+            None => None,
+        }
+    }
+}
+
 /// Preamble header field.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub struct HeaderPreamble {
     /// Whether the packet requires confirmation.
     pub confirmed: bool,
@@ -46,7 +82,18 @@ impl ByteField for HeaderPreamble {
     }
 }
 
+impl FromByte for HeaderPreamble {
+    fn from_byte(b: u8) -> Option<Self> {
+        DataPacketOpcode::from_bits(b & 0x1F).map(|format| HeaderPreamble {
+            confirmed: b >> 6 & 1 == 1,
+            outbound: b >> 5 & 1 == 1,
+            format: format,
+        })
+    }
+}
+
 /// Preamble for confirmed data packet.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub struct ConfirmedPreamble(HeaderPreamble);
 
 impl ConfirmedPreamble {
@@ -54,7 +101,7 @@ impl ConfirmedPreamble {
         ConfirmedPreamble(HeaderPreamble {
             confirmed: true,
             outbound: outbound,
-            format: fields::DataPacketOpcode::ConfirmedPacket,
+            format: DataPacketOpcode::ConfirmedPacket,
         })
     }
 
@@ -66,7 +113,26 @@ impl ByteField for ConfirmedPreamble {
     fn byte(&self) -> u8 { self.0.byte() }
 }
 
+impl FromByte for ConfirmedPreamble {
+    fn from_byte(b: u8) -> Option<Self> {
+        // The high bit is reserved.
+        if b >> 7 != 0 {
+            return None;
+        }
+
+        match HeaderPreamble::from_byte(b) {
+            Some(p) => if p.confirmed && p.format == DataPacketOpcode::ConfirmedPacket {
+                Some(ConfirmedPreamble(p))
+            } else {
+                None
+            },
+            None => None,
+        }
+    }
+}
+
 /// Preamble for unconfirmed data packet.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub struct UnconfirmedPreamble(HeaderPreamble);
 
 impl UnconfirmedPreamble {
@@ -74,7 +140,7 @@ impl UnconfirmedPreamble {
         UnconfirmedPreamble(HeaderPreamble {
             confirmed: false,
             outbound: outbound,
-            format: fields::DataPacketOpcode::UnconfirmedPacket
+            format: DataPacketOpcode::UnconfirmedPacket
         })
     }
 
@@ -86,7 +152,26 @@ impl ByteField for UnconfirmedPreamble {
     fn byte(&self) -> u8 { self.0.byte() }
 }
 
+impl FromByte for UnconfirmedPreamble {
+    fn from_byte(b: u8) -> Option<Self> {
+        // The high bit is reserved.
+        if b >> 7 != 0 {
+            return None;
+        }
+
+        match HeaderPreamble::from_byte(b) {
+            Some(p) => if !p.confirmed && p.format == DataPacketOpcode::UnconfirmedPacket {
+                Some(UnconfirmedPreamble(p))
+            } else {
+                None
+            },
+            None => None,
+        }
+    }
+}
+
 /// Service access point (SAP) field.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub struct ServiceAccessPoint(pub fields::ServiceAccessPoint);
 
 impl ByteField for ServiceAccessPoint {
@@ -95,14 +180,71 @@ impl ByteField for ServiceAccessPoint {
     }
 }
 
-/// Manufacturer's ID field.
+impl FromByte for ServiceAccessPoint {
+    fn from_byte(b: u8) -> Option<Self> {
+        // The two high bits must be one.
+        if b >> 6 != 0b11 {
+            return None;
+        }
+
+        fields::ServiceAccessPoint::from_bits(b & 0x3F).map(ServiceAccessPoint)
+    }
+}
+
+/// Manufacturer's ID field, which also identifies the manufacturer of nonstandard link
+/// control words, TSBKs, and AMBTs.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub struct Manufacturer(pub u8);
 
+impl Manufacturer {
+    /// Create a new `Manufacturer` with the given manufacturer ID.
+    pub fn from_id(id: u8) -> Manufacturer { Manufacturer(id) }
+
+    /// Manufacturer ID.
+    pub fn id(&self) -> u8 { self.0 }
+
+    /// Whether the ID indicates a standard packet, not specific to any manufacturer.
+    pub fn is_standard(&self) -> bool { mfg::is_standard(self.0) }
+
+    /// Name of the manufacturer assigned the ID, or `None` for a standard or unknown ID.
+    pub fn name(&self) -> Option<&'static str> {
+        match self.0 {
+            0x10 => Some("RELM/BK Radio"),
+            0x38 => Some("Icom"),
+            0x68 => Some("Kenwood"),
+            0x90 => Some("Motorola"),
+            0xA0 => Some("Thales"),
+            0xA4 => Some("Harris"),
+            0xD8 => Some("Tait"),
+            0xF8 => Some("Vertex Standard"),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Manufacturer {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if self.is_standard() {
+            return write!(f, "standard");
+        }
+
+        match self.name() {
+            Some(name) => write!(f, "{}", name),
+            None => write!(f, "unknown ({:#04X})", self.0),
+        }
+    }
+}
+
 impl ByteField for Manufacturer {
     fn byte(&self) -> u8 { self.0 }
 }
 
+impl FromByte for Manufacturer {
+    fn from_byte(b: u8) -> Option<Self> { Some(Manufacturer(b)) }
+}
+
 /// Logical link ID field for addressing source or destination subscriber.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub struct LogicalLink(pub u32);
 
 impl BufWrite for LogicalLink {
@@ -115,7 +257,16 @@ impl BufWrite for LogicalLink {
     }
 }
 
+impl BufRead for LogicalLink {
+    fn read<'a, 'b, T: Iterator<Item = &'a u8>>(buf: &'b mut T) -> Option<Self> {
+        buf.take(3).fold(Some((0, 0)), |acc, &b| {
+            acc.map(|(addr, n)| (addr << 8 | b as u32, n + 1))
+        }).and_then(|(addr, n)| if n == 3 { Some(LogicalLink(addr)) } else { None })
+    }
+}
+
 /// FMF and blocks-to-follow fields.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub struct BlockCount {
     /// Whether the packet is "complete", not being partially retransmitted.
     pub full_pkt: bool,
@@ -130,7 +281,17 @@ impl ByteField for BlockCount {
     }
 }
 
+impl FromByte for BlockCount {
+    fn from_byte(b: u8) -> Option<Self> {
+        Some(BlockCount {
+            full_pkt: b >> 7 == 1,
+            count: b & 0x7F,
+        })
+    }
+}
+
 /// Number of pad bytes at the end of the data.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub struct PadCount(pub u8);
 
 impl ByteField for PadCount {
@@ -140,7 +301,12 @@ impl ByteField for PadCount {
     }
 }
 
+impl FromByte for PadCount {
+    fn from_byte(b: u8) -> Option<Self> { Some(PadCount(b & 0x1F)) }
+}
+
 /// Syn, N(S), and FSNF fields.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub struct Sequencing {
     /// Whether the receiver should resynchronize its sequence numbers using `pkt_seq` and
     /// `frag_seq`.
@@ -160,7 +326,18 @@ impl ByteField for Sequencing {
     }
 }
 
+impl FromByte for Sequencing {
+    fn from_byte(b: u8) -> Option<Self> {
+        Some(Sequencing {
+            resync: b >> 7 == 1,
+            pkt_seq: b >> 4 & 0b111,
+            frag_seq: b & 0xF,
+        })
+    }
+}
+
 /// Byte offset into data payload where data header stops and data information begins.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub struct DataOffset(pub u8);
 
 impl ByteField for DataOffset {
@@ -170,7 +347,31 @@ impl ByteField for DataOffset {
     }
 }
 
+impl DataOffset {
+    /// Number of octets of data header at the start of the reassembled payload.
+    pub fn octets(&self) -> usize { self.0 as usize }
+
+    /// Check the offset against a reassembled payload of the given number of octets
+    /// (excluding pad octets), returning the offset if it points within the payload and
+    /// `Err(DataOffsetOutOfRange)` otherwise.
+    pub fn validate(&self, len: usize) -> Result<usize> {
+        if self.octets() <= len {
+            Ok(self.octets())
+        } else {
+            Err(P25Error::DataOffsetOutOfRange)
+        }
+    }
+}
+
+impl FromByte for DataOffset {
+    fn from_byte(b: u8) -> Option<Self> {
+        // The two high bits are reserved.
+        if b >> 6 == 0 { Some(DataOffset(b)) } else { None }
+    }
+}
+
 /// Header fields for confirmed packet.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub struct ConfirmedFields {
     pub preamble: ConfirmedPreamble,
     pub sap: ServiceAccessPoint,
@@ -195,7 +396,32 @@ impl BufWrite for ConfirmedFields {
     }
 }
 
+impl BufRead for ConfirmedFields {
+    fn read<'a, 'b, T: Iterator<Item = &'a u8>>(buf: &'b mut T) -> Option<Self> {
+        let preamble = try_opt!(ConfirmedPreamble::read(buf));
+        let sap = try_opt!(ServiceAccessPoint::read(buf));
+        let mfg = try_opt!(Manufacturer::read(buf));
+        let addr = try_opt!(LogicalLink::read(buf));
+        let blocks = try_opt!(BlockCount::read(buf));
+        let pads = try_opt!(PadCount::read(buf));
+        let seq = try_opt!(Sequencing::read(buf));
+        let data_offset = try_opt!(DataOffset::read(buf));
+
+        Some(ConfirmedFields {
+            preamble: preamble,
+            sap: sap,
+            mfg: mfg,
+            addr: addr,
+            blocks: blocks,
+            pads: pads,
+            seq: seq,
+            data_offset: data_offset,
+        })
+    }
+}
+
 /// Header fields for unconfirmed packet.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub struct UnconfirmedFields {
     pub preamble: UnconfirmedPreamble,
     pub sap: ServiceAccessPoint,
@@ -219,6 +445,35 @@ impl BufWrite for UnconfirmedFields {
     }
 }
 
+impl BufRead for UnconfirmedFields {
+    fn read<'a, 'b, T: Iterator<Item = &'a u8>>(buf: &'b mut T) -> Option<Self> {
+        let preamble = try_opt!(UnconfirmedPreamble::read(buf));
+        let sap = try_opt!(ServiceAccessPoint::read(buf));
+        let mfg = try_opt!(Manufacturer::read(buf));
+        let addr = try_opt!(LogicalLink::read(buf));
+        let blocks = try_opt!(BlockCount::read(buf));
+        let pads = try_opt!(PadCount::read(buf));
+
+        // The reserved byte must be zero.
+        match buf.next() {
+            Some(&0) => {},
+            _ => return None,
+        }
+
+        let data_offset = try_opt!(DataOffset::read(buf));
+
+        Some(UnconfirmedFields {
+            preamble: preamble,
+            sap: sap,
+            mfg: mfg,
+            addr: addr,
+            blocks: blocks,
+            pads: pads,
+            data_offset: data_offset,
+        })
+    }
+}
+
 /// Builds a checksummed header based on the given fields.
 pub struct Header<F: BufWrite>(F);
 
@@ -228,23 +483,43 @@ impl<F: BufWrite> Header<F> {
         Header(fields)
     }
 
+    /// Get the wrapped header fields.
+    pub fn fields(&self) -> &F { &self.0 }
+
     /// Get the fields and checksum that make up the header, in that order.
     pub fn build(self) -> ([u8; 10], [u8; 2]) {
-        let fields = self.fields();
-        let checksum = self.checksum(&fields);
+        let mut buf = [0; 12];
+        self.write(&mut buf);
+
+        let mut fields = [0; 10];
+        let mut checksum = [0; 2];
+
+        fields.copy_from_slice(&buf[..10]);
+        checksum.copy_from_slice(&buf[10..]);
 
         (fields, checksum)
     }
 
+    /// Write the 10 field bytes followed by the 2 checksum bytes into the given buffer,
+    /// which must have room for at least 12 bytes.
+    pub fn write(&self, out: &mut [u8]) {
+        assert!(out.len() >= 12);
+
+        let fields = self.field_bytes();
+
+        out[..10].copy_from_slice(&fields);
+        out[10..12].copy_from_slice(&Self::checksum(&fields));
+    }
+
     /// Build a byte buffer from the header fields.
-    fn fields(&self) -> [u8; 10] {
+    fn field_bytes(&self) -> [u8; 10] {
         let mut buf = [0; 10];
         self.0.write(&mut buf.iter_mut());
         buf
     }
 
     /// Calculate the checksum of the header fields.
-    fn checksum(&self, fields: &[u8]) -> [u8; 2] {
+    fn checksum(fields: &[u8]) -> [u8; 2] {
         assert!(fields.len() == 10);
 
         let checksum = crc::CRC16::new()
@@ -255,6 +530,167 @@ impl<F: BufWrite> Header<F> {
     }
 }
 
+impl<F: BufWrite + BufRead> Header<F> {
+    /// Parse a header from the given 10 field bytes followed by 2 checksum bytes.
+    /// Return an error if the buffer is too short, the checksum doesn't match, any
+    /// reserved or must-be-one bit has the wrong value, or any field is invalid.
+    pub fn parse(buf: &[u8]) -> Result<Header<F>> {
+        if let Err(e) = check_len(buf, 12) {
+            return Err(e);
+        }
+
+        if Self::checksum(&buf[..10]) != buf[10..12] {
+            return Err(P25Error::HeaderCrcMismatch);
+        }
+
+        F::read(&mut buf[..10].iter()).map(Header).ok_or(P25Error::InvalidHeader)
+    }
+}
+
+/// Extended address fields carried at the start of the first data block of a confirmed
+/// packet whose header SAP is `ExtendedAddressing`.
+///
+/// These bytes are covered by the data block and packet checksums rather than the header
+/// checksum, and the block that carries them is included in the header block count.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ExtendedAddress {
+    /// Destination service of the packet, in place of the header SAP.
+    pub sap: ServiceAccessPoint,
+    /// Source subscriber of the packet.
+    pub src: LogicalLink,
+}
+
+impl ExtendedAddress {
+    /// Number of bytes taken by the fields at the start of the first data block.
+    pub fn size() -> usize { 4 }
+}
+
+impl BufWrite for ExtendedAddress {
+    fn write<'a, 'b, T: Iterator<Item = &'a mut u8>>(&self, mut buf: &'b mut T) {
+        self.sap.write(buf);
+        self.src.write(buf);
+    }
+}
+
+impl BufRead for ExtendedAddress {
+    fn read<'a, 'b, T: Iterator<Item = &'a u8>>(buf: &'b mut T) -> Option<Self> {
+        let sap = try_opt!(ServiceAccessPoint::read(buf));
+        let src = try_opt!(LogicalLink::read(buf));
+
+        Some(ExtendedAddress {
+            sap: sap,
+            src: src,
+        })
+    }
+}
+
+/// Header of either a confirmed or unconfirmed packet.
+pub enum DataHeader {
+    Confirmed(ConfirmedHeader),
+    Unconfirmed(UnconfirmedHeader),
+    /// Confirmed packet with extended addressing, along with the address fields from the
+    /// first data block.
+    Extended(ConfirmedHeader, ExtendedAddress),
+}
+
+impl DataHeader {
+    /// Destination service of the packet.
+    pub fn sap(&self) -> fields::ServiceAccessPoint {
+        match *self {
+            DataHeader::Confirmed(ref h) => h.fields().sap.0,
+            DataHeader::Unconfirmed(ref h) => h.fields().sap.0,
+            DataHeader::Extended(_, ref e) => e.sap.0,
+        }
+    }
+
+    /// Number of data blocks following the header, including the block carrying any
+    /// extended address fields.
+    pub fn blocks(&self) -> BlockCount {
+        match *self {
+            DataHeader::Confirmed(ref h) => h.fields().blocks,
+            DataHeader::Unconfirmed(ref h) => h.fields().blocks,
+            DataHeader::Extended(ref h, _) => h.fields().blocks,
+        }
+    }
+
+    /// Subscriber the packet is addressed to, or sent from for inbound packets without
+    /// extended addressing.
+    pub fn dest(&self) -> LogicalLink {
+        match *self {
+            DataHeader::Confirmed(ref h) => h.fields().addr,
+            DataHeader::Unconfirmed(ref h) => h.fields().addr,
+            DataHeader::Extended(ref h, _) => h.fields().addr,
+        }
+    }
+
+    /// Source subscriber of the packet, if carried by extended address fields.
+    pub fn src(&self) -> Option<LogicalLink> {
+        match *self {
+            DataHeader::Extended(_, ref e) => Some(e.src),
+            _ => None,
+        }
+    }
+
+    /// Number of bytes at the start of the first data block that precede the user data.
+    pub fn payload_offset(&self) -> usize {
+        match *self {
+            DataHeader::Extended(..) => ExtendedAddress::size(),
+            _ => 0,
+        }
+    }
+}
+
+/// Parse the given header block of 10 field bytes followed by 2 checksum bytes,
+/// determining the packet type from the preamble. Return an error if the buffer is too
+/// short, the packet format is reserved, the packet type isn't confirmed or
+/// unconfirmed, the checksum doesn't match, or any field is invalid.
+pub fn parse(buf: &[u8]) -> Result<DataHeader> {
+    if let Err(e) = check_len(buf, 12) {
+        return Err(e);
+    }
+
+    let preamble = match HeaderPreamble::from_byte(buf[0]) {
+        Some(p) => p,
+        None => return Err(P25Error::Reserved),
+    };
+
+    match (preamble.confirmed, preamble.format) {
+        (true, DataPacketOpcode::ConfirmedPacket) =>
+            ConfirmedHeader::parse(buf).map(DataHeader::Confirmed),
+        (false, DataPacketOpcode::UnconfirmedPacket) =>
+            UnconfirmedHeader::parse(buf).map(DataHeader::Unconfirmed),
+        _ => Err(P25Error::InvalidHeader),
+    }
+}
+
+/// Parse the given header block along with the data bytes of the first data block,
+/// additionally reading the extended address fields from the data block if the header is
+/// for a confirmed packet with extended addressing. Return an error if the header is
+/// invalid as for `parse` or the extended address fields are truncated or invalid.
+pub fn parse_extended(buf: &[u8], first: &[u8]) -> Result<DataHeader> {
+    match parse(buf) {
+        Ok(DataHeader::Confirmed(h)) => {
+            if h.fields().sap.0 != fields::ServiceAccessPoint::ExtendedAddressing {
+                return Ok(DataHeader::Confirmed(h));
+            }
+
+            // The extended fields occupy the first data block, so there must be one.
+            if h.fields().blocks.count == 0 {
+                return Err(P25Error::InvalidHeader);
+            }
+
+            if let Err(e) = check_len(first, ExtendedAddress::size()) {
+                return Err(e);
+            }
+
+            ExtendedAddress::read(&mut first.iter())
+                .map(|e| DataHeader::Extended(h, e))
+                .ok_or(P25Error::InvalidHeader)
+        },
+        h => h,
+    }
+}
+
 /// Convert the given Boolean to a single bit.
 fn bool_to_bit(b: bool) -> u8 {
     if b { 1 } else { 0 }
@@ -283,6 +719,19 @@ mod test {
     fn test_mfg() {
         let m = Manufacturer(0b11011011);
         assert_eq!(m.byte(), 0b11011011);
+
+        assert_eq!(Manufacturer::from_id(0x90).name(), Some("Motorola"));
+        assert_eq!(Manufacturer::from_id(0xA4).name(), Some("Harris"));
+        assert_eq!(Manufacturer::from_id(0xD8).id(), 0xD8);
+        assert_eq!(Manufacturer::from_id(0x00).name(), None);
+        assert!(Manufacturer::from_id(0x00).is_standard());
+        assert!(Manufacturer::from_id(0x01).is_standard());
+        assert!(!Manufacturer::from_id(0x90).is_standard());
+        assert_eq!(Manufacturer::from_id(0x42).name(), None);
+
+        assert_eq!(Manufacturer::from_id(0x90).to_string(), "Motorola");
+        assert_eq!(Manufacturer::from_id(0x01).to_string(), "standard");
+        assert_eq!(Manufacturer::from_id(0x42).to_string(), "unknown (0x42)");
     }
 
     #[test]
@@ -323,6 +772,16 @@ mod test {
     fn test_do() {
         let d = DataOffset(11);
         assert_eq!(d.byte(), 0b00001011);
+        assert_eq!(d.octets(), 11);
+    }
+
+    #[test]
+    fn test_do_octets() {
+        assert_eq!(DataOffset(0).validate(0), Ok(0));
+        assert_eq!(DataOffset(11).validate(32), Ok(11));
+        assert_eq!(DataOffset(11).validate(11), Ok(11));
+        assert_eq!(DataOffset(11).validate(10), Err(P25Error::DataOffsetOutOfRange));
+        assert_eq!(DataOffset(63).validate(0), Err(P25Error::DataOffsetOutOfRange));
     }
 
     #[test]
@@ -439,4 +898,237 @@ mod test {
     fn test_do_validate() {
         DataOffset(0b11111111).byte();
     }
+
+    #[test]
+    fn test_confirmed_roundtrip() {
+        let fields = ConfirmedFields {
+            preamble: ConfirmedPreamble::inbound(),
+            sap: ServiceAccessPoint(fields::ServiceAccessPoint::PacketData),
+            mfg: Manufacturer(0x90),
+            addr: LogicalLink(0xABCDEF),
+            blocks: BlockCount {
+                full_pkt: true,
+                count: 3,
+            },
+            pads: PadCount(17),
+            seq: Sequencing {
+                resync: true,
+                pkt_seq: 3,
+                frag_seq: 9,
+            },
+            data_offset: DataOffset(42),
+        };
+
+        let mut buf = [0; 12];
+        ConfirmedHeader::new(fields).write(&mut buf);
+
+        assert_eq!(buf[0] >> 7, 0);
+        assert_eq!(buf[1] >> 6, 0b11);
+
+        let h = ConfirmedHeader::parse(&buf).unwrap();
+        assert_eq!(*h.fields(), fields);
+        assert_eq!(h.build(), ConfirmedHeader::new(fields).build());
+
+        // Bad checksum.
+        buf[4] ^= 1;
+        assert_eq!(ConfirmedHeader::parse(&buf).err(), Some(P25Error::HeaderCrcMismatch));
+        buf[4] ^= 1;
+
+        // Wrong packet type.
+        assert_eq!(UnconfirmedHeader::parse(&buf).err(), Some(P25Error::InvalidHeader));
+        assert_eq!(ConfirmedHeader::parse(&buf[..11]).err(),
+                   Some(P25Error::TooShort { needed: 12, got: 11 }));
+
+        // Reserved and must-be-one bits with the wrong value, under a valid checksum.
+        for &(idx, bit) in [(0, 7), (1, 7), (1, 6), (9, 7), (9, 6)].iter() {
+            let mut bad = buf;
+            bad[idx] ^= 1 << bit;
+
+            let crc = ConfirmedHeader::checksum(&bad[..10]);
+            bad[10..].copy_from_slice(&crc);
+
+            assert_eq!(ConfirmedHeader::parse(&bad).err(), Some(P25Error::InvalidHeader));
+        }
+    }
+
+    #[test]
+    fn test_unconfirmed_roundtrip() {
+        let fields = UnconfirmedFields {
+            preamble: UnconfirmedPreamble::outbound(),
+            sap: ServiceAccessPoint(fields::ServiceAccessPoint::TrunkingControl),
+            mfg: Manufacturer(0x00),
+            addr: LogicalLink(0x123456),
+            blocks: BlockCount {
+                full_pkt: false,
+                count: 127,
+            },
+            pads: PadCount(0),
+            data_offset: DataOffset(63),
+        };
+
+        let mut buf = [0xFF; 12];
+        UnconfirmedHeader::new(fields).write(&mut buf);
+
+        assert_eq!(buf[0] >> 7, 0);
+        assert_eq!(buf[1] >> 6, 0b11);
+        assert_eq!(buf[8], 0);
+
+        let h = UnconfirmedHeader::parse(&buf).unwrap();
+        assert_eq!(*h.fields(), fields);
+        assert!(ConfirmedHeader::parse(&buf).is_err());
+
+        // Nonzero reserved byte under a valid checksum.
+        buf[8] = 0x01;
+        let crc = UnconfirmedHeader::checksum(&buf[..10]);
+        buf[10..].copy_from_slice(&crc);
+        assert_eq!(UnconfirmedHeader::parse(&buf).err(), Some(P25Error::InvalidHeader));
+    }
+
+    #[test]
+    fn test_parse() {
+        let mut buf = [0; 12];
+
+        ConfirmedHeader::new(ConfirmedFields {
+            preamble: ConfirmedPreamble::outbound(),
+            sap: ServiceAccessPoint(fields::ServiceAccessPoint::PacketData),
+            mfg: Manufacturer(0x12),
+            addr: LogicalLink(0x342134),
+            blocks: BlockCount {
+                full_pkt: true,
+                count: 5,
+            },
+            pads: PadCount(3),
+            seq: Sequencing {
+                resync: false,
+                pkt_seq: 5,
+                frag_seq: 2,
+            },
+            data_offset: DataOffset(0),
+        }).write(&mut buf);
+
+        let h = parse(&buf).unwrap();
+        assert_eq!(h.sap(), fields::ServiceAccessPoint::PacketData);
+        assert_eq!(h.blocks().count, 5);
+
+        match h {
+            DataHeader::Confirmed(h) => assert_eq!(h.fields().mfg, Manufacturer(0x12)),
+            _ => panic!(),
+        }
+
+        UnconfirmedHeader::new(UnconfirmedFields {
+            preamble: UnconfirmedPreamble::inbound(),
+            sap: ServiceAccessPoint(fields::ServiceAccessPoint::SNDCPControl),
+            mfg: Manufacturer(0x00),
+            addr: LogicalLink(0x000001),
+            blocks: BlockCount {
+                full_pkt: true,
+                count: 2,
+            },
+            pads: PadCount(8),
+            data_offset: DataOffset(0),
+        }).write(&mut buf);
+
+        let h = parse(&buf).unwrap();
+        assert_eq!(h.sap(), fields::ServiceAccessPoint::SNDCPControl);
+        assert_eq!(h.blocks().count, 2);
+
+        match h {
+            DataHeader::Unconfirmed(h) => assert_eq!(h.fields().pads, PadCount(8)),
+            _ => panic!(),
+        }
+
+        // Reserved SAP, with a valid checksum.
+        buf[1] = 0b11000111;
+        let checksum = crc::CRC16::new()
+            .feed_bytes(buf[..10].iter().cloned())
+            .finish();
+        buf[10] = (checksum >> 8) as u8;
+        buf[11] = checksum as u8;
+        assert_eq!(UnconfirmedHeader::parse(&buf).err(), Some(P25Error::InvalidHeader));
+        assert_eq!(parse(&buf).err(), Some(P25Error::InvalidHeader));
+
+        assert_eq!(parse(&[]).err(), Some(P25Error::TooShort { needed: 12, got: 0 }));
+        assert_eq!(parse(&buf[..11]).err(),
+                   Some(P25Error::TooShort { needed: 12, got: 11 }));
+        assert_eq!(parse(&[0b00000011; 12]).err(), Some(P25Error::InvalidHeader));
+        assert_eq!(parse(&[0b00000001; 12]).err(), Some(P25Error::Reserved));
+    }
+
+    #[test]
+    fn test_parse_extended() {
+        let mut buf = [0; 12];
+        let mut hdr = ConfirmedFields {
+            preamble: ConfirmedPreamble::outbound(),
+            sap: ServiceAccessPoint(fields::ServiceAccessPoint::ExtendedAddressing),
+            mfg: Manufacturer(0x00),
+            addr: LogicalLink(0x123456),
+            blocks: BlockCount {
+                full_pkt: true,
+                count: 2,
+            },
+            pads: PadCount(4),
+            seq: Sequencing {
+                resync: false,
+                pkt_seq: 1,
+                frag_seq: 0,
+            },
+            data_offset: DataOffset(0),
+        };
+
+        ConfirmedHeader::new(hdr).write(&mut buf);
+
+        let mut first = [0xAA; 16];
+        ExtendedAddress {
+            sap: ServiceAccessPoint(fields::ServiceAccessPoint::PacketData),
+            src: LogicalLink(0xABCDEF),
+        }.write(&mut first.iter_mut());
+
+        assert_eq!(&first[..5], &[0b11000100, 0xAB, 0xCD, 0xEF, 0xAA]);
+
+        let h = parse_extended(&buf, &first).unwrap();
+        assert_eq!(h.sap(), fields::ServiceAccessPoint::PacketData);
+        assert_eq!(h.dest(), LogicalLink(0x123456));
+        assert_eq!(h.src(), Some(LogicalLink(0xABCDEF)));
+        assert_eq!(h.blocks().count, 2);
+        assert_eq!(h.payload_offset(), 4);
+
+        match h {
+            DataHeader::Extended(h, e) => {
+                assert_eq!(h.fields().pads, PadCount(4));
+                assert_eq!(e.src, LogicalLink(0xABCDEF));
+            },
+            _ => panic!(),
+        }
+
+        // Header alone doesn't include the extended fields.
+        assert_eq!(parse(&buf).unwrap().sap(),
+                   fields::ServiceAccessPoint::ExtendedAddressing);
+
+        // Truncated or invalid extended fields.
+        assert_eq!(parse_extended(&buf, &first[..3]).err(),
+                   Some(P25Error::TooShort { needed: 4, got: 3 }));
+        first[0] = 0b11000111;
+        assert_eq!(parse_extended(&buf, &first).err(), Some(P25Error::InvalidHeader));
+
+        // Extended addressing without any data blocks.
+        hdr.blocks.count = 0;
+        ConfirmedHeader::new(hdr).write(&mut buf);
+        assert_eq!(parse_extended(&buf, &first).err(), Some(P25Error::InvalidHeader));
+        assert!(parse_extended(&buf[..11], &first).is_err());
+
+        // Standard header through the same entry point.
+        hdr.sap = ServiceAccessPoint(fields::ServiceAccessPoint::PacketData);
+        ConfirmedHeader::new(hdr).write(&mut buf);
+
+        let h = parse_extended(&buf, &first).unwrap();
+        assert_eq!(h.sap(), fields::ServiceAccessPoint::PacketData);
+        assert_eq!(h.dest(), LogicalLink(0x123456));
+        assert_eq!(h.src(), None);
+        assert_eq!(h.payload_offset(), 0);
+
+        match h {
+            DataHeader::Confirmed(_) => {},
+            _ => panic!(),
+        }
+    }
 }
@@ -5,6 +5,255 @@ use std;
 use bits;
 use consts;
 
+/// Errors in a custom interleave table.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum InterleaveError {
+    /// The entry at the given position points outside the table.
+    OutOfRange(usize),
+    /// The entry at the given position repeats an earlier index.
+    Duplicate(usize),
+}
+
+/// Check that the given interleave table is a permutation of `0..table.len()`.
+pub fn validate(table: &[usize]) -> Result<(), InterleaveError> {
+    let mut seen = vec![false; table.len()];
+
+    for (pos, &idx) in table.iter().enumerate() {
+        match seen.get_mut(idx) {
+            Some(&mut true) => return Err(InterleaveError::Duplicate(pos)),
+            Some(s) => *s = true,
+            None => return Err(InterleaveError::OutOfRange(pos)),
+        }
+    }
+
+    Ok(())
+}
+
+/// Create the table that undoes the given interleave table, which must be valid.
+pub fn invert(table: &[usize]) -> Vec<usize> {
+    assert!(validate(table).is_ok());
+
+    let mut inv = vec![0; table.len()];
+
+    for (pos, &idx) in table.iter().enumerate() {
+        inv[idx] = pos;
+    }
+
+    inv
+}
+
+/// Get the table used to interleave each coded block of a data packet, which gives the
+/// position in the coded block of each transmitted dibit.
+///
+/// This is the table given in the standard, so other implementations can be checked
+/// against it.
+pub fn interleave_pattern() -> &'static [usize] { &INTERLEAVE[..] }
+
+/// Position in the input block of each interleaved dibit, in transmitted order.
+static INTERLEAVE: [usize; consts::CODING_DIBITS] = [
+    0,
+    1,
+    8,
+    9,
+    16,
+    17,
+    24,
+    25,
+    32,
+    33,
+    40,
+    41,
+    48,
+    49,
+    56,
+    57,
+    64,
+    65,
+    72,
+    73,
+    80,
+    81,
+    88,
+    89,
+    96,
+    97,
+    2,
+    3,
+    10,
+    11,
+    18,
+    19,
+    26,
+    27,
+    34,
+    35,
+    42,
+    43,
+    50,
+    51,
+    58,
+    59,
+    66,
+    67,
+    74,
+    75,
+    82,
+    83,
+    90,
+    91,
+    4,
+    5,
+    12,
+    13,
+    20,
+    21,
+    28,
+    29,
+    36,
+    37,
+    44,
+    45,
+    52,
+    53,
+    60,
+    61,
+    68,
+    69,
+    76,
+    77,
+    84,
+    85,
+    92,
+    93,
+    6,
+    7,
+    14,
+    15,
+    22,
+    23,
+    30,
+    31,
+    38,
+    39,
+    46,
+    47,
+    54,
+    55,
+    62,
+    63,
+    70,
+    71,
+    78,
+    79,
+    86,
+    87,
+    94,
+    95,
+];
+
+/// Position in the interleaved block of each deinterleaved dibit, the inverse of
+/// `INTERLEAVE`.
+static DEINTERLEAVE: [usize; consts::CODING_DIBITS] = [
+    0,
+    1,
+    26,
+    27,
+    50,
+    51,
+    74,
+    75,
+    2,
+    3,
+    28,
+    29,
+    52,
+    53,
+    76,
+    77,
+    4,
+    5,
+    30,
+    31,
+    54,
+    55,
+    78,
+    79,
+    6,
+    7,
+    32,
+    33,
+    56,
+    57,
+    80,
+    81,
+    8,
+    9,
+    34,
+    35,
+    58,
+    59,
+    82,
+    83,
+    10,
+    11,
+    36,
+    37,
+    60,
+    61,
+    84,
+    85,
+    12,
+    13,
+    38,
+    39,
+    62,
+    63,
+    86,
+    87,
+    14,
+    15,
+    40,
+    41,
+    64,
+    65,
+    88,
+    89,
+    16,
+    17,
+    42,
+    43,
+    66,
+    67,
+    90,
+    91,
+    18,
+    19,
+    44,
+    45,
+    68,
+    69,
+    92,
+    93,
+    20,
+    21,
+    46,
+    47,
+    70,
+    71,
+    94,
+    95,
+    22,
+    23,
+    48,
+    49,
+    72,
+    73,
+    96,
+    97,
+    24,
+    25,
+];
+
 trait Redirector {
     /// Redirector the given index to another within the buffer.
     fn redirect(idx: usize) -> usize;
@@ -15,108 +264,7 @@ struct InterleaveRedirector;
 
 impl Redirector for InterleaveRedirector {
     fn redirect(idx: usize) -> usize {
-        const REDIRECTS: [usize; consts::CODING_DIBITS] = [
-            0,
-            1,
-            8,
-            9,
-            16,
-            17,
-            24,
-            25,
-            32,
-            33,
-            40,
-            41,
-            48,
-            49,
-            56,
-            57,
-            64,
-            65,
-            72,
-            73,
-            80,
-            81,
-            88,
-            89,
-            96,
-            97,
-            2,
-            3,
-            10,
-            11,
-            18,
-            19,
-            26,
-            27,
-            34,
-            35,
-            42,
-            43,
-            50,
-            51,
-            58,
-            59,
-            66,
-            67,
-            74,
-            75,
-            82,
-            83,
-            90,
-            91,
-            4,
-            5,
-            12,
-            13,
-            20,
-            21,
-            28,
-            29,
-            36,
-            37,
-            44,
-            45,
-            52,
-            53,
-            60,
-            61,
-            68,
-            69,
-            76,
-            77,
-            84,
-            85,
-            92,
-            93,
-            6,
-            7,
-            14,
-            15,
-            22,
-            23,
-            30,
-            31,
-            38,
-            39,
-            46,
-            47,
-            54,
-            55,
-            62,
-            63,
-            70,
-            71,
-            78,
-            79,
-            86,
-            87,
-            94,
-            95,
-        ];
-
-        REDIRECTS[idx]
+        INTERLEAVE[idx]
     }
 }
 
@@ -125,108 +273,7 @@ struct DeinterleaveRedirector;
 
 impl Redirector for DeinterleaveRedirector {
     fn redirect(idx: usize) -> usize {
-        const REDIRECTS: [usize; consts::CODING_DIBITS] = [
-            0,
-            1,
-            26,
-            27,
-            50,
-            51,
-            74,
-            75,
-            2,
-            3,
-            28,
-            29,
-            52,
-            53,
-            76,
-            77,
-            4,
-            5,
-            30,
-            31,
-            54,
-            55,
-            78,
-            79,
-            6,
-            7,
-            32,
-            33,
-            56,
-            57,
-            80,
-            81,
-            8,
-            9,
-            34,
-            35,
-            58,
-            59,
-            82,
-            83,
-            10,
-            11,
-            36,
-            37,
-            60,
-            61,
-            84,
-            85,
-            12,
-            13,
-            38,
-            39,
-            62,
-            63,
-            86,
-            87,
-            14,
-            15,
-            40,
-            41,
-            64,
-            65,
-            88,
-            89,
-            16,
-            17,
-            42,
-            43,
-            66,
-            67,
-            90,
-            91,
-            18,
-            19,
-            44,
-            45,
-            68,
-            69,
-            92,
-            93,
-            20,
-            21,
-            46,
-            47,
-            70,
-            71,
-            94,
-            95,
-            22,
-            23,
-            48,
-            49,
-            72,
-            73,
-            96,
-            97,
-            24,
-            25,
-        ];
-
-        REDIRECTS[idx]
+        DEINTERLEAVE[idx]
     }
 }
 
@@ -294,6 +341,61 @@ impl<'a> Iterator for Deinterleaver<'a> {
     }
 }
 
+/// Deinterleaves a stream of received dibits block by block, so each block of a
+/// multi-block packet can be decoded as soon as it arrives rather than after the whole
+/// packet is buffered.
+pub struct StreamDeinterleaver {
+    /// Position in the received block of each deinterleaved dibit.
+    table: Vec<usize>,
+    /// Dibits received so far in the current block.
+    block: Vec<bits::Dibit>,
+    /// Most recent deinterleaved block.
+    out: Vec<bits::Dibit>,
+}
+
+impl StreamDeinterleaver {
+    /// Create a new `StreamDeinterleaver` using the standard data packet interleaving.
+    pub fn new() -> StreamDeinterleaver {
+        Self::build(Indexes::<DeinterleaveRedirector>::new().collect())
+    }
+
+    /// Create a new `StreamDeinterleaver` using the given block-local table, which gives
+    /// the position in the received block of each deinterleaved dibit. Return an error
+    /// if the table isn't a valid permutation.
+    pub fn with_table(table: Vec<usize>) -> Result<StreamDeinterleaver, InterleaveError> {
+        validate(&table[..]).map(|_| Self::build(table))
+    }
+
+    fn build(table: Vec<usize>) -> StreamDeinterleaver {
+        StreamDeinterleaver {
+            block: Vec::with_capacity(table.len()),
+            out: vec![bits::Dibit::default(); table.len()],
+            table: table,
+        }
+    }
+
+    /// Feed in a received dibit. Return the deinterleaved block if the dibit completed
+    /// one, and `None` otherwise.
+    pub fn feed(&mut self, dibit: bits::Dibit) -> Option<&[bits::Dibit]> {
+        self.block.push(dibit);
+
+        if self.block.len() < self.table.len() {
+            return None;
+        }
+
+        for (out, &idx) in self.out.iter_mut().zip(self.table.iter()) {
+            *out = self.block[idx];
+        }
+
+        self.block.clear();
+
+        Some(&self.out[..])
+    }
+
+    /// Discard any partially received block.
+    pub fn reset(&mut self) { self.block.clear(); }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -360,4 +462,115 @@ mod test {
 
         assert!(deint.next().is_none());
     }
+
+    #[test]
+    fn test_validate() {
+        let int = Indexes::<InterleaveRedirector>::new().collect::<Vec<usize>>();
+        let deint = Indexes::<DeinterleaveRedirector>::new().collect::<Vec<usize>>();
+
+        assert_eq!(validate(&int[..]), Ok(()));
+        assert_eq!(validate(&deint[..]), Ok(()));
+        assert_eq!(validate(&[]), Ok(()));
+        assert_eq!(validate(&[2, 0, 1]), Ok(()));
+        assert_eq!(validate(&[2, 0, 2]), Err(InterleaveError::Duplicate(2)));
+        assert_eq!(validate(&[0, 3, 1]), Err(InterleaveError::OutOfRange(1)));
+    }
+
+    #[test]
+    fn test_stream() {
+        // Pseudorandom 3-block packet.
+        let mut state = 0xACE1u16;
+        let pkt: Vec<Dibit> = (0..consts::CODING_DIBITS * 3).map(|_| {
+            state = state >> 1 ^ (state & 1).wrapping_neg() & 0xB400;
+            Dibit::new(state as u8 & 0b11)
+        }).collect();
+
+        let oneshot: Vec<Dibit> = pkt.chunks(consts::CODING_DIBITS).flat_map(|chunk| {
+            let mut block = [Dibit::default(); consts::CODING_DIBITS];
+            block.copy_from_slice(chunk);
+            Deinterleaver::new(&block).collect::<Vec<Dibit>>()
+        }).collect();
+
+        let mut s = StreamDeinterleaver::new();
+        let mut streamed = vec![];
+
+        for (i, &d) in pkt.iter().enumerate() {
+            match s.feed(d) {
+                Some(block) => {
+                    assert_eq!((i + 1) % consts::CODING_DIBITS, 0);
+                    streamed.extend_from_slice(block);
+                },
+                None => assert!((i + 1) % consts::CODING_DIBITS != 0),
+            }
+        }
+
+        assert_eq!(streamed, oneshot);
+
+        // The inverted table interleaves instead.
+        let deint = Indexes::<DeinterleaveRedirector>::new().collect::<Vec<usize>>();
+        let mut s = StreamDeinterleaver::with_table(invert(&deint[..])).unwrap();
+        let mut block = [Dibit::default(); consts::CODING_DIBITS];
+        block.copy_from_slice(&pkt[..consts::CODING_DIBITS]);
+
+        let out = block.iter().filter_map(|&d| s.feed(d).map(|b| b.to_vec()))
+            .next().unwrap();
+        assert_eq!(out, Interleaver::new(block).collect::<Vec<Dibit>>());
+
+        // Partial blocks are dropped on reset.
+        let mut s = StreamDeinterleaver::with_table(vec![1, 0]).unwrap();
+        assert!(s.feed(Dibit::new(0b01)).is_none());
+        s.reset();
+        assert!(s.feed(Dibit::new(0b10)).is_none());
+        assert_eq!(s.feed(Dibit::new(0b11)).unwrap(),
+                   &[Dibit::new(0b11), Dibit::new(0b10)]);
+
+        assert!(StreamDeinterleaver::with_table(vec![0, 0]).is_err());
+    }
+
+    #[test]
+    fn test_invert() {
+        let int = Indexes::<InterleaveRedirector>::new().collect::<Vec<usize>>();
+        let deint = Indexes::<DeinterleaveRedirector>::new().collect::<Vec<usize>>();
+
+        assert_eq!(invert(&int[..]), deint);
+        assert_eq!(invert(&invert(&int[..])[..]), int);
+        assert_eq!(invert(&[2, 0, 1]), vec![1, 2, 0]);
+    }
+
+    #[test]
+    fn test_interleave_pattern() {
+        // Output index of each input 4-bit symbol (dibit pair), as listed in the
+        // standard's data interleave table.
+        const SPEC: [usize; 49] = [
+            0, 13, 25, 37, 1, 14, 26, 38, 2, 15, 27, 39, 3, 16, 28, 40, 4, 17, 29, 41,
+            5, 18, 30, 42, 6, 19, 31, 43, 7, 20, 32, 44, 8, 21, 33, 45, 9, 22, 34, 46,
+            10, 23, 35, 47, 11, 24, 36, 48, 12,
+        ];
+
+        for (sym, &out) in SPEC.iter().enumerate() {
+            assert_eq!(interleave_pattern()[out * 2], sym * 2);
+            assert_eq!(interleave_pattern()[out * 2 + 1], sym * 2 + 1);
+        }
+
+        assert!(validate(interleave_pattern()).is_ok());
+
+        // The first 16 input bits are sent as bits 0-3, 52-55, 100-103, and 148-151.
+        assert_eq!(&invert(interleave_pattern())[..8], &[0, 1, 26, 27, 50, 51, 74, 75]);
+
+        let mut buf = [Dibit::default(); 98];
+
+        for i in 0..98 {
+            buf[i] = Dibit::new((i * 7 + i / 5) as u8 % 4);
+        }
+
+        let out = Interleaver::new(buf).collect::<Vec<Dibit>>();
+
+        for (sym, &pos) in SPEC.iter().enumerate() {
+            assert_eq!(&out[pos * 2..pos * 2 + 2], &buf[sym * 2..sym * 2 + 2]);
+        }
+
+        for (i, &p) in interleave_pattern().iter().enumerate() {
+            assert_eq!(DEINTERLEAVE[p], i);
+        }
+    }
 }
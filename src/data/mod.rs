@@ -1,18 +1,31 @@
 //! Implements Project 25's data packet specification.
 
+pub mod ambt;
+pub mod channel;
 pub mod coder;
 pub mod crc;
 pub mod fields;
 pub mod fragment;
+#[macro_use]
 pub mod header;
 pub mod interleave;
 pub mod packet;
 pub mod params;
 pub mod payload;
+pub mod randomizer;
+pub mod response;
+pub mod sndcp;
 
-pub use self::fragment::{ConfirmedFragments, UnconfirmedFragments};
+pub use self::fragment::{
+    BitOrder,
+    ConfirmedFragments,
+    UnconfirmedFragments,
+    ConfirmedBlocks,
+    UnconfirmedBlocks,
+};
 
 pub use self::header::{
+    DataHeader,
     ConfirmedHeader,
     ConfirmedFields,
     ConfirmedPreamble,
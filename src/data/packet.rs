@@ -1,7 +1,20 @@
 //! Generate data packets.
 
+use collect_slice::CollectSlice;
+
 use bits;
+use coding::trellis::{self, States};
+use consts::{CODING_DIBITS, TSBK_DIBITS};
 use data::{self, payload, coder, interleave};
+use data::fields::DataPacketOpcode;
+use data::fragment::BlockParams;
+use data::header::{FromByte, HeaderPreamble};
+use data::params::{PacketParams, ConfirmedParams, UnconfirmedParams};
+use data::header::{LogicalLink, Manufacturer};
+use data::response::{self, NakReason, ResponseFields, ResponseHeader, ResponseKind};
+use data::response::{ResponsePreamble, ResponseStatus};
+use error::{P25Error, Result};
+use util::check_len;
 
 /// Construct a confirmed data packet with the given header, payload blocks, and serial
 /// number generator. The returned value is the coded, interleaved set of dibit symbols
@@ -95,3 +108,453 @@ pub fn unconfirmed(header: data::UnconfirmedHeader, payload: data::UnconfirmedPa
 
     pkt
 }
+
+/// Size of a data packet carrying a given payload.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct PacketPlan {
+    /// Number of data blocks following the header, including the tail block.
+    pub blocks: usize,
+    /// Number of pad bytes added to fill out the data blocks.
+    pub pads: usize,
+    /// Total number of coded dibit symbols in the header and data blocks.
+    pub symbols: usize,
+}
+
+/// Calculate the size of a confirmed or unconfirmed data packet carrying the given
+/// number of payload bytes, which must fit in a single packet.
+pub fn plan(payload_len: usize, confirmed: bool) -> PacketPlan {
+    let (blocks, pads) = if confirmed {
+        assert!(payload_len <= ConfirmedParams::packet_bytes());
+        (ConfirmedParams::blocks(payload_len), ConfirmedParams::pads(payload_len))
+    } else {
+        assert!(payload_len <= UnconfirmedParams::packet_bytes());
+        (UnconfirmedParams::blocks(payload_len), UnconfirmedParams::pads(payload_len))
+    };
+
+    PacketPlan {
+        blocks: blocks,
+        pads: pads,
+        // Every block, including the header, is coded into the same number of symbols.
+        symbols: (blocks + 1) * CODING_DIBITS,
+    }
+}
+
+/// Construct an inbound response to the confirmed packet with the given sequence number
+/// from the given unit, given whether each block of the packet was received.
+///
+/// If every block was received, the response acknowledges the whole packet. Otherwise,
+/// it requests retransmission of the missing blocks.
+pub fn build_ack(llid: u32, seq: u8, received: &[bool]) -> Vec<bits::Dibit> {
+    if received.iter().all(|&r| r) {
+        return response(llid, seq, ResponseKind::Ack, None);
+    }
+
+    let flags = response::build_flags(received);
+    response(llid, seq, ResponseKind::SelectiveRetry, Some(flags))
+}
+
+/// Construct an inbound response rejecting the confirmed packet with the given sequence
+/// number from the given unit for the given reason.
+pub fn build_nak(llid: u32, seq: u8, reason: NakReason) -> Vec<bits::Dibit> {
+    response(llid, seq, ResponseKind::Nak(reason), None)
+}
+
+/// Response packet decoded by `parse_response`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Response {
+    /// Fields of the response header.
+    pub fields: ResponseFields,
+    /// For a selective retry, whether each block of the original packet was received.
+    pub received: Option<Vec<bool>>,
+}
+
+/// Decode the given coded response packet, where `blocks` is the number of blocks in the
+/// confirmed packet being responded to.
+///
+/// The header block is decoded first, and its packet type must be a response. For a
+/// selective retry, the following block is decoded into the received flags. Return an
+/// error if the packet is too short or isn't a response, either block can't be decoded,
+/// or either checksum doesn't match.
+pub fn parse_response(dibits: &[bits::Dibit], blocks: usize) -> Result<Response> {
+    if let Err(e) = check_len(dibits, CODING_DIBITS) {
+        return Err(e);
+    }
+
+    let header = match decode_block(dibits[..CODING_DIBITS].iter().cloned()) {
+        Some(h) => h,
+        None => return Err(P25Error::ViterbiUnrecoverable),
+    };
+
+    match HeaderPreamble::from_byte(header[0]) {
+        Some(p) => if p.format != DataPacketOpcode::ResponsePacket {
+            return Err(P25Error::InvalidHeader);
+        },
+        None => return Err(P25Error::Reserved),
+    }
+
+    // Header checksum is validated by the parse.
+    let fields = match ResponseHeader::parse(&header) {
+        Ok(h) => *h.fields(),
+        Err(e) => return Err(e),
+    };
+
+    if fields.status.kind != ResponseKind::SelectiveRetry {
+        return Ok(Response {
+            fields: fields,
+            received: None,
+        });
+    }
+
+    if fields.blocks == 0 {
+        return Err(P25Error::InvalidHeader);
+    }
+
+    if let Err(e) = check_len(dibits, CODING_DIBITS * 2) {
+        return Err(e);
+    }
+
+    let mut block = [bits::Dibit::default(); CODING_DIBITS];
+    block.copy_from_slice(&dibits[CODING_DIBITS..CODING_DIBITS * 2]);
+
+    let flags = match decode_block(interleave::Deinterleaver::new(&block)) {
+        Some(f) => f,
+        None => return Err(P25Error::ViterbiUnrecoverable),
+    };
+
+    response::parse_flags(&flags, blocks).map(|received| Response {
+        fields: fields,
+        received: Some(received),
+    })
+}
+
+/// Check whether the given coded, interleaved confirmed data block passes its block
+/// checksum as received, without any error correction. If `tail` is set, the block is
+/// taken to be the tail block, whose checksum doesn't cover the packet checksum.
+///
+/// The block is deinterleaved and each dibit pair is mapped straight back through the
+/// 3/4-rate trellis, then the CRC-9 is computed over the resulting bytes. This is much
+/// cheaper than the full Viterbi decode, so on a clean channel a block that passes can
+/// skip it. Return `false` if the block isn't `CODING_DIBITS` long, any dibit pair isn't
+/// a valid trellis transition, or the checksum doesn't match, in which case the block
+/// should go through the full decode.
+pub fn quick_check(block: &[bits::Dibit], tail: bool) -> bool {
+    if block.len() != CODING_DIBITS {
+        return false;
+    }
+
+    let mut coded = [bits::Dibit::default(); CODING_DIBITS];
+    coded.copy_from_slice(block);
+
+    let mut dibits = interleave::Deinterleaver::new(&coded);
+    let mut tribits = [bits::Tribit::default(); CODING_DIBITS / 2];
+    let mut state = 0;
+
+    for t in tribits.iter_mut() {
+        let pair = (dibits.next().unwrap(), dibits.next().unwrap());
+
+        let next = (0..8).find(|&next| trellis::TribitStates::pair(state, next) == pair);
+
+        state = match next {
+            Some(next) => next,
+            None => return false,
+        };
+
+        *t = bits::Tribit::new(state as u8);
+    }
+
+    let mut bytes = [0; 18];
+    bits::TribitBytes::new(tribits[..tribits.len() - 1].iter().cloned())
+        .collect_slice_checked(&mut bytes[..]);
+
+    let (header, data) = bytes.split_at(ConfirmedParams::header_bytes());
+
+    let data = if tail {
+        &data[..ConfirmedParams::tail_bytes()]
+    } else {
+        data
+    };
+
+    ConfirmedParams::check(0, header, data).1
+}
+
+/// Decode the given 1/2-rate coded block into its 12 bytes.
+fn decode_block<T: Iterator<Item = bits::Dibit>>(dibits: T) -> Option<[u8; 12]> {
+    let mut decoded = [bits::Dibit::default(); TSBK_DIBITS];
+
+    let count = trellis::DibitDecoder::new(dibits)
+        .filter_map(|x| x.ok())
+        .collect_slice_exhaust(&mut decoded[..]);
+
+    if count != decoded.len() {
+        return None;
+    }
+
+    let mut bytes = [0; 12];
+    bits::DibitBytes::new(decoded.iter().cloned()).collect_slice_checked(&mut bytes[..]);
+
+    Some(bytes)
+}
+
+/// Construct a response packet with the given type and optional selective retry block.
+fn response(llid: u32, seq: u8, kind: ResponseKind, flags: Option<[u8; 12]>)
+    -> Vec<bits::Dibit>
+{
+    let mut pkt = vec![];
+
+    pkt.extend({
+        let (fields, checksum) = ResponseHeader::new(ResponseFields {
+            preamble: ResponsePreamble::inbound(),
+            status: ResponseStatus {
+                kind: kind,
+                seq: seq,
+            },
+            mfg: Manufacturer(0),
+            addr: LogicalLink(llid),
+            blocks: if flags.is_some() { 1 } else { 0 },
+            src: LogicalLink(0),
+        }).build();
+
+        coder::DibitCoder::new()
+            .feed_bytes(fields.iter().cloned())
+            .feed_bytes(checksum.iter().cloned())
+            .finish()
+            .iter().cloned()
+    });
+
+    if let Some(flags) = flags {
+        pkt.extend(interleave::Interleaver::new(coder::DibitCoder::new()
+            .feed_bytes(flags.iter().cloned())
+            .finish()));
+    }
+
+    pkt
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bits::{Dibit, DibitBytes};
+    use coding::trellis;
+    use consts::CODING_DIBITS;
+    use data::header::*;
+    use data::response::parse_flags;
+
+    /// Decode the given coded block into bytes.
+    fn decode<T: Iterator<Item = Dibit>>(dibits: T) -> [u8; 12] {
+        let dibits = trellis::DibitDecoder::new(dibits)
+            .map(|d| d.unwrap())
+            .collect::<Vec<_>>();
+
+        let mut bytes = [0; 12];
+
+        for (b, x) in bytes.iter_mut().zip(DibitBytes::new(dibits.into_iter())) {
+            *b = x;
+        }
+
+        bytes
+    }
+
+    #[test]
+    fn test_build_ack() {
+        let received = [true, true, false, true, false, true];
+        let pkt = build_ack(0x123456, 4, &received);
+
+        assert_eq!(pkt.len(), CODING_DIBITS * 2);
+
+        let header = ResponseHeader::parse(&decode(pkt[..CODING_DIBITS].iter().cloned()))
+            .unwrap();
+        let fields = header.fields();
+
+        assert_eq!(fields.status, ResponseStatus {
+            kind: ResponseKind::SelectiveRetry,
+            seq: 4,
+        });
+        assert_eq!(fields.addr, LogicalLink(0x123456));
+        assert_eq!(fields.blocks, 1);
+
+        let mut block = [Dibit::default(); CODING_DIBITS];
+        block.copy_from_slice(&pkt[CODING_DIBITS..]);
+        let flags = decode(interleave::Deinterleaver::new(&block));
+
+        assert_eq!(parse_flags(&flags, received.len()).unwrap(), received.to_vec());
+
+        // Everything received.
+        let pkt = build_ack(0x123456, 4, &[true; 6]);
+        assert_eq!(pkt.len(), CODING_DIBITS);
+
+        let header = ResponseHeader::parse(&decode(pkt.into_iter())).unwrap();
+        assert_eq!(header.fields().status.kind, ResponseKind::Ack);
+        assert_eq!(header.fields().blocks, 0);
+    }
+
+    #[test]
+    fn test_parse_response() {
+        let received = [true, false, true, true, false, true, true, true, true];
+        let pkt = build_ack(0xABCDEF, 3, &received);
+
+        let resp = parse_response(&pkt, received.len()).unwrap();
+        assert_eq!(resp.fields.status, ResponseStatus {
+            kind: ResponseKind::SelectiveRetry,
+            seq: 3,
+        });
+        assert_eq!(resp.fields.addr, LogicalLink(0xABCDEF));
+        assert_eq!(resp.received, Some(received.to_vec()));
+
+        // Missing flag block.
+        assert_eq!(parse_response(&pkt[..CODING_DIBITS], received.len()).err(),
+                   Some(P25Error::TooShort {
+                       needed: CODING_DIBITS * 2,
+                       got: CODING_DIBITS,
+                   }));
+
+        let resp = parse_response(&build_ack(0x000001, 5, &[true; 4]), 4).unwrap();
+        assert_eq!(resp.fields.status.kind, ResponseKind::Ack);
+        assert_eq!(resp.fields.status.seq, 5);
+        assert_eq!(resp.received, None);
+
+        let resp = parse_response(&build_nak(0x000001, 2, NakReason::PacketCrc), 4)
+            .unwrap();
+        assert_eq!(resp.fields.status.kind, ResponseKind::Nak(NakReason::PacketCrc));
+
+        // Corrupted header checksum.
+        let (fields, mut checksum) = ResponseHeader::new(resp.fields).build();
+        checksum[1] ^= 1;
+
+        let pkt = coder::DibitCoder::new()
+            .feed_bytes(fields.iter().cloned())
+            .feed_bytes(checksum.iter().cloned())
+            .finish();
+
+        assert_eq!(parse_response(&pkt, 4).err(), Some(P25Error::HeaderCrcMismatch));
+
+        // Data packet header isn't a response.
+        let pkt = confirmed(data::ConfirmedHeader::new(ConfirmedFields {
+            preamble: ConfirmedPreamble::outbound(),
+            sap: ServiceAccessPoint(data::fields::ServiceAccessPoint::PacketData),
+            mfg: Manufacturer(0),
+            addr: LogicalLink(0x123456),
+            blocks: BlockCount {
+                full_pkt: true,
+                count: 1,
+            },
+            pads: PadCount(0),
+            seq: Sequencing {
+                resync: false,
+                pkt_seq: 0,
+                frag_seq: 0,
+            },
+            data_offset: DataOffset(0),
+        }), data::ConfirmedPayload::new(&[0; 12]), 0..);
+
+        assert_eq!(parse_response(&pkt, 1).err(), Some(P25Error::InvalidHeader));
+        assert_eq!(parse_response(&[], 1).err(),
+                   Some(P25Error::TooShort { needed: CODING_DIBITS, got: 0 }));
+        assert_eq!(parse_response(&pkt[..CODING_DIBITS - 1], 1).err(),
+                   Some(P25Error::TooShort {
+                       needed: CODING_DIBITS,
+                       got: CODING_DIBITS - 1,
+                   }));
+    }
+
+    #[test]
+    fn test_quick_check() {
+        let bytes = (0..40).collect::<Vec<u8>>();
+        let payload = data::ConfirmedPayload::new(&bytes);
+
+        let header = data::ConfirmedHeader::new(ConfirmedFields {
+            preamble: ConfirmedPreamble::outbound(),
+            sap: ServiceAccessPoint(data::fields::ServiceAccessPoint::PacketData),
+            mfg: Manufacturer(0),
+            addr: LogicalLink(0x123456),
+            blocks: BlockCount { full_pkt: true, count: payload.blocks() as u8 },
+            pads: PadCount(payload.pads() as u8),
+            seq: Sequencing { resync: false, pkt_seq: 0, frag_seq: 0 },
+            data_offset: DataOffset(0),
+        });
+
+        let pkt = confirmed(header, payload, 0..127);
+
+        let blocks = pkt[CODING_DIBITS..].chunks(CODING_DIBITS).collect::<Vec<_>>();
+        assert_eq!(blocks.len(), 3);
+
+        assert!(quick_check(blocks[0], false));
+        assert!(quick_check(blocks[1], false));
+        assert!(quick_check(blocks[2], true));
+        assert!(!quick_check(blocks[2], false));
+
+        let mut block = pkt[CODING_DIBITS..CODING_DIBITS * 2].to_vec();
+
+        for idx in 0..CODING_DIBITS {
+            let orig = block[idx];
+            block[idx] = Dibit::new(orig.bits() ^ 0b01);
+            assert!(!quick_check(&block, false));
+            block[idx] = orig;
+        }
+
+        assert!(quick_check(&block, false));
+        assert!(!quick_check(&block[1..], false));
+        assert!(!quick_check(&[], false));
+    }
+
+    #[test]
+    fn test_plan() {
+        for &len in [0, 1, 8, 12, 13, 28, 29, 100, 2028].iter() {
+            let bytes = vec![0xA5; len];
+            let p = plan(len, true);
+            let payload = data::ConfirmedPayload::new(&bytes);
+
+            assert_eq!(p.blocks, payload.blocks());
+            assert_eq!(p.pads, payload.pads());
+
+            let header = data::ConfirmedHeader::new(ConfirmedFields {
+                preamble: ConfirmedPreamble::outbound(),
+                sap: ServiceAccessPoint(data::fields::ServiceAccessPoint::PacketData),
+                mfg: Manufacturer(0),
+                addr: LogicalLink(0x123456),
+                blocks: BlockCount { full_pkt: true, count: p.blocks as u8 },
+                pads: PadCount(p.pads as u8),
+                seq: Sequencing { resync: false, pkt_seq: 0, frag_seq: 0 },
+                data_offset: DataOffset(0),
+            });
+
+            let pkt = confirmed(header, payload, 0..127);
+            assert_eq!(pkt.len(), p.symbols);
+
+            // Every payload byte and pad fills the blocks exactly.
+            assert_eq!(len + p.pads + 4, (p.blocks * 16));
+        }
+
+        for &len in [0, 1, 8, 9, 20, 21, 100, 1520].iter() {
+            let bytes = vec![0xA5; len];
+            let p = plan(len, false);
+            let payload = data::UnconfirmedPayload::new(&bytes);
+
+            assert_eq!(p.blocks, payload.blocks());
+            assert_eq!(p.pads, payload.pads());
+
+            let header = data::UnconfirmedHeader::new(UnconfirmedFields {
+                preamble: UnconfirmedPreamble::outbound(),
+                sap: ServiceAccessPoint(data::fields::ServiceAccessPoint::PacketData),
+                mfg: Manufacturer(0),
+                addr: LogicalLink(0x123456),
+                blocks: BlockCount { full_pkt: true, count: p.blocks as u8 },
+                pads: PadCount(p.pads as u8),
+                data_offset: DataOffset(0),
+            });
+
+            let pkt = unconfirmed(header, payload);
+            assert_eq!(pkt.len(), p.symbols);
+            assert_eq!(len + p.pads + 4, (p.blocks * 12));
+        }
+    }
+
+    #[test]
+    fn test_build_nak() {
+        let pkt = build_nak(0xABCDEF, 1, NakReason::MemoryFull);
+        let header = ResponseHeader::parse(&decode(pkt.into_iter())).unwrap();
+
+        assert_eq!(header.fields().status.kind, ResponseKind::Nak(NakReason::MemoryFull));
+        assert_eq!(header.fields().status.seq, 1);
+        assert_eq!(header.fields().addr, LogicalLink(0xABCDEF));
+    }
+}
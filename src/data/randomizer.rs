@@ -0,0 +1,66 @@
+//! Raw output of the P25 pseudo-random (PN) generator, for building test vectors and
+//! checking the alignment of scrambled data.
+//!
+//! P25 FDMA has no randomizer separate from the one that scrambles voice frames, so this
+//! exposes that generator: each state is computed as `p_n = 173 p_{n-1} + 13849 mod
+//! 2^16`, and the output bit is the MSB of the state.
+
+use voice::rand::PseudoRand;
+
+/// Generate the first `len_bits` bits of the PN sequence starting from a zero seed,
+/// packed MSB first into bytes. The last byte is padded with zeros if `len_bits` isn't
+/// a multiple of 8.
+pub fn sequence(len_bits: usize) -> Vec<u8> {
+    generate(PseudoRand::new(0), len_bits)
+}
+
+/// Generate the first `len_bits` bits of the PN sequence in the same way as `sequence`,
+/// starting from the given 12-bit seed, such as the `u_0` chunk that seeds the
+/// scrambling of a voice frame. Return `None` if the seed is wider than 12 bits.
+pub fn seeded_sequence(init: u16, len_bits: usize) -> Option<Vec<u8>> {
+    if init >> 12 != 0 {
+        return None;
+    }
+
+    Some(generate(PseudoRand::new(init), len_bits))
+}
+
+/// Pack the first `len_bits` bits output by the given generator MSB first into bytes.
+fn generate(mut prand: PseudoRand, len_bits: usize) -> Vec<u8> {
+    let mut buf = vec![0; (len_bits + 7) / 8];
+
+    for i in 0..len_bits {
+        buf[i / 8] |= (prand.advance() as u8) << (7 - i % 8);
+    }
+
+    buf
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sequence() {
+        // The worked state sequence for seed 0xABC in the voice PN generator tests
+        // starts 18137, 5822, 38015, 36844, 30869, 45770, 2203, 1752, whose MSBs give
+        // the first byte.
+        assert_eq!(seeded_sequence(0xABC, 8), Some(vec![0b00110100]));
+
+        assert_eq!(seeded_sequence(0xABC, 128), Some(vec![
+            0x34, 0xC6, 0xC2, 0x81, 0x63, 0xF0, 0x87, 0xC7,
+            0x51, 0x4C, 0xED, 0x79, 0x9B, 0xA7, 0x7C, 0x88,
+        ]));
+
+        // From a zero state, the first states are 13849, 50430, 21951, 10284, whose MSBs
+        // are 0, 1, 0, 0.
+        assert_eq!(sequence(128), vec![
+            0x42, 0xCC, 0x47, 0x12, 0xD9, 0x5A, 0xB4, 0xFD,
+            0x50, 0xE8, 0x75, 0xFC, 0x98, 0xA1, 0xE6, 0x45,
+        ]);
+
+        assert_eq!(seeded_sequence(0xABC, 15), Some(vec![0x34, 0xC6]));
+        assert_eq!(sequence(0), vec![]);
+        assert_eq!(seeded_sequence(0x1000, 8), None);
+    }
+}
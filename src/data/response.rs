@@ -0,0 +1,303 @@
+//! Response packets sent by the recipient of a confirmed data packet.
+//!
+//! A response is a header block that either acknowledges the whole packet, rejects it
+//! with a reason, or requests selective retransmission. A selective retry response is
+//! followed by a data block with a flag for each block of the original packet, set if
+//! that block was received, followed by a 32-bit CRC over the flags.
+
+use data::crc;
+use data::fields::DataPacketOpcode;
+use data::header::{BufRead, BufWrite, ByteField, FromByte, Header, HeaderPreamble};
+use data::header::{LogicalLink, Manufacturer};
+use error::{Result, P25Error};
+
+/// Packet header block for a response packet.
+pub type ResponseHeader = Header<ResponseFields>;
+
+/// Number of flag bytes in a selective retry block.
+const FLAG_BYTES: usize = 8;
+
+/// Maximum number of blocks that can be flagged in a selective retry block.
+pub const MAX_FLAGS: usize = FLAG_BYTES * 8;
+
+/// Reason a confirmed packet was rejected.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum NakReason {
+    /// Packet had an illegal format.
+    IllegalFormat,
+    /// Packet failed its CRC check.
+    PacketCrc,
+    /// Recipient had no memory available for the packet.
+    MemoryFull,
+    /// Fragment sequence number was out of sequence.
+    FsnSequence,
+    /// Packet couldn't be delivered.
+    Undeliverable,
+    /// Packet sequence number was out of sequence.
+    NsSequence,
+    /// Recipient isn't a valid user.
+    InvalidUser,
+}
+
+impl NakReason {
+    /// Convert the reason to its 3-bit type field.
+    pub fn to_bits(self) -> u8 {
+        use self::NakReason::*;
+
+        match self {
+            IllegalFormat => 0,
+            PacketCrc => 1,
+            MemoryFull => 2,
+            FsnSequence => 3,
+            Undeliverable => 4,
+            NsSequence => 5,
+            InvalidUser => 6,
+        }
+    }
+
+    /// Parse a reason from the given 3-bit type field.
+    pub fn from_bits(bits: u8) -> Option<NakReason> {
+        use self::NakReason::*;
+
+        match bits {
+            0 => Some(IllegalFormat),
+            1 => Some(PacketCrc),
+            2 => Some(MemoryFull),
+            3 => Some(FsnSequence),
+            4 => Some(Undeliverable),
+            5 => Some(NsSequence),
+            6 => Some(InvalidUser),
+            _ => None,
+        }
+    }
+}
+
+/// Type of response.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ResponseKind {
+    /// All blocks of the packet were received.
+    Ack,
+    /// The packet was rejected.
+    Nak(NakReason),
+    /// Only the blocks not flagged in the following block need to be retransmitted.
+    SelectiveRetry,
+}
+
+/// Class, type, and status fields.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ResponseStatus {
+    /// Type of response.
+    pub kind: ResponseKind,
+    /// Sequence number, N(S), of the packet being responded to.
+    pub seq: u8,
+}
+
+impl ByteField for ResponseStatus {
+    fn byte(&self) -> u8 {
+        assert!(self.seq >> 3 == 0);
+
+        let (class, kind) = match self.kind {
+            ResponseKind::Ack => (0b00, 0b001),
+            ResponseKind::Nak(r) => (0b01, r.to_bits()),
+            ResponseKind::SelectiveRetry => (0b10, 0b000),
+        };
+
+        class << 6 | kind << 3 | self.seq
+    }
+}
+
+impl FromByte for ResponseStatus {
+    fn from_byte(b: u8) -> Option<Self> {
+        let kind = match (b >> 6, b >> 3 & 0b111) {
+            (0b00, 0b001) => ResponseKind::Ack,
+            (0b01, r) => ResponseKind::Nak(match NakReason::from_bits(r) {
+                Some(r) => r,
+                None => return None,
+            }),
+            (0b10, 0b000) => ResponseKind::SelectiveRetry,
+            _ => return None,
+        };
+
+        Some(ResponseStatus {
+            kind: kind,
+            seq: b & 0b111,
+        })
+    }
+}
+
+/// Preamble for response packet.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ResponsePreamble(HeaderPreamble);
+
+impl ResponsePreamble {
+    fn new(outbound: bool) -> ResponsePreamble {
+        ResponsePreamble(HeaderPreamble {
+            confirmed: false,
+            outbound: outbound,
+            format: DataPacketOpcode::ResponsePacket,
+        })
+    }
+
+    pub fn outbound() -> Self { Self::new(true) }
+    pub fn inbound() -> Self { Self::new(false) }
+}
+
+impl ByteField for ResponsePreamble {
+    fn byte(&self) -> u8 { self.0.byte() }
+}
+
+impl FromByte for ResponsePreamble {
+    fn from_byte(b: u8) -> Option<Self> {
+        match HeaderPreamble::from_byte(b) {
+            Some(p) => if !p.confirmed && p.format == DataPacketOpcode::ResponsePacket {
+                Some(ResponsePreamble(p))
+            } else {
+                None
+            },
+            None => None,
+        }
+    }
+}
+
+/// Header fields for response packet.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ResponseFields {
+    pub preamble: ResponsePreamble,
+    pub status: ResponseStatus,
+    pub mfg: Manufacturer,
+    /// Address of the unit the response is for.
+    pub addr: LogicalLink,
+    /// Number of blocks following the header.
+    pub blocks: u8,
+    /// Address of the unit sending the response.
+    pub src: LogicalLink,
+}
+
+impl BufWrite for ResponseFields {
+    fn write<'a, 'b, T: Iterator<Item = &'a mut u8>>(&self, mut buf: &'b mut T) {
+        assert!(self.blocks >> 7 == 0);
+
+        self.preamble.write(buf);
+        self.status.write(buf);
+        self.mfg.write(buf);
+        self.addr.write(buf);
+        *buf.next().unwrap() = self.blocks;
+        self.src.write(buf);
+    }
+}
+
+impl BufRead for ResponseFields {
+    fn read<'a, 'b, T: Iterator<Item = &'a u8>>(buf: &'b mut T) -> Option<Self> {
+        let preamble = try_opt!(ResponsePreamble::read(buf));
+        let status = try_opt!(ResponseStatus::read(buf));
+        let mfg = try_opt!(Manufacturer::read(buf));
+        let addr = try_opt!(LogicalLink::read(buf));
+        let blocks = try_opt!(buf.next()) & 0x7F;
+        let src = try_opt!(LogicalLink::read(buf));
+
+        Some(ResponseFields {
+            preamble: preamble,
+            status: status,
+            mfg: mfg,
+            addr: addr,
+            blocks: blocks,
+            src: src,
+        })
+    }
+}
+
+/// Build the selective retry block for the given received flags, one for each block of
+/// the original packet, returning the 8 flag bytes followed by the 4 checksum bytes.
+///
+/// Flags are packed MSB first, so block 0 is the MSB of the first byte.
+pub fn build_flags(received: &[bool]) -> [u8; 12] {
+    assert!(received.len() <= MAX_FLAGS);
+
+    let mut buf = [0; 12];
+
+    for (idx, _) in received.iter().enumerate().filter(|&(_, &r)| r) {
+        buf[idx / 8] |= 0x80 >> (idx % 8);
+    }
+
+    let checksum = crc::crc32(&buf[..FLAG_BYTES]);
+
+    buf[8] = (checksum >> 24) as u8;
+    buf[9] = (checksum >> 16) as u8;
+    buf[10] = (checksum >> 8) as u8;
+    buf[11] = checksum as u8;
+
+    buf
+}
+
+/// Parse the received flags for the given number of blocks from the given selective
+/// retry block. Return an error if the checksum doesn't match.
+pub fn parse_flags(buf: &[u8; 12], blocks: usize) -> Result<Vec<bool>> {
+    assert!(blocks <= MAX_FLAGS);
+
+    let checksum = (buf[8] as u32) << 24 | (buf[9] as u32) << 16 |
+        (buf[10] as u32) << 8 | buf[11] as u32;
+
+    if crc::crc32(&buf[..FLAG_BYTES]) != checksum {
+        return Err(P25Error::DataCrcMismatch);
+    }
+
+    Ok((0..blocks).map(|idx| buf[idx / 8] & 0x80 >> (idx % 8) != 0).collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_status() {
+        let s = ResponseStatus { kind: ResponseKind::Ack, seq: 5 };
+        assert_eq!(s.byte(), 0b00001101);
+        assert_eq!(ResponseStatus::from_byte(s.byte()), Some(s));
+
+        let s = ResponseStatus { kind: ResponseKind::Nak(NakReason::PacketCrc), seq: 2 };
+        assert_eq!(s.byte(), 0b01001010);
+        assert_eq!(ResponseStatus::from_byte(s.byte()), Some(s));
+
+        let s = ResponseStatus { kind: ResponseKind::SelectiveRetry, seq: 7 };
+        assert_eq!(s.byte(), 0b10000111);
+        assert_eq!(ResponseStatus::from_byte(s.byte()), Some(s));
+
+        assert_eq!(ResponseStatus::from_byte(0b11000000), None);
+        assert_eq!(ResponseStatus::from_byte(0b01111000), None);
+    }
+
+    #[test]
+    fn test_header() {
+        let f = ResponseFields {
+            preamble: ResponsePreamble::inbound(),
+            status: ResponseStatus { kind: ResponseKind::SelectiveRetry, seq: 3 },
+            mfg: Manufacturer(0),
+            addr: LogicalLink(0x123456),
+            blocks: 1,
+            src: LogicalLink(0xABCDEF),
+        };
+
+        let (fields, checksum) = ResponseHeader::new(f).build();
+        assert_eq!(fields[0], 0b00000011);
+
+        let mut buf = [0; 12];
+        buf[..10].copy_from_slice(&fields);
+        buf[10..].copy_from_slice(&checksum);
+
+        assert_eq!(ResponseHeader::parse(&buf).unwrap().fields(), &f);
+    }
+
+    #[test]
+    fn test_flags() {
+        let received = [true, false, true, true, false, false, false, false, false, true];
+        let buf = build_flags(&received);
+
+        assert_eq!(buf[0], 0b10110000);
+        assert_eq!(buf[1], 0b01000000);
+        assert_eq!(parse_flags(&buf, received.len()).unwrap(), received.to_vec());
+
+        let mut bad = buf;
+        bad[2] ^= 1;
+        assert_eq!(parse_flags(&bad, received.len()), Err(P25Error::DataCrcMismatch));
+    }
+}
@@ -0,0 +1,229 @@
+//! Decode Subnetwork Dependent Convergence Protocol (SNDCP) context management PDUs
+//! carried in single data blocks.
+//!
+//! These blocks use the same interleaving, 1/2-rate trellis code, and CRC as a data
+//! packet header: each 98-dibit block decodes to 10 bytes of fields followed by a 16-bit
+//! checksum. The first byte of every PDU holds the PDU type and the Network Service
+//! Access Point Identifier (NSAPI), which identifies the data context the PDU applies
+//! to.
+
+use collect_slice::CollectSlice;
+
+use bits::{Dibit, DibitBytes};
+use coding::trellis;
+use consts::{CODING_DIBITS, TSBK_DIBITS, TSBK_BYTES};
+use data::{crc, interleave};
+use error::{Result, P25Error};
+use util::{slice_u16, slice_u32};
+
+/// Buffer of bytes that represents an SNDCP block.
+pub type Buf = [u8; TSBK_BYTES];
+
+/// Deinterleave and decode the given block of dibits into an SNDCP block.
+pub fn decode(dibits: &[Dibit; CODING_DIBITS]) -> Result<SndcpBlock> {
+    let mut decoded = [Dibit::default(); TSBK_DIBITS];
+
+    let count = trellis::DibitDecoder::new(interleave::Deinterleaver::new(dibits))
+        .filter_map(|x| x.ok())
+        .collect_slice_exhaust(&mut decoded[..]);
+
+    if count != decoded.len() {
+        return Err(P25Error::ViterbiUnrecoverable);
+    }
+
+    let mut bytes = [0; TSBK_BYTES];
+    DibitBytes::new(decoded.iter().cloned()).collect_slice_checked(&mut bytes[..]);
+
+    Ok(SndcpBlock::new(bytes))
+}
+
+/// Type of an SNDCP PDU.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "ser", derive(Serialize, Deserialize))]
+pub enum SndcpPduType {
+    ActivateRequest,
+    ActivateAccept,
+    ActivateReject,
+    DeactivateRequest,
+    DeactivateAccept,
+    ConfirmedData,
+    UnconfirmedData,
+}
+
+impl SndcpPduType {
+    /// Try to parse a PDU type from the given 4 bits, where the meaning of some types
+    /// depends on whether the PDU is outbound (from the FNE) or inbound.
+    pub fn from_bits(bits: u8, outbound: bool) -> Option<SndcpPduType> {
+        use self::SndcpPduType::*;
+
+        assert!(bits >> 4 == 0);
+
+        match (bits, outbound) {
+            (0, false) => Some(ActivateRequest),
+            (0, true) => Some(ActivateAccept),
+            (1, _) => Some(DeactivateAccept),
+            (2, _) => Some(DeactivateRequest),
+            (3, true) => Some(ActivateReject),
+            (4, true) | (5, false) => Some(UnconfirmedData),
+            (4, false) | (5, true) => Some(ConfirmedData),
+            _ => None,
+        }
+    }
+}
+
+/// Base SNDCP decoder, common to all PDUs.
+#[derive(Copy, Clone)]
+#[cfg_attr(feature = "ser", derive(Serialize, Deserialize))]
+pub struct SndcpBlock(Buf);
+
+impl SndcpBlock {
+    /// Interpret the given bytes as an SNDCP block.
+    pub fn new(buf: Buf) -> SndcpBlock { SndcpBlock(buf) }
+
+    /// Type of PDU, given whether it was received outbound or inbound.
+    pub fn pdu_type(&self, outbound: bool) -> Option<SndcpPduType> {
+        SndcpPduType::from_bits(self.0[0] >> 4, outbound)
+    }
+    /// Network service access point, which identifies the data context.
+    pub fn nsapi(&self) -> u8 { self.0[0] & 0xF }
+    /// Transmitted CRC.
+    pub fn crc(&self) -> u16 { slice_u16(&self.0[10..]) }
+
+    /// Calculate 16-bit CRC over bytes in block.
+    pub fn calc_crc(&self) -> u16 {
+        crc::CRC16::new()
+            .feed_bytes((&self.0[..10]).iter().cloned())
+            .finish() as u16
+    }
+
+    /// Verify if the calculated CRC matches the transmitted one.
+    pub fn crc_valid(&self) -> bool {
+        self.crc() == self.calc_crc()
+    }
+}
+
+/// Request from a subscriber to activate a data context.
+pub struct ActivateRequest(Buf);
+
+impl ActivateRequest {
+    /// Create a new `ActivateRequest` decoder from the base SNDCP decoder.
+    pub fn new(block: SndcpBlock) -> Self { ActivateRequest(block.0) }
+
+    /// Network service access point of the context to activate.
+    pub fn nsapi(&self) -> u8 { self.0[0] & 0xF }
+    /// Type of network address requested.
+    pub fn addr_type(&self) -> u8 { self.0[1] >> 4 }
+    /// Requested IPv4 address, if static.
+    pub fn ip_addr(&self) -> u32 { slice_u32(&self.0[1..]) << 4 | (self.0[5] >> 4) as u32 }
+    /// Type of data subscriber unit.
+    pub fn unit_type(&self) -> u8 { self.0[5] & 0xF }
+}
+
+/// Acceptance by the FNE of a data context activation.
+pub struct ActivateAccept(Buf);
+
+impl ActivateAccept {
+    /// Create a new `ActivateAccept` decoder from the base SNDCP decoder.
+    pub fn new(block: SndcpBlock) -> Self { ActivateAccept(block.0) }
+
+    /// Network service access point of the activated context.
+    pub fn nsapi(&self) -> u8 { self.0[0] & 0xF }
+    /// Priority of the data context.
+    pub fn priority(&self) -> u8 { self.0[1] >> 4 }
+    /// Code for the ready timer duration.
+    pub fn ready_timer(&self) -> u8 { self.0[1] & 0xF }
+    /// Code for the standby timer duration.
+    pub fn standby_timer(&self) -> u8 { self.0[2] >> 4 }
+    /// Type of network address assigned.
+    pub fn addr_type(&self) -> u8 { self.0[2] & 0xF }
+    /// Assigned IPv4 address.
+    pub fn ip_addr(&self) -> u32 { slice_u32(&self.0[3..]) }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use data::coder::DibitCoder;
+
+    fn encode(fields: [u8; 10]) -> [Dibit; CODING_DIBITS] {
+        let checksum = crc::CRC16::new()
+            .feed_bytes(fields.iter().cloned())
+            .finish();
+
+        let coded = DibitCoder::new()
+            .feed_bytes(fields.iter().cloned())
+            .feed_bytes([(checksum >> 8) as u8, checksum as u8].iter().cloned())
+            .finish();
+
+        let mut dibits = [Dibit::default(); CODING_DIBITS];
+        interleave::Interleaver::new(coded).collect_slice_checked(&mut dibits[..]);
+
+        dibits
+    }
+
+    #[test]
+    fn test_pdu_type() {
+        assert_eq!(SndcpPduType::from_bits(0, false), Some(SndcpPduType::ActivateRequest));
+        assert_eq!(SndcpPduType::from_bits(0, true), Some(SndcpPduType::ActivateAccept));
+        assert_eq!(SndcpPduType::from_bits(3, true), Some(SndcpPduType::ActivateReject));
+        assert_eq!(SndcpPduType::from_bits(3, false), None);
+        assert_eq!(SndcpPduType::from_bits(0xF, true), None);
+    }
+
+    #[test]
+    fn test_activate_request() {
+        let mut dibits = encode([
+            0x05, 0x0C, 0x0A, 0x80, 0x01, 0x23, 0, 0, 0, 0,
+        ]);
+
+        // Corrupt a couple of symbols to exercise the trellis decoder.
+        dibits[10] = Dibit::new(dibits[10].bits() ^ 0b01);
+        dibits[60] = Dibit::new(dibits[60].bits() ^ 0b10);
+
+        let block = decode(&dibits).unwrap();
+
+        assert!(block.crc_valid());
+        assert_eq!(block.pdu_type(false), Some(SndcpPduType::ActivateRequest));
+        assert_eq!(block.nsapi(), 5);
+
+        let req = ActivateRequest::new(block);
+        assert_eq!(req.nsapi(), 5);
+        assert_eq!(req.addr_type(), 0);
+        assert_eq!(req.ip_addr(), 0xC0A80012);
+        assert_eq!(req.unit_type(), 3);
+    }
+
+    #[test]
+    fn test_activate_accept() {
+        let block = decode(&encode([
+            0x0A, 0x27, 0x40, 0x0A, 0x00, 0x00, 0x2A, 0, 0, 0,
+        ])).unwrap();
+
+        assert!(block.crc_valid());
+        assert_eq!(block.pdu_type(true), Some(SndcpPduType::ActivateAccept));
+        assert_eq!(block.nsapi(), 0xA);
+
+        let acc = ActivateAccept::new(block);
+        assert_eq!(acc.nsapi(), 0xA);
+        assert_eq!(acc.priority(), 2);
+        assert_eq!(acc.ready_timer(), 7);
+        assert_eq!(acc.standby_timer(), 4);
+        assert_eq!(acc.addr_type(), 0);
+        assert_eq!(acc.ip_addr(), 0x0A00002A);
+    }
+
+    #[test]
+    fn test_bad_crc() {
+        let mut fields = [0x05, 0x0C, 0x0A, 0x80, 0x01, 0x23, 0, 0, 0, 0];
+        let dibits = encode(fields);
+        let block = decode(&dibits).unwrap();
+        assert!(block.crc_valid());
+
+        fields[0] = 0x06;
+        let mut buf = [0; TSBK_BYTES];
+        buf[..10].copy_from_slice(&fields);
+        buf[10] = (block.crc() >> 8) as u8;
+        buf[11] = block.crc() as u8;
+        assert!(!SndcpBlock::new(buf).crc_valid());
+    }
+}
@@ -19,7 +19,118 @@ pub enum P25Error {
     ViterbiUnrecoverable,
     /// An unknown or corrupted NID was encountered.
     UnknownNid,
+    /// A data header offset pointed past the end of the packet payload.
+    DataOffsetOutOfRange,
+    /// A TSBK's transmitted CRC didn't match its contents.
+    TsbkCrcMismatch,
+    /// A frame decoded with more total corrections than allowed, so was likely noise.
+    CorrectionBudgetExceeded,
+    /// An AMBT packet had a different number of data blocks than its header declared.
+    AmbtBlockCountMismatch,
+    /// An AMBT packet's transmitted checksum didn't match its data blocks.
+    AmbtCrcMismatch,
+    /// A data packet header's transmitted checksum didn't match its fields.
+    HeaderCrcMismatch,
+    /// A data packet header had the wrong packet type or an invalid field value.
+    InvalidHeader,
+    /// A data block's transmitted checksum didn't match its contents.
+    DataCrcMismatch,
+    /// A buffer was shorter than the item being parsed from it.
+    TooShort {
+        /// Number of items needed.
+        needed: usize,
+        /// Number of items given.
+        got: usize,
+    },
+    /// A field held a value reserved by the standard.
+    Reserved,
+    /// Frame sync wasn't found where a frame was expected to start.
+    SyncLost,
+    /// A header claimed a packet larger than the configured limit.
+    TooLarge,
+    /// A word had bits set beyond the width of its code.
+    WordTooWide,
+}
+
+impl P25Error {
+    /// Whether the error was caused by too many bit errors for a code to correct.
+    pub fn is_uncorrectable(&self) -> bool {
+        use self::P25Error::*;
+
+        match *self {
+            ReedSolomonUnrecoverable | BchUnrecoverable | GolayUnrecoverable |
+                HammingUnrecoverable | CyclicUnrecoverable | ViterbiUnrecoverable |
+                CorrectionBudgetExceeded => true,
+            _ => false,
+        }
+    }
+
+    /// Whether the error was caused by a transmitted checksum not matching its contents.
+    pub fn is_crc_mismatch(&self) -> bool {
+        use self::P25Error::*;
+
+        match *self {
+            TsbkCrcMismatch | AmbtCrcMismatch | HeaderCrcMismatch | DataCrcMismatch => true,
+            _ => false,
+        }
+    }
+}
+
+impl std::fmt::Display for P25Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        use self::P25Error::*;
+
+        match *self {
+            ReedSolomonUnrecoverable => write!(f, "unrecoverable Reed-Solomon word"),
+            BchUnrecoverable => write!(f, "unrecoverable BCH word"),
+            GolayUnrecoverable => write!(f, "unrecoverable Golay word"),
+            HammingUnrecoverable => write!(f, "unrecoverable Hamming word"),
+            CyclicUnrecoverable => write!(f, "unrecoverable cyclic word"),
+            ViterbiUnrecoverable => write!(f, "unrecoverable trellis-coded block"),
+            UnknownNid => write!(f, "unknown or corrupted NID"),
+            DataOffsetOutOfRange => write!(f, "data offset past end of payload"),
+            TsbkCrcMismatch => write!(f, "TSBK checksum mismatch"),
+            CorrectionBudgetExceeded => write!(f, "too many corrections in frame"),
+            AmbtBlockCountMismatch => write!(f, "AMBT block count mismatch"),
+            AmbtCrcMismatch => write!(f, "AMBT checksum mismatch"),
+            HeaderCrcMismatch => write!(f, "data header checksum mismatch"),
+            InvalidHeader => write!(f, "invalid data header"),
+            DataCrcMismatch => write!(f, "data block checksum mismatch"),
+            TooShort { needed, got } =>
+                write!(f, "buffer too short: needed {}, got {}", needed, got),
+            Reserved => write!(f, "reserved field value"),
+            SyncLost => write!(f, "frame sync lost"),
+            TooLarge => write!(f, "packet exceeds size limit"),
+            WordTooWide => write!(f, "word wider than code"),
+        }
+    }
+}
+
+impl std::error::Error for P25Error {
+    fn description(&self) -> &str { "P25 error" }
 }
 
 /// Standard result using `P25Error`.
 pub type Result<T> = std::result::Result<T, P25Error>;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use data::header;
+
+    #[test]
+    fn test_categories() {
+        let mut buf = [0b00010101, 0b11111101, 0, 0, 0, 1, 0x80, 0, 0, 0, 0, 0];
+        buf[5] ^= 1;
+
+        let err = header::parse(&buf).err().unwrap();
+        assert_eq!(err, P25Error::HeaderCrcMismatch);
+        assert!(err.is_crc_mismatch());
+        assert!(!err.is_uncorrectable());
+
+        assert!(P25Error::ViterbiUnrecoverable.is_uncorrectable());
+        assert!(!P25Error::Reserved.is_crc_mismatch());
+        assert_eq!(P25Error::TooShort { needed: 12, got: 3 }.to_string(),
+                   "buffer too short: needed 12, got 3");
+    }
+}
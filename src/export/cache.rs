@@ -0,0 +1,463 @@
+//! Cache decoded message events in a compact binary format, so a capture can be
+//! reloaded without decoding it again.
+//!
+//! A cache file starts with a 4-byte magic number and a format version byte. Each event
+//! follows as a record holding a 1-byte event tag, a 1-byte payload length, the payload,
+//! and a 32-bit checksum over the tag, length, and payload, with all multibyte fields
+//! stored big-endian. A record that fails its checksum causes a load error, so corrupted
+//! cache files aren't silently decoded into bogus events.
+
+use std::io::{self, Read, Write};
+
+use data::crc;
+use error::P25Error;
+use message::nid::NetworkId;
+use message::receiver::MessageEvent;
+use trunking::tsbk::TsbkFields;
+use util::{slice_u16, slice_u32};
+use voice::control::LinkControlFields;
+use voice::crypto::CryptoControlFields;
+use voice::frame::VoiceFrame;
+use voice::header::VoiceHeaderFields;
+
+/// Magic number identifying a cache file.
+const MAGIC: [u8; 4] = *b"P25C";
+/// Version of the record format written by this module.
+pub const VERSION: u8 = 1;
+
+/// Writes cache records to an underlying stream.
+pub struct CacheWriter<W: Write> {
+    /// Destination stream.
+    stream: W,
+}
+
+impl<W: Write> CacheWriter<W> {
+    /// Create a new `CacheWriter` over the given stream, writing the file header.
+    pub fn new(mut stream: W) -> io::Result<CacheWriter<W>> {
+        let mut hdr = [0; 5];
+
+        hdr[..4].copy_from_slice(&MAGIC);
+        hdr[4] = VERSION;
+
+        stream.write_all(&hdr).map(|_| CacheWriter {
+            stream: stream,
+        })
+    }
+
+    /// Write a record for the given event. Return an error without writing anything if
+    /// the event can't be represented, such as a `TooShort` error with a length that
+    /// doesn't fit in 16 bits.
+    pub fn write(&mut self, event: &MessageEvent) -> io::Result<()> {
+        let (tag, payload) = match encode(event) {
+            Ok(rec) => rec,
+            Err(e) => return Err(e),
+        };
+
+        let mut rec = vec![tag, payload.len() as u8];
+        rec.extend(payload);
+
+        let checksum = crc::crc32(&rec);
+
+        rec.extend(&[
+            (checksum >> 24) as u8,
+            (checksum >> 16) as u8,
+            (checksum >> 8) as u8,
+            checksum as u8,
+        ]);
+
+        self.stream.write_all(&rec)
+    }
+
+    /// Flush the underlying stream.
+    pub fn flush(&mut self) -> io::Result<()> { self.stream.flush() }
+
+    /// Consume the writer, returning the underlying stream.
+    pub fn into_inner(self) -> W { self.stream }
+}
+
+/// Reads cache records from an underlying stream.
+pub struct CacheReader<R: Read> {
+    /// Source stream.
+    stream: R,
+}
+
+impl<R: Read> CacheReader<R> {
+    /// Create a new `CacheReader` over the given stream, verifying the file header.
+    /// Return an error if the stream isn't a cache file of the supported version.
+    pub fn new(mut stream: R) -> io::Result<CacheReader<R>> {
+        let mut hdr = [0; 5];
+
+        if let Err(e) = stream.read_exact(&mut hdr) {
+            return Err(e);
+        }
+
+        if hdr[..4] != MAGIC {
+            return Err(invalid("not a cache file"));
+        }
+
+        if hdr[4] != VERSION {
+            return Err(invalid("unsupported cache version"));
+        }
+
+        Ok(CacheReader {
+            stream: stream,
+        })
+    }
+
+    /// Read the next event. Return `Ok(None)` at the end of the stream, and an error if
+    /// the record is truncated, fails its checksum, or holds an invalid event.
+    pub fn read(&mut self) -> io::Result<Option<MessageEvent>> {
+        let mut head = [0; 2];
+
+        match self.stream.read(&mut head[..1]) {
+            Ok(0) => return Ok(None),
+            Ok(_) => {},
+            Err(e) => return Err(e),
+        }
+
+        if let Err(e) = self.stream.read_exact(&mut head[1..]) {
+            return Err(e);
+        }
+
+        let mut rest = vec![0; head[1] as usize + 4];
+
+        if let Err(e) = self.stream.read_exact(&mut rest) {
+            return Err(e);
+        }
+
+        let (payload, checksum) = rest.split_at(head[1] as usize);
+
+        let calc = crc::CRC32::new()
+            .feed_bytes(head.iter().cloned())
+            .feed_bytes(payload.iter().cloned())
+            .finish() as u32;
+
+        if calc != slice_u32(checksum) {
+            return Err(invalid("cache record checksum mismatch"));
+        }
+
+        match decode(head[0], payload) {
+            Some(event) => Ok(Some(event)),
+            None => Err(invalid("invalid cache record")),
+        }
+    }
+}
+
+/// Convert the given event to its record tag and payload.
+fn encode(event: &MessageEvent) -> io::Result<(u8, Vec<u8>)> {
+    use message::receiver::MessageEvent::*;
+
+    Ok(match *event {
+        Error(e) => return encode_error(e),
+        PacketNID(ref nid) => {
+            let bits = nid.to_bits();
+            (1, vec![(bits >> 8) as u8, bits as u8])
+        },
+        VoiceHeader(ref h) => (2, h.bytes().to_vec()),
+        VoiceFrame(ref f) => {
+            let mut buf = vec![];
+
+            for &c in f.chunks.iter() {
+                buf.extend(&[(c >> 24) as u8, (c >> 16) as u8, (c >> 8) as u8, c as u8]);
+            }
+
+            buf.extend(f.errors.iter().map(|&e| e as u8));
+
+            (3, buf)
+        },
+        LinkControl(ref lc) => (4, lc.bytes().to_vec()),
+        CryptoControl(ref cc) => (5, cc.bytes().to_vec()),
+        LowSpeedDataFragment(x) =>
+            (6, vec![(x >> 24) as u8, (x >> 16) as u8, (x >> 8) as u8, x as u8]),
+        TrunkingControl(ref tsbk) => (7, tsbk.bytes().to_vec()),
+        VoiceTerm(ref lc) => (8, lc.bytes().to_vec()),
+    })
+}
+
+/// Convert the given error to its record tag and payload.
+///
+/// Each error has its own code, given here and reversed by `decode_error`, so a new
+/// error variant fails to compile until it's given one. A `TooShort` error has its own
+/// record type to hold its lengths.
+fn encode_error(e: P25Error) -> io::Result<(u8, Vec<u8>)> {
+    use error::P25Error::*;
+
+    let code = match e {
+        TooShort { needed, got } => return if needed >> 16 == 0 && got >> 16 == 0 {
+            Ok((9, vec![(needed >> 8) as u8, needed as u8, (got >> 8) as u8, got as u8]))
+        } else {
+            Err(io::Error::new(io::ErrorKind::InvalidInput,
+                               "error length too large for cache record"))
+        },
+        ReedSolomonUnrecoverable => 0,
+        BchUnrecoverable => 1,
+        GolayUnrecoverable => 2,
+        HammingUnrecoverable => 3,
+        CyclicUnrecoverable => 4,
+        ViterbiUnrecoverable => 5,
+        UnknownNid => 6,
+        DataOffsetOutOfRange => 7,
+        TsbkCrcMismatch => 8,
+        CorrectionBudgetExceeded => 9,
+        AmbtBlockCountMismatch => 10,
+        AmbtCrcMismatch => 11,
+        HeaderCrcMismatch => 12,
+        InvalidHeader => 13,
+        DataCrcMismatch => 14,
+        Reserved => 15,
+        SyncLost => 16,
+        TooLarge => 17,
+        WordTooWide => 18,
+    };
+
+    Ok((0, vec![code]))
+}
+
+/// Convert the given error record code back to an error, or return `None` for an
+/// unknown code.
+fn decode_error(code: u8) -> Option<P25Error> {
+    use error::P25Error::*;
+
+    Some(match code {
+        0 => ReedSolomonUnrecoverable,
+        1 => BchUnrecoverable,
+        2 => GolayUnrecoverable,
+        3 => HammingUnrecoverable,
+        4 => CyclicUnrecoverable,
+        5 => ViterbiUnrecoverable,
+        6 => UnknownNid,
+        7 => DataOffsetOutOfRange,
+        8 => TsbkCrcMismatch,
+        9 => CorrectionBudgetExceeded,
+        10 => AmbtBlockCountMismatch,
+        11 => AmbtCrcMismatch,
+        12 => HeaderCrcMismatch,
+        13 => InvalidHeader,
+        14 => DataCrcMismatch,
+        15 => Reserved,
+        16 => SyncLost,
+        17 => TooLarge,
+        18 => WordTooWide,
+        _ => return None,
+    })
+}
+
+/// Convert the given record tag and payload back to an event. Return `None` if the tag
+/// is unknown or the payload doesn't fit the tag.
+fn decode(tag: u8, payload: &[u8]) -> Option<MessageEvent> {
+    use message::receiver::MessageEvent::*;
+
+    /// Copy the payload into a fixed-size buffer, if it's the right size.
+    macro_rules! buf {
+        ($len:expr) => {{
+            if payload.len() != $len {
+                return None;
+            }
+
+            let mut buf = [0; $len];
+            buf.copy_from_slice(payload);
+            buf
+        }};
+    }
+
+    Some(match tag {
+        0 => match decode_error(buf!(1)[0]) {
+            Some(e) => Error(e),
+            None => return None,
+        },
+        1 => {
+            let buf = buf!(2);
+
+            match NetworkId::from_bits((buf[0] as u16) << 8 | buf[1] as u16) {
+                Some(nid) => PacketNID(nid),
+                None => return None,
+            }
+        },
+        2 => VoiceHeader(VoiceHeaderFields::new(buf!(15))),
+        3 => {
+            let buf = buf!(8 * 4 + 7);
+            let mut chunks = [0; 8];
+            let mut errors = [0; 7];
+
+            for (i, c) in chunks.iter_mut().enumerate() {
+                *c = slice_u32(&buf[i * 4..]);
+            }
+
+            for (i, e) in errors.iter_mut().enumerate() {
+                *e = buf[8 * 4 + i] as usize;
+            }
+
+            VoiceFrame(self::VoiceFrame {
+                chunks: chunks,
+                errors: errors,
+            })
+        },
+        4 => LinkControl(LinkControlFields::new(buf!(9))),
+        5 => CryptoControl(CryptoControlFields::new(buf!(12))),
+        6 => LowSpeedDataFragment(slice_u32(&buf!(4))),
+        7 => TrunkingControl(TsbkFields::new(buf!(12))),
+        8 => VoiceTerm(LinkControlFields::new(buf!(9))),
+        9 => {
+            let buf = buf!(4);
+
+            Error(P25Error::TooShort {
+                needed: slice_u16(&buf[..]) as usize,
+                got: slice_u16(&buf[2..]) as usize,
+            })
+        },
+        _ => return None,
+    })
+}
+
+/// Create an error for malformed cache data.
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use message::nid::{NetworkAccessCode, DataUnit};
+    use message::receiver::MessageEvent;
+
+    /// Write the given events to a new cache file.
+    fn cache(events: &[MessageEvent]) -> Vec<u8> {
+        let mut w = CacheWriter::new(vec![]).unwrap();
+
+        for e in events.iter() {
+            w.write(e).unwrap();
+        }
+
+        w.into_inner()
+    }
+
+    fn events() -> Vec<MessageEvent> {
+        vec![
+            MessageEvent::PacketNID(NetworkId::new(NetworkAccessCode::Default,
+                                                   DataUnit::VoiceLCFrameGroup)),
+            MessageEvent::VoiceFrame(VoiceFrame {
+                chunks: [0xABC, 0x123, 0xFFF, 0, 0x7FF, 0x1, 0x2A5, 0x7F],
+                errors: [0, 1, 3, 0, 1, 0, 0],
+            }),
+            MessageEvent::LinkControl(LinkControlFields::new([
+                0x00, 0x00, 0x00, 0x00, 0x01, 0x23, 0x45, 0x67, 0x89,
+            ])),
+            MessageEvent::LowSpeedDataFragment(0xDEADBEEF),
+            MessageEvent::TrunkingControl(TsbkFields::new([
+                0x80, 0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xAA,
+            ])),
+            MessageEvent::Error(P25Error::GolayUnrecoverable),
+            MessageEvent::Error(P25Error::TooShort { needed: 300, got: 2 }),
+        ]
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let events = events();
+        let buf = cache(&events);
+
+        assert_eq!(&buf[..5], b"P25C\x01");
+
+        let mut r = CacheReader::new(&buf[..]).unwrap();
+        let mut loaded = vec![];
+
+        while let Some(e) = r.read().unwrap() {
+            loaded.push(e);
+        }
+
+        assert_eq!(loaded.len(), events.len());
+
+        // Reloaded events encode to the same records.
+        for (a, b) in events.iter().zip(loaded.iter()) {
+            assert_eq!(encode(a).unwrap(), encode(b).unwrap());
+        }
+
+        match loaded[1] {
+            MessageEvent::VoiceFrame(ref f) => {
+                assert_eq!(f.chunks, [0xABC, 0x123, 0xFFF, 0, 0x7FF, 0x1, 0x2A5, 0x7F]);
+                assert_eq!(f.errors, [0, 1, 3, 0, 1, 0, 0]);
+            },
+            _ => panic!(),
+        }
+
+        match loaded[5] {
+            MessageEvent::Error(e) => assert_eq!(e, P25Error::GolayUnrecoverable),
+            _ => panic!(),
+        }
+
+        match loaded[6] {
+            MessageEvent::Error(e) =>
+                assert_eq!(e, P25Error::TooShort { needed: 300, got: 2 }),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        // Every code decodes to an error that encodes back to the same code.
+        let codes = (0..256).filter_map(|c| decode_error(c as u8).map(|e| (c, e)))
+            .collect::<Vec<_>>();
+
+        assert_eq!(codes.len(), 19);
+
+        for &(c, e) in codes.iter() {
+            assert_eq!(encode_error(e).unwrap(), (0, vec![c as u8]));
+        }
+
+        assert_eq!(encode_error(P25Error::TooShort { needed: 65535, got: 0 }).unwrap(),
+                   (9, vec![0xFF, 0xFF, 0, 0]));
+
+        // Lengths that don't fit in the record are an error rather than a panic.
+        let mut w = CacheWriter::new(vec![]).unwrap();
+        let e = w.write(&MessageEvent::Error(P25Error::TooShort {
+            needed: 65536,
+            got: 2,
+        })).unwrap_err();
+
+        assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+        assert!(w.write(&MessageEvent::Error(P25Error::TooShort {
+            needed: 12,
+            got: 70000,
+        })).is_err());
+        assert_eq!(w.into_inner().len(), 5);
+    }
+
+    #[test]
+    fn test_corruption() {
+        let buf = cache(&events());
+
+        // Flipping any byte after the file header fails the load.
+        for i in 5..buf.len() {
+            let mut bad = buf.clone();
+            bad[i] ^= 0x20;
+
+            let mut r = CacheReader::new(&bad[..]).unwrap();
+            let mut failed = false;
+
+            loop {
+                match r.read() {
+                    Ok(Some(_)) => {},
+                    Ok(None) => break,
+                    Err(_) => { failed = true; break; },
+                }
+            }
+
+            assert!(failed);
+        }
+
+        let mut bad = buf.clone();
+        bad[4] = VERSION + 1;
+        assert!(CacheReader::new(&bad[..]).is_err());
+
+        let mut bad = buf.clone();
+        bad[0] = b'X';
+        assert!(CacheReader::new(&bad[..]).is_err());
+
+        // Truncated record.
+        let mut r = CacheReader::new(&buf[..buf.len() - 1]).unwrap();
+        for _ in 0..6 {
+            r.read().unwrap();
+        }
+        assert!(r.read().is_err());
+    }
+}
@@ -0,0 +1,6 @@
+//! Export decoded traffic to formats understood by other tools.
+
+#[cfg(feature = "cache")]
+pub mod cache;
+#[cfg(feature = "pcap")]
+pub mod pcap;
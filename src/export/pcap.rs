@@ -0,0 +1,214 @@
+//! Write reassembled data packet payloads as pcap records that can be opened in
+//! Wireshark.
+//!
+//! A pcap file has a single link type for all its records. With `LinkType::RawIp`, the
+//! payloads of packets addressed to the packet data SAP are written as bare IP datagrams
+//! and all other packets are skipped. With `LinkType::P25`, every payload is written
+//! under the first user-defined link type, behind a 4-byte pseudo-header holding the SAP
+//! and the 24-bit logical link ID.
+
+use std::io::{self, Write};
+
+use data::fields::ServiceAccessPoint;
+
+/// Magic number identifying a microsecond-resolution pcap file.
+const MAGIC: u32 = 0xA1B2C3D4;
+/// Maximum number of bytes captured in each record.
+const SNAPLEN: u32 = 65535;
+
+/// Link type of the records in a pcap file.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum LinkType {
+    /// Bare IP datagrams from the packet data SAP.
+    RawIp,
+    /// Payloads of any SAP behind a SAP/LLID pseudo-header.
+    P25,
+}
+
+impl LinkType {
+    /// Value of the link type field in the pcap global header.
+    pub fn to_bits(self) -> u32 {
+        match self {
+            LinkType::RawIp => 101,
+            // LINKTYPE_USER0.
+            LinkType::P25 => 147,
+        }
+    }
+}
+
+/// Metadata of a reassembled data packet.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct PacketInfo {
+    /// Destination service of the packet.
+    pub sap: ServiceAccessPoint,
+    /// Logical link ID from the packet header.
+    pub llid: u32,
+    /// Capture time, in whole seconds since the Unix epoch.
+    pub secs: u32,
+    /// Fractional part of the capture time, in microseconds.
+    pub usecs: u32,
+}
+
+/// Writes pcap records to an underlying stream.
+pub struct PcapWriter<W: Write> {
+    /// Destination stream.
+    stream: W,
+    /// Link type given in the global header.
+    link: LinkType,
+}
+
+impl<W: Write> PcapWriter<W> {
+    /// Create a new `PcapWriter` over the given stream, writing the global header for
+    /// the given link type.
+    pub fn new(mut stream: W, link: LinkType) -> io::Result<PcapWriter<W>> {
+        let mut hdr = [0; 24];
+
+        put_u32(&mut hdr[0..4], MAGIC);
+        // Format version 2.4.
+        put_u16(&mut hdr[4..6], 2);
+        put_u16(&mut hdr[6..8], 4);
+        // Timestamps are in UTC with no stated accuracy, so the zone and sigfigs fields
+        // are left zero.
+        put_u32(&mut hdr[16..20], SNAPLEN);
+        put_u32(&mut hdr[20..24], link.to_bits());
+
+        match stream.write_all(&hdr) {
+            Ok(()) => Ok(PcapWriter {
+                stream: stream,
+                link: link,
+            }),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Write a record for the given packet payload. Return `Ok(false)` if the packet
+    /// was skipped because it can't be represented in the file's link type.
+    pub fn write(&mut self, info: &PacketInfo, payload: &[u8]) -> io::Result<bool> {
+        assert!(info.llid >> 24 == 0);
+        assert!(info.usecs < 1_000_000);
+
+        let pseudo = match self.link {
+            LinkType::RawIp if info.sap != ServiceAccessPoint::PacketData =>
+                return Ok(false),
+            LinkType::RawIp => 0,
+            LinkType::P25 => 4,
+        };
+
+        let len = (pseudo + payload.len()) as u32;
+        assert!(len <= SNAPLEN);
+
+        let mut hdr = [0; 16];
+
+        put_u32(&mut hdr[0..4], info.secs);
+        put_u32(&mut hdr[4..8], info.usecs);
+        // Captured and original lengths are the same since nothing is truncated.
+        put_u32(&mut hdr[8..12], len);
+        put_u32(&mut hdr[12..16], len);
+
+        if let Err(e) = self.stream.write_all(&hdr) {
+            return Err(e);
+        }
+
+        if self.link == LinkType::P25 {
+            let pseudo = [
+                info.sap.to_bits(),
+                (info.llid >> 16) as u8,
+                (info.llid >> 8) as u8,
+                info.llid as u8,
+            ];
+
+            if let Err(e) = self.stream.write_all(&pseudo) {
+                return Err(e);
+            }
+        }
+
+        self.stream.write_all(payload).map(|_| true)
+    }
+
+    /// Flush the underlying stream.
+    pub fn flush(&mut self) -> io::Result<()> { self.stream.flush() }
+
+    /// Consume the writer, returning the underlying stream.
+    pub fn into_inner(self) -> W { self.stream }
+}
+
+/// Store the given value in the given 2 bytes, little-endian.
+fn put_u16(buf: &mut [u8], val: u16) {
+    buf[0] = val as u8;
+    buf[1] = (val >> 8) as u8;
+}
+
+/// Store the given value in the given 4 bytes, little-endian.
+fn put_u32(buf: &mut [u8], val: u32) {
+    put_u16(&mut buf[0..2], val as u16);
+    put_u16(&mut buf[2..4], (val >> 16) as u16);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Read a little-endian `u32` from the start of the given bytes.
+    fn get_u32(buf: &[u8]) -> u32 {
+        buf[..4].iter().rev().fold(0, |v, &b| v << 8 | b as u32)
+    }
+
+    #[test]
+    fn test_raw_ip() {
+        let ip = [0x45, 0x00, 0x00, 0x14, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14,
+                  15, 16];
+
+        let mut w = PcapWriter::new(vec![], LinkType::RawIp).unwrap();
+
+        let info = PacketInfo {
+            sap: ServiceAccessPoint::PacketData,
+            llid: 0x123456,
+            secs: 1500000000,
+            usecs: 250000,
+        };
+
+        assert!(w.write(&info, &ip).unwrap());
+
+        // Non-IP packets are skipped.
+        assert!(!w.write(&PacketInfo {
+            sap: ServiceAccessPoint::SNDCPControl,
+            .. info
+        }, &[1, 2, 3]).unwrap());
+
+        let buf = w.into_inner();
+        assert_eq!(buf.len(), 24 + 16 + 20);
+
+        // Global header.
+        assert_eq!(&buf[..4], &[0xD4, 0xC3, 0xB2, 0xA1]);
+        assert_eq!(&buf[4..8], &[2, 0, 4, 0]);
+        assert_eq!(get_u32(&buf[8..]), 0);
+        assert_eq!(get_u32(&buf[12..]), 0);
+        assert_eq!(get_u32(&buf[16..]), 65535);
+        assert_eq!(get_u32(&buf[20..]), 101);
+
+        // Record header.
+        assert_eq!(get_u32(&buf[24..]), 1500000000);
+        assert_eq!(get_u32(&buf[28..]), 250000);
+        assert_eq!(get_u32(&buf[32..]), 20);
+        assert_eq!(get_u32(&buf[36..]), 20);
+        assert_eq!(&buf[40..], &ip[..]);
+    }
+
+    #[test]
+    fn test_p25() {
+        let mut w = PcapWriter::new(vec![], LinkType::P25).unwrap();
+
+        assert!(w.write(&PacketInfo {
+            sap: ServiceAccessPoint::SNDCPControl,
+            llid: 0xABCDEF,
+            secs: 1,
+            usecs: 2,
+        }, &[0xAA, 0xBB]).unwrap());
+
+        let buf = w.into_inner();
+        assert_eq!(get_u32(&buf[20..]), 147);
+        assert_eq!(get_u32(&buf[32..]), 6);
+        assert_eq!(get_u32(&buf[36..]), 6);
+        assert_eq!(&buf[40..], &[0x06, 0xAB, 0xCD, 0xEF, 0xAA, 0xBB]);
+    }
+}
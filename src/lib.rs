@@ -15,11 +15,20 @@ extern crate serde_derive;
 #[cfg(feature = "ser")]
 extern crate serde;
 
+#[cfg(all(test, feature = "ser"))]
+extern crate serde_json;
+
 #[macro_use]
 extern crate static_fir;
 
+#[cfg(feature = "log")]
+#[macro_use]
+extern crate log;
+
+#[macro_use]
+mod logging;
+
 mod buffer;
-mod util;
 
 pub mod baseband;
 pub mod bits;
@@ -27,6 +36,13 @@ pub mod coding;
 pub mod consts;
 pub mod data;
 pub mod error;
+#[cfg(any(feature = "pcap", feature = "cache"))]
+pub mod export;
 pub mod message;
+pub mod phase2;
+pub mod symbol;
+#[cfg(any(test, feature = "testutil"))]
+pub mod testutil;
 pub mod trunking;
+pub mod util;
 pub mod voice;
@@ -0,0 +1,29 @@
+//! Wrappers around the `log` crate macros that compile to nothing when the `log` feature
+//! is disabled.
+//!
+//! The disabled macros still type check their arguments, so values only used for logging
+//! don't cause unused warnings.
+
+/// Log a message at the debug level.
+#[cfg(feature = "log")]
+macro_rules! log_debug {
+    ($($arg:tt)*) => { debug!($($arg)*) };
+}
+
+/// Log a message at the debug level.
+#[cfg(not(feature = "log"))]
+macro_rules! log_debug {
+    ($($arg:tt)*) => { if false { format_args!($($arg)*); } };
+}
+
+/// Log a message at the trace level.
+#[cfg(feature = "log")]
+macro_rules! log_trace {
+    ($($arg:tt)*) => { trace!($($arg)*) };
+}
+
+/// Log a message at the trace level.
+#[cfg(not(feature = "log"))]
+macro_rules! log_trace {
+    ($($arg:tt)*) => { if false { format_args!($($arg)*); } };
+}
@@ -1,8 +1,11 @@
 //! General low-level receiver for all data units, covering frame synchronization up to
 //! symbol decoding.
 
-use baseband::decode::{Decoder, Decider};
-use baseband::sync::{SyncCorrelator, SyncDetector};
+use baseband::decode::{Decoder, Decider, Mapping};
+use baseband::sync::{DibitSyncDetector, SyncCorrelator, SyncDetector};
+use bits::Dibit;
+use coding::bch;
+use coding::profile::{self, Field};
 use error::{P25Error, Result};
 use message::nid;
 use message::status::{StreamSymbol, StatusDeinterleaver};
@@ -18,8 +21,8 @@ const PRIME_SAMPLES: u32 = 6000;
 /// symbols.
 #[derive(Copy, Clone)]
 struct SymbolReceiver {
-    /// Symbol decoder.
-    decoder: Decoder,
+    /// Symbol decoder, or `None` if fed hard-decision dibits.
+    decoder: Option<Decoder>,
     /// Data/Status symbol deinterleaver.
     status: StatusDeinterleaver,
 }
@@ -28,18 +31,34 @@ impl SymbolReceiver {
     /// Create a new `SymbolReceiver` using the given symbol decoder.
     pub fn new(decoder: Decoder) -> SymbolReceiver {
         SymbolReceiver {
-            decoder: decoder,
+            decoder: Some(decoder),
+            status: StatusDeinterleaver::new(),
+        }
+    }
+
+    /// Create a new `SymbolReceiver` that is fed hard-decision dibits.
+    pub fn dibits() -> SymbolReceiver {
+        SymbolReceiver {
+            decoder: None,
             status: StatusDeinterleaver::new(),
         }
     }
 
     /// Feed in a baseband symbol, possibly producing a data or status symbol.
     pub fn feed(&mut self, s: f32) -> Option<StreamSymbol> {
-        match self.decoder.feed(s) {
+        let dibit = match self.decoder {
+            Some(ref mut decoder) => decoder.feed(s),
+            None => None,
+        };
+
+        match dibit {
             Some(dibit) => Some(self.status.feed(dibit)),
             None => None,
         }
     }
+
+    /// Feed in a hard-decision dibit, producing a data or status symbol.
+    pub fn feed_dibit(&mut self, dibit: Dibit) -> StreamSymbol { self.status.feed(dibit) }
 }
 
 
@@ -52,6 +71,34 @@ pub enum ReceiverEvent {
     NetworkId(nid::NetworkId),
 }
 
+/// Stage where decoding of a data unit stopped.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum DecodeFailure {
+    /// The NID couldn't be decoded.
+    Nid(P25Error),
+    /// The NID was decoded but its NAC didn't pass the filter.
+    NacFiltered(nid::NetworkAccessCode),
+}
+
+/// Intermediate results from decoding the most recent data unit, recorded when debugging
+/// is enabled on the receiver.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct Diagnostics {
+    /// Index of the input sample where frame sync was detected.
+    pub sync_offset: Option<u64>,
+    /// Raw coded NID word, before error correction.
+    pub nid_bits: Option<u64>,
+    /// BCH syndrome of the raw NID word.
+    pub nid_syndrome: Option<u64>,
+    /// Number of bits corrected in the NID word, or `None` if it couldn't be corrected.
+    pub nid_corrected: Option<usize>,
+    /// Stage where decoding stopped, or `None` if the NID was accepted.
+    pub failure: Option<DecodeFailure>,
+    /// Error from an NID that failed to decode but was recovered using the data unit
+    /// hint, which isn't counted as a failure.
+    pub nid_recovered: Option<P25Error>,
+}
+
 /// Internal state of the state machine.
 enum State {
     /// Prime the signal power tracker.
@@ -76,6 +123,8 @@ enum StateChange {
     EventChange(ReceiverEvent, State),
     /// Propagate the given error.
     Error(P25Error),
+    /// Change to the given state and propagate the given error.
+    ErrorChange(P25Error, State),
     /// No action necessary.
     NoChange,
 }
@@ -92,6 +141,11 @@ impl State {
         DecodeNID(SymbolReceiver::new(decoder), nid::NidReceiver::new())
     }
 
+    /// Initial NID decode state when fed hard-decision dibits.
+    pub fn decode_nid_dibits() -> State {
+        DecodeNID(SymbolReceiver::dibits(), nid::NidReceiver::new())
+    }
+
     /// Initial symbol decode state.
     pub fn decode_packet(recv: SymbolReceiver) -> State { DecodePacket(recv) }
 
@@ -109,11 +163,36 @@ impl State {
 /// 3. Deinterleave status symbols
 /// 4. Decode NID information
 /// 5. Decode dibit symbols until stopped
+///
+/// Hard-decision dibits, such as from a capture of already-demodulated symbols, can be
+/// fed in place of samples with `feed_dibit`, which skips the first step and locks onto
+/// the frame sync sequence directly. A receiver should be fed only samples or only
+/// dibits.
 pub struct DataUnitReceiver {
     /// Current state.
     state: State,
     /// Tracks input signal power and frame synchronization statistics.
     corr: SyncCorrelator,
+    /// Locks onto frame synchronization when fed dibits.
+    dibit_sync: DibitSyncDetector,
+    /// Packets with NACs not passing this filter are dropped after NID decode.
+    nacs: nid::NacFilter,
+    /// Mapping from deviation levels to dibits.
+    mapping: Mapping,
+    /// Number of consecutive NIDs that failed to decode.
+    nid_failures: usize,
+    /// Number of consecutive NID failures before returning to frame synchronization.
+    max_nid_failures: usize,
+    /// Whether to reject NIDs with an invalid parity bit.
+    strict_nid: bool,
+    /// Data unit to assume when an NID fails to decode, if any.
+    duid_hint: Option<nid::DataUnit>,
+    /// Whether to record diagnostics.
+    debug: bool,
+    /// Diagnostics for the current data unit.
+    diag: Diagnostics,
+    /// Number of samples, or dibits, seen so far.
+    samples: u64,
 }
 
 impl DataUnitReceiver {
@@ -122,9 +201,63 @@ impl DataUnitReceiver {
         DataUnitReceiver {
             state: State::prime(),
             corr: SyncCorrelator::new(),
+            dibit_sync: DibitSyncDetector::new(),
+            nacs: nid::NacFilter::new(),
+            mapping: Mapping::default(),
+            nid_failures: 0,
+            max_nid_failures: 1,
+            strict_nid: false,
+            duid_hint: None,
+            debug: false,
+            diag: Diagnostics::default(),
+            samples: 0,
         }
     }
 
+    /// Enable or disable recording of diagnostics for each data unit.
+    pub fn set_debug(&mut self, debug: bool) {
+        self.debug = debug;
+        self.diag = Diagnostics::default();
+    }
+
+    /// Retrieve the diagnostics recorded for the most recent data unit, if debugging is
+    /// enabled.
+    pub fn diagnostics(&self) -> Option<&Diagnostics> {
+        if self.debug { Some(&self.diag) } else { None }
+    }
+
+    /// Only receive packets whose NAC passes the given filter, silently dropping any
+    /// others and returning to frame synchronization.
+    pub fn set_nac_filter(&mut self, nacs: nid::NacFilter) { self.nacs = nacs; }
+
+    /// Decode symbols using the given level-to-dibit mapping rather than the standard
+    /// one.
+    pub fn set_mapping(&mut self, mapping: Mapping) { self.mapping = mapping; }
+
+    /// Return to frame synchronization after the given number of consecutive NIDs fail
+    /// to decode, rather than on the first failure. Until then, each failure is reported
+    /// and the following symbols are decoded as another NID.
+    pub fn set_max_nid_failures(&mut self, max: usize) {
+        assert!(max > 0);
+        self.max_nid_failures = max;
+    }
+
+    /// Enable or disable rejecting NIDs whose final parity bit doesn't match the
+    /// corrected codeword, which cuts down on false frame detections from noise.
+    pub fn set_strict_nid(&mut self, strict: bool) { self.strict_nid = strict; }
+
+    /// Recover data units whose NID fails to decode by assuming they have the given data
+    /// unit, or disable recovery with `None`.
+    ///
+    /// This is meant for salvaging payloads from damaged captures, where the data unit is
+    /// known from context such as the frame cadence. When an NID fails to decode, an NID
+    /// is reported with the hinted data unit and the uncorrected NAC bits, and the
+    /// payload is decoded as normal. These NIDs still pass through the NAC filter, and
+    /// no error is reported for the failure.
+    pub fn set_duid_hint(&mut self, hint: Option<nid::DataUnit>) {
+        self.duid_hint = hint;
+    }
+
     /// Flush any remaining padding symbols at the end of the current packet, and reenter
     /// the frame synchronization state afterwards.
     pub fn flush_pads(&mut self) {
@@ -138,45 +271,184 @@ impl DataUnitReceiver {
     /// Force the receiver into frame synchronization.
     pub fn resync(&mut self) { self.state = State::sync(); }
 
+    /// Record that frame sync was acquired at the most recent sample or dibit.
+    fn sync_acquired(&mut self) {
+        log_debug!("frame sync acquired at sample {}", self.samples - 1);
+
+        if self.debug {
+            self.diag = Diagnostics::default();
+            self.diag.sync_offset = Some(self.samples - 1);
+        }
+    }
+
     /// Determine the next action to take based on the given sample.
     fn handle(&mut self, s: f32) -> StateChange {
         // Continuously track the input signal power.
         let (power, thresh) = self.corr.feed(s);
+        self.samples += 1;
 
-        match self.state {
-            Prime(t) => if t == PRIME_SAMPLES {
+        let sym = match self.state {
+            Prime(t) => return if t == PRIME_SAMPLES {
                 Change(State::sync())
             } else {
                 Change(Prime(t + 1))
             },
             Sync(ref mut sync) => if sync.feed(power, thresh) {
+                None
+            } else {
+                return NoChange;
+            },
+            DecodeNID(ref mut recv, _) | DecodePacket(ref mut recv) |
+                FlushPads(ref mut recv) => match recv.feed(s)
+            {
+                Some(sym) => Some(sym),
+                None => return NoChange,
+            },
+        };
+
+        match sym {
+            Some(sym) => self.handle_symbol(sym),
+            None => {
+                self.sync_acquired();
+
                 let (p, m, n) = self.corr.thresholds();
-                Change(State::decode_nid(Decoder::new(Decider::new(p, m, n))))
+                let decider = Decider::with_mapping(p, m, n, self.mapping);
+
+                Change(State::decode_nid(Decoder::new(decider)))
+            },
+        }
+    }
+
+    /// Determine the next action to take based on the given hard-decision dibit.
+    fn handle_dibit(&mut self, dibit: Dibit) -> StateChange {
+        self.samples += 1;
+
+        let sym = match self.state {
+            Prime(_) | Sync(_) => if self.dibit_sync.feed(dibit) {
+                None
             } else {
-                NoChange
+                return NoChange;
+            },
+            DecodeNID(ref mut recv, _) | DecodePacket(ref mut recv) |
+                FlushPads(ref mut recv) => Some(recv.feed_dibit(dibit)),
+        };
+
+        match sym {
+            Some(sym) => self.handle_symbol(sym),
+            None => {
+                self.sync_acquired();
+                self.dibit_sync = DibitSyncDetector::new();
+
+                Change(State::decode_nid_dibits())
             },
-            DecodeNID(ref mut recv, ref mut nid) => {
-                let dibit = match recv.feed(s) {
-                    Some(StreamSymbol::Data(d)) => d,
-                    Some(s) => return Event(ReceiverEvent::Symbol(s)),
+        }
+    }
+
+    /// Determine the next action to take based on the given data or status symbol,
+    /// received after frame sync.
+    fn handle_symbol(&mut self, sym: StreamSymbol) -> StateChange {
+        match self.state {
+            Prime(_) | Sync(_) => unreachable!(),
+            DecodeNID(ref recv, ref mut nid) => {
+                let dibit = match sym {
+                    StreamSymbol::Data(d) => d,
+                    s => return Event(ReceiverEvent::Symbol(s)),
+                };
+
+                let bits = match nid.feed_word(dibit) {
+                    Some(bits) => bits,
                     None => return NoChange,
                 };
 
-                match nid.feed(dibit) {
-                    Some(Ok(nid)) => EventChange(ReceiverEvent::NetworkId(nid),
-                                                 State::decode_packet(*recv)),
-                    Some(Err(e)) => Error(e),
-                    None => NoChange,
+                let decoded = if self.strict_nid {
+                    nid::decode_word_strict(bits)
+                } else {
+                    nid::decode_word(bits)
+                };
+
+                if self.debug {
+                    self.diag.nid_bits = Some(bits);
+                    self.diag.nid_syndrome = Some(bch::syndrome(bits));
+                    self.diag.nid_corrected = profile::inner_code(Field::Nid)
+                        .decode(bits)
+                        .map(|(_, err)| err);
+                    self.diag.nid_recovered = None;
+                    self.diag.failure = match decoded {
+                        Ok((nid, _)) if !self.nacs.allows(nid.access_code) =>
+                            Some(DecodeFailure::NacFiltered(nid.access_code)),
+                        Ok(_) => None,
+                        Err(e) if self.duid_hint.is_some() => {
+                            self.diag.nid_recovered = Some(e);
+
+                            let nac = recovered_nac(bits);
+
+                            if self.nacs.allows(nac) {
+                                None
+                            } else {
+                                Some(DecodeFailure::NacFiltered(nac))
+                            }
+                        },
+                        Err(e) => Some(DecodeFailure::Nid(e)),
+                    };
+                }
+
+                match decoded {
+                    Ok((nid, err)) => {
+                        self.nid_failures = 0;
+
+                        log_debug!("NID decoded: NAC {:?}, DUID {:?}, {} corrected",
+                                   nid.access_code, nid.data_unit, err);
+
+                        if self.nacs.allows(nid.access_code) {
+                            EventChange(ReceiverEvent::NetworkId(nid),
+                                        State::decode_packet(*recv))
+                        } else {
+                            log_trace!("NAC {:?} filtered", nid.access_code);
+                            Change(State::sync())
+                        }
+                    },
+                    Err(_) if self.duid_hint.is_some() => {
+                        self.nid_failures = 0;
+
+                        let nid = nid::NetworkId::new(recovered_nac(bits),
+                                                      self.duid_hint.unwrap());
+
+                        log_debug!("NID failed, assuming NAC {:?}, DUID {:?}",
+                                   nid.access_code, nid.data_unit);
+
+                        if self.nacs.allows(nid.access_code) {
+                            EventChange(ReceiverEvent::NetworkId(nid),
+                                        State::decode_packet(*recv))
+                        } else {
+                            log_trace!("NAC {:?} filtered", nid.access_code);
+                            Change(State::sync())
+                        }
+                    },
+                    Err(e) => {
+                        self.nid_failures += 1;
+
+                        log_debug!("NID failed: {:?} ({} consecutive)", e,
+                                   self.nid_failures);
+
+                        // Likely lost symbol timing, so search for the next frame sync
+                        // rather than decoding more garbage.
+                        if self.nid_failures >= self.max_nid_failures {
+                            self.nid_failures = 0;
+                            ErrorChange(e, State::sync())
+                        } else {
+                            Error(e)
+                        }
+                    },
                 }
             },
-            DecodePacket(ref mut recv) => match recv.feed(s) {
-                Some(x) => Event(ReceiverEvent::Symbol(x)),
-                None => NoChange,
-            },
-            FlushPads(ref mut recv) => match recv.feed(s) {
+            DecodePacket(_) => Event(ReceiverEvent::Symbol(sym)),
+            FlushPads(_) => match sym {
                 /// According to the spec, the stream is padded until the next status
                 /// symbol boundary.
-                Some(StreamSymbol::Status(_)) => Change(State::sync()),
+                StreamSymbol::Status(_) => {
+                    log_trace!("flushed pads, returning to frame sync");
+                    Change(State::sync())
+                },
                 _ => NoChange,
             },
         }
@@ -186,7 +458,23 @@ impl DataUnitReceiver {
     /// `Some(Ok(event))` for any normal event, `Some(Err(err))` for any error, and `None`
     /// if no event occurred.
     pub fn feed(&mut self, s: f32) -> Option<Result<ReceiverEvent>> {
-        match self.handle(s) {
+        let change = self.handle(s);
+        self.apply(change)
+    }
+
+    /// Feed in a hard-decision dibit in place of the samples of a symbol period,
+    /// possibly producing a receiver event as in `feed`.
+    ///
+    /// Each dibit counts as one sample in the diagnostics, and the level mapping isn't
+    /// used.
+    pub fn feed_dibit(&mut self, dibit: Dibit) -> Option<Result<ReceiverEvent>> {
+        let change = self.handle_dibit(dibit);
+        self.apply(change)
+    }
+
+    /// Apply the given state change, returning any event or error it carries.
+    fn apply(&mut self, change: StateChange) -> Option<Result<ReceiverEvent>> {
+        match change {
             Change(state) => {
                 self.state = state;
                 None
@@ -197,7 +485,344 @@ impl DataUnitReceiver {
                 Some(Ok(event))
             },
             Error(err) => Some(Err(err)),
+            ErrorChange(err, state) => {
+                self.state = state;
+                Some(Err(err))
+            },
             NoChange => None,
         }
     }
 }
+
+/// Take the uncorrected NAC bits from the given coded NID word, for recovering an NID
+/// that failed to decode.
+fn recovered_nac(bits: u64) -> nid::NetworkAccessCode {
+    nid::NetworkAccessCode::from_bits((bits >> 52) as u16)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bits;
+    use consts;
+    use message::nid::NetworkAccessCode::*;
+    use message::nid::DataUnit::*;
+
+    /// Feed the given NID into a receiver that has just seen frame sync, returning any
+    /// decoded NID.
+    fn feed_nid(recv: &mut DataUnitReceiver, nid: nid::NetworkId)
+        -> Option<nid::NetworkId>
+    {
+        recv.state = State::decode_nid(Decoder::new(Decider::new(0.5, 0.0, -0.5)));
+        // Status symbol following the first 11 NID dibits.
+        feed_dibits(recv, nid_dibits(nid), 11).0
+    }
+
+    /// Feed the given NID dibits into a receiver that has just seen frame sync,
+    /// returning any decoded NID and the number of errors.
+    fn feed_nid_dibits(recv: &mut DataUnitReceiver, dibits: Vec<bits::Dibit>)
+        -> (Option<nid::NetworkId>, usize)
+    {
+        recv.state = State::decode_nid(Decoder::new(Decider::new(0.5, 0.0, -0.5)));
+        feed_dibits(recv, dibits, 11)
+    }
+
+    /// Generate the data dibits of the given NID.
+    fn nid_dibits(nid: nid::NetworkId) -> Vec<bits::Dibit> {
+        let bytes = nid.encode();
+        bits::Dibits::new(bytes.iter().cloned()).collect()
+    }
+
+    /// Feed the given data dibits into a receiver, with a status symbol inserted at the
+    /// given position, returning any decoded NID and the number of errors.
+    fn feed_dibits(recv: &mut DataUnitReceiver, mut dibits: Vec<bits::Dibit>,
+                   status: usize)
+        -> (Option<nid::NetworkId>, usize)
+    {
+        let mut out = None;
+        let mut errors = 0;
+
+        dibits.insert(status, bits::Dibit::new(0b00));
+
+        for dibit in dibits {
+            let s = match dibit.bits() {
+                0b01 => 1.0,
+                0b00 => 0.25,
+                0b10 => -0.25,
+                _ => -1.0,
+            };
+
+            for _ in 0..consts::SYMBOL_PERIOD {
+                match recv.feed(s) {
+                    Some(Ok(ReceiverEvent::NetworkId(nid))) => out = Some(nid),
+                    Some(Ok(ReceiverEvent::Symbol(_))) | None => {},
+                    Some(Err(_)) => errors += 1,
+                }
+            }
+        }
+
+        (out, errors)
+    }
+
+    #[test]
+    fn test_nac_filter() {
+        let mut recv = DataUnitReceiver::new();
+        recv.set_nac_filter(nid::NacFilter::new().allow(Other(0x123)));
+
+        let a = nid::NetworkId::new(Other(0x123), VoiceHeader);
+        let b = nid::NetworkId::new(Other(0x456), VoiceHeader);
+
+        assert_eq!(feed_nid(&mut recv, a).unwrap().access_code, Other(0x123));
+        assert!(feed_nid(&mut recv, b).is_none());
+        assert!(match recv.state { Sync(_) => true, _ => false });
+        assert_eq!(feed_nid(&mut recv, a).unwrap().access_code, Other(0x123));
+        assert!(feed_nid(&mut recv, b).is_none());
+
+        recv.set_nac_filter(nid::NacFilter::new().allow(ReceiveAny));
+        assert_eq!(feed_nid(&mut recv, b).unwrap().access_code, Other(0x456));
+    }
+
+    #[test]
+    fn test_strict_nid() {
+        let nid = nid::NetworkId::new(Other(0x123), TrunkingSignaling);
+
+        // Flip the final parity bit.
+        let mut dibits = nid_dibits(nid);
+        dibits[31] = bits::Dibit::new(dibits[31].bits() ^ 0b01);
+
+        let mut recv = DataUnitReceiver::new();
+        assert!(feed_nid_dibits(&mut recv, dibits.clone()).0.is_some());
+
+        recv.set_strict_nid(true);
+        let (out, errors) = feed_nid_dibits(&mut recv, dibits);
+        assert!(out.is_none());
+        assert_eq!(errors, 1);
+        assert!(feed_nid(&mut recv, nid).is_some());
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let nid = nid::NetworkId::new(Other(0x123), VoiceLCFrameGroup);
+        let bits = bch::encode(nid.to_bits());
+
+        let mut recv = DataUnitReceiver::new();
+        assert!(recv.diagnostics().is_none());
+
+        recv.set_debug(true);
+        assert_eq!(recv.diagnostics(), Some(&Diagnostics::default()));
+
+        // Correctable errors.
+        let mut dibits = nid_dibits(nid);
+        dibits[3] = bits::Dibit::new(dibits[3].bits() ^ 0b11);
+
+        assert!(feed_nid_dibits(&mut recv, dibits).0.is_some());
+
+        let diag = *recv.diagnostics().unwrap();
+        assert_eq!(diag.nid_bits, Some(bits ^ 0b11 << 56));
+        assert_eq!(diag.nid_syndrome, Some(bch::syndrome(bits ^ 0b11 << 56)));
+        assert_eq!(diag.nid_corrected, Some(2));
+        assert_eq!(diag.failure, None);
+
+        // Too many errors for the BCH code.
+        let mut dibits = nid_dibits(nid);
+
+        for d in dibits.iter_mut().take(12) {
+            *d = bits::Dibit::new(d.bits() ^ 0b11);
+        }
+
+        assert_eq!(feed_nid_dibits(&mut recv, dibits).1, 1);
+
+        let diag = *recv.diagnostics().unwrap();
+        assert_eq!(diag.nid_bits, Some(bits ^ 0xFFFFFF << 40));
+        assert!(diag.nid_syndrome.unwrap() != 0);
+        assert_eq!(diag.nid_corrected, None);
+        assert_eq!(diag.failure, Some(DecodeFailure::Nid(P25Error::BchUnrecoverable)));
+
+        // Filtered NAC.
+        recv.set_nac_filter(nid::NacFilter::new().allow(Other(0x456)));
+        assert!(feed_nid(&mut recv, nid).is_none());
+
+        let diag = *recv.diagnostics().unwrap();
+        assert_eq!(diag.nid_syndrome, Some(0));
+        assert_eq!(diag.nid_corrected, Some(0));
+        assert_eq!(diag.failure, Some(DecodeFailure::NacFiltered(Other(0x123))));
+    }
+
+    #[cfg(feature = "log")]
+    #[test]
+    fn test_log() {
+        use log;
+        use std::cell::RefCell;
+
+        thread_local!(static EVENTS: RefCell<Vec<String>> = RefCell::new(vec![]));
+
+        /// Logger that records messages logged on the current thread.
+        struct Capture;
+
+        impl log::Log for Capture {
+            fn enabled(&self, _: &log::LogMetadata) -> bool { true }
+
+            fn log(&self, record: &log::LogRecord) {
+                let msg = format!("{}", record.args());
+                EVENTS.with(|e| e.borrow_mut().push(msg));
+            }
+        }
+
+        log::set_logger(|max| {
+            max.set(log::LogLevelFilter::Trace);
+            Box::new(Capture)
+        }).unwrap();
+
+        let nid = nid::NetworkId::new(Other(0x123), VoiceLCFrameGroup);
+        let mut dibits = nid_dibits(nid);
+        dibits[3] = bits::Dibit::new(dibits[3].bits() ^ 0b11);
+
+        let mut recv = DataUnitReceiver::new();
+        recv.set_nac_filter(nid::NacFilter::new().allow(Other(0x456)));
+
+        feed_nid_dibits(&mut recv, dibits);
+
+        let events = EVENTS.with(|e| e.borrow().clone());
+
+        assert_eq!(events, vec![
+            "NID decoded: NAC Other(291), DUID VoiceLCFrameGroup, 2 corrected",
+            "NAC Other(291) filtered",
+        ]);
+    }
+
+    #[test]
+    fn test_duid_hint() {
+        let nid = nid::NetworkId::new(Other(0x123), VoiceLCFrameGroup);
+
+        // Destroy the DUID and parity portions of the NID.
+        let mut dibits = nid_dibits(nid);
+
+        for d in dibits.iter_mut().skip(6).take(12) {
+            *d = bits::Dibit::new(d.bits() ^ 0b10);
+        }
+
+        let mut recv = DataUnitReceiver::new();
+        recv.set_debug(true);
+        recv.set_nac_filter(nid::NacFilter::new().allow(Other(0x456)));
+
+        let (out, errors) = feed_nid_dibits(&mut recv, dibits.clone());
+        assert!(out.is_none());
+        assert_eq!(errors, 1);
+
+        recv.set_duid_hint(Some(VoiceLCFrameGroup));
+
+        // The recovered NAC still goes through the filter.
+        let (out, errors) = feed_nid_dibits(&mut recv, dibits.clone());
+        assert!(out.is_none());
+        assert_eq!(errors, 0);
+        assert!(match recv.state { Sync(_) => true, _ => false });
+
+        let diag = *recv.diagnostics().unwrap();
+        assert_eq!(diag.failure, Some(DecodeFailure::NacFiltered(Other(0x123))));
+        assert_eq!(diag.nid_recovered, Some(P25Error::BchUnrecoverable));
+
+        recv.set_nac_filter(nid::NacFilter::new().allow(Other(0x123)));
+
+        let (out, errors) = feed_nid_dibits(&mut recv, dibits);
+        let out = out.unwrap();
+        assert_eq!(errors, 0);
+
+        // Recovery is recorded separately rather than as an NID failure.
+        let diag = *recv.diagnostics().unwrap();
+        assert_eq!(diag.failure, None);
+        assert_eq!(diag.nid_recovered, Some(P25Error::BchUnrecoverable));
+
+        assert_eq!(out.access_code, Other(0x123));
+        assert_eq!(out.data_unit, VoiceLCFrameGroup);
+        assert!(match recv.state { DecodePacket(_) => true, _ => false });
+
+        // Payload symbols follow.
+        let payload = [0b01, 0b10, 0b11, 0b00].iter().map(|&b| bits::Dibit::new(b));
+        let mut got = vec![];
+
+        for dibit in payload {
+            let s = match dibit.bits() {
+                0b01 => 1.0,
+                0b00 => 0.25,
+                0b10 => -0.25,
+                _ => -1.0,
+            };
+
+            for _ in 0..consts::SYMBOL_PERIOD {
+                match recv.feed(s) {
+                    Some(Ok(ReceiverEvent::Symbol(StreamSymbol::Data(d)))) =>
+                        got.push(d.bits()),
+                    _ => {},
+                }
+            }
+        }
+
+        assert_eq!(got, vec![0b01, 0b10, 0b11, 0b00]);
+
+        // Clean NIDs are still decoded and filtered as normal.
+        recv.set_nac_filter(nid::NacFilter::new().allow(Other(0x456)));
+        assert!(feed_nid(&mut recv, nid).is_none());
+        assert_eq!(recv.diagnostics().unwrap().nid_recovered, None);
+    }
+
+    #[test]
+    fn test_nid_failures() {
+        let nid = nid::NetworkId::new(Other(0x123), VoiceLCFrameGroup);
+
+        // Slip the NID by an inserted symbol.
+        let mut slipped = nid_dibits(nid);
+        slipped.insert(0, bits::Dibit::new(0b11));
+        slipped.pop();
+
+        let mut recv = DataUnitReceiver::new();
+        recv.state = State::decode_nid(Decoder::new(Decider::new(0.5, 0.0, -0.5)));
+
+        // Default is to drop back to sync on the first failure.
+        assert_eq!(feed_dibits(&mut recv, slipped.clone(), 11).1, 1);
+        assert!(match recv.state { Sync(_) => true, _ => false });
+
+        recv.set_max_nid_failures(2);
+        recv.state = State::decode_nid(Decoder::new(Decider::new(0.5, 0.0, -0.5)));
+
+        assert_eq!(feed_dibits(&mut recv, slipped.clone(), 11).1, 1);
+        assert!(match recv.state { DecodeNID(_, _) => true, _ => false });
+
+        // Status symbols continue at the same spacing.
+        assert_eq!(feed_dibits(&mut recv, slipped.clone(), 14).1, 1);
+        assert!(match recv.state { Sync(_) => true, _ => false });
+
+        // Next frame decodes cleanly after resync, and resets the count.
+        assert_eq!(feed_nid(&mut recv, nid).unwrap().access_code, Other(0x123));
+        assert_eq!(recv.nid_failures, 0);
+    }
+
+    #[test]
+    fn test_feed_dibit() {
+        use baseband::sync::patterns;
+
+        let nid = nid::NetworkId::new(Other(0x123), TrunkingSignaling);
+
+        let mut dibits = (0..40).map(|i| bits::Dibit::new(i as u8 * 3 & 0b11))
+            .collect::<Vec<_>>();
+        dibits.extend(patterns::PHASE1_DIBITS.iter().map(|&d| bits::Dibit::new(d)));
+        dibits.extend(nid_dibits(nid));
+        // Status symbol following the first 11 NID dibits.
+        dibits.insert(40 + consts::SYNC_SYMBOLS + 11, bits::Dibit::new(0b10));
+
+        let mut recv = DataUnitReceiver::new();
+        recv.set_debug(true);
+
+        let events = dibits.into_iter().filter_map(|d| recv.feed_dibit(d))
+            .map(|e| match e {
+                Ok(ReceiverEvent::NetworkId(n)) => Some(n.data_unit),
+                Ok(ReceiverEvent::Symbol(_)) => None,
+                Err(_) => panic!("unexpected error"),
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(events, [None, Some(TrunkingSignaling)]);
+        assert!(match recv.state { DecodePacket(_) => true, _ => false });
+        assert_eq!(recv.diagnostics().unwrap().sync_offset,
+                   Some(40 + consts::SYNC_SYMBOLS as u64 - 1));
+    }
+}
@@ -3,10 +3,12 @@
 use bits::Dibit;
 use buffer;
 use coding::bch;
+use coding::profile::{self, Field};
 use error::{Result, P25Error};
 
 /// "Digital squelch" NAC field of the NID.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "ser", derive(Serialize, Deserialize))]
 pub enum NetworkAccessCode {
     /// Default P25 NAC.
     Default,
@@ -44,10 +46,52 @@ impl NetworkAccessCode {
             Other(bits) => bits,
         }
     }
+
+    /// Whether the NAC is the special `ReceiveAny` value ($F7E), which unsquelches a
+    /// receiver on any NAC.
+    pub fn is_receive_any(self) -> bool { self == NetworkAccessCode::ReceiveAny }
+
+    /// Whether the NAC is the special `RepeatAny` value ($F7F), which lets a repeater
+    /// retransmit any NAC.
+    pub fn is_repeat_any(self) -> bool { self == NetworkAccessCode::RepeatAny }
+
+    /// Whether the NAC is the default P25 NAC.
+    pub fn is_default(self) -> bool { self == NetworkAccessCode::Default }
+}
+
+/// Set of NACs a receiver accepts, with all others being dropped.
+#[derive(Clone, Debug)]
+pub struct NacFilter {
+    /// Allowed NACs, where an empty set allows any NAC.
+    allowed: Vec<NetworkAccessCode>,
+}
+
+impl NacFilter {
+    /// Create a new `NacFilter` that allows any NAC.
+    pub fn new() -> NacFilter {
+        NacFilter {
+            allowed: vec![],
+        }
+    }
+
+    /// Add the given NAC to the allowed set. Allowing `ReceiveAny` causes every NAC to
+    /// be accepted.
+    pub fn allow(mut self, nac: NetworkAccessCode) -> NacFilter {
+        self.allowed.push(nac);
+        self
+    }
+
+    /// Check if the given received NAC passes the filter.
+    pub fn allows(&self, nac: NetworkAccessCode) -> bool {
+        self.allowed.is_empty() || self.allowed.iter().any(|&a| {
+            a.is_receive_any() || a == nac
+        })
+    }
 }
 
 /// Data unit of associated packet.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "ser", derive(Serialize, Deserialize))]
 pub enum DataUnit {
     /// Voice header packet.
     VoiceHeader,
@@ -102,6 +146,7 @@ impl DataUnit {
 
 /// NID word associated with each P25 packet.
 #[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "ser", derive(Serialize, Deserialize))]
 pub struct NetworkId {
     /// NAC field.
     pub access_code: NetworkAccessCode,
@@ -131,6 +176,13 @@ impl NetworkId {
         (self.access_code.to_bits() as u16) << 4 | self.data_unit.to_bits() as u16
     }
 
+    /// Compute the final bit of the coded NID word, which follows the BCH codeword and
+    /// isn't covered by it. The last column of the spec's generator matrix makes it the
+    /// parity of the two low DUID bits, so it's set only for LDU1 and LDU2.
+    pub fn parity_bit(&self) -> u8 {
+        (bch::encode(self.to_bits()) & 1) as u8
+    }
+
     /// Encode NID into a byte sequence.
     pub fn encode(&self) -> [u8; 8] {
         let bits = self.to_bits();
@@ -167,19 +219,136 @@ impl NidReceiver {
     /// an NID was successfully parsed, `Some(Err(err))` if an unrecoverable error
     /// occurred, and `None` for no event.
     pub fn feed(&mut self, dibit: Dibit) -> Option<Result<NetworkId>> {
-        let buf = match self.dibits.feed(dibit) {
-            Some(buf) => *buf,
-            None => return None,
-        };
-
-        let data = match bch::decode(buf) {
-            Some((data, err)) => data,
-            None => return Some(Err(P25Error::BchUnrecoverable)),
-        };
-
-        match NetworkId::from_bits(data) {
-            Some(nid) => Some(Ok(nid)),
-            None => Some(Err(P25Error::UnknownNid)),
+        self.feed_word(dibit).map(|buf| decode_word(buf).map(|(nid, _)| nid))
+    }
+
+    /// Feed in a data symbol, possibly producing the raw 64-bit coded NID word.
+    pub fn feed_word(&mut self, dibit: Dibit) -> Option<u64> {
+        self.dibits.feed(dibit).map(|buf| *buf)
+    }
+}
+
+/// Decode the given 64-bit coded NID word. Return `Ok((nid, err))`, where `err` is the
+/// number of corrected bits, if the NID was successfully parsed, and `Err(err)` if an
+/// unrecoverable error occurred.
+pub fn decode_word(buf: u64) -> Result<(NetworkId, usize)> {
+    let (data, err) = match profile::inner_code(Field::Nid).decode(buf) {
+        Some((data, err)) => (data as u16, err),
+        None => return Err(P25Error::BchUnrecoverable),
+    };
+
+    match NetworkId::from_bits(data) {
+        Some(nid) => Ok((nid, err)),
+        None => Err(P25Error::UnknownNid),
+    }
+}
+
+/// Decode the given 64-bit coded NID word in the same way as `decode_word`, but also
+/// reject the NID with `UnknownNid` if the final parity bit, which BCH decoding can't
+/// correct, doesn't match the parity computed from the decoded data unit.
+pub fn decode_word_strict(buf: u64) -> Result<(NetworkId, usize)> {
+    match decode_word(buf) {
+        Ok((nid, _)) if (buf & 1) as u8 != nid.parity_bit() => Err(P25Error::UnknownNid),
+        x => x,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::NetworkAccessCode::*;
+
+    #[test]
+    fn test_nac_filter() {
+        let f = NacFilter::new();
+        assert!(f.allows(Default));
+        assert!(f.allows(Other(0x123)));
+
+        let f = NacFilter::new().allow(Other(0x123));
+        assert!(f.allows(Other(0x123)));
+        assert!(!f.allows(Other(0x124)));
+        assert!(!f.allows(Default));
+
+        let f = NacFilter::new().allow(Other(0x123)).allow(ReceiveAny);
+        assert!(f.allows(Other(0x124)));
+        assert!(f.allows(Default));
+
+        // RepeatAny is a repeater setting, so it only matches itself.
+        let f = NacFilter::new().allow(NetworkAccessCode::from_bits(0xF7F));
+        assert!(f.allows(RepeatAny));
+        assert!(!f.allows(Other(0x124)));
+        assert!(!f.allows(Default));
+        assert!(!f.allows(ReceiveAny));
+    }
+
+    #[test]
+    fn test_nac_special() {
+        assert!(NetworkAccessCode::from_bits(0xF7E).is_receive_any());
+        assert!(!NetworkAccessCode::from_bits(0xF7F).is_receive_any());
+        assert!(!NetworkAccessCode::from_bits(0x3A1).is_receive_any());
+        assert!(!NetworkAccessCode::from_bits(0x293).is_receive_any());
+
+        assert!(NetworkAccessCode::from_bits(0xF7F).is_repeat_any());
+        assert!(!NetworkAccessCode::from_bits(0xF7E).is_repeat_any());
+        assert!(!NetworkAccessCode::from_bits(0x293).is_repeat_any());
+
+        assert!(NetworkAccessCode::from_bits(0x293).is_default());
+        assert!(!NetworkAccessCode::from_bits(0x3A1).is_default());
+        assert!(!NetworkAccessCode::from_bits(0xF7E).is_default());
+    }
+
+    #[test]
+    fn test_decode_word_strict() {
+        use self::DataUnit::*;
+
+        let units = [VoiceHeader, VoiceSimpleTerminator, VoiceLCTerminator,
+                     VoiceLCFrameGroup, VoiceCCFrameGroup, DataPacket, TrunkingSignaling];
+
+        for &du in units.iter() {
+            for &nac in [Default, Other(0x123), Other(0xABC), ReceiveAny].iter() {
+                let nid = NetworkId::new(nac, du);
+                let word = bch::encode(nid.to_bits());
+
+                let duid = du.to_bits();
+                assert_eq!(nid.parity_bit(), (duid ^ duid >> 1) & 1);
+
+                // Spec values always pass, even with correctable errors.
+                let (dec, err) = decode_word_strict(word).unwrap();
+                assert_eq!(dec.to_bits(), nid.to_bits());
+                assert_eq!(err, 0);
+
+                let (dec, err) = decode_word_strict(word ^ 1 << 40 ^ 1 << 7).unwrap();
+                assert_eq!(dec.to_bits(), nid.to_bits());
+                assert_eq!(err, 2);
+
+                // The parity bit isn't corrected, so only the strict decode catches it.
+                assert!(decode_word(word ^ 1).is_ok());
+                assert_eq!(decode_word_strict(word ^ 1).err(), Some(P25Error::UnknownNid));
+                assert_eq!(decode_word_strict(word ^ 1 ^ 1 << 20).err(),
+                           Some(P25Error::UnknownNid));
+            }
         }
     }
+
+    #[test]
+    fn test_parity_bit() {
+        // LDU1 with the default NAC. The BCH bits were computed by dividing by the
+        // generator polynomial 6331 1413 6723 5453 (octal) rather than through
+        // `bch::encode`, and the parity bit is set for LDU1.
+        let word = 0x293555EF2C653437;
+        let nid = NetworkId::new(Default, DataUnit::VoiceLCFrameGroup);
+
+        assert_eq!(nid.parity_bit(), 1);
+        assert_eq!(bch::encode(nid.to_bits()), word);
+
+        let (dec, err) = decode_word_strict(word).unwrap();
+        assert_eq!(dec.to_bits(), 0x2935);
+        assert_eq!(err, 0);
+        assert_eq!(decode_word_strict(word ^ 1).err(), Some(P25Error::UnknownNid));
+
+        // Trunking signaling codeword computed the same way, whose parity bit is clear.
+        let nid = NetworkId::new(Default, DataUnit::TrunkingSignaling);
+        assert_eq!(nid.parity_bit(), 0);
+        assert_eq!(bch::encode(nid.to_bits()), 0x2937F88514ABAECC);
+    }
 }
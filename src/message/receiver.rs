@@ -1,6 +1,9 @@
 //! High-level receiver for receiving P25 voice, data, and trunking messages.
 
-use error::P25Error;
+use std::io::{self, Read};
+
+use bits::Dibit;
+use error::{P25Error, Result};
 use message::data_unit::{DataUnitReceiver, ReceiverEvent};
 use message::nid::NetworkId;
 use message::status::StreamSymbol;
@@ -61,6 +64,8 @@ enum StateChange {
     Event(MessageEvent),
     /// Propagate an event and change state.
     EventChange(MessageEvent, State),
+    /// Change state without an event.
+    Change(State),
     /// Do nothing.
     NoChange,
 }
@@ -71,6 +76,10 @@ pub struct MessageReceiver {
     pub recv: DataUnitReceiver,
     /// Current state.
     state: State,
+    /// Most total corrections allowed in a voice frame, if any.
+    budget: Option<usize>,
+    /// Whether voice frames are skipped rather than decoded.
+    metadata_only: bool,
 }
 
 impl MessageReceiver {
@@ -79,34 +88,65 @@ impl MessageReceiver {
         MessageReceiver {
             recv: DataUnitReceiver::new(),
             state: State::Idle,
+            budget: None,
+            metadata_only: false,
         }
     }
 
+    /// Reject voice frames whose total corrections exceed the given budget, as described
+    /// by `FrameGroupReceiver::set_correction_budget`.
+    pub fn set_correction_budget(&mut self, budget: Option<usize>) {
+        self.budget = budget;
+    }
+
+    /// Decode only metadata, skipping the FEC of voice frames so no `VoiceFrame` events
+    /// are produced. NIDs, headers, link and crypto control, low-speed data, and TSBKs
+    /// are still decoded, which speeds up passes that only index a capture.
+    pub fn set_metadata_only(&mut self, metadata_only: bool) {
+        self.metadata_only = metadata_only;
+    }
+
     /// Feed in a baseband sample, possibly producing a new event or message to be handled
     /// by the given handler.
     pub fn feed(&mut self, s: f32) -> Option<MessageEvent> {
-        match self.handle(s) {
+        let event = self.recv.feed(s);
+        self.apply(event)
+    }
+
+    /// Feed in a hard-decision dibit in place of the samples of a symbol period, as
+    /// described by `DataUnitReceiver::feed_dibit`, possibly producing a new event or
+    /// message.
+    pub fn feed_dibit(&mut self, dibit: Dibit) -> Option<MessageEvent> {
+        let event = self.recv.feed_dibit(dibit);
+        self.apply(event)
+    }
+
+    /// Handle the given low-level receiver event and update state.
+    fn apply(&mut self, event: Option<Result<ReceiverEvent>>) -> Option<MessageEvent> {
+        match self.handle(event) {
             StateChange::Event(e) => Some(e),
             StateChange::EventChange(e, s) => {
                 self.state = s;
                 Some(e)
             },
+            StateChange::Change(s) => {
+                self.state = s;
+                None
+            },
             StateChange::NoChange => None,
         }
     }
 
-    /// Process the given sample and determine how to update state.
-    fn handle(&mut self, s: f32) -> StateChange {
+    /// Process the given low-level receiver event and determine how to update state.
+    fn handle(&mut self, event: Option<Result<ReceiverEvent>>) -> StateChange {
         use self::State::*;
         use self::StateChange::*;
         use message::nid::DataUnit::*;
 
-        let event = match self.recv.feed(s) {
+        let event = match event {
             Some(Ok(event)) => event,
-            Some(Err(err)) => {
-                self.recv.resync();
-                return Event(MessageEvent::Error(err));
-            },
+            // The low-level receiver decides when to fall back to frame sync.
+            Some(Err(err)) => return Event(MessageEvent::Error(err)),
             None => return NoChange,
         };
 
@@ -121,10 +161,18 @@ impl MessageReceiver {
                     },
                     VoiceLCTerminator =>
                         DecodeLCTerminator(VoiceLCTerminatorReceiver::new()),
-                    VoiceLCFrameGroup =>
-                        DecodeLCFrameGroup(VoiceLCFrameGroupReceiver::new()),
-                    VoiceCCFrameGroup =>
-                        DecodeCCFrameGroup(VoiceCCFrameGroupReceiver::new()),
+                    VoiceLCFrameGroup => {
+                        let mut fg = VoiceLCFrameGroupReceiver::new();
+                        fg.set_correction_budget(self.budget);
+                        fg.set_skip_voice(self.metadata_only);
+                        DecodeLCFrameGroup(fg)
+                    },
+                    VoiceCCFrameGroup => {
+                        let mut fg = VoiceCCFrameGroupReceiver::new();
+                        fg.set_correction_budget(self.budget);
+                        fg.set_skip_voice(self.metadata_only);
+                        DecodeCCFrameGroup(fg)
+                    },
                     TrunkingSignaling =>
                         DecodeTSBK(TsbkReceiver::new()),
                     DataPacket => {
@@ -142,10 +190,12 @@ impl MessageReceiver {
         match self.state {
             DecodeHeader(ref mut head) => match head.feed(dibit) {
                 Some(Ok(h)) => {
+                    log_trace!("voice header received");
                     self.recv.flush_pads();
                     EventChange(MessageEvent::VoiceHeader(h), Idle)
                 },
                 Some(Err(err)) => {
+                    log_debug!("decode failed: {:?}, returning to frame sync", err);
                     self.recv.resync();
                     EventChange(MessageEvent::Error(err), Idle)
                 },
@@ -166,11 +216,26 @@ impl MessageReceiver {
                             Event(MessageEvent::LowSpeedDataFragment(frag)),
                     }
                 },
+                // The frame group receiver has already moved past the rejected frame.
+                Some(Err(err @ P25Error::CorrectionBudgetExceeded)) => {
+                    if fg.done() {
+                        self.recv.flush_pads();
+                    }
+
+                    Event(MessageEvent::Error(err))
+                },
                 Some(Err(err)) => {
+                    log_debug!("decode failed: {:?}, returning to frame sync", err);
                     self.recv.resync();
                     EventChange(MessageEvent::Error(err), Idle)
                 },
-                None => NoChange,
+                // A skipped voice frame can finish the group without an event.
+                None => if fg.done() {
+                    self.recv.flush_pads();
+                    Change(Idle)
+                } else {
+                    NoChange
+                },
             },
             DecodeCCFrameGroup(ref mut fg) => match fg.feed(dibit) {
                 Some(Ok(event)) => match event {
@@ -186,18 +251,33 @@ impl MessageReceiver {
                     FrameGroupEvent::DataFragment(frag) =>
                         Event(MessageEvent::LowSpeedDataFragment(frag))
                 },
+                Some(Err(err @ P25Error::CorrectionBudgetExceeded)) => {
+                    if fg.done() {
+                        self.recv.flush_pads();
+                    }
+
+                    Event(MessageEvent::Error(err))
+                },
                 Some(Err(err)) => {
+                    log_debug!("decode failed: {:?}, returning to frame sync", err);
                     self.recv.resync();
                     EventChange(MessageEvent::Error(err), Idle)
                 },
-                None => NoChange,
+                None => if fg.done() {
+                    self.recv.flush_pads();
+                    Change(Idle)
+                } else {
+                    NoChange
+                },
             },
             DecodeLCTerminator(ref mut term) => match term.feed(dibit) {
                 Some(Ok(lc)) => {
+                    log_trace!("voice terminator received");
                     self.recv.flush_pads();
                     EventChange(MessageEvent::VoiceTerm(lc), Idle)
                 },
                 Some(Err(err)) => {
+                    log_debug!("decode failed: {:?}, returning to frame sync", err);
                     self.recv.resync();
                     EventChange(MessageEvent::Error(err), Idle)
                 },
@@ -205,6 +285,10 @@ impl MessageReceiver {
             },
             DecodeTSBK(ref mut dec) => match dec.feed(dibit) {
                 Some(Ok(tsbk)) => {
+                    log_trace!("TSBK received: opcode {:?}, mfg {:#04x}, CRC {}",
+                               tsbk.opcode(), tsbk.mfg(),
+                               if tsbk.crc_valid() { "pass" } else { "fail" });
+
                     if tsbk.is_tail() {
                         self.recv.flush_pads();
                     }
@@ -212,6 +296,7 @@ impl MessageReceiver {
                     Event(MessageEvent::TrunkingControl(tsbk))
                 },
                 Some(Err(err)) => {
+                    log_debug!("decode failed: {:?}, returning to frame sync", err);
                     self.recv.resync();
                     EventChange(MessageEvent::Error(err), Idle)
                 },
@@ -221,3 +306,292 @@ impl MessageReceiver {
         }
     }
 }
+
+/// Number of bytes requested from the reader at a time by `Frames`.
+const READ_BYTES: usize = 512;
+
+/// Create an iterator that decodes messages from the given reader of packed dibits, 4 per
+/// byte with the first dibit in the MSBs.
+///
+/// Each dibit is fed to a `MessageReceiver` as a hard decision. Bytes are pulled from
+/// the reader only as the receiver needs them, so large captures don't have to be
+/// loaded into memory.
+pub fn frames<R: Read>(reader: R) -> Frames<R> {
+    Frames {
+        reader: reader,
+        recv: MessageReceiver::new(),
+        buf: [0; READ_BYTES],
+        pos: 0,
+        len: 0,
+        dibit: 0,
+        done: false,
+    }
+}
+
+/// Iterator over the messages decoded from a reader, created by `frames`.
+///
+/// Receiver errors are yielded as `MessageEvent::Error` events and decoding continues
+/// after them. The iterator ends at the end of the reader, or after yielding any I/O
+/// error other than an interrupted read.
+pub struct Frames<R: Read> {
+    /// Source of packed dibits.
+    reader: R,
+    /// Receiver the dibits are fed to.
+    recv: MessageReceiver,
+    /// Bytes read from the reader.
+    buf: [u8; READ_BYTES],
+    /// Index of the current byte in `buf`.
+    pos: usize,
+    /// Number of valid bytes in `buf`.
+    len: usize,
+    /// Index of the next dibit in the current byte.
+    dibit: usize,
+    /// Whether the reader has been exhausted.
+    done: bool,
+}
+
+impl<R: Read> Frames<R> {
+    /// Get the underlying receiver.
+    pub fn receiver(&mut self) -> &mut MessageReceiver { &mut self.recv }
+
+    /// Get the next dibit, reading more bytes if needed, or `None` if the reader is
+    /// exhausted.
+    fn next_dibit(&mut self) -> Option<io::Result<Dibit>> {
+        while self.pos == self.len {
+            if self.done {
+                return None;
+            }
+
+            match self.reader.read(&mut self.buf[..]) {
+                Ok(0) => self.done = true,
+                Ok(n) => {
+                    self.pos = 0;
+                    self.len = n;
+                },
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {},
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                },
+            }
+        }
+
+        let dibit = Dibit::new(self.buf[self.pos] >> (6 - self.dibit * 2) & 0b11);
+
+        self.dibit += 1;
+
+        if self.dibit == 4 {
+            self.dibit = 0;
+            self.pos += 1;
+        }
+
+        Some(Ok(dibit))
+    }
+}
+
+impl<R: Read> Iterator for Frames<R> {
+    type Item = io::Result<MessageEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let dibit = match self.next_dibit() {
+                Some(Ok(d)) => d,
+                Some(Err(e)) => return Some(Err(e)),
+                None => return None,
+            };
+
+            if let Some(event) = self.recv.feed_dibit(dibit) {
+                return Some(Ok(event));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::{self, Cursor};
+    use testutil::{max_errors_in_group, voice_chunks};
+    use voice::frame_group::{encode_group, LinkControlExtra, GROUP_BYTES, GROUP_FRAMES};
+
+    /// Capture with two TSBK frames followed by a truncated TSBK frame.
+    const CAPTURE: &'static [u8] = include_bytes!("../../examples/data/tsbk.bin");
+
+    /// Reader that returns at most one byte per read and is interrupted before each.
+    struct Trickle<'a> {
+        bytes: &'a [u8],
+        interrupt: bool,
+    }
+
+    impl<'a> Read for Trickle<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.interrupt = !self.interrupt;
+
+            if self.interrupt {
+                return Err(io::Error::new(io::ErrorKind::Interrupted, "interrupted"));
+            }
+
+            match self.bytes.split_first() {
+                Some((&b, rest)) => {
+                    buf[0] = b;
+                    self.bytes = rest;
+                    Ok(1)
+                },
+                None => Ok(0),
+            }
+        }
+    }
+
+    /// Reader that fails with the given error once its bytes run out.
+    struct Failing<'a> {
+        bytes: &'a [u8],
+        kind: io::ErrorKind,
+    }
+
+    impl<'a> Read for Failing<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match self.bytes.read(buf) {
+                Ok(0) => Err(io::Error::new(self.kind, "failed")),
+                x => x,
+            }
+        }
+    }
+
+    /// Summarize the given events as NIDs and TSBK opcodes.
+    fn summarize<T: Iterator<Item = io::Result<MessageEvent>>>(events: T) -> Vec<String> {
+        events.map(|e| match e {
+            Ok(MessageEvent::PacketNID(nid)) => format!("{:?}", nid.data_unit),
+            Ok(MessageEvent::TrunkingControl(tsbk)) => format!("{:?}", tsbk.opcode()),
+            Ok(MessageEvent::Error(e)) => format!("{:?}", e),
+            Ok(_) => "other".to_string(),
+            Err(e) => format!("io {:?}", e.kind()),
+        }).collect()
+    }
+
+    #[test]
+    fn test_frames() {
+        let events = summarize(frames(Cursor::new(CAPTURE)));
+
+        // The truncated frame's NID is decoded, then the iterator ends cleanly.
+        assert_eq!(events, [
+            "TrunkingSignaling", "Some(GroupVoiceGrant)",
+            "TrunkingSignaling", "Some(GroupVoiceGrant)",
+            "TrunkingSignaling",
+        ]);
+
+        assert_eq!(events, summarize(frames(Trickle {
+            bytes: CAPTURE,
+            interrupt: false,
+        })));
+
+        assert!(frames(Cursor::new(&[][..])).next().is_none());
+
+        // An I/O error is yielded after the events decoded so far, then ends the
+        // iterator.
+        let mut f = frames(Failing { bytes: CAPTURE, kind: io::ErrorKind::Other });
+        let failed = summarize(f.by_ref());
+        assert_eq!(failed[..events.len()], events[..]);
+        assert_eq!(failed[events.len()..], ["io Other"]);
+        assert!(f.next().is_none());
+    }
+
+    /// Summarize the given events, keeping the contents of metadata events.
+    fn events(f: Frames<Cursor<Vec<u8>>>) -> Vec<String> {
+        f.map(|e| match e {
+            Ok(MessageEvent::PacketNID(nid)) => format!("{:?}", nid.data_unit),
+            Ok(MessageEvent::VoiceFrame(vf)) => format!("voice {}", vf.chunks[0]),
+            Ok(MessageEvent::LinkControl(lc)) => format!("lc {:?}", lc.bytes()),
+            Ok(MessageEvent::LowSpeedDataFragment(f)) => format!("lsd {:#x}", f),
+            Ok(MessageEvent::Error(e)) => format!("{:?}", e),
+            Ok(_) => "other".to_string(),
+            Err(e) => format!("io {:?}", e.kind()),
+        }).collect()
+    }
+
+    /// Build an LDU1 whose voice frames start with their 1-based index.
+    fn test_group() -> [u8; GROUP_BYTES] {
+        let mut chunks = [[0; 8]; GROUP_FRAMES];
+
+        for (i, c) in chunks.iter_mut().enumerate() {
+            *c = voice_chunks(i as u16 + 1);
+        }
+
+        let lc = LinkControlFields::new([0x00, 0x00, 0x80, 0, 0, 1, 2, 3, 4]);
+        encode_group::<LinkControlExtra>(&chunks, &lc, [0x12, 0x34])
+    }
+
+    /// Build a capture of packed dibits holding noise followed by the given LDU1.
+    fn group_capture(group: &[u8; GROUP_BYTES]) -> Vec<u8> {
+        use baseband::sync::patterns;
+        use bits::{DibitBytes, Dibits};
+        use collect_slice::CollectSlice;
+        use message::nid::{DataUnit, NetworkAccessCode};
+        use message::status::StatusCode;
+        use symbol::{frame_out, Layout};
+
+        let nid = NetworkId::new(NetworkAccessCode::Default, DataUnit::VoiceLCFrameGroup);
+
+        let data = Dibits::new(nid.encode().iter().cloned())
+            .chain(Dibits::new(group.iter().cloned()))
+            .collect::<Vec<_>>();
+
+        // Start with noise, then send the frame group.
+        let mut dibits = (0..1024u32)
+            .map(|i| Dibit::new((i * 1103 + 12345) as u8 >> 3 & 0b11))
+            .collect::<Vec<_>>();
+        dibits.extend(patterns::PHASE1_DIBITS.iter().map(|&d| Dibit::new(d)));
+        dibits.extend(frame_out(&data, 0, Layout::Plain, StatusCode::InboundIdle)
+            .unwrap());
+        dibits.resize((dibits.len() / 4 + 16) * 4, Dibit::new(0b01));
+
+        let mut bytes = vec![0; dibits.len() / 4];
+        DibitBytes::new(dibits.into_iter()).collect_slice_checked(&mut bytes[..]);
+
+        bytes
+    }
+
+    #[test]
+    fn test_metadata_only() {
+        let bytes = group_capture(&test_group());
+        let full = events(frames(Cursor::new(bytes.clone())));
+
+        assert_eq!(full.iter().filter(|e| e.starts_with("voice")).count(), GROUP_FRAMES);
+        assert_eq!(full[0], "VoiceLCFrameGroup");
+        assert_eq!(full[1], "voice 1");
+
+        let mut f = frames(Cursor::new(bytes));
+        f.receiver().set_metadata_only(true);
+        let meta = events(f);
+
+        // Metadata matches the full decode, without any voice frames.
+        assert_eq!(meta, full.into_iter().filter(|e| !e.starts_with("voice"))
+                                .collect::<Vec<_>>());
+        assert_eq!(meta, [
+            "VoiceLCFrameGroup",
+            "lc [0, 0, 128, 0, 0, 1, 2, 3, 4]",
+            "lsd 0x1234",
+        ]);
+    }
+
+    #[test]
+    fn test_correction_budget() {
+        use voice::frame::MAX_CORRECTIONS;
+
+        let mut group = test_group();
+
+        // Add the most correctable errors to every chunk of the fourth frame.
+        max_errors_in_group(&mut group, 3, &voice_chunks(4));
+
+        let mut f = frames(Cursor::new(group_capture(&group)));
+        f.receiver().set_correction_budget(Some(MAX_CORRECTIONS - 1));
+
+        // The rejected frame is reported without dropping the rest of the LDU.
+        assert_eq!(events(f), [
+            "VoiceLCFrameGroup",
+            "voice 1", "voice 2", "voice 3", "CorrectionBudgetExceeded",
+            "voice 5", "voice 6", "voice 7",
+            "lc [0, 0, 128, 0, 0, 1, 2, 3, 4]",
+            "voice 8", "lsd 0x1234", "voice 9",
+        ]);
+    }
+}
@@ -57,6 +57,7 @@ impl<T: Iterator<Item = bits::Dibit>> Iterator for StatusInterleaver<T> {
 
 /// A P25 status symbol.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "ser", derive(Serialize, Deserialize))]
 pub enum StatusCode {
     /// Used by a repeater when the inbound channel is idle.
     InboundIdle,
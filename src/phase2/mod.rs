@@ -0,0 +1,8 @@
+//! Initial support for the Phase 2 TDMA air interface, covering frame synchronization.
+//!
+//! The informational inter-slot signaling channel (I-ISCH), which identifies the slot
+//! structure, isn't decoded yet. Its 40-bit channel code and the layout of its 9
+//! information bits need to be checked against the standard and a known-good codeword
+//! before they can be relied on.
+
+pub mod sync;
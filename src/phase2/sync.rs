@@ -0,0 +1,98 @@
+//! Detect the Phase 2 frame sync sequence in a stream of hard-decision dibits.
+
+use baseband::sync::patterns;
+use bits::Dibit;
+use util::hamming_distance;
+
+/// Frame sync sequence carried in the S-ISCH, with the first dibit in the MSBs.
+pub const SYNC: u64 = patterns::PHASE2;
+
+/// Number of dibits in the frame sync sequence.
+pub const SYNC_DIBITS: usize = 20;
+
+/// Mask for the bits that make up the sync sequence.
+const SYNC_MASK: u64 = (1 << SYNC_DIBITS * 2) - 1;
+
+/// Detects the Phase 2 frame sync sequence, allowing for a number of bit errors.
+pub struct SyncDetector {
+    /// Most recently received bits, with the latest dibit in the LSBs.
+    bits: u64,
+    /// Number of dibits received, up to the length of the sync sequence.
+    count: usize,
+    /// Maximum number of bit errors to allow in a detected sequence.
+    max_errors: u32,
+}
+
+impl SyncDetector {
+    /// Create a new `SyncDetector` that allows up to the given number of bit errors.
+    pub fn new(max_errors: u32) -> SyncDetector {
+        SyncDetector {
+            bits: 0,
+            count: 0,
+            max_errors: max_errors,
+        }
+    }
+
+    /// Feed in a dibit. Return `Some(err)` if it completed a sync sequence with `err`
+    /// bit errors, and `None` otherwise.
+    pub fn feed(&mut self, dibit: Dibit) -> Option<u32> {
+        self.bits = (self.bits << 2 | dibit.bits() as u64) & SYNC_MASK;
+
+        if self.count < SYNC_DIBITS {
+            self.count += 1;
+
+            if self.count < SYNC_DIBITS {
+                return None;
+            }
+        }
+
+        let err = hamming_distance(self.bits, SYNC);
+
+        if err <= self.max_errors {
+            Some(err)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bits::Dibit;
+
+    fn sync_dibits() -> Vec<Dibit> {
+        (0..SYNC_DIBITS).rev().map(|i| Dibit::new((SYNC >> i * 2) as u8 & 0b11)).collect()
+    }
+
+    #[test]
+    fn test_sync() {
+        let mut d = SyncDetector::new(4);
+
+        for _ in 0..30 {
+            assert!(d.feed(Dibit::new(0b00)).is_none());
+        }
+
+        let sync = sync_dibits();
+
+        for &dibit in &sync[..SYNC_DIBITS - 1] {
+            assert!(d.feed(dibit).is_none());
+        }
+
+        assert_eq!(d.feed(sync[SYNC_DIBITS - 1]), Some(0));
+        assert!(d.feed(Dibit::new(0b01)).is_none());
+
+        // Corrupted sequence.
+        let mut d = SyncDetector::new(4);
+        let mut sync = sync_dibits();
+        sync[3] = Dibit::new(sync[3].bits() ^ 0b11);
+        sync[10] = Dibit::new(sync[10].bits() ^ 0b01);
+
+        let last = sync.iter().map(|&dibit| d.feed(dibit)).last().unwrap();
+        assert_eq!(last, Some(3));
+
+        let mut d = SyncDetector::new(2);
+        let last = sync.iter().map(|&dibit| d.feed(dibit)).last().unwrap();
+        assert_eq!(last, None);
+    }
+}
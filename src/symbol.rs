@@ -0,0 +1,171 @@
+//! Build the symbols of a frame for transmission, as the inverse of receiving them.
+//!
+//! After the frame sync, the data dibits of a frame are sent with a status symbol after
+//! every 35 dibits, counted from the start of the frame sync. Trellis-coded blocks, such
+//! as TSBKs and data packet blocks, are also interleaved before they're sent.
+
+use bits::Dibit;
+use consts::{CODING_DIBITS, SYNC_SYMBOLS};
+use data::interleave::Interleaver;
+use error::{P25Error, Result};
+use message::status::StatusCode;
+
+/// Number of dibits sent between status symbols.
+const STATUS_PERIOD: usize = 35;
+
+/// Number of data dibits sent after the frame sync before the first status symbol.
+const FIRST_STATUS: usize = STATUS_PERIOD - SYNC_SYMBOLS;
+
+/// Layout of the data dibits given to `frame_out`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Layout {
+    /// Dibits are sent as given, such as the NID, voice frames, or packets already
+    /// interleaved by the builders in `data::packet`.
+    Plain,
+    /// Dibits are trellis-coded blocks of `CODING_DIBITS` each, which are interleaved
+    /// before they're sent.
+    Blocks,
+}
+
+/// Build the symbols that carry the given data dibits, interleaving them according to the
+/// given layout and inserting the given status code at each status position.
+///
+/// The dibits are taken to follow `start` data dibits already sent after the frame sync,
+/// so a frame can be built in pieces, and the status cadence starts over with each
+/// frame. Each status symbol is output with the piece holding the dibit before it, so
+/// the status symbol ending a frame is included. Return an error if the layout is
+/// `Blocks` and the dibits end in a partial block.
+pub fn frame_out(dibits: &[Dibit], start: usize, layout: Layout, status: StatusCode)
+    -> Result<Vec<Dibit>>
+{
+    let data = match layout {
+        Layout::Plain => dibits.to_vec(),
+        Layout::Blocks => {
+            if dibits.len() % CODING_DIBITS != 0 {
+                return Err(P25Error::TooShort {
+                    needed: (dibits.len() / CODING_DIBITS + 1) * CODING_DIBITS,
+                    got: dibits.len(),
+                });
+            }
+
+            let mut out = Vec::with_capacity(dibits.len());
+
+            for block in dibits.chunks(CODING_DIBITS) {
+                let mut buf = [Dibit::default(); CODING_DIBITS];
+                buf.copy_from_slice(block);
+
+                out.extend(Interleaver::new(buf));
+            }
+
+            out
+        },
+    };
+
+    let mut out = Vec::with_capacity(data.len() + data.len() / STATUS_PERIOD + 1);
+
+    for (idx, &dibit) in data.iter().enumerate() {
+        out.push(dibit);
+
+        let sent = start + idx + 1;
+
+        if sent >= FIRST_STATUS && (sent - FIRST_STATUS) % STATUS_PERIOD == 0 {
+            out.push(status.to_dibit());
+        }
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use baseband::sync::patterns;
+    use bits::Dibits;
+    use data::{coder, interleave};
+    use data::channel::strip_status;
+    use message::nid::{DataUnit, NetworkAccessCode, NetworkId};
+    use message::status::StatusInterleaver;
+
+    #[test]
+    fn test_frame_out() {
+        let dibits = (0..200).map(|i| Dibit::new(i as u8 & 0b11)).collect::<Vec<_>>();
+        let symbols = frame_out(&dibits, 0, Layout::Plain, StatusCode::InboundBusy)
+            .unwrap();
+
+        // First status follows 11 data dibits, after the 24 symbol frame sync.
+        assert_eq!(symbols.len(), 200 + 6);
+        assert_eq!(symbols[11], StatusCode::InboundBusy.to_dibit());
+        assert_eq!(symbols[11 + 36], StatusCode::InboundBusy.to_dibit());
+        assert_eq!(strip_status(&symbols), dibits);
+
+        // Building in pieces gives the same frame, wherever the pieces are split.
+        for &split in [1, 10, 11, 12, 32, 46, 47, 199].iter() {
+            let mut pieces = frame_out(&dibits[..split], 0, Layout::Plain,
+                                       StatusCode::InboundBusy).unwrap();
+            pieces.extend(frame_out(&dibits[split..], split, Layout::Plain,
+                                    StatusCode::InboundBusy).unwrap());
+            assert_eq!(pieces, symbols);
+        }
+
+        // The status symbol ending a frame is included.
+        let end = frame_out(&dibits[..46], 0, Layout::Plain, StatusCode::InboundIdle)
+            .unwrap();
+        assert_eq!(end.len(), 48);
+        assert_eq!(end[47], StatusCode::InboundIdle.to_dibit());
+
+        // Matches a full frame built with the status interleaver.
+        let nid = NetworkId::new(NetworkAccessCode::Default, DataUnit::DataPacket);
+        let nid = Dibits::new(nid.encode().iter().cloned()).collect::<Vec<_>>();
+
+        let mut out = patterns::PHASE1_DIBITS.iter().map(|&d| Dibit::new(d))
+            .collect::<Vec<_>>();
+        out.extend(frame_out(&nid, 0, Layout::Plain, StatusCode::InboundIdle).unwrap());
+        out.extend(frame_out(&dibits, nid.len(), Layout::Plain, StatusCode::InboundIdle)
+            .unwrap());
+
+        let full = StatusInterleaver::new(patterns::PHASE1_DIBITS.iter()
+                .map(|&d| Dibit::new(d))
+                .chain(nid.into_iter())
+                .chain(dibits.into_iter()),
+            StatusCode::InboundIdle).collect::<Vec<_>>();
+        assert_eq!(&out[..], &full[..out.len()]);
+    }
+
+    #[test]
+    fn test_blocks() {
+        let coded = (0..2).flat_map(|b| coder::DibitCoder::new()
+            .feed_bytes((0..12).map(move |i| i * 17 + b))
+            .finish()
+            .to_vec())
+            .collect::<Vec<_>>();
+
+        let interleaved = coded.chunks(CODING_DIBITS).flat_map(|b| {
+            let mut buf = [Dibit::default(); CODING_DIBITS];
+            buf.copy_from_slice(b);
+            interleave::Interleaver::new(buf)
+        }).collect::<Vec<_>>();
+
+        let symbols = frame_out(&coded, 32, Layout::Blocks, StatusCode::InboundBusy)
+            .unwrap();
+        assert_eq!(symbols, frame_out(&interleaved, 32, Layout::Plain,
+                                      StatusCode::InboundBusy).unwrap());
+
+        // Removing status symbols and deinterleaving recovers the coded blocks.
+        let mut padded = vec![Dibit::default(); 32];
+        padded.extend(interleaved.iter().cloned());
+        let stripped = strip_status(&frame_out(&padded, 0, Layout::Plain,
+                                               StatusCode::InboundBusy).unwrap());
+        assert_eq!(&stripped[32..], &interleaved[..]);
+
+        for (block, orig) in stripped[32..].chunks(CODING_DIBITS)
+            .zip(coded.chunks(CODING_DIBITS))
+        {
+            let mut buf = [Dibit::default(); CODING_DIBITS];
+            buf.copy_from_slice(block);
+            assert_eq!(interleave::Deinterleaver::new(&buf).collect::<Vec<_>>(), orig);
+        }
+
+        assert_eq!(frame_out(&coded[..100], 0, Layout::Blocks, StatusCode::InboundBusy),
+                   Err(P25Error::TooShort { needed: 196, got: 100 }));
+    }
+}
@@ -0,0 +1,237 @@
+//! Structural comparison of decoded messages, for building regression suites that check
+//! decodes of real captures against known-good results.
+//!
+//! Messages are compared field by field using their decoded accessors, so a mismatch
+//! names the field that differs rather than just the raw bytes. Payloads of opcodes
+//! without a field-level comparison are compared byte by byte.
+//!
+//! Also provides the voice frame fixtures shared by the crate's own tests.
+
+use collect_slice::CollectSlice;
+use std;
+
+use bits::{Dibit, DibitBytes};
+use consts::FRAME_DIBITS;
+use message::receiver::MessageEvent;
+use trunking::tsbk::{GroupVoiceGrant, TsbkFields, TsbkOpcode};
+use voice::control::{GroupVoiceTraffic, LinkControlFields, LinkControlOpcode};
+use voice::control::UnitVoiceTraffic;
+use voice::descramble::{deinterleave, interleave};
+use voice::frame::encode_dibits;
+use voice::frame_group::{FRAME_BYTES, FRAME_OFFSETS, GROUP_BYTES};
+
+/// Build the voice chunks used as a test fixture, starting with the given chunk so
+/// frames can be told apart.
+pub fn voice_chunks(first: u16) -> [u16; 8] {
+    [first, 0xFED, 0x0F0, 0x555, 0x1AB, 0x7FF, 0x000, 0x42]
+}
+
+/// Encode the given voice chunks with the most errors each chunk's code can correct,
+/// placing the Golay errors in the data bits so they're counted as corrections.
+pub fn max_errors(chunks: &[u16; 8]) -> [Dibit; FRAME_DIBITS] {
+    let mut coded = deinterleave(&encode_dibits(chunks));
+
+    for (idx, c) in coded[..7].iter_mut().enumerate() {
+        *c ^= if idx < 4 { 0x420800 } else { 0b1 };
+    }
+
+    interleave(&coded)
+}
+
+/// Replace the voice frame at the given index in the given encoded frame group with the
+/// given chunks, coded with errors as in `max_errors`.
+pub fn max_errors_in_group(group: &mut [u8; GROUP_BYTES], frame: usize,
+                           chunks: &[u16; 8])
+{
+    let start = FRAME_OFFSETS[frame] / 4;
+
+    DibitBytes::new(max_errors(chunks).iter().cloned())
+        .collect_slice_checked(&mut group[start..start + FRAME_BYTES]);
+}
+
+/// A field that differs between two decoded messages.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FieldDiff {
+    /// Name of the field.
+    pub field: String,
+    /// Value of the field in the expected message.
+    pub expected: String,
+    /// Value of the field in the actual message.
+    pub actual: String,
+}
+
+/// Compare the given expected and actual messages, returning a `FieldDiff` for each
+/// field that differs. The result is empty if the messages match.
+///
+/// Messages of different kinds produce a single `FieldDiff` for the `kind` field.
+pub fn diff_frames(expected: &MessageEvent, actual: &MessageEvent) -> Vec<FieldDiff> {
+    use message::receiver::MessageEvent::*;
+
+    let mut d = Diff(vec![]);
+
+    match (expected, actual) {
+        (&Error(a), &Error(b)) => d.field("error", a, b),
+        (&PacketNID(a), &PacketNID(b)) => {
+            d.field("access_code", a.access_code.to_bits(), b.access_code.to_bits());
+            d.field("data_unit", a.data_unit, b.data_unit);
+        },
+        (&VoiceHeader(ref a), &VoiceHeader(ref b)) => {
+            d.field("crypto_init", a.crypto_init(), b.crypto_init());
+            d.field("mfg", a.mfg(), b.mfg());
+            d.field("crypto_alg", a.crypto_alg(), b.crypto_alg());
+            d.field("crypto_key", a.crypto_key(), b.crypto_key());
+            d.field("talk_group", a.talk_group(), b.talk_group());
+        },
+        (&VoiceFrame(ref a), &VoiceFrame(ref b)) => {
+            for (i, (&x, &y)) in a.chunks.iter().zip(b.chunks.iter()).enumerate() {
+                d.field(&format!("chunks[{}]", i), x, y);
+            }
+        },
+        (&LinkControl(a), &LinkControl(b)) | (&VoiceTerm(a), &VoiceTerm(b)) =>
+            d.link_control(a, b),
+        (&CryptoControl(ref a), &CryptoControl(ref b)) => {
+            d.field("mi", a.mi(), b.mi());
+            d.field("alg", a.alg(), b.alg());
+            d.field("key", a.key(), b.key());
+        },
+        (&LowSpeedDataFragment(a), &LowSpeedDataFragment(b)) => d.field("fragment", a, b),
+        (&TrunkingControl(a), &TrunkingControl(b)) => d.tsbk(a, b),
+        (a, b) => d.field("kind", kind(a), kind(b)),
+    }
+
+    d.0
+}
+
+/// Name of the kind of the given message.
+fn kind(e: &MessageEvent) -> &'static str {
+    use message::receiver::MessageEvent::*;
+
+    match *e {
+        Error(_) => "Error",
+        PacketNID(_) => "PacketNID",
+        VoiceHeader(_) => "VoiceHeader",
+        VoiceFrame(_) => "VoiceFrame",
+        LinkControl(_) => "LinkControl",
+        CryptoControl(_) => "CryptoControl",
+        LowSpeedDataFragment(_) => "LowSpeedDataFragment",
+        TrunkingControl(_) => "TrunkingControl",
+        VoiceTerm(_) => "VoiceTerm",
+    }
+}
+
+/// Collects differing fields.
+struct Diff(Vec<FieldDiff>);
+
+impl Diff {
+    /// Record the given field if its expected and actual values differ.
+    fn field<T>(&mut self, name: &str, expected: T, actual: T) where
+        T: std::fmt::Debug + PartialEq
+    {
+        if expected != actual {
+            self.0.push(FieldDiff {
+                field: name.to_string(),
+                expected: format!("{:?}", expected),
+                actual: format!("{:?}", actual),
+            });
+        }
+    }
+
+    /// Record each payload byte that differs.
+    fn payload(&mut self, expected: &[u8], actual: &[u8]) {
+        for (i, (&x, &y)) in expected.iter().zip(actual.iter()).enumerate() {
+            self.field(&format!("payload[{}]", i), x, y);
+        }
+    }
+
+    /// Compare the given link control words.
+    fn link_control(&mut self, a: LinkControlFields, b: LinkControlFields) {
+        self.field("protected", a.protected(), b.protected());
+        self.field("opcode", a.opcode(), b.opcode());
+        self.field("mfg", a.mfg(), b.mfg());
+
+        if !self.0.is_empty() {
+            return;
+        }
+
+        match a.opcode() {
+            Some(LinkControlOpcode::GroupVoiceTraffic) => {
+                let (x, y) = (GroupVoiceTraffic::new(a), GroupVoiceTraffic::new(b));
+                self.field("opts", x.opts(), y.opts());
+                self.field("talkgroup", x.talkgroup(), y.talkgroup());
+                self.field("src_unit", x.src_unit(), y.src_unit());
+            },
+            Some(LinkControlOpcode::UnitVoiceTraffic) => {
+                let (x, y) = (UnitVoiceTraffic::new(a), UnitVoiceTraffic::new(b));
+                self.field("opts", x.opts(), y.opts());
+                self.field("dest", x.dest(), y.dest());
+                self.field("src", x.src(), y.src());
+            },
+            _ => self.payload(a.payload(), b.payload()),
+        }
+    }
+
+    /// Compare the given TSBKs.
+    fn tsbk(&mut self, a: TsbkFields, b: TsbkFields) {
+        self.field("is_tail", a.is_tail(), b.is_tail());
+        self.field("protected", a.protected(), b.protected());
+        self.field("opcode", a.opcode(), b.opcode());
+        self.field("mfg", a.mfg(), b.mfg());
+        self.field("crc_valid", a.crc_valid(), b.crc_valid());
+
+        if a.opcode() != b.opcode() || a.mfg() != b.mfg() {
+            return;
+        }
+
+        match a.opcode() {
+            Some(TsbkOpcode::GroupVoiceGrant) => {
+                let (x, y) = (GroupVoiceGrant::new(a), GroupVoiceGrant::new(b));
+                self.field("opts", x.opts(), y.opts());
+                self.field("channel", x.channel(), y.channel());
+                self.field("talkgroup", x.talkgroup(), y.talkgroup());
+                self.field("src_unit", x.src_unit(), y.src_unit());
+            },
+            _ => self.payload(a.payload(), b.payload()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use error::P25Error;
+
+    /// Build a group voice traffic LC for the given talkgroup.
+    fn lc(tg: u8) -> LinkControlFields {
+        LinkControlFields::new([0b00000000, 0, 0b10000000, 0, 0, tg, 0x12, 0x34, 0x56])
+    }
+
+    #[test]
+    fn test_diff_frames() {
+        use message::receiver::MessageEvent::*;
+
+        assert_eq!(diff_frames(&LinkControl(lc(0x10)), &LinkControl(lc(0x10))), vec![]);
+
+        assert_eq!(diff_frames(&LinkControl(lc(0x10)), &LinkControl(lc(0x20))), vec![
+            FieldDiff {
+                field: "talkgroup".to_string(),
+                expected: "Other(16)".to_string(),
+                actual: "Other(32)".to_string(),
+            },
+        ]);
+
+        let d = diff_frames(&VoiceTerm(lc(0x10)), &LinkControl(lc(0x10)));
+        assert_eq!(d.len(), 1);
+        assert_eq!(d[0].field, "kind");
+        assert_eq!(d[0].actual, "\"LinkControl\"");
+
+        let d = diff_frames(&Error(P25Error::UnknownNid), &Error(P25Error::UnknownNid));
+        assert!(d.is_empty());
+
+        // Opcodes without field-level comparison are compared by payload byte.
+        let a = LinkControlFields::new([0b00001111, 0, 0, 0, 0, 0, 0, 0, 0]);
+        let b = LinkControlFields::new([0b00001111, 0, 0, 0, 7, 0, 0, 0, 0]);
+        let d = diff_frames(&LinkControl(a), &LinkControl(b));
+        assert_eq!(d.len(), 1);
+        assert_eq!(d[0].field, "payload[3]");
+    }
+}
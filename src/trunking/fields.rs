@@ -1,8 +1,13 @@
 //! Decode various trunking-related packet fields.
 
-use util::{slice_u16, slice_u24, slice_u32};
+use std;
+
+use error::Result;
+use util::{check_len, slice_u16, slice_u24, slice_u32};
 
 /// Options that can be requested/granted by a service.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "ser", derive(Serialize, Deserialize))]
 pub struct ServiceOptions(u8);
 
 impl ServiceOptions {
@@ -25,11 +30,21 @@ impl ServiceOptions {
 
 /// Uniquely identifies a channel within a site.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "ser", derive(Serialize, Deserialize))]
 pub struct Channel(u16);
 
 impl Channel {
+    /// Parse a channel from the given 16 bit slice.
+    pub fn new(bytes: &[u8]) -> Channel { Self::from_bits(slice_u16(bytes)) }
+
+    /// Parse a channel in the same way as `new`, or return a `TooShort` error if the
+    /// slice has fewer than 2 bytes.
+    pub fn try_new(bytes: &[u8]) -> Result<Channel> {
+        check_len(bytes, 2).map(|_| Self::new(bytes))
+    }
+
     /// Create a new `Channel` from the given 16 bits.
-    pub fn new(bytes: &[u8]) -> Channel { Channel(slice_u16(bytes)) }
+    pub fn from_bits(bits: u16) -> Channel { Channel(bits) }
 
     /// Channel ID whose parameters to use.
     pub fn id(&self) -> u8 { (self.0 >> 12) as u8 }
@@ -44,7 +59,7 @@ impl Channel {
 /// Additionally, the user directs each transmission to a talkgroup selected on the
 /// radio.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
-#[cfg_attr(feature = "ser", derive(Serialize))]
+#[cfg_attr(feature = "ser", derive(Serialize, Deserialize))]
 pub enum TalkGroup {
     /// Includes nobody.
     Nobody,
@@ -62,6 +77,12 @@ impl TalkGroup {
         Self::from_bits(slice_u16(bytes))
     }
 
+    /// Parse a talkgroup in the same way as `new`, or return a `TooShort` error if the
+    /// slice has fewer than 2 bytes.
+    pub fn try_new(bytes: &[u8]) -> Result<TalkGroup> {
+        check_len(bytes, 2).map(|_| Self::new(bytes))
+    }
+
     /// Parse a talkgroup from the given 16 bits.
     pub fn from_bits(bits: u16) -> TalkGroup {
         use self::TalkGroup::*;
@@ -73,10 +94,138 @@ impl TalkGroup {
             _ => Other(bits),
         }
     }
+
+    /// Get the 16-bit ID of the talkgroup.
+    pub fn bits(&self) -> u16 {
+        use self::TalkGroup::*;
+
+        match *self {
+            Nobody => 0x0000,
+            Default => 0x0001,
+            Everbody => 0xFFFF,
+            Other(bits) => bits,
+        }
+    }
+
+    /// Whether the ID addresses any users.
+    pub fn is_group(&self) -> bool { *self != TalkGroup::Nobody }
+    /// Whether the ID addresses all users.
+    pub fn is_all_call(&self) -> bool { *self == TalkGroup::Everbody }
+
+    /// Whether the ID is a specific talkgroup rather than one of the special IDs.
+    pub fn is_talkgroup(&self) -> bool {
+        match *self {
+            TalkGroup::Other(_) => true,
+            _ => false,
+        }
+    }
+}
+
+impl std::fmt::Display for TalkGroup {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        use self::TalkGroup::*;
+
+        match *self {
+            Nobody => write!(f, "none"),
+            Default => write!(f, "default"),
+            Everbody => write!(f, "all"),
+            Other(bits) => write!(f, "{}", bits),
+        }
+    }
+}
+
+/// Identifies an individual unit, or one of the special addresses for the network.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "ser", derive(Serialize, Deserialize))]
+pub enum UnitId {
+    /// Addresses no unit.
+    Nobody,
+    /// Fixed network equipment.
+    Fne,
+    /// System default address.
+    SystemDefault,
+    /// Registration default address, used by units that haven't yet registered.
+    RegistrationDefault,
+    /// Addresses all units.
+    Everybody,
+    /// Specific unit.
+    Other(u32),
+}
+
+impl UnitId {
+    /// Parse a unit ID from the given 24 bit slice.
+    pub fn new(bytes: &[u8]) -> UnitId {
+        Self::from_bits(slice_u24(bytes))
+    }
+
+    /// Parse a unit ID in the same way as `new`, or return a `TooShort` error if the
+    /// slice has fewer than 3 bytes.
+    pub fn try_new(bytes: &[u8]) -> Result<UnitId> {
+        check_len(bytes, 3).map(|_| Self::new(bytes))
+    }
+
+    /// Parse a unit ID from the given 24 bits.
+    pub fn from_bits(bits: u32) -> UnitId {
+        use self::UnitId::*;
+
+        assert!(bits >> 24 == 0);
+
+        match bits {
+            0x000000 => Nobody,
+            0xFFFFFC => Fne,
+            0xFFFFFD => SystemDefault,
+            0xFFFFFE => RegistrationDefault,
+            0xFFFFFF => Everybody,
+            _ => Other(bits),
+        }
+    }
+
+    /// Get the 24-bit ID of the unit.
+    pub fn bits(&self) -> u32 {
+        use self::UnitId::*;
+
+        match *self {
+            Nobody => 0x000000,
+            Fne => 0xFFFFFC,
+            SystemDefault => 0xFFFFFD,
+            RegistrationDefault => 0xFFFFFE,
+            Everybody => 0xFFFFFF,
+            Other(bits) => bits,
+        }
+    }
+
+    /// Whether the ID addresses more than one unit.
+    pub fn is_group(&self) -> bool { *self == UnitId::Everybody }
+    /// Whether the ID addresses all units.
+    pub fn is_all_call(&self) -> bool { *self == UnitId::Everybody }
+
+    /// Whether the ID is a specific unit rather than one of the special IDs.
+    pub fn is_unit(&self) -> bool {
+        match *self {
+            UnitId::Other(_) => true,
+            _ => false,
+        }
+    }
+}
+
+impl std::fmt::Display for UnitId {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        use self::UnitId::*;
+
+        match *self {
+            Nobody => write!(f, "none"),
+            Fne => write!(f, "FNE"),
+            SystemDefault => write!(f, "system default"),
+            RegistrationDefault => write!(f, "registration default"),
+            Everybody => write!(f, "all"),
+            Other(bits) => write!(f, "{}", bits),
+        }
+    }
 }
 
 /// Supported services of a control channel.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "ser", derive(Serialize, Deserialize))]
 pub struct SystemServices(u8);
 
 impl SystemServices {
@@ -107,13 +256,31 @@ pub struct ChannelParamsMap([Option<ChannelParams>; 16]);
 impl ChannelParamsMap {
     /// Update the map with the given channel parameters.
     pub fn update(&mut self, upd: &ChannelParamsUpdate) {
-        self.0[upd.id() as usize] = Some(upd.params());
+        self.insert(upd.id(), upd.params());
+    }
+
+    /// Set the parameters for the given channel ID, as decoded from an identifier update
+    /// of either form.
+    pub fn insert(&mut self, id: u8, params: ChannelParams) {
+        self.0[id as usize] = Some(params);
     }
 
     /// Try to retrieve channel parameters for the given channel ID.
     pub fn lookup(&self, id: u8) -> Option<ChannelParams> {
         self.0[id as usize]
     }
+
+    /// Receive frequency in Hz of the given channel, if parameters have been received
+    /// for its channel ID.
+    pub fn rx_freq(&self, ch: Channel) -> Option<u32> {
+        self.lookup(ch.id()).map(|p| p.rx_freq(ch.number()))
+    }
+
+    /// Transmit frequency in Hz of the given channel, if parameters have been received
+    /// for its channel ID.
+    pub fn tx_freq(&self, ch: Channel) -> Option<u32> {
+        self.lookup(ch.id()).map(|p| p.tx_freq(ch.number()))
+    }
 }
 
 /// Computes TX/RX frequencies and bandwidth for channel numbers within a site.
@@ -144,6 +311,16 @@ impl ChannelParams {
         }
     }
 
+    /// Create a new `ChannelParams` from the given frequencies in Hz.
+    fn from_hz(base: u32, bandwidth: u32, offset: i32, spacing: u32) -> ChannelParams {
+        ChannelParams {
+            base: base,
+            spacing: spacing,
+            offset: offset,
+            bandwidth: bandwidth,
+        }
+    }
+
     /// Receive frequency for the given channel number in Hz.
     pub fn rx_freq(&self, ch: u16) -> u32 {
         self.base + self.spacing * ch as u32
@@ -151,11 +328,23 @@ impl ChannelParams {
 
     /// Transmit frequency for the given channel number in Hz.
     pub fn tx_freq(&self, ch: u16) -> u32 {
-        self.rx_freq(ch) + self.offset as u32
+        (self.rx_freq(ch) as i64 + self.offset as i64) as u32
+    }
+}
+
+/// Compute the receive frequency in Hz of the given 12-bit channel number using the
+/// parameters in the given identifier update, or `None` if the channel number is wider
+/// than 12 bits.
+pub fn channel_to_freq(upd: &ChannelParamsUpdate, ch: u16) -> Option<u64> {
+    if ch >> 12 == 0 {
+        Some(upd.params().rx_freq(ch) as u64)
+    } else {
+        None
     }
 }
 
 /// Options for a P25 site.
+#[cfg_attr(feature = "ser", derive(Serialize, Deserialize))]
 pub struct SiteOptions(u8);
 
 impl SiteOptions {
@@ -257,6 +446,51 @@ impl<'a> ChannelParamsUpdate<'a> {
     fn base(&self) -> u32 { slice_u32(&self.0[4...7]) }
 }
 
+/// Advertisement of parameters used to calculate TX/RX frequencies within the given
+/// associated channel, in the form used for VHF and UHF bands.
+///
+/// Unlike the standard form, the bandwidth is coded as one of a few fixed values and the
+/// TX offset is given as a number of channel spacings.
+pub struct ChannelParamsUpdateVhfUhf<'a>(&'a [u8]);
+
+impl<'a> ChannelParamsUpdateVhfUhf<'a> {
+    /// Create a new `ChannelParamsUpdateVhfUhf` decoder from the given payload bytes.
+    pub fn new(payload: &'a [u8]) -> Self { ChannelParamsUpdateVhfUhf(payload) }
+
+    /// Channel ID associated with the enclosed parameters (can be up to 16 per control
+    /// channel.)
+    pub fn id(&self) -> u8 { self.0[0] >> 4 }
+
+    /// Parameters for the associated channel.
+    pub fn params(&self) -> ChannelParams {
+        let spacing = self.spacing() as u32 * 125;
+        let off = self.offset() as i32 * spacing as i32;
+
+        ChannelParams::from_hz(self.base() * 5, self.bandwidth(),
+                               if self.0[1] >> 7 == 0 { -off } else { off }, spacing)
+    }
+
+    /// Channel bandwidth in Hz, or 0 if the bandwidth code is reserved.
+    fn bandwidth(&self) -> u32 {
+        match self.0[0] & 0xF {
+            0b0100 => 6_250,
+            0b0101 => 12_500,
+            _ => 0,
+        }
+    }
+
+    /// Magnitude of the TX offset in channel spacings.
+    fn offset(&self) -> u16 { slice_u16(&self.0[1...2]) >> 2 & 0x1FFF }
+
+    /// Spacing between individual channel numbers in steps of 125Hz.
+    fn spacing(&self) -> u16 {
+        (self.0[2] as u16 & 0x3) << 8 | self.0[3] as u16
+    }
+
+    /// Base RX frequency in steps of 5Hz.
+    fn base(&self) -> u32 { slice_u32(&self.0[4...7]) }
+}
+
 /// Advertisement of one or more alternative control channels for the current site.
 pub struct AltControlChannel<'a>(&'a [u8]);
 
@@ -325,7 +559,7 @@ impl<'a> NetworkStatusBroadcast<'a> {
 
 /// Registration response.
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
-#[cfg_attr(feature = "ser", derive(Serialize))]
+#[cfg_attr(feature = "ser", derive(Serialize, Deserialize))]
 pub enum RegResponse {
     /// Registration is accepted.
     Accept,
@@ -398,6 +632,64 @@ impl<'a> PhoneAlert<'a> {
 #[cfg(test)]
 mod test {
     use super::*;
+    use error::P25Error;
+
+    #[test]
+    fn test_talkgroup() {
+        let tg = TalkGroup::from_bits(0xFFFF);
+        assert_eq!(tg, TalkGroup::Everbody);
+        assert!(tg.is_all_call());
+        assert!(tg.is_group());
+        assert!(!tg.is_talkgroup());
+        assert_eq!(tg.bits(), 0xFFFF);
+        assert_eq!(tg.to_string(), "all");
+
+        let tg = TalkGroup::new(&[0x12, 0x34]);
+        assert_eq!(tg, TalkGroup::Other(0x1234));
+        assert!(!tg.is_all_call());
+        assert!(tg.is_group());
+        assert!(tg.is_talkgroup());
+        assert_eq!(tg.bits(), 0x1234);
+        assert_eq!(tg.to_string(), "4660");
+
+        let tg = TalkGroup::from_bits(0);
+        assert!(!tg.is_group());
+        assert!(!tg.is_talkgroup());
+        assert_eq!(tg.to_string(), "none");
+    }
+
+    #[test]
+    fn test_unit_id() {
+        let u = UnitId::from_bits(0xFFFFFF);
+        assert_eq!(u, UnitId::Everybody);
+        assert!(u.is_all_call());
+        assert!(u.is_group());
+        assert!(!u.is_unit());
+        assert_eq!(u.to_string(), "all");
+
+        let u = UnitId::new(&[0x00, 0x30, 0x39]);
+        assert_eq!(u, UnitId::Other(12345));
+        assert!(!u.is_all_call());
+        assert!(!u.is_group());
+        assert!(u.is_unit());
+        assert_eq!(u.bits(), 12345);
+        assert_eq!(u.to_string(), "12345");
+
+        assert_eq!(UnitId::from_bits(0xFFFFFC), UnitId::Fne);
+        assert_eq!(UnitId::from_bits(0xFFFFFE).to_string(), "registration default");
+        assert_eq!(UnitId::from_bits(0).bits(), 0);
+    }
+
+    #[test]
+    fn test_short_slices() {
+        assert_eq!(TalkGroup::try_new(&[0x12]),
+                   Err(P25Error::TooShort { needed: 2, got: 1 }));
+        assert_eq!(UnitId::try_new(&[0x00, 0x30]),
+                   Err(P25Error::TooShort { needed: 3, got: 2 }));
+        assert_eq!(Channel::try_new(&[]),
+                   Err(P25Error::TooShort { needed: 2, got: 0 }));
+        assert_eq!(TalkGroup::try_new(&[0x12, 0x34]), Ok(TalkGroup::Other(0x1234)));
+    }
 
     #[test]
     fn test_channel_params() {
@@ -408,6 +700,25 @@ mod test {
         assert_eq!(p.offset, -45_000_000);
         assert_eq!(p.bandwidth, 12_500);
         assert_eq!(p.rx_freq(0b1001), 851_062_500);
+        assert_eq!(p.tx_freq(0b1001), 806_062_500);
+
+        // Positive offset.
+        let p = ChannelParams::new(170201250, 0x64, 0b110110100, 0x32);
+        assert_eq!(p.tx_freq(0b1001), 896_062_500);
+    }
+
+    #[test]
+    fn test_channel_params_map() {
+        let upd = [0b0110_0011, 0b00100_010, 0b110100_00, 0b00110010,
+                   0b00001010, 0b00100101, 0b00010000, 0b10100010];
+
+        let mut m = ChannelParamsMap::default();
+        assert_eq!(m.rx_freq(Channel::from_bits(0x6009)), None);
+
+        m.update(&ChannelParamsUpdate::new(&upd));
+        assert_eq!(m.rx_freq(Channel::from_bits(0x6009)), Some(851_062_500));
+        assert_eq!(m.tx_freq(Channel::from_bits(0x6009)), Some(806_062_500));
+        assert_eq!(m.rx_freq(Channel::from_bits(0x5009)), None);
     }
 
     #[test]
@@ -0,0 +1,124 @@
+//! Hook manufacturer-specific link control and TSBK payloads into custom parsers.
+//!
+//! Packets with a nonstandard manufacturer ID carry proprietary opcodes and payloads
+//! that this crate doesn't decode. Applications can register a handler for each
+//! manufacturer ID they understand, which receives the raw opcode and payload bytes
+//! and returns its own parsed value.
+
+/// Manufacturer IDs that indicate a standard packet.
+const STANDARD_MFGS: [u8; 2] = [0x00, 0x01];
+
+/// Whether the given manufacturer ID indicates a standard packet.
+pub fn is_standard(mfg: u8) -> bool { STANDARD_MFGS.contains(&mfg) }
+
+/// Parses the payload of packets from a specific manufacturer.
+pub trait MfgHandler<T> {
+    /// Parse the payload bytes following the manufacturer ID for the given 6-bit opcode,
+    /// returning `None` if the opcode or payload isn't recognized.
+    fn parse(&self, opcode: u8, payload: &[u8]) -> Option<T>;
+}
+
+impl<T, F: Fn(u8, &[u8]) -> Option<T>> MfgHandler<T> for F {
+    fn parse(&self, opcode: u8, payload: &[u8]) -> Option<T> { self(opcode, payload) }
+}
+
+/// Result of passing a packet through a `MfgRegistry`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum MfgPayload<T> {
+    /// Packet has a standard manufacturer ID and should be decoded with the standard
+    /// opcode parsers.
+    Standard,
+    /// Payload parsed by the handler registered for the manufacturer.
+    Custom(T),
+    /// No registered handler recognized the packet.
+    Unknown,
+}
+
+/// Set of manufacturer-specific handlers, keyed by manufacturer ID.
+pub struct MfgRegistry<T> {
+    /// Registered manufacturer IDs and their handlers.
+    handlers: Vec<(u8, Box<MfgHandler<T>>)>,
+}
+
+impl<T> MfgRegistry<T> {
+    /// Create a new `MfgRegistry` with no registered handlers.
+    pub fn new() -> MfgRegistry<T> {
+        MfgRegistry {
+            handlers: vec![],
+        }
+    }
+
+    /// Register the given handler for packets with the given manufacturer ID, replacing
+    /// any previously registered handler for that ID.
+    pub fn register<H: MfgHandler<T> + 'static>(&mut self, mfg: u8, handler: H) {
+        self.handlers.retain(|&(m, _)| m != mfg);
+        self.handlers.push((mfg, Box::new(handler)));
+    }
+
+    /// Whether a handler is registered for the given manufacturer ID.
+    pub fn handles(&self, mfg: u8) -> bool {
+        self.handlers.iter().any(|&(m, _)| m == mfg)
+    }
+
+    /// Parse a packet with the given manufacturer ID, opcode, and payload bytes.
+    pub fn parse(&self, mfg: u8, opcode: u8, payload: &[u8]) -> MfgPayload<T> {
+        if is_standard(mfg) {
+            return MfgPayload::Standard;
+        }
+
+        let handler = match self.handlers.iter().find(|&&(m, _)| m == mfg) {
+            Some(&(_, ref h)) => h,
+            None => return MfgPayload::Unknown,
+        };
+
+        match handler.parse(opcode, payload) {
+            Some(p) => MfgPayload::Custom(p),
+            None => MfgPayload::Unknown,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_registry() {
+        let calls = Rc::new(Cell::new(0));
+        let mut reg = MfgRegistry::new();
+
+        {
+            let calls = calls.clone();
+
+            reg.register(0x90, move |opcode: u8, payload: &[u8]| {
+                calls.set(calls.get() + 1);
+
+                if opcode == 0x05 {
+                    Some((opcode, payload[0]))
+                } else {
+                    None
+                }
+            });
+        }
+
+        assert!(reg.handles(0x90));
+        assert!(!reg.handles(0xA4));
+
+        assert_eq!(reg.parse(0x90, 0x05, &[0xAB, 0xCD]), MfgPayload::Custom((0x05, 0xAB)));
+        assert_eq!(calls.get(), 1);
+        assert_eq!(reg.parse(0x90, 0x06, &[0xAB]), MfgPayload::Unknown);
+        assert_eq!(calls.get(), 2);
+
+        // Other manufacturers and standard packets don't reach the handler.
+        assert_eq!(reg.parse(0xA4, 0x05, &[0xAB]), MfgPayload::Unknown);
+        assert_eq!(reg.parse(0x00, 0x05, &[0xAB]), MfgPayload::Standard);
+        assert_eq!(reg.parse(0x01, 0x05, &[0xAB]), MfgPayload::Standard);
+        assert_eq!(calls.get(), 2);
+
+        reg.register(0x90, |_: u8, _: &[u8]| Some((0, 0)));
+        assert_eq!(reg.parse(0x90, 0x05, &[0xAB]), MfgPayload::Custom((0, 0)));
+        assert_eq!(calls.get(), 2);
+    }
+}
@@ -1,4 +1,5 @@
 //! Trunking control message decoding.
 
 pub mod fields;
+pub mod mfg;
 pub mod tsbk;
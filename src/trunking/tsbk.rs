@@ -7,10 +7,13 @@ use buffer::{Buffer, DataPayloadStorage};
 use coding::trellis;
 use consts::{TSBK_DIBITS, TSBK_BYTES};
 use data::{crc, interleave};
+use data::header::Manufacturer;
 use error::{Result, P25Error};
 use util::{slice_u16, slice_u24};
 
 use trunking::fields::{Channel, TalkGroup, ServiceOptions, RegResponse};
+use trunking::fields::{ChannelParams, ChannelParamsUpdate, ChannelParamsUpdateVhfUhf};
+use trunking::mfg::{self, MfgRegistry, MfgPayload};
 
 /// State machine for receiving a TSBK packet.
 ///
@@ -62,8 +65,86 @@ impl TsbkReceiver {
     }
 }
 
+/// Maximum number of TSBKs sent together in a group.
+pub const GROUP_TSBKS: usize = 3;
+
+/// Collects consecutive TSBKs into a group ending with the last-block flag.
+///
+/// TSBKs only flag the last block of a group, so the start of a group must be signalled
+/// with `start` when a new TSDU begins. A group that overflows `GROUP_TSBKS` blocks
+/// without a last block is dropped, and the overflowing block starts a new group.
+pub struct TsbkGroup {
+    /// Blocks collected so far.
+    blocks: [TsbkFields; GROUP_TSBKS],
+    /// Number of collected blocks.
+    len: usize,
+}
+
+impl TsbkGroup {
+    /// Create a new `TsbkGroup` with no collected blocks.
+    pub fn new() -> TsbkGroup {
+        TsbkGroup {
+            blocks: [TsbkFields::new([0; TSBK_BYTES]); GROUP_TSBKS],
+            len: 0,
+        }
+    }
+
+    /// Drop any partially collected group so the next block fed in starts a new one.
+    pub fn start(&mut self) {
+        self.len = 0;
+    }
+
+    /// Feed in a received TSBK, possibly completing a group. Return `Some(Ok(blocks))`
+    /// when the last block of a group is received, `Some(Err(err))` if the block has
+    /// an invalid CRC, and `None` in the case of no event.
+    ///
+    /// A block with an invalid CRC drops the current group.
+    pub fn feed(&mut self, tsbk: TsbkFields) -> Option<Result<TsbkBlocks>> {
+        if !tsbk.crc_valid() {
+            log_debug!("TSBK CRC failed, dropping {} grouped blocks", self.len);
+            self.start();
+            return Some(Err(P25Error::TsbkCrcMismatch));
+        }
+
+        if self.len == GROUP_TSBKS {
+            self.start();
+        }
+
+        self.blocks[self.len] = tsbk;
+        self.len += 1;
+
+        if !tsbk.is_tail() {
+            return None;
+        }
+
+        let blocks = TsbkBlocks {
+            blocks: self.blocks,
+            len: self.len,
+        };
+
+        self.start();
+
+        Some(Ok(blocks))
+    }
+}
+
+/// A complete group of TSBKs.
+#[derive(Copy, Clone)]
+pub struct TsbkBlocks {
+    /// Blocks in the group, valid up to `len`.
+    blocks: [TsbkFields; GROUP_TSBKS],
+    /// Number of blocks in the group.
+    len: usize,
+}
+
+impl TsbkBlocks {
+    /// Blocks in the group, in the order received.
+    pub fn blocks(&self) -> &[TsbkFields] { &self.blocks[..self.len] }
+}
+
 /// Type of a TSBK payload.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "ser", derive(Serialize, Deserialize))]
 pub enum TsbkOpcode {
     GroupVoiceGrant,
     GroupVoiceUpdate,
@@ -101,6 +182,7 @@ pub enum TsbkOpcode {
     NetworkStatusBroadcast,
     AdjacentSite,
     ChannelParamsUpdate,
+    ChannelParamsUpdateVhfUhf,
     ProtectionParamBroadcast,
     ProtectionParamUpdate,
     Reserved,
@@ -160,7 +242,9 @@ impl TsbkOpcode {
             0b101110 => Some(UnitAuthCommand),
             0b101111 => Some(UnitDeregAck),
 
-            0b110000...0b110101 => Some(Reserved),
+            0b110000...0b110011 => Some(Reserved),
+            0b110100 => Some(ChannelParamsUpdateVhfUhf),
+            0b110101 => Some(Reserved),
             0b110110 => Some(RoamingAddrCommand),
             0b110111 => Some(RoamingAddrUpdate),
 
@@ -189,6 +273,9 @@ impl TsbkFields {
     /// Interpret the given bytes as a TSBK packet.
     pub fn new(buf: Buf) -> TsbkFields { TsbkFields(buf) }
 
+    /// Raw bytes of the packet.
+    pub fn bytes(&self) -> &Buf { &self.0 }
+
     /// Whether this packet is the last one in the TSBK group.
     pub fn is_tail(&self) -> bool { self.0[0] >> 7 == 1 }
     /// Whether the packet is encrypted.
@@ -197,6 +284,8 @@ impl TsbkFields {
     pub fn opcode(&self) -> Option<TsbkOpcode> { TsbkOpcode::from_bits(self.0[0] & 0x3F) }
     /// Manufacturer ID, which determines if the packet is standardized.
     pub fn mfg(&self) -> u8 { self.0[1] }
+    /// Manufacturer identified by the manufacturer ID.
+    pub fn manufacturer(&self) -> Manufacturer { Manufacturer::from_id(self.mfg()) }
     /// Transmitted CRC.
     pub fn crc(&self) -> u16 { slice_u16(&self.0[10..]) }
 
@@ -214,6 +303,66 @@ impl TsbkFields {
 
     /// Bytes that make up the payload of the packet.
     pub fn payload(&self) -> &[u8] { &self.0[2...9] }
+
+    /// Parse a manufacturer-specific payload with the handler registered for the
+    /// packet's manufacturer ID.
+    pub fn parse_mfg<T>(&self, reg: &MfgRegistry<T>) -> MfgPayload<T> {
+        reg.parse(self.mfg(), self.0[0] & 0x3F, self.payload())
+    }
+
+    /// Decode the channel ID and its parameters if the packet is an identifier update
+    /// of either form, dispatching on the opcode.
+    pub fn channel_params(&self) -> Option<(u8, ChannelParams)> {
+        if !mfg::is_standard(self.mfg()) {
+            return None;
+        }
+
+        match self.opcode() {
+            Some(TsbkOpcode::ChannelParamsUpdate) => {
+                let p = ChannelParamsUpdate::new(self.payload());
+                Some((p.id(), p.params()))
+            },
+            Some(TsbkOpcode::ChannelParamsUpdateVhfUhf) => {
+                let p = ChannelParamsUpdateVhfUhf::new(self.payload());
+                Some((p.id(), p.params()))
+            },
+            _ => None,
+        }
+    }
+}
+
+/// A TSBK with a verified CRC, split by whether its payload is standardized.
+#[derive(Copy, Clone)]
+pub enum Tsbk {
+    /// Standard packet, which can be decoded by opcode with the payload types in this
+    /// module.
+    Standard(TsbkFields),
+    /// Manufacturer-specific packet, passed through with its raw bytes intact.
+    Manufacturer {
+        /// Manufacturer ID.
+        mfid: u8,
+        /// Raw bytes of the packet, including opcode and CRC.
+        bytes: Buf,
+    },
+}
+
+impl Tsbk {
+    /// Verify the CRC of the given packet and route it by manufacturer ID. Return
+    /// `Err(TsbkCrcMismatch)` if the CRC is invalid.
+    pub fn parse(tsbk: TsbkFields) -> Result<Tsbk> {
+        if !tsbk.crc_valid() {
+            return Err(P25Error::TsbkCrcMismatch);
+        }
+
+        if mfg::is_standard(tsbk.mfg()) {
+            Ok(Tsbk::Standard(tsbk))
+        } else {
+            Ok(Tsbk::Manufacturer {
+                mfid: tsbk.mfg(),
+                bytes: tsbk.0,
+            })
+        }
+    }
 }
 
 /// Response given to a location registration request.
@@ -346,6 +495,105 @@ mod test {
     use super::*;
     use trunking::fields::*;
 
+    #[test]
+    fn test_parse_mfg() {
+        let mut reg = MfgRegistry::new();
+        reg.register(0xA4, |opcode: u8, payload: &[u8]| Some((opcode, payload[0])));
+
+        let mut buf = [0; TSBK_BYTES];
+        buf[0] = 0b10000011;
+        buf[1] = 0xA4;
+        buf[2] = 0x42;
+
+        assert_eq!(TsbkFields::new(buf).parse_mfg(&reg), MfgPayload::Custom((0b11, 0x42)));
+
+        buf[1] = 0x90;
+        assert_eq!(TsbkFields::new(buf).parse_mfg(&reg), MfgPayload::Unknown);
+    }
+
+    /// Build a TSBK with a valid CRC and the given opcode and last-block flag.
+    fn tsbk(opcode: u8, tail: bool) -> TsbkFields {
+        let mut buf = [0; TSBK_BYTES];
+        buf[0] = (tail as u8) << 7 | opcode;
+        buf[2] = opcode;
+
+        let crc = TsbkFields::new(buf).calc_crc();
+        buf[10] = (crc >> 8) as u8;
+        buf[11] = crc as u8;
+
+        TsbkFields::new(buf)
+    }
+
+    #[test]
+    fn test_tsbk_parse() {
+        let mut buf = tsbk(0x3B, true).0;
+        buf[1] = 0x90;
+        buf[2...9].copy_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8]);
+
+        let crc = TsbkFields::new(buf).calc_crc();
+        buf[10] = (crc >> 8) as u8;
+        buf[11] = crc as u8;
+
+        match Tsbk::parse(TsbkFields::new(buf)) {
+            Ok(Tsbk::Manufacturer { mfid, bytes }) => {
+                assert_eq!(mfid, 0x90);
+                assert_eq!(bytes, buf);
+            },
+            _ => panic!(),
+        }
+
+        buf[11] ^= 1;
+        assert_eq!(Tsbk::parse(TsbkFields::new(buf)).err(),
+                   Some(P25Error::TsbkCrcMismatch));
+
+        match Tsbk::parse(tsbk(0x3B, true)) {
+            Ok(Tsbk::Standard(t)) => assert_eq!(t.bytes(), tsbk(0x3B, true).bytes()),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_tsbk_group() {
+        let mut g = TsbkGroup::new();
+
+        assert!(g.feed(tsbk(0x16, false)).is_none());
+
+        let blocks = g.feed(tsbk(0x3A, true)).unwrap().unwrap();
+        assert_eq!(blocks.blocks().len(), 2);
+        assert_eq!(blocks.blocks()[0].payload()[0], 0x16);
+        assert_eq!(blocks.blocks()[1].payload()[0], 0x3A);
+        assert!(blocks.blocks()[1].is_tail());
+
+        // Single-block group.
+        let blocks = g.feed(tsbk(0x00, true)).unwrap().unwrap();
+        assert_eq!(blocks.blocks().len(), 1);
+
+        // Interrupted group.
+        assert!(g.feed(tsbk(0x01, false)).is_none());
+        g.start();
+        assert!(g.feed(tsbk(0x02, false)).is_none());
+        let blocks = g.feed(tsbk(0x03, true)).unwrap().unwrap();
+        assert_eq!(blocks.blocks().len(), 2);
+        assert_eq!(blocks.blocks()[0].payload()[0], 0x02);
+
+        // Overflowing group.
+        for i in 0..4 {
+            assert!(g.feed(tsbk(i, false)).is_none());
+        }
+        let blocks = g.feed(tsbk(0x05, true)).unwrap().unwrap();
+        assert_eq!(blocks.blocks().len(), 2);
+        assert_eq!(blocks.blocks()[0].payload()[0], 3);
+
+        // Bad CRC drops the group.
+        assert!(g.feed(tsbk(0x01, false)).is_none());
+        let mut bad = tsbk(0x02, true).0;
+        bad[11] ^= 1;
+        assert_eq!(g.feed(TsbkFields::new(bad)).unwrap().err(),
+                   Some(P25Error::TsbkCrcMismatch));
+        let blocks = g.feed(tsbk(0x04, true)).unwrap().unwrap();
+        assert_eq!(blocks.blocks().len(), 1);
+    }
+
     #[test]
     fn test_tsbk_fields() {
         let t = TsbkFields::new([
@@ -443,6 +691,64 @@ mod test {
         assert_eq!(p.id(), 0b0110);
         assert_eq!(p.params().bandwidth, 12_500);
         assert_eq!(p.params().rx_freq(0b1001), 851_062_500);
+        assert_eq!(p.params().tx_freq(0b1001), 806_062_500);
+        assert_eq!(channel_to_freq(&p, 0b1001), Some(851_062_500));
+        assert_eq!(channel_to_freq(&p, 0x123), Some(852_825_000));
+        assert_eq!(channel_to_freq(&p, 0xFFF), Some(876_600_000));
+        assert_eq!(channel_to_freq(&p, 0x1000), None);
+    }
+
+    #[test]
+    fn test_channel_params_update_vhf_uhf() {
+        let t = TsbkFields::new([
+            0b00110100,
+            0b00000000,
+            // ID 2, 12.5kHz bandwidth.
+            0b0010_0101,
+            // Positive offset of 480 channels.
+            0b1_0000111,
+            0b100000_00,
+            // 6.25kHz spacing.
+            0b00110010,
+            // Base of 136MHz.
+            0b00000001,
+            0b10011111,
+            0b00001010,
+            0b00000000,
+            0b00000000,
+            0b00000000,
+        ]);
+        assert_eq!(t.opcode(), Some(TsbkOpcode::ChannelParamsUpdateVhfUhf));
+
+        let (id, p) = t.channel_params().unwrap();
+        assert_eq!(id, 2);
+        assert_eq!(p.bandwidth, 12_500);
+        assert_eq!(p.rx_freq(0), 136_000_000);
+        assert_eq!(p.rx_freq(100), 136_625_000);
+        assert_eq!(p.tx_freq(100), 139_625_000);
+
+        // Standard form dispatched through the same call.
+        let t = TsbkFields::new([
+            0b00111101,
+            0b00000000,
+            0b0110_0011,
+            0b00100_010,
+            0b110100_00,
+            0b00110010,
+            0b00001010,
+            0b00100101,
+            0b00010000,
+            0b10100010,
+            0b11111111,
+            0b11111111,
+        ]);
+
+        let (id, p) = t.channel_params().unwrap();
+        assert_eq!(id, 0b0110);
+        assert_eq!(p.rx_freq(0b1001), 851_062_500);
+        assert_eq!(p.tx_freq(0b1001), 806_062_500);
+
+        assert!(tsbk(0x3B, true).channel_params().is_none());
     }
 
     #[test]
@@ -3,6 +3,8 @@
 use num::One;
 use std;
 
+use error::{P25Error, Result};
+
 /// Calculate ceil(a / b).
 pub fn div_ceil<T>(a: T, b: T) -> T where
     T: std::ops::Add<T, Output = T> + std::ops::Sub<T, Output = T> +
@@ -11,6 +13,19 @@ pub fn div_ceil<T>(a: T, b: T) -> T where
     (a + b - T::one()) / b
 }
 
+/// Check that the given buffer holds at least the given number of items, returning a
+/// `TooShort` error otherwise.
+pub fn check_len<T>(buf: &[T], needed: usize) -> Result<()> {
+    if buf.len() < needed {
+        Err(P25Error::TooShort { needed: needed, got: buf.len() })
+    } else {
+        Ok(())
+    }
+}
+
+/// Count the number of bit positions where the given patterns differ.
+pub fn hamming_distance(a: u64, b: u64) -> u32 { (a ^ b).count_ones() }
+
 /// Slice 16 bits from the given bytes (in P25 big endian format.).
 pub fn slice_u16(bytes: &[u8]) -> u16 {
     (bytes[0] as u16) << 8 | bytes[1] as u16
@@ -37,6 +52,93 @@ mod test {
         assert_eq!(div_ceil(0, 3), 0);
     }
 
+    #[test]
+    fn test_hamming_distance() {
+        assert_eq!(hamming_distance(0, 0), 0);
+        assert_eq!(hamming_distance(0x5575F5FF77FF, 0x5575F5FF77FF), 0);
+        assert_eq!(hamming_distance(0, !0), 64);
+        assert_eq!(hamming_distance(0x5575F5FF77FF, !0x5575F5FF77FF), 64);
+        assert_eq!(hamming_distance(0b1011, 0b0110), 3);
+        assert_eq!(hamming_distance(1 << 63, 1), 2);
+    }
+
+    #[test]
+    fn test_check_len() {
+        assert_eq!(check_len(&[1, 2, 3], 3), Ok(()));
+        assert_eq!(check_len(&[1, 2, 3], 2), Ok(()));
+        assert_eq!(check_len(&[1, 2, 3], 4),
+                   Err(P25Error::TooShort { needed: 4, got: 3 }));
+        assert_eq!(check_len::<u8>(&[], 1),
+                   Err(P25Error::TooShort { needed: 1, got: 0 }));
+    }
+
+    #[test]
+    fn test_truncated_parsers() {
+        use bits::{Dibit, Hexbit};
+        use consts::{CODING_DIBITS, SYNC_SYMBOLS, TSBK_BYTES};
+        use data::ambt::AmbtHeader;
+        use data::header::{self, ConfirmedHeader, UnconfirmedHeader};
+        use data::response::ResponseHeader;
+        use data::{channel, packet};
+        use trunking::fields::{Channel, TalkGroup, UnitId};
+        use voice::crypto::CryptoControlFields;
+        use voice::frame_group::{Extra, LinkControlExtra};
+        use voice::lsd;
+
+        /// Parser under test, taking the length of the buffer to build for it.
+        type Parser = Box<Fn(usize) -> Result<()>>;
+
+        let bytes = |len| vec![0u8; len];
+        let dibits = |len| vec![Dibit::default(); len];
+        let hexbits = |len| vec![Hexbit::default(); len];
+
+        // Each parser with the input length it needs.
+        let parsers: Vec<(&str, usize, Parser)> = vec![
+            ("header::parse", 12,
+             Box::new(move |n| header::parse(&bytes(n)).map(|_| ()))),
+            ("header::parse_extended", 12,
+             Box::new(move |n| header::parse_extended(&bytes(n), &[]).map(|_| ()))),
+            ("ConfirmedHeader::parse", 12,
+             Box::new(move |n| ConfirmedHeader::parse(&bytes(n)).map(|_| ()))),
+            ("UnconfirmedHeader::parse", 12,
+             Box::new(move |n| UnconfirmedHeader::parse(&bytes(n)).map(|_| ()))),
+            ("ResponseHeader::parse", 12,
+             Box::new(move |n| ResponseHeader::parse(&bytes(n)).map(|_| ()))),
+            ("AmbtHeader::parse", TSBK_BYTES,
+             Box::new(move |n| AmbtHeader::parse(&bytes(n)).map(|_| ()))),
+            ("lsd::decode", lsd::LSD_BYTES,
+             Box::new(move |n| lsd::decode(&bytes(n)).map(|_| ()))),
+            ("lsd::decode_ldu", lsd::LSD_OFFSET / 4 + lsd::LSD_BYTES * 2,
+             Box::new(move |n| lsd::decode_ldu(&bytes(n)).map(|_| ()))),
+            ("Channel::try_new", 2,
+             Box::new(move |n| Channel::try_new(&bytes(n)).map(|_| ()))),
+            ("TalkGroup::try_new", 2,
+             Box::new(move |n| TalkGroup::try_new(&bytes(n)).map(|_| ()))),
+            ("UnitId::try_new", 3,
+             Box::new(move |n| UnitId::try_new(&bytes(n)).map(|_| ()))),
+            ("packet::parse_response", CODING_DIBITS,
+             Box::new(move |n| packet::parse_response(&dibits(n), 1).map(|_| ()))),
+            ("channel::decode_packet", CODING_DIBITS,
+             Box::new(move |n| channel::decode_packet(&dibits(n)).map(|_| ()))),
+            ("channel::decode_frame", SYNC_SYMBOLS,
+             Box::new(move |n| channel::decode_frame(&dibits(n)).map(|_| ()))),
+            ("CryptoControlFields::from_hexbits", 16,
+             Box::new(move |n| CryptoControlFields::from_hexbits(&hexbits(n))
+                 .map(|_| ()))),
+            ("LinkControlExtra::decode_extra", 12,
+             Box::new(move |n| LinkControlExtra::decode_extra(&hexbits(n)).map(|_| ()))),
+        ];
+
+        for &(name, needed, ref parse) in parsers.iter() {
+            for &got in [needed - 1, 0].iter() {
+                assert_eq!(parse(got).err(), Some(P25Error::TooShort {
+                    needed: needed,
+                    got: got,
+                }), "{} with {} items", name, got);
+            }
+        }
+    }
+
     #[test]
     fn test_slice_u16() {
         assert_eq!(slice_u16(&[0xDE, 0xAD]), 0xDEAD);
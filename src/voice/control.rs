@@ -1,15 +1,18 @@
 //! Decode Link Control (LC) packets and payloads.
 
 use consts::LINK_CONTROL_BYTES;
+use data::header::Manufacturer;
 use util::{slice_u16, slice_u24};
 
-use trunking::fields::{TalkGroup, ServiceOptions};
+use trunking::fields::{TalkGroup, ServiceOptions, UnitId};
+use trunking::mfg::{MfgRegistry, MfgPayload};
 
 /// Buffer of bytes that represents a link control packet.
 pub type Buf = [u8; LINK_CONTROL_BYTES];
 
 /// Type of a link control payload.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "ser", derive(Serialize, Deserialize))]
 pub enum LinkControlOpcode {
     GroupVoiceTraffic,
     GroupVoiceUpdate,
@@ -90,6 +93,9 @@ impl LinkControlFields {
     /// Interpret the given bytes as a link control packet.
     pub fn new(buf: Buf) -> Self { LinkControlFields(buf) }
 
+    /// Raw bytes of the packet.
+    pub fn bytes(&self) -> &Buf { &self.0 }
+
     /// Whether the packet is encrypted.
     pub fn protected(&self) -> bool { self.0[0] >> 7 == 1 }
 
@@ -100,6 +106,23 @@ impl LinkControlFields {
 
     /// Bytes that make up the payload.
     pub fn payload(&self) -> &[u8] { &self.0[1...8] }
+
+    /// Whether the packet uses the explicit format, with the manufacturer ID carried in
+    /// the second byte, rather than the implicit format, where the manufacturer is
+    /// standard and the second byte carries payload fields.
+    pub fn explicit(&self) -> bool { self.0[0] >> 6 & 1 == 0 }
+
+    /// Manufacturer ID, which determines if the packet is standardized.
+    pub fn mfg(&self) -> u8 { if self.explicit() { self.0[1] } else { 0 } }
+
+    /// Manufacturer identified by the manufacturer ID.
+    pub fn manufacturer(&self) -> Manufacturer { Manufacturer::from_id(self.mfg()) }
+
+    /// Parse a manufacturer-specific payload with the handler registered for the
+    /// packet's manufacturer ID.
+    pub fn parse_mfg<T>(&self, reg: &MfgRegistry<T>) -> MfgPayload<T> {
+        reg.parse(self.mfg(), self.0[0] & 0x3F, &self.0[2..])
+    }
 }
 
 /// Identity of unit transmitting on the current talkgroup traffic channel.
@@ -120,20 +143,35 @@ impl GroupVoiceTraffic {
 }
 
 /// Identity of units transmitting on current unit-to-unit traffic channel.
-pub struct UnitVoiceTraffic(Buf);
+///
+/// In the explicit format the fields follow the manufacturer ID, and in the implicit
+/// format they start one byte earlier, in its place.
+pub struct UnitVoiceTraffic(LinkControlFields);
 
 impl UnitVoiceTraffic {
     /// Create a new `UnitVoiceTraffic` from the base LC decoder.
-    pub fn new(lc: LinkControlFields) -> Self { UnitVoiceTraffic(lc.0) }
+    pub fn new(lc: LinkControlFields) -> Self { UnitVoiceTraffic(lc) }
 
+    /// Whether the packet uses the explicit format.
+    pub fn explicit(&self) -> bool { self.0.explicit() }
     /// Manufacturer ID of current packet.
-    pub fn mfg(&self) -> u8 { self.0[1] }
+    pub fn mfg(&self) -> u8 { self.0.mfg() }
     /// Service options provided by current traffic channel.
-    pub fn opts(&self) -> ServiceOptions { ServiceOptions::new(self.0[2]) }
+    pub fn opts(&self) -> ServiceOptions { ServiceOptions::new(self.field(1)[0]) }
     /// Destination user address for current transmission.
-    pub fn dest_unit(&self) -> u32 { slice_u24(&self.0[3..]) }
+    pub fn dest_unit(&self) -> u32 { slice_u24(self.field(2)) }
     /// Source user address for current transmission.
-    pub fn src_unit(&self) -> u32 { slice_u24(&self.0[6..]) }
+    pub fn src_unit(&self) -> u32 { slice_u24(self.field(5)) }
+    /// Destination unit for current transmission.
+    pub fn dest(&self) -> UnitId { UnitId::new(self.field(2)) }
+    /// Source unit for current transmission.
+    pub fn src(&self) -> UnitId { UnitId::new(self.field(5)) }
+
+    /// Get the bytes starting at the given offset into the implicit format fields.
+    fn field(&self, offset: usize) -> &[u8] {
+        let start = if self.explicit() { offset + 1 } else { offset };
+        &self.0.bytes()[start..]
+    }
 }
 
 /// Identity of unit participating in current phone call.
@@ -178,6 +216,62 @@ mod test {
         ]);
     }
 
+    #[test]
+    fn test_unit_voice_traffic() {
+        // Explicit format, with the manufacturer ID before the fields.
+        let lc = LinkControlFields::new([
+            0b00000011, 0x00, 0b10000000,
+            0x12, 0x34, 0x56,
+            0xAB, 0xCD, 0xEF,
+        ]);
+
+        assert_eq!(lc.opcode(), Some(LinkControlOpcode::UnitVoiceTraffic));
+        assert!(lc.explicit());
+
+        let dec = UnitVoiceTraffic::new(lc);
+        assert!(dec.explicit());
+        assert_eq!(dec.mfg(), 0);
+        assert!(dec.opts().emergency());
+        assert_eq!(dec.dest(), UnitId::Other(0x123456));
+        assert_eq!(dec.src(), UnitId::Other(0xABCDEF));
+        assert_eq!(dec.src_unit(), 0xABCDEF);
+
+        // Implicit format, with the fields starting in place of the manufacturer ID.
+        let lc = LinkControlFields::new([
+            0b01000011, 0b10000000,
+            0x12, 0x34, 0x56,
+            0xFF, 0xFF, 0xFC,
+            0x00,
+        ]);
+
+        assert_eq!(lc.opcode(), Some(LinkControlOpcode::UnitVoiceTraffic));
+        assert!(!lc.explicit());
+        assert_eq!(lc.mfg(), 0);
+
+        let dec = UnitVoiceTraffic::new(lc);
+        assert!(!dec.explicit());
+        assert!(dec.opts().emergency());
+        assert_eq!(dec.dest(), UnitId::Other(0x123456));
+        assert_eq!(dec.dest_unit(), 0x123456);
+        assert_eq!(dec.src(), UnitId::Fne);
+    }
+
+    #[test]
+    fn test_parse_mfg() {
+        let mut reg = MfgRegistry::new();
+        reg.register(0x90, |opcode: u8, payload: &[u8]| Some((opcode, payload.len())));
+
+        let lc = LinkControlFields::new([0b00000101, 0x90, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(lc.opcode(), Some(LinkControlOpcode::UnitCallRequest));
+        assert_eq!(lc.parse_mfg(&reg), MfgPayload::Custom((0b000101, 7)));
+
+        let lc = LinkControlFields::new([0b00000101, 0xA4, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(lc.parse_mfg(&reg), MfgPayload::Unknown);
+
+        let lc = LinkControlFields::new([0b00000101, 0x00, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(lc.parse_mfg(&reg), MfgPayload::Standard);
+    }
+
     #[test]
     fn test_adjacent_site() {
         let lc = LinkControlFields::new([
@@ -474,4 +568,14 @@ mod test {
         assert_eq!(p.call_timer(), 0b1000000000000010);
         assert_eq!(p.unit(), 0b111100000011001111100010);
     }
+
+    #[cfg(feature = "ser")]
+    #[test]
+    fn test_serde() {
+        use serde_json;
+
+        let json = serde_json::to_string(&LinkControlOpcode::PhoneAlert).unwrap();
+        assert_eq!(serde_json::from_str::<LinkControlOpcode>(&json).unwrap(),
+                   LinkControlOpcode::PhoneAlert);
+    }
 }
@@ -1,7 +1,18 @@
-//! Decode Cryptographic Control (CC) packets.
+//! Decode Cryptographic Control (CC) packets and track encryption state over a call.
+//!
+//! No block cipher is implemented here: keystream bytes come from a caller-supplied
+//! `Keystream`, so any timing behavior with respect to the key is that of the cipher
+//! implementation behind it. The message indicator handling in this module only depends
+//! on the indicator, which is sent in the clear.
 
-use consts::CRYPTO_CONTROL_BYTES;
-use util::slice_u16;
+use collect_slice::CollectSlice;
+
+use bits::{Hexbit, HexbitBytes, Hexbits};
+use coding::reed_solomon;
+use consts::{CRYPTO_CONTROL_BYTES, EXTRA_HEXBITS, MI_BYTES, MI_HEXBITS};
+use error::Result;
+use util::{check_len, slice_u16};
+use voice::header::VoiceHeaderFields;
 
 /// Buffer of bytes that represent a crypto control packet.
 pub type Buf = [u8; CRYPTO_CONTROL_BYTES];
@@ -13,17 +24,61 @@ impl CryptoControlFields {
     /// Create a new `CryptoControlFields` decoder from the given bytes.
     pub fn new(buf: Buf) -> Self { CryptoControlFields(buf) }
 
+    /// Raw bytes of the packet.
+    pub fn bytes(&self) -> &Buf { &self.0 }
+
+    /// Create a new `CryptoControlFields` decoder from the given 16 data hexbits,
+    /// failing with `TooShort` if fewer are given.
+    pub fn from_hexbits(buf: &[Hexbit]) -> Result<Self> {
+        const DATA_HEXBITS: usize = CRYPTO_CONTROL_BYTES * 8 / 6;
+
+        if let Err(e) = check_len(buf, DATA_HEXBITS) {
+            return Err(e);
+        }
+
+        let mut mi = [Hexbit::default(); MI_HEXBITS];
+        mi.copy_from_slice(&buf[..MI_HEXBITS]);
+
+        let mut bytes = [0; CRYPTO_CONTROL_BYTES];
+        bytes[..MI_BYTES].copy_from_slice(&reassemble_mi(&mi));
+        HexbitBytes::new(buf[MI_HEXBITS..DATA_HEXBITS].iter().cloned())
+            .collect_slice_checked(&mut bytes[MI_BYTES..]);
+
+        Ok(CryptoControlFields::new(bytes))
+    }
+
+    /// Try to decode the given Reed-Solomon coded hexbits, as carried in an LDU2 frame
+    /// group, into crypto control fields.
+    ///
+    /// If decoding was successful, return `Some((fields, err))`, where `err` is the
+    /// number of corrected hexbits. Otherwise, return `None` to indicate an
+    /// unrecoverable error.
+    pub fn decode(buf: &mut [Hexbit; EXTRA_HEXBITS]) -> Option<(Self, usize)> {
+        reed_solomon::medium::decode(buf).and_then(|(data, err)| {
+            CryptoControlFields::from_hexbits(data).ok().map(|f| (f, err))
+        })
+    }
+
     /// Initialization vector used internally by associated crypto algorithm.
     pub fn init(&self) -> &[u8] { &self.0[..9] }
+    /// 72-bit message indicator, the same as `init` as an owned array.
+    pub fn mi(&self) -> [u8; 9] {
+        let mut mi = [0; 9];
+        mi.copy_from_slice(self.init());
+        mi
+    }
     /// Type of crypto algorithm in use, if any.
     pub fn alg(&self) -> CryptoAlgorithm { CryptoAlgorithm::from_bits(self.0[9]) }
     /// Encryption key to use.
     pub fn key(&self) -> u16 { slice_u16(&self.0[10..]) }
+    /// Whether the following voice is encrypted. The message indicator is meaningless
+    /// for a clear call.
+    pub fn is_encrypted(&self) -> bool { self.alg().is_encrypted() }
 }
 
 /// Type of cryptographic algorithm.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
-#[cfg_attr(feature = "ser", derive(Serialize))]
+#[cfg_attr(feature = "ser", derive(Serialize, Deserialize))]
 pub enum CryptoAlgorithm {
     Accordion,
     BatonEven,
@@ -57,12 +112,242 @@ impl CryptoAlgorithm {
             b => Other(b),
         }
     }
+
+    /// Whether the algorithm encrypts traffic, which is the case for all but
+    /// `Unencrypted`.
+    pub fn is_encrypted(&self) -> bool { *self != CryptoAlgorithm::Unencrypted }
+}
+
+/// Reassemble the 72-bit message indicator from the hexbits that carry it, where the
+/// first hexbit holds the 6 MSBs of the first byte.
+pub fn reassemble_mi(words: &[Hexbit; MI_HEXBITS]) -> [u8; MI_BYTES] {
+    let mut mi = [0; MI_BYTES];
+    HexbitBytes::new(words.iter().cloned()).collect_slice_checked(&mut mi[..]);
+    mi
+}
+
+/// Split the 72-bit message indicator into the hexbits that carry it, the inverse of
+/// `reassemble_mi`.
+pub fn split_mi(mi: &[u8; MI_BYTES]) -> [Hexbit; MI_HEXBITS] {
+    let mut words = [Hexbit::default(); MI_HEXBITS];
+    Hexbits::new(mi.iter().cloned()).collect_slice_checked(&mut words[..]);
+    words
+}
+
+/// Number of voice frames in a superframe (LDU1 and LDU2.)
+const SUPERFRAME_FRAMES: usize = 18;
+
+/// Number of keystream bytes used by each IMBE voice frame.
+const FRAME_KEYSTREAM_BYTES: usize = 11;
+
+/// Generate the message indicator for the next superframe from the given one.
+///
+/// The first 64 bits are clocked 64 times through the LFSR with characteristic
+/// polynomial C(x) = 1 + x^15 + x^27 + x^38 + x^46 + x^62 + x^64, and the last byte is
+/// always zero.
+pub fn next_mi(mi: &[u8; 9]) -> [u8; 9] {
+    let lfsr = (0..64).fold(mi[..8].iter().fold(0, |s, &b| s << 8 | b as u64), |l, _| {
+        let bit = (l >> 63 ^ l >> 61 ^ l >> 45 ^ l >> 37 ^ l >> 26 ^ l >> 14) & 1;
+        l << 1 | bit
+    });
+
+    let mut next = [0; 9];
+
+    for (i, b) in next[..8].iter_mut().enumerate() {
+        *b = (lfsr >> (56 - i * 8)) as u8;
+    }
+
+    next
+}
+
+/// Generates keystream bytes for a crypto algorithm and key.
+///
+/// Implementations that need to resist timing side channels should wrap a block cipher
+/// whose running time doesn't depend on the key, such as a bitsliced or hardware AES,
+/// rather than one using key-dependent table lookups.
+pub trait Keystream {
+    /// Fill `out` with the keystream bytes starting at byte `offset` of the keystream
+    /// seeded with the given message indicator.
+    fn keystream(&mut self, mi: &[u8; 9], offset: usize, out: &mut [u8]);
+}
+
+/// Tracks the message indicator over an encrypted call, advancing it every superframe
+/// and resynchronizing it with the indicator received in each LDU2.
+pub struct CallCrypto<K: Keystream> {
+    /// Keystream generator.
+    gen: K,
+    /// Superframe that `mi` applies to.
+    superframe: usize,
+    /// Message indicator for `superframe`.
+    mi: [u8; 9],
+    /// Message indicator for the superframe before `superframe`, if known.
+    prev: Option<[u8; 9]>,
+    /// Whether the last received message indicator differed from the predicted one.
+    diverged: bool,
+}
+
+impl<K: Keystream> CallCrypto<K> {
+    /// Create a new `CallCrypto` using the given keystream generator and the message
+    /// indicator received in the voice header, which applies to the first superframe.
+    pub fn new(gen: K, mi: [u8; 9]) -> CallCrypto<K> {
+        CallCrypto {
+            gen: gen,
+            superframe: 0,
+            mi: mi,
+            prev: None,
+            diverged: false,
+        }
+    }
+
+    /// Create a new `CallCrypto` for the call started by the given voice header, or
+    /// return `None` for a clear call so keystream is never generated for it.
+    pub fn from_header(gen: K, header: &VoiceHeaderFields) -> Option<CallCrypto<K>> {
+        if !header.is_encrypted() {
+            return None;
+        }
+
+        let mut mi = [0; MI_BYTES];
+        mi.copy_from_slice(header.crypto_init());
+
+        Some(CallCrypto::new(gen, mi))
+    }
+
+    /// Create a new `CallCrypto` for a call joined late, in the same way as
+    /// `late_entry`, from the crypto control word received in the LDU2 of the given
+    /// superframe, or return `None` for a clear call.
+    pub fn from_crypto_control(gen: K, superframe: usize, cc: &CryptoControlFields)
+        -> Option<CallCrypto<K>>
+    {
+        if cc.is_encrypted() {
+            Some(CallCrypto::late_entry(gen, superframe, cc.mi()))
+        } else {
+            None
+        }
+    }
+
+    /// Create a new `CallCrypto` for a call joined after its voice header, using the
+    /// message indicator received in the LDU2 of the given superframe.
+    ///
+    /// The indicator in an LDU2 applies to the following superframe, not the one
+    /// carrying it, so only frames from `first_frame` onward can be decrypted. Frames
+    /// are indexed relative to the given superframe, so a receiver that doesn't know how
+    /// far into the call it joined can count from the LDU1 before the LDU2 and pass a
+    /// superframe of 0.
+    pub fn late_entry(gen: K, superframe: usize, mi: [u8; 9]) -> CallCrypto<K> {
+        CallCrypto {
+            gen: gen,
+            superframe: superframe + 1,
+            mi: mi,
+            prev: None,
+            diverged: false,
+        }
+    }
+
+    /// Index of the first voice frame with a known message indicator.
+    pub fn first_frame(&self) -> usize { self.superframe * SUPERFRAME_FRAMES }
+
+    /// Predict the message indicator for the given superframe.
+    ///
+    /// The indicator of the superframe just before the last received one is kept, so the
+    /// voice frames of an LDU2 can still be decrypted after its indicator was passed to
+    /// `update_mi`. Return `None` for any earlier superframe, or for one before
+    /// `first_frame`.
+    pub fn mi_for(&self, superframe: usize) -> Option<[u8; 9]> {
+        if superframe >= self.superframe {
+            Some((self.superframe..superframe).fold(self.mi, |mi, _| next_mi(&mi)))
+        } else if superframe + 1 == self.superframe {
+            self.prev
+        } else {
+            None
+        }
+    }
+
+    /// Fill `out` with the keystream for the voice frame at the given index, counted
+    /// from the first frame after the voice header. Dropped frames are accounted for by
+    /// the index, so the message indicator is advanced even if the LDU2 carrying it was
+    /// missed. Return `false`, leaving `out` untouched, if the frame's message indicator
+    /// isn't known according to `mi_for`.
+    pub fn voice_keystream(&mut self, frame_index: usize, out: &mut [u8]) -> bool {
+        let mi = match self.mi_for(frame_index / SUPERFRAME_FRAMES) {
+            Some(mi) => mi,
+            None => return false,
+        };
+
+        let offset = frame_index % SUPERFRAME_FRAMES * FRAME_KEYSTREAM_BYTES;
+        self.gen.keystream(&mi, offset, out);
+
+        true
+    }
+
+    /// Resynchronize with the message indicator received in the LDU2 of the given
+    /// superframe, which applies to the following superframe. Return `true` if it
+    /// matched the predicted indicator and `false` if sync was lost or the indicator
+    /// was for a superframe that has already passed.
+    pub fn update_mi(&mut self, superframe: usize, mi: [u8; 9]) -> bool {
+        self.diverged = self.mi_for(superframe + 1) != Some(mi);
+        self.prev = self.mi_for(superframe);
+        self.superframe = superframe + 1;
+        self.mi = mi;
+
+        !self.diverged
+    }
+
+    /// Whether the last received message indicator differed from the predicted one.
+    pub fn diverged(&self) -> bool { self.diverged }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
     use super::CryptoAlgorithm::*;
+    use bits::{Hexbit, Hexbits};
+    use coding::reed_solomon;
+    use collect_slice::CollectSlice;
+    use error::P25Error;
+
+    #[test]
+    fn test_reassemble_mi() {
+        let words = [3, 8, 13, 18, 23, 28, 33, 38, 43, 48, 53, 58];
+        let mut hexbits = [Hexbit::default(); 12];
+
+        for (h, &w) in hexbits.iter_mut().zip(words.iter()) {
+            *h = Hexbit::new(w);
+        }
+
+        let mi = reassemble_mi(&hexbits);
+        assert_eq!(mi, [0x0C, 0x83, 0x52, 0x5D, 0xC8, 0x66, 0xAF, 0x0D, 0x7A]);
+        assert_eq!(split_mi(&mi), hexbits);
+
+        let mi = [0xFF, 0x00, 0x80, 0x01, 0x7E, 0x5A, 0xA5, 0xC3, 0x3C];
+        assert_eq!(reassemble_mi(&split_mi(&mi)), mi);
+    }
+
+    #[test]
+    fn test_decode() {
+        let bytes = [
+            0x12, 0x34, 0x56, 0x78, 0x9A, 0xBC, 0xDE, 0xF0, 0x11,
+            0x81,
+            0xBE, 0xEF,
+        ];
+
+        let mut buf = [Hexbit::default(); 24];
+        Hexbits::new(bytes.iter().cloned()).collect_slice_checked(&mut buf[..16]);
+        reed_solomon::medium::encode(&mut buf);
+
+        buf[0] = Hexbit::new(0b111111);
+        buf[7] = Hexbit::new(0b000000);
+        buf[20] = Hexbit::new(0b101010);
+
+        let (c, err) = CryptoControlFields::decode(&mut buf).unwrap();
+
+        assert_eq!(err, 3);
+        assert_eq!(c.mi(), [0x12, 0x34, 0x56, 0x78, 0x9A, 0xBC, 0xDE, 0xF0, 0x11]);
+        assert_eq!(c.alg(), Des);
+        assert_eq!(c.key(), 0xBEEF);
+
+        assert_eq!(CryptoControlFields::from_hexbits(&buf[..15]).err(),
+                   Some(P25Error::TooShort { needed: 16, got: 15 }));
+    }
 
     #[test]
     fn test_cypto() {
@@ -76,4 +361,157 @@ mod test {
         assert_eq!(c.alg(), Aes);
         assert_eq!(c.key(), 0xDEAD);
     }
+
+    /// Keystream that just records its inputs.
+    struct TestKeystream;
+
+    impl Keystream for TestKeystream {
+        fn keystream(&mut self, mi: &[u8; 9], offset: usize, out: &mut [u8]) {
+            out[0] = mi[0];
+            out[1] = mi[7];
+            out[2] = offset as u8;
+        }
+    }
+
+    /// Keystream that must never be used.
+    struct NoKeystream;
+
+    impl Keystream for NoKeystream {
+        fn keystream(&mut self, _: &[u8; 9], _: usize, _: &mut [u8]) {
+            panic!("keystream generated for clear call");
+        }
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut buf = [0; 15];
+        buf[..9].copy_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8, 9]);
+        buf[10] = 0x80;
+
+        let h = VoiceHeaderFields::new(buf);
+        assert!(!h.is_encrypted());
+        assert!(CallCrypto::from_header(NoKeystream, &h).is_none());
+
+        let mut cc = [0; 12];
+        cc[9] = 0x80;
+        let cc = CryptoControlFields::new(cc);
+        assert!(!cc.is_encrypted());
+        assert!(CallCrypto::from_crypto_control(NoKeystream, 0, &cc).is_none());
+
+        buf[10] = 0x84;
+        let h = VoiceHeaderFields::new(buf);
+        assert!(h.is_encrypted());
+
+        let mut c = CallCrypto::from_header(TestKeystream, &h).unwrap();
+        let mut ks = [0; 3];
+        c.voice_keystream(0, &mut ks);
+        assert_eq!(ks, [1, 8, 0]);
+
+        assert!(CryptoAlgorithm::Other(0x42).is_encrypted());
+        assert!(!CryptoAlgorithm::from_bits(0x80).is_encrypted());
+    }
+
+    #[test]
+    fn test_next_mi() {
+        // Computed with the LFSR from the LFSRP routine of DSD-FME.
+        assert_eq!(next_mi(&[0x12, 0x34, 0x56, 0x78, 0x9A, 0xBC, 0xDE, 0xF0, 0x00]),
+                   [0x0B, 0x16, 0x5E, 0x3F, 0x95, 0x17, 0x3D, 0xCD, 0x00]);
+        assert_eq!(next_mi(&[0, 0, 0, 0, 0, 0, 0, 1, 0xAA]),
+                   [0x00, 0x02, 0x00, 0x24, 0x04, 0x0C, 0x04, 0x95, 0x00]);
+        assert_eq!(next_mi(&[0; 9]), [0; 9]);
+    }
+
+    #[test]
+    fn test_call_crypto() {
+        let mi0 = [0x12, 0x34, 0x56, 0x78, 0x9A, 0xBC, 0xDE, 0xF0, 0x00];
+        let mi1 = next_mi(&mi0);
+        let mi2 = next_mi(&mi1);
+        let mut c = CallCrypto::new(TestKeystream, mi0);
+        let mut ks = [0; 3];
+
+        c.voice_keystream(0, &mut ks);
+        assert_eq!(ks, [mi0[0], mi0[7], 0]);
+        c.voice_keystream(17, &mut ks);
+        assert_eq!(ks, [mi0[0], mi0[7], 17 * 11]);
+
+        // LDU2 of first superframe was dropped, so the MI is predicted.
+        c.voice_keystream(18, &mut ks);
+        assert_eq!(ks, [mi1[0], mi1[7], 0]);
+        c.voice_keystream(40, &mut ks);
+        assert_eq!(ks, [mi2[0], mi2[7], 4 * 11]);
+
+        // Matching MI from the LDU2 of the second superframe.
+        assert!(c.update_mi(1, mi2));
+        assert!(!c.diverged());
+        c.voice_keystream(37, &mut ks);
+        assert_eq!(ks, [mi2[0], mi2[7], 11]);
+
+        // Voice frames of the LDU2 that carried the indicator are still decryptable.
+        assert_eq!(c.mi_for(1), Some(mi1));
+        assert!(c.voice_keystream(35, &mut ks));
+        assert_eq!(ks, [mi1[0], mi1[7], 17 * 11]);
+
+        // Earlier superframes are no longer known.
+        assert_eq!(c.mi_for(0), None);
+        ks = [0xEE; 3];
+        assert!(!c.voice_keystream(17, &mut ks));
+        assert_eq!(ks, [0xEE; 3]);
+
+        // Received MI doesn't match, so resync to it.
+        let fresh = [0xFF, 0, 0, 0, 0, 0, 0, 0x42, 0];
+        assert!(!c.update_mi(2, fresh));
+        assert!(c.diverged());
+        c.voice_keystream(54, &mut ks);
+        assert_eq!(ks, [0xFF, 0x42, 0]);
+
+        let next = next_mi(&fresh);
+        c.voice_keystream(72, &mut ks);
+        assert_eq!(ks, [next[0], next[7], 0]);
+
+        assert!(c.update_mi(3, next));
+        assert!(!c.diverged());
+    }
+
+    #[test]
+    fn test_late_entry() {
+        let mi0 = [0x12, 0x34, 0x56, 0x78, 0x9A, 0xBC, 0xDE, 0xF0, 0x00];
+        let mi1 = next_mi(&mi0);
+        let mi2 = next_mi(&mi1);
+        let mut ks = [0; 3];
+
+        // Joined the call during the first superframe, so the frames of that superframe
+        // can't be decrypted.
+        let mut c = CallCrypto::late_entry(TestKeystream, 0, mi1);
+        assert_eq!(c.first_frame(), 18);
+        assert_eq!(c.mi_for(0), None);
+        assert!(!c.voice_keystream(17, &mut ks));
+
+        // First frame of the following LDU1 starts the keystream over.
+        c.voice_keystream(18, &mut ks);
+        assert_eq!(ks, [mi1[0], mi1[7], 0]);
+        c.voice_keystream(20, &mut ks);
+        assert_eq!(ks, [mi1[0], mi1[7], 2 * 11]);
+
+        // LDU2 frames of the same superframe continue the keystream.
+        c.voice_keystream(27, &mut ks);
+        assert_eq!(ks, [mi1[0], mi1[7], 9 * 11]);
+
+        // Matches the call as decrypted from the voice header.
+        let mut full = CallCrypto::new(TestKeystream, mi0);
+        let mut expect = [0; 3];
+
+        for i in 18..54 {
+            c.voice_keystream(i, &mut ks);
+            full.voice_keystream(i, &mut expect);
+            assert_eq!(ks, expect);
+        }
+
+        assert!(c.update_mi(1, mi2));
+
+        // Joined later in the call.
+        let mut c = CallCrypto::late_entry(TestKeystream, 5, mi2);
+        assert_eq!(c.first_frame(), 6 * 18);
+        c.voice_keystream(6 * 18 + 1, &mut ks);
+        assert_eq!(ks, [mi2[0], mi2[7], 11]);
+    }
 }
@@ -3,12 +3,45 @@
 use bits::Dibit;
 use consts;
 
+/// Number of coded bits in each chunk `u_0`, ..., `u_7`.
+pub const CHUNK_BITS: [usize; 8] = [23, 23, 23, 23, 15, 15, 15, 7];
+
 /// Descramble a portion of the given voice frame dibits into the PN-scrambled, coded
 /// chunk `u_{idx}`.
 pub fn descramble(dibits: &[Dibit; consts::FRAME_DIBITS], idx: usize) -> u32 {
     DESCRAMBLERS[idx].descramble(dibits)
 }
 
+/// Deinterleave the given voice frame dibits into the coded chunks `u_0`, ..., `u_7`.
+pub fn deinterleave(dibits: &[Dibit; consts::FRAME_DIBITS]) -> [u32; 8] {
+    let mut chunks = [0; 8];
+
+    for (idx, chunk) in chunks.iter_mut().enumerate() {
+        *chunk = descramble(dibits, idx);
+    }
+
+    chunks
+}
+
+/// Interleave the given coded chunks `u_0`, ..., `u_7` into voice frame dibits, the
+/// inverse of `deinterleave`.
+pub fn interleave(chunks: &[u32; 8]) -> [Dibit; consts::FRAME_DIBITS] {
+    let mut bits = [0; consts::FRAME_DIBITS];
+
+    for (idx, d) in DESCRAMBLERS.iter().enumerate() {
+        assert!(chunks[idx] >> CHUNK_BITS[idx] == 0);
+        d.scramble(chunks[idx], CHUNK_BITS[idx], &mut bits);
+    }
+
+    let mut dibits = [Dibit::default(); consts::FRAME_DIBITS];
+
+    for (dibit, &b) in dibits.iter_mut().zip(bits.iter()) {
+        *dibit = Dibit::new(b);
+    }
+
+    dibits
+}
+
 /// Set of descramblers for each associated chunk `u_0`, ..., `u_7`.
 const DESCRAMBLERS: [VoiceFrameDescrambler; 8] = [
     VoiceFrameDescrambler(&[
@@ -59,6 +92,19 @@ impl VoiceFrameDescrambler {
             })
         })
     }
+
+    /// Scatter the bits of the given chunk of the given size, MSB first, into the
+    /// corresponding positions of the given dibit values.
+    pub fn scramble(&self, chunk: u32, size: usize,
+                    dibits: &mut [u8; consts::FRAME_DIBITS])
+    {
+        let positions = self.0.iter().flat_map(|&zz| zz);
+
+        for (bit, (idx, hi)) in (0..size).rev().zip(positions) {
+            let b = (chunk >> bit & 1) as u8;
+            dibits[idx] |= if hi { b << 1 } else { b };
+        }
+    }
 }
 
 /// Walks the zigzagging interleave schedule used for voice frames.
@@ -117,9 +163,46 @@ impl Iterator for ZigZag {
 
 #[cfg(test)]
 mod test {
+    use super::*;
     use super::DESCRAMBLERS;
     use consts;
 
+    #[test]
+    fn test_chunk_bits() {
+        for (d, &size) in DESCRAMBLERS.iter().zip(CHUNK_BITS.iter()) {
+            assert_eq!(d.0.iter().map(|zz| zz.remain).sum::<usize>(), size);
+        }
+
+        assert_eq!(CHUNK_BITS.iter().sum::<usize>(), consts::FRAME_DIBITS * 2);
+    }
+
+    #[test]
+    fn test_interleave() {
+        // MSB of u_1 is carried in the low bit of dibit 69.
+        let dibits = interleave(&[0, 1 << 22, 0, 0, 0, 0, 0, 0]);
+
+        for (idx, d) in dibits.iter().enumerate() {
+            assert_eq!(d.bits(), if idx == 69 { 0b01 } else { 0b00 });
+        }
+
+        // MSB of u_0 is carried in the high bit of dibit 0.
+        assert_eq!(interleave(&[1 << 22, 0, 0, 0, 0, 0, 0, 0])[0].bits(), 0b10);
+
+        let chunks = [
+            0x5A5A5A, 0x123456, 0x7FFFFF, 0x000001,
+            0x7ACE, 0x1234, 0x4001, 0x55,
+        ];
+
+        assert_eq!(deinterleave(&interleave(&chunks)), chunks);
+
+        let dibits = interleave(&chunks);
+        assert_eq!(interleave(&deinterleave(&dibits))[..], dibits[..]);
+
+        for idx in 0..8 {
+            assert_eq!(descramble(&dibits, idx), chunks[idx]);
+        }
+    }
+
     #[test]
     fn test_steps_exhaustive() {
         let mut visited = [0u32; consts::FRAME_DIBITS];
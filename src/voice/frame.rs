@@ -1,16 +1,24 @@
 //! Decode a voice frame into chunks suitable for IMBE.
 
-use bits::Dibit;
+use collect_slice::CollectSlice;
+
+use bits::{Dibit, DibitBytes, Dibits};
 use coding::{golay, hamming};
+use coding::profile::{self, Field};
 use consts;
 use error::Result;
 
-use voice::descramble::descramble;
+use voice::descramble::{descramble, interleave};
 use voice::rand;
 
 use error::P25Error::*;
 
+/// Most bit corrections a decodable voice frame can have: 3 data bits in each Golay chunk
+/// and 1 bit in each Hamming chunk.
+pub const MAX_CORRECTIONS: usize = 4 * 3 + 3 * 1;
+
 /// IMBE-encoded voice frame.
+#[cfg_attr(feature = "ser", derive(Serialize, Deserialize))]
 pub struct VoiceFrame {
     /// Chunks of IMBE-prioritized data, Known as `u_0`, ..., `u_7` in the standard.
     pub chunks: [u32; 8],
@@ -27,8 +35,10 @@ impl VoiceFrame {
         let mut errors = [0; 7];
 
         // Decode u_0 to recover the PN seed.
-        let (init, err) = match golay::standard::decode(descramble(dibits, 0)) {
-            Some(x) => x,
+        let code = profile::inner_code(Field::VoiceWord(0));
+
+        let (init, err) = match code.decode(descramble(dibits, 0) as u64) {
+            Some((data, err)) => (data as u16, err),
             None => return Err(GolayUnrecoverable),
         };
 
@@ -40,8 +50,9 @@ impl VoiceFrame {
         // Decode "higher-priority" Golay chunks.
         for idx in 1...3 {
             let bits = descramble(dibits, idx) ^ prand.next_23();
+            let code = profile::inner_code(Field::VoiceWord(idx));
 
-            let (data, err) = match golay::standard::decode(bits) {
+            let (data, err) = match code.decode(bits as u64) {
                 Some(x) => x,
                 None => return Err(GolayUnrecoverable),
             };
@@ -53,8 +64,9 @@ impl VoiceFrame {
         // Decode "lower-priority" Hamming chunks.
         for idx in 4...6 {
             let bits = descramble(dibits, idx) ^ prand.next_15();
+            let code = profile::inner_code(Field::VoiceWord(idx));
 
-            let (data, err) = match hamming::standard::decode(bits as u16) {
+            let (data, err) = match code.decode(bits as u64) {
                 Some(x) => x,
                 None => return Err(HammingUnrecoverable),
             };
@@ -70,4 +82,203 @@ impl VoiceFrame {
             errors: errors,
         })
     }
+
+    /// Try to decode a `VoiceFrame` in the same way as `new` from the given coded
+    /// dibits packed 4 per byte, MSB first.
+    pub fn from_bytes(buf: &[u8; consts::FRAME_DIBITS / 4]) -> Result<VoiceFrame> {
+        let mut dibits = [Dibit::default(); consts::FRAME_DIBITS];
+        Dibits::new(buf.iter().cloned()).collect_slice_checked(&mut dibits[..]);
+
+        VoiceFrame::new(&dibits)
+    }
+
+    /// Total number of bits corrected over all chunks.
+    pub fn corrections(&self) -> usize { self.errors.iter().sum() }
+
+    /// Pack the decoded chunks into the IMBE frame handed to a vocoder, as for
+    /// `to_imbe_frame`.
+    pub fn imbe_frame(&self) -> [u8; consts::FRAME_DIBITS / 4] {
+        let mut chunks = [0; 8];
+
+        for (dst, &src) in chunks.iter_mut().zip(self.chunks.iter()) {
+            *dst = src as u16;
+        }
+
+        to_imbe_frame(&chunks)
+    }
+}
+
+/// Encode the given chunks `u_0`, ..., `u_7` into coded, PN-scrambled, interleaved
+/// dibits, the inverse of `VoiceFrame::new`.
+///
+/// Chunks `u_0`, ..., `u_3` must fit in 12 bits, `u_4`, ..., `u_6` in 11 bits, and
+/// `u_7` in 7 bits.
+pub fn encode_dibits(chunks: &[u16; 8]) -> [Dibit; consts::FRAME_DIBITS] {
+    interleave(&encode_vectors(chunks))
+}
+
+/// Pack the given chunks `u_0`, ..., `u_7` into the 144-bit IMBE frame handed to a
+/// vocoder, made up of the coded, PN-scrambled vectors `c_0`, ..., `c_7` in order, each
+/// MSB first. This is the frame before interleaving, as given in the IMBE standard.
+///
+/// Chunks must fit in the same sizes as for `encode_dibits`.
+pub fn to_imbe_frame(chunks: &[u16; 8]) -> [u8; consts::FRAME_DIBITS / 4] {
+    let coded = encode_vectors(chunks);
+    let mut buf = [0; consts::FRAME_DIBITS / 4];
+    let mut pos = 0;
+
+    for (idx, &c) in coded.iter().enumerate() {
+        let size = match idx {
+            0...3 => 23,
+            4...6 => 15,
+            _ => 7,
+        };
+
+        for bit in (0..size).rev() {
+            buf[pos / 8] |= ((c >> bit & 1) as u8) << (7 - pos % 8);
+            pos += 1;
+        }
+    }
+
+    assert!(pos == buf.len() * 8);
+
+    buf
+}
+
+/// Encode the given chunks into the coded, PN-scrambled vectors `c_0`, ..., `c_7`.
+fn encode_vectors(chunks: &[u16; 8]) -> [u32; 8] {
+    assert!(chunks[..4].iter().all(|&c| c >> 12 == 0));
+    assert!(chunks[4..7].iter().all(|&c| c >> 11 == 0));
+    assert!(chunks[7] >> 7 == 0);
+
+    let mut coded = [0; 8];
+    let mut prand = rand::PseudoRand::new(chunks[0]);
+
+    // The PN sequence is seeded by u_0, so it's sent unscrambled.
+    coded[0] = golay::standard::encode(chunks[0]);
+
+    for idx in 1...3 {
+        coded[idx] = golay::standard::encode(chunks[idx]) ^ prand.next_23();
+    }
+
+    for idx in 4...6 {
+        coded[idx] = hamming::standard::encode(chunks[idx]) as u32 ^ prand.next_15();
+    }
+
+    coded[7] = chunks[7] as u32;
+
+    coded
+}
+
+/// Encode the given chunks in the same way as `encode_dibits`, packing the dibits 4 per
+/// byte, MSB first.
+pub fn encode_frame(chunks: &[u16; 8]) -> [u8; consts::FRAME_DIBITS / 4] {
+    let mut buf = [0; consts::FRAME_DIBITS / 4];
+
+    DibitBytes::new(encode_dibits(chunks).iter().cloned())
+        .collect_slice_checked(&mut buf[..]);
+
+    buf
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use testutil::max_errors;
+    use voice::descramble::deinterleave;
+
+    #[test]
+    fn test_imbe_frame() {
+        let chunks = [0xABC, 0x123, 0x000, 0xFFF, 0x7FF, 0x2AA, 0x001, 0x7F];
+        let frame = to_imbe_frame(&chunks);
+
+        // The unscrambled u_0 leads the frame, followed by its Golay parity.
+        assert_eq!(frame[0], 0xAB);
+        assert_eq!(frame[1] >> 4, 0xC);
+        let c0 = (frame[0] as u32) << 15 | (frame[1] as u32) << 7 | frame[2] as u32 >> 1;
+        assert_eq!(c0, golay::standard::encode(0xABC));
+
+        // The 7 bits of u_7 end the frame.
+        assert_eq!(frame[17] & 0x7F, 0x7F);
+
+        // Unpacking gives back the transmitted vectors.
+        let coded = deinterleave(&encode_dibits(&chunks));
+        let bits: Vec<u32> = frame.iter()
+            .flat_map(|&b| (0..8).rev().map(move |i| (b >> i & 1) as u32))
+            .collect();
+
+        let mut pos = 0;
+
+        for (idx, &c) in coded.iter().enumerate() {
+            let size = [23, 23, 23, 23, 15, 15, 15, 7][idx];
+            let v = bits[pos..pos + size].iter().fold(0, |v, &b| v << 1 | b);
+
+            assert_eq!(v, c);
+            pos += size;
+        }
+
+        let zeros = to_imbe_frame(&[0; 8]);
+        assert_eq!(&zeros[..2], &[0, 0]);
+        assert!(zeros[3..].iter().any(|&b| b != 0));
+    }
+
+    #[test]
+    fn test_corrections() {
+        let chunks = [0xABC, 0x123, 0x000, 0xFFF, 0x7FF, 0x2AA, 0x001, 0x7F];
+
+        let frame = VoiceFrame::from_bytes(&encode_frame(&chunks)).unwrap();
+        assert_eq!(frame.corrections(), 0);
+
+        let frame = VoiceFrame::new(&max_errors(&chunks)).unwrap();
+        assert_eq!(frame.corrections(), MAX_CORRECTIONS);
+        assert_eq!(frame.errors, [3, 3, 3, 3, 1, 1, 1]);
+
+        for (&dec, &c) in frame.chunks.iter().zip(chunks.iter()) {
+            assert_eq!(dec, c as u32);
+        }
+    }
+
+    #[test]
+    fn test_encode_roundtrip() {
+        // Simple LCG for reproducible chunk values.
+        let mut state = 0x1234_5678u32;
+
+        let mut next = |bits: usize| {
+            state = state.wrapping_mul(1103515245).wrapping_add(12345);
+            (state >> 8) as u16 & ((1 << bits) - 1)
+        };
+
+        for _ in 0..500 {
+            let mut chunks = [0; 8];
+
+            for (idx, c) in chunks.iter_mut().enumerate() {
+                *c = next(match idx {
+                    0...3 => 12,
+                    4...6 => 11,
+                    _ => 7,
+                });
+            }
+
+            let frame = VoiceFrame::from_bytes(&encode_frame(&chunks)).unwrap();
+
+            for (&dec, &c) in frame.chunks.iter().zip(chunks.iter()) {
+                assert_eq!(dec, c as u32);
+            }
+
+            assert_eq!(frame.errors, [0; 7]);
+
+            // Correctable errors in the PN-seeding and scrambled chunks.
+            let mut dibits = encode_dibits(&chunks);
+            dibits[0] = Dibit::new(dibits[0].bits() ^ 0b10);
+            dibits[8] = Dibit::new(dibits[8].bits() ^ 0b01);
+
+            let frame = VoiceFrame::new(&dibits).unwrap();
+
+            for (&dec, &c) in frame.chunks.iter().zip(chunks.iter()) {
+                assert_eq!(dec, c as u32);
+            }
+
+            assert_eq!(frame.errors.iter().sum::<usize>(), 2);
+        }
+    }
 }
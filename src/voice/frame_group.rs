@@ -7,10 +7,13 @@ use std;
 
 use collect_slice::CollectSlice;
 
-use bits::{Hexbit, HexbitBytes, Dibit};
+use bits::{Hexbit, HexbitBytes, Hexbits, Dibit, DibitBytes, Dibits};
 use coding::{cyclic, hamming, reed_solomon};
+use coding::profile::{self, Field};
 use error::{P25Error, Result};
-use voice::frame::VoiceFrame;
+use message::nid::DataUnit;
+use util::check_len;
+use voice::frame::{encode_dibits, VoiceFrame};
 use voice::{control, crypto};
 
 use buffer::{
@@ -22,9 +25,11 @@ use buffer::{
 };
 
 use consts::{
-    CRYPTO_CONTROL_BYTES,
+    DATA_FRAG_DIBITS,
     EXTRA_HEXBITS,
     EXTRA_PIECE_DIBITS,
+    EXTRA_WORD_DIBITS,
+    FRAME_DIBITS,
     LINK_CONTROL_BYTES,
 };
 
@@ -32,6 +37,185 @@ use error::P25Error::*;
 use self::State::*;
 use self::StateChange::*;
 
+/// Number of voice frames in a frame group.
+pub const GROUP_FRAMES: usize = 9;
+
+/// Number of bytes in a coded voice frame.
+pub const FRAME_BYTES: usize = FRAME_DIBITS / 4;
+
+/// Number of bytes in the packed data symbols of a frame group.
+pub const GROUP_BYTES: usize =
+    (FRAME_DIBITS * GROUP_FRAMES + EXTRA_PIECE_DIBITS * 6 + DATA_FRAG_DIBITS * 2) / 4;
+
+/// Dibit offset of each voice frame within the data symbols of a frame group, following
+/// the NID and with status symbols removed. These are the same for LDU1 and LDU2.
+pub const FRAME_OFFSETS: [usize; GROUP_FRAMES] = [
+    0,
+    FRAME_DIBITS,
+    FRAME_DIBITS * 2 + EXTRA_PIECE_DIBITS,
+    FRAME_DIBITS * 3 + EXTRA_PIECE_DIBITS * 2,
+    FRAME_DIBITS * 4 + EXTRA_PIECE_DIBITS * 3,
+    FRAME_DIBITS * 5 + EXTRA_PIECE_DIBITS * 4,
+    FRAME_DIBITS * 6 + EXTRA_PIECE_DIBITS * 5,
+    FRAME_DIBITS * 7 + EXTRA_PIECE_DIBITS * 6,
+    FRAME_DIBITS * 8 + EXTRA_PIECE_DIBITS * 6 + DATA_FRAG_DIBITS * 2,
+];
+
+/// Iterate over the coded voice frames in the given frame group, where `bits` holds the
+/// data symbols following the NID, with status symbols removed, packed 4 dibits per byte
+/// MSB first. Only frames entirely within `bits` are yielded.
+pub fn ldu_frames<'a>(bits: &'a [u8]) -> LduFrames<'a> {
+    LduFrames {
+        bits: bits,
+        frame: 0,
+    }
+}
+
+/// Iterates over the coded voice frames in a frame group.
+pub struct LduFrames<'a> {
+    /// Packed data symbols of the frame group.
+    bits: &'a [u8],
+    /// Index of the next frame.
+    frame: usize,
+}
+
+impl<'a> Iterator for LduFrames<'a> {
+    type Item = [u8; FRAME_BYTES];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let start = match FRAME_OFFSETS.get(self.frame) {
+            // Every frame starts on a byte boundary.
+            Some(&off) => off / 4,
+            None => return None,
+        };
+
+        if start + FRAME_BYTES > self.bits.len() {
+            return None;
+        }
+
+        self.frame += 1;
+
+        let mut frame = [0; FRAME_BYTES];
+        frame.copy_from_slice(&self.bits[start..start + FRAME_BYTES]);
+
+        Some(frame)
+    }
+}
+
+/// Encode the given voice frame chunks, extra packet, and low-speed data words into the
+/// data symbols of a frame group, packed in the same way as for `ldu_frames`.
+///
+/// The NID and status symbols aren't included.
+pub fn encode_group<E: Extra>(frames: &[[u16; 8]; GROUP_FRAMES], extra: &E::Fields,
+                              lsd: [u8; 2])
+    -> [u8; GROUP_BYTES]
+{
+    let mut hexbits = E::encode_extra(extra);
+    E::encode_rs(&mut hexbits);
+
+    // Each piece of the extra packet carries 4 Hamming coded hexbits.
+    let mut pieces = hexbits.chunks(EXTRA_PIECE_DIBITS / EXTRA_WORD_DIBITS);
+    let mut dibits = Vec::with_capacity(GROUP_BYTES * 4);
+
+    for (idx, frame) in frames.iter().enumerate() {
+        dibits.extend(encode_dibits(frame).iter().cloned());
+
+        match idx {
+            1...6 => for h in pieces.next().unwrap() {
+                let word = hamming::shortened::encode(h.bits());
+                dibits.extend((0..EXTRA_WORD_DIBITS).rev().map(|i| {
+                    Dibit::new((word >> i * 2) as u8 & 0b11)
+                }));
+            },
+            7 => for &data in lsd.iter() {
+                let word = cyclic::encode(data);
+                dibits.extend((0..DATA_FRAG_DIBITS).rev().map(|i| {
+                    Dibit::new((word >> i * 2) as u8 & 0b11)
+                }));
+            },
+            _ => {},
+        }
+    }
+
+    let mut buf = [0; GROUP_BYTES];
+    DibitBytes::new(dibits.into_iter()).collect_slice_checked(&mut buf[..]);
+
+    buf
+}
+
+/// Extra packet carried in a frame group, which depends on the type of frame group.
+pub enum LduExtra {
+    /// Link control word, carried in LDU1.
+    LinkControl(control::LinkControlFields),
+    /// Crypto control word with the message indicator, carried in LDU2.
+    CryptoControl(crypto::CryptoControlFields),
+}
+
+/// A fully decoded frame group.
+pub struct Ldu {
+    /// Voice frames in the order transmitted.
+    frames: Vec<VoiceFrame>,
+    /// Extra packet.
+    extra: LduExtra,
+    /// Low-speed data word.
+    lsd: u32,
+}
+
+impl Ldu {
+    /// Voice frames in the order transmitted.
+    pub fn frames(&self) -> &[VoiceFrame] { &self.frames[..] }
+    /// Extra packet, either link control or crypto control.
+    pub fn extra(&self) -> &LduExtra { &self.extra }
+    /// Low-speed data word, made of the two 16-bit fragments in the group.
+    pub fn lsd(&self) -> u32 { self.lsd }
+}
+
+/// Decode the given frame group, packed in the same way as for `ldu_frames`, selecting
+/// the extra packet by the given data unit from the NID.
+///
+/// Return `Err(UnknownNid)` if the data unit isn't a voice frame group.
+pub fn decode_ldu(duid: DataUnit, bits: &[u8]) -> Result<Ldu> {
+    if bits.len() < GROUP_BYTES {
+        return Err(TooShort { needed: GROUP_BYTES, got: bits.len() });
+    }
+
+    match duid {
+        DataUnit::VoiceLCFrameGroup =>
+            decode_group::<LinkControlExtra, _>(bits, LduExtra::LinkControl),
+        DataUnit::VoiceCCFrameGroup =>
+            decode_group::<CryptoControlExtra, _>(bits, LduExtra::CryptoControl),
+        _ => Err(UnknownNid),
+    }
+}
+
+/// Decode the given frame group with the receiver for the given extra packet, wrapping
+/// the extra packet with the given function.
+fn decode_group<E, F>(bits: &[u8], wrap: F) -> Result<Ldu> where
+    E: Extra,
+    F: Fn(E::Fields) -> LduExtra
+{
+    let mut recv = FrameGroupReceiver::<E>::new();
+    let mut frames = Vec::with_capacity(GROUP_FRAMES);
+    let mut extra = None;
+    let mut lsd = 0;
+
+    for dibit in Dibits::new(bits[..GROUP_BYTES].iter().cloned()) {
+        match recv.feed(dibit) {
+            Some(Ok(FrameGroupEvent::VoiceFrame(vf))) => frames.push(vf),
+            Some(Ok(FrameGroupEvent::Extra(e))) => extra = Some(wrap(e)),
+            Some(Ok(FrameGroupEvent::DataFragment(d))) => lsd = d,
+            Some(Err(e)) => return Err(e),
+            None => {},
+        }
+    }
+
+    Ok(Ldu {
+        frames: frames,
+        extra: extra.expect("extra packet decoded"),
+        lsd: lsd,
+    })
+}
+
 /// Receiver for Link Control (LC) frame group.
 pub type VoiceLCFrameGroupReceiver = FrameGroupReceiver<LinkControlExtra>;
 /// Receiver for Crypto Control (CC) frame group.
@@ -59,6 +243,17 @@ impl State {
     pub fn decode_data_frag() -> State {
         DecodeDataFragment(DataFragmentReceiver::new())
     }
+
+    /// Determine the state following the given number of voice frames.
+    pub fn after_frame(frame: usize) -> State {
+        match frame {
+            1 => State::decode_voice_frame(),
+            2...7 => DecodeExtra,
+            8 => State::decode_data_frag(),
+            9 => Done,
+            _ => unreachable!(),
+        }
+    }
 }
 
 /// Action the state machine should take.
@@ -71,6 +266,8 @@ enum StateChange<E: Extra> {
     EventChange(FrameGroupEvent<E>, State),
     /// Propagate an error.
     Error(P25Error),
+    /// Change to the enclosed state and propagate an error.
+    ErrorChange(P25Error, State),
 }
 
 /// Events that can occur when receiving a frame group.
@@ -92,6 +289,10 @@ pub struct FrameGroupReceiver<E: Extra> {
     extra: ExtraReceiver<E>,
     /// The current frame position within the frame group.
     frame: usize,
+    /// Most total corrections allowed in a voice frame, if any.
+    budget: Option<usize>,
+    /// Whether to pass over voice frames without decoding them.
+    skip_voice: bool,
 }
 
 impl<E: Extra> FrameGroupReceiver<E> {
@@ -101,9 +302,30 @@ impl<E: Extra> FrameGroupReceiver<E> {
             state: State::decode_voice_frame(),
             extra: ExtraReceiver::new(),
             frame: 0,
+            budget: None,
+            skip_voice: false,
         }
     }
 
+    /// Pass over the symbols of each voice frame without decoding them or producing
+    /// `VoiceFrame` events, leaving only the extra packet and low-speed data. This avoids
+    /// the voice frame FEC when only metadata is needed.
+    pub fn set_skip_voice(&mut self, skip: bool) {
+        self.skip_voice = skip;
+    }
+
+    /// Reject voice frames whose total corrections over all chunks exceed the given
+    /// budget with `CorrectionBudgetExceeded`, or accept every decodable frame with
+    /// `None`. A rejected frame is passed over in the same way as a decoded one, so the
+    /// rest of the frame group is still received.
+    ///
+    /// A frame that needed the most corrections in every chunk is more likely noise that
+    /// happened to decode than a real frame, and a budget below `MAX_CORRECTIONS` drops
+    /// these. A clean frame always passes.
+    pub fn set_correction_budget(&mut self, budget: Option<usize>) {
+        self.budget = budget;
+    }
+
     /// Whether the full frame group has been received.
     pub fn done(&self) -> bool {
         if let Done = self.state { true } else { false }
@@ -111,18 +333,28 @@ impl<E: Extra> FrameGroupReceiver<E> {
 
     /// Determine what action to take based on the given symbol.
     fn handle(&mut self, dibit: Dibit) -> StateChange<E> {
+        let budget = self.budget;
+
         match self.state {
+            DecodeVoiceFrame(ref mut decoder) if self.skip_voice =>
+                if decoder.skip(dibit) {
+                    self.frame += 1;
+                    Change(State::after_frame(self.frame))
+                } else {
+                    NoChange
+                },
             DecodeVoiceFrame(ref mut decoder) => match decoder.feed(dibit) {
+                Some(Ok(ref vf)) if budget.map_or(false, |b| vf.corrections() > b) => {
+                    self.frame += 1;
+
+                    ErrorChange(P25Error::CorrectionBudgetExceeded,
+                                State::after_frame(self.frame))
+                },
                 Some(Ok(vf)) => {
                     self.frame += 1;
 
-                    EventChange(FrameGroupEvent::VoiceFrame(vf), match self.frame {
-                        1 => State::decode_voice_frame(),
-                        2...7 => DecodeExtra,
-                        8 => State::decode_data_frag(),
-                        9 => Done,
-                        _ => unreachable!(),
-                    })
+                    EventChange(FrameGroupEvent::VoiceFrame(vf),
+                                State::after_frame(self.frame))
                 },
                 Some(Err(e)) => Error(e),
                 None => NoChange,
@@ -161,6 +393,10 @@ impl<E: Extra> FrameGroupReceiver<E> {
                 None
             },
             Error(e) => Some(Err(e)),
+            ErrorChange(e, next) => {
+                self.state = next;
+                Some(Err(e))
+            },
             NoChange => None,
         }
     }
@@ -171,10 +407,17 @@ pub trait Extra {
     /// Base decoder for the packet.
     type Fields;
 
+    /// Field carried by the packet, which determines its codes.
+    fn field() -> Field;
     /// Decode the inner Reed Soloman code.
     fn decode_rs(buf: &mut [Hexbit; EXTRA_HEXBITS]) -> Option<(&[Hexbit], usize)>;
-    /// Transform the given hexbits into a base packet decoder.
-    fn decode_extra(buf: &[Hexbit]) -> Self::Fields;
+    /// Transform the given data hexbits into a base packet decoder, failing with
+    /// `TooShort` if there are too few of them.
+    fn decode_extra(buf: &[Hexbit]) -> Result<Self::Fields>;
+    /// Fill in the inner Reed Soloman parity hexbits after the data hexbits.
+    fn encode_rs(buf: &mut [Hexbit; EXTRA_HEXBITS]);
+    /// Transform the given fields into data hexbits at the start of a coded buffer.
+    fn encode_extra(fields: &Self::Fields) -> [Hexbit; EXTRA_HEXBITS];
 }
 
 /// Link control frame group extra.
@@ -183,15 +426,35 @@ pub struct LinkControlExtra;
 impl Extra for LinkControlExtra {
     type Fields = control::LinkControlFields;
 
+    fn field() -> Field { Field::LinkControl }
+
     fn decode_rs(buf: &mut [Hexbit; EXTRA_HEXBITS]) -> Option<(&[Hexbit], usize)> {
         reed_solomon::short::decode(buf)
     }
 
-    fn decode_extra(buf: &[Hexbit]) -> Self::Fields {
+    fn decode_extra(buf: &[Hexbit]) -> Result<Self::Fields> {
+        const DATA_HEXBITS: usize = LINK_CONTROL_BYTES * 8 / 6;
+
+        if let Err(e) = check_len(buf, DATA_HEXBITS) {
+            return Err(e);
+        }
+
         let mut bytes = [0; LINK_CONTROL_BYTES];
-        HexbitBytes::new(buf.iter().cloned()).collect_slice_checked(&mut bytes[..]);
+        HexbitBytes::new(buf[..DATA_HEXBITS].iter().cloned())
+            .collect_slice_checked(&mut bytes[..]);
 
-        control::LinkControlFields::new(bytes)
+        Ok(control::LinkControlFields::new(bytes))
+    }
+
+    fn encode_rs(buf: &mut [Hexbit; EXTRA_HEXBITS]) {
+        reed_solomon::short::encode(buf)
+    }
+
+    fn encode_extra(fields: &Self::Fields) -> [Hexbit; EXTRA_HEXBITS] {
+        let mut buf = [Hexbit::default(); EXTRA_HEXBITS];
+        Hexbits::new(fields.bytes().iter().cloned())
+            .collect_slice_checked(&mut buf[..12]);
+        buf
     }
 }
 
@@ -201,15 +464,25 @@ pub struct CryptoControlExtra;
 impl Extra for CryptoControlExtra {
     type Fields = crypto::CryptoControlFields;
 
+    fn field() -> Field { Field::CryptoControl }
+
     fn decode_rs(buf: &mut [Hexbit; EXTRA_HEXBITS]) -> Option<(&[Hexbit], usize)> {
         reed_solomon::medium::decode(buf)
     }
 
-    fn decode_extra(buf: &[Hexbit]) -> Self::Fields {
-        let mut bytes = [0; CRYPTO_CONTROL_BYTES];
-        HexbitBytes::new(buf.iter().cloned()).collect_slice_checked(&mut bytes[..]);
+    fn decode_extra(buf: &[Hexbit]) -> Result<Self::Fields> {
+        crypto::CryptoControlFields::from_hexbits(buf)
+    }
+
+    fn encode_rs(buf: &mut [Hexbit; EXTRA_HEXBITS]) {
+        reed_solomon::medium::encode(buf)
+    }
 
-        crypto::CryptoControlFields::new(bytes)
+    fn encode_extra(fields: &Self::Fields) -> [Hexbit; EXTRA_HEXBITS] {
+        let mut buf = [Hexbit::default(); EXTRA_HEXBITS];
+        Hexbits::new(fields.bytes().iter().cloned())
+            .collect_slice_checked(&mut buf[..16]);
+        buf
     }
 }
 
@@ -236,6 +509,12 @@ impl VoiceFrameReceiver {
             None => None,
         }
     }
+
+    /// Feed in a baseband symbol without decoding, returning whether the symbol
+    /// completed a voice frame.
+    pub fn skip(&mut self, dibit: Dibit) -> bool {
+        self.dibits.feed(dibit).is_some()
+    }
 }
 
 /// Receives and decodes a frame group extra packet.
@@ -274,8 +553,8 @@ impl<E: Extra> ExtraReceiver<E> {
             None => return None,
         };
 
-        let bits = match hamming::shortened::decode(buf) {
-            Some((data, err)) => data,
+        let bits = match profile::inner_code(E::field()).decode(buf as u64) {
+            Some((data, err)) => data as u8,
             // Let the following RS code attempt to fix these errors.
             None => 0,
         };
@@ -290,7 +569,7 @@ impl<E: Extra> ExtraReceiver<E> {
             None => return Some(Err(ReedSolomonUnrecoverable)),
         };
 
-        Some(Ok(E::decode_extra(data)))
+        Some(E::decode_extra(data))
     }
 }
 
@@ -324,8 +603,8 @@ impl DataFragmentReceiver {
             None => return None,
         };
 
-        let bits = match cyclic::decode(buf) {
-            Some((data, err)) => data,
+        let bits = match profile::inner_code(Field::LowSpeedData).decode(buf as u64) {
+            Some((data, err)) => data as u8,
             None => return Some(Err(CyclicUnrecoverable)),
         };
 
@@ -341,3 +620,172 @@ impl DataFragmentReceiver {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use testutil::{max_errors, max_errors_in_group, voice_chunks};
+    use voice::frame::MAX_CORRECTIONS;
+
+    #[test]
+    fn test_correction_budget() {
+        let chunks = voice_chunks(0x321);
+        let clean = encode_dibits(&chunks);
+
+        // Add the most correctable errors to every chunk, in the Golay data bits.
+        let noisy = max_errors(&chunks);
+
+        let first = |recv: &mut VoiceLCFrameGroupReceiver, dibits: &[Dibit]| {
+            dibits.iter().filter_map(|&d| recv.feed(d)).next().unwrap()
+        };
+
+        let mut recv = VoiceLCFrameGroupReceiver::new();
+        match first(&mut recv, &noisy[..]) {
+            Ok(FrameGroupEvent::VoiceFrame(vf)) =>
+                assert_eq!(vf.corrections(), MAX_CORRECTIONS),
+            _ => panic!(),
+        }
+
+        let mut recv = VoiceLCFrameGroupReceiver::new();
+        recv.set_correction_budget(Some(MAX_CORRECTIONS - 1));
+        assert_eq!(first(&mut recv, &noisy[..]).err(),
+                   Some(P25Error::CorrectionBudgetExceeded));
+
+        let mut recv = VoiceLCFrameGroupReceiver::new();
+        recv.set_correction_budget(Some(0));
+        match first(&mut recv, &clean[..]) {
+            Ok(FrameGroupEvent::VoiceFrame(vf)) => assert_eq!(vf.corrections(), 0),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_correction_budget_group() {
+        let mut frames = [[0; 8]; GROUP_FRAMES];
+
+        for (i, f) in frames.iter_mut().enumerate() {
+            *f = voice_chunks(i as u16);
+        }
+
+        let lc = control::LinkControlFields::new([0x00, 0x00, 0x80, 0, 0, 1, 2, 3, 4]);
+        let mut bits = encode_group::<LinkControlExtra>(&frames, &lc, [0x12, 0x34]);
+
+        // Add the most correctable errors to every chunk of the third frame.
+        max_errors_in_group(&mut bits, 2, &frames[2]);
+
+        let mut recv = VoiceLCFrameGroupReceiver::new();
+        recv.set_correction_budget(Some(MAX_CORRECTIONS - 1));
+
+        let mut chunks = vec![];
+        let mut extra = None;
+        let mut lsd = None;
+        let mut errors = vec![];
+
+        for dibit in Dibits::new(bits.iter().cloned()) {
+            match recv.feed(dibit) {
+                Some(Ok(FrameGroupEvent::VoiceFrame(vf))) => chunks.push(vf.chunks[0]),
+                Some(Ok(FrameGroupEvent::Extra(e))) => extra = Some(e),
+                Some(Ok(FrameGroupEvent::DataFragment(d))) => lsd = Some(d),
+                Some(Err(e)) => errors.push((chunks.len(), e)),
+                None => {},
+            }
+        }
+
+        // Only the noisy frame is rejected, and the rest of the group still decodes.
+        assert_eq!(errors, vec![(2, P25Error::CorrectionBudgetExceeded)]);
+        assert_eq!(chunks, vec![0, 1, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(extra.unwrap().bytes(), lc.bytes());
+        assert_eq!(lsd, Some(0x1234));
+        assert!(recv.done());
+    }
+
+    #[test]
+    fn test_offsets() {
+        assert!(FRAME_OFFSETS.iter().all(|&off| off % 4 == 0));
+        assert_eq!(FRAME_OFFSETS[2], 164);
+        assert_eq!(FRAME_OFFSETS[7], 624);
+        assert_eq!(FRAME_OFFSETS[8], 712);
+        // Frame group data symbols end after the last voice frame.
+        assert_eq!(FRAME_OFFSETS[8] + FRAME_DIBITS, 784);
+    }
+
+    #[test]
+    fn test_ldu_frames() {
+        // Mark each voice frame byte with its frame number and everything else with 0xFF.
+        let mut bits = [0xFF; 196];
+
+        for (i, &off) in FRAME_OFFSETS.iter().enumerate() {
+            for b in bits[off / 4..off / 4 + FRAME_BYTES].iter_mut() {
+                *b = i as u8;
+            }
+        }
+
+        assert_eq!(bits.iter().filter(|&&b| b == 0xFF).count(),
+                   (EXTRA_PIECE_DIBITS * 6 + DATA_FRAG_DIBITS * 2) / 4);
+
+        let frames = ldu_frames(&bits).collect::<Vec<_>>();
+        assert_eq!(frames.len(), 9);
+
+        for (i, f) in frames.iter().enumerate() {
+            assert_eq!(f, &[i as u8; FRAME_BYTES]);
+        }
+
+        // Truncated frame group.
+        assert_eq!(ldu_frames(&bits[..195]).count(), 8);
+        assert_eq!(ldu_frames(&[]).count(), 0);
+    }
+
+    #[test]
+    fn test_decode_ldu() {
+        let mut frames = [[0; 8]; GROUP_FRAMES];
+
+        for (i, f) in frames.iter_mut().enumerate() {
+            *f = voice_chunks(i as u16);
+        }
+
+        let lc = control::LinkControlFields::new([0x00, 0x00, 0x80, 0, 0, 1, 2, 3, 4]);
+        let bits = encode_group::<LinkControlExtra>(&frames, &lc, [0x12, 0x34]);
+
+        let ldu = decode_ldu(DataUnit::VoiceLCFrameGroup, &bits).unwrap();
+        assert_eq!(ldu.lsd(), 0x1234);
+
+        match *ldu.extra() {
+            LduExtra::LinkControl(lc) => assert_eq!(lc.bytes(), &[0x00, 0x00, 0x80, 0, 0,
+                                                                  1, 2, 3, 4]),
+            _ => panic!(),
+        }
+
+        let lc_frames = ldu.frames().iter().map(|f| f.chunks).collect::<Vec<_>>();
+
+        let mut buf = [0; 12];
+        buf[..9].copy_from_slice(&[9, 8, 7, 6, 5, 4, 3, 2, 1]);
+        buf[9] = 0x84;
+        buf[10] = 0x12;
+        buf[11] = 0x34;
+        let cc = crypto::CryptoControlFields::new(buf);
+        let bits = encode_group::<CryptoControlExtra>(&frames, &cc, [0x56, 0x78]);
+
+        let ldu = decode_ldu(DataUnit::VoiceCCFrameGroup, &bits).unwrap();
+        assert_eq!(ldu.lsd(), 0x5678);
+
+        match *ldu.extra() {
+            LduExtra::CryptoControl(ref cc) => {
+                assert_eq!(cc.mi(), [9, 8, 7, 6, 5, 4, 3, 2, 1]);
+                assert_eq!(cc.key(), 0x1234);
+            },
+            _ => panic!(),
+        }
+
+        // Voice frames are decoded the same for both types.
+        assert_eq!(ldu.frames().len(), GROUP_FRAMES);
+        assert_eq!(ldu.frames().iter().map(|f| f.chunks).collect::<Vec<_>>(), lc_frames);
+
+        for (i, f) in ldu.frames().iter().enumerate() {
+            assert_eq!(f.chunks[0], i as u32);
+        }
+
+        assert_eq!(decode_ldu(DataUnit::VoiceHeader, &bits).err(), Some(UnknownNid));
+        assert_eq!(decode_ldu(DataUnit::VoiceCCFrameGroup, &bits[..10]).err(),
+                   Some(TooShort { needed: GROUP_BYTES, got: 10 }));
+    }
+}
@@ -4,12 +4,13 @@ use collect_slice::CollectSlice;
 
 use bits::{Dibit, Hexbit, HexbitBytes};
 use buffer::{Buffer, VoiceHeaderWordStorage, VoiceHeaderStorage};
-use coding::{reed_solomon, golay};
-use consts::HEADER_BYTES;
+use coding::reed_solomon;
+use coding::profile::{self, Field};
+use consts::{HEADER_BYTES, MI_BYTES, MI_HEXBITS};
 use error::Result;
 use trunking::fields::TalkGroup;
 use util::slice_u16;
-use voice::crypto::CryptoAlgorithm;
+use voice::crypto::{self, CryptoAlgorithm};
 
 use error::P25Error::*;
 
@@ -39,8 +40,8 @@ impl VoiceHeaderReceiver {
             None => return None,
         };
 
-        let data = match golay::shortened::decode(buf) {
-            Some((data, err)) => data,
+        let data = match profile::inner_code(Field::Header).decode(buf as u64) {
+            Some((data, err)) => data as u8,
             // Let the following RS code attempt to fix these errors.
             None => 0,
         };
@@ -55,9 +56,13 @@ impl VoiceHeaderReceiver {
             None => return Some(Err(ReedSolomonUnrecoverable)),
         };
 
+        let mut mi = [Hexbit::default(); MI_HEXBITS];
+        mi.copy_from_slice(&data[..MI_HEXBITS]);
+
         let mut bytes = [0; HEADER_BYTES];
-        HexbitBytes::new(data.iter().cloned())
-            .collect_slice_checked(&mut bytes[..]);
+        bytes[..MI_BYTES].copy_from_slice(&crypto::reassemble_mi(&mi));
+        HexbitBytes::new(data[MI_HEXBITS..].iter().cloned())
+            .collect_slice_checked(&mut bytes[MI_BYTES..]);
 
         Some(Ok(VoiceHeaderFields::new(bytes)))
     }
@@ -74,6 +79,9 @@ impl VoiceHeaderFields {
     /// Create a new `VoiceHeaderFields` decoder from the given bytes.
     pub fn new(buf: Buf) -> Self { VoiceHeaderFields(buf) }
 
+    /// Raw bytes of the packet.
+    pub fn bytes(&self) -> &Buf { &self.0 }
+
     /// Initialization vector for cryptographic algorithm.
     pub fn crypto_init(&self) -> &[u8] { &self.0[..9] }
     /// Manufacturer ID.
@@ -82,6 +90,9 @@ impl VoiceHeaderFields {
     pub fn crypto_alg(&self) -> CryptoAlgorithm { CryptoAlgorithm::from_bits(self.0[10]) }
     /// Encryption key to use.
     pub fn crypto_key(&self) -> u16 { slice_u16(&self.0[11..]) }
+    /// Whether the following voice is encrypted. The crypto initialization vector is
+    /// meaningless for a clear call.
+    pub fn is_encrypted(&self) -> bool { self.crypto_alg().is_encrypted() }
 
     /// Talkgroup participating in the voice message.
     pub fn talk_group(&self) -> TalkGroup {
@@ -0,0 +1,224 @@
+//! Decode the low-speed data (LSD) words carried in each frame group.
+//!
+//! Each LDU carries two 8-bit low-speed data words between its eighth and ninth voice
+//! frames, each protected by the (16, 8, 5) shortened cyclic code.
+
+use std;
+
+use coding::profile::{self, Field};
+use consts::{DATA_FRAG_DIBITS, EXTRA_PIECE_DIBITS, FRAME_DIBITS};
+use error::{P25Error, Result};
+use util::{check_len, slice_u16};
+
+/// Number of bytes in a coded low-speed data word.
+pub const LSD_BYTES: usize = DATA_FRAG_DIBITS / 4;
+
+/// Dibit offset of the first low-speed data word within the data symbols of a frame
+/// group, following the NID and with status symbols removed.
+pub const LSD_OFFSET: usize = FRAME_DIBITS * 8 + EXTRA_PIECE_DIBITS * 6;
+
+/// Try to decode the coded low-speed data word packed MSB first in the first 2 bytes of
+/// the given buffer.
+///
+/// If decoding was successful, return `Ok((data, err))`, where `data` is the 8 data bits
+/// and `err` is the number of corrected bits. Otherwise, return an error if the buffer
+/// was too short or the word was unrecoverable.
+pub fn decode(bits: &[u8]) -> Result<(u8, usize)> {
+    if let Err(e) = check_len(bits, LSD_BYTES) {
+        return Err(e);
+    }
+
+    match profile::inner_code(Field::LowSpeedData).decode(slice_u16(bits) as u64) {
+        Some((data, err)) => Ok((data as u8, err)),
+        None => Err(P25Error::CyclicUnrecoverable),
+    }
+}
+
+/// Try to decode both low-speed data words in the given frame group, packed in the same
+/// way as for `ldu_frames`.
+///
+/// If decoding was successful, return `Ok((data, err))`, where `data` holds the 2 data
+/// words in order and `err` is the total number of corrected bits.
+pub fn decode_ldu(bits: &[u8]) -> Result<([u8; 2], usize)> {
+    let start = LSD_OFFSET / 4;
+
+    if let Err(e) = check_len(bits, start + LSD_BYTES * 2) {
+        return Err(e);
+    }
+
+    let (first, ferr) = match decode(&bits[start..]) {
+        Ok(x) => x,
+        Err(e) => return Err(e),
+    };
+
+    let (second, serr) = match decode(&bits[start + LSD_BYTES..]) {
+        Ok(x) => x,
+        Err(e) => return Err(e),
+    };
+
+    Ok(([first, second], ferr + serr))
+}
+
+/// Item in a stream of low-speed data.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum LsdItem {
+    /// Successfully decoded data word.
+    Byte(u8),
+    /// Data word that couldn't be decoded, breaking the continuity of the stream.
+    Gap,
+}
+
+/// Collects the low-speed data words of consecutive LDUs into a continuous stream,
+/// marking each word that couldn't be decoded with a gap so the surrounding bytes stay
+/// in position.
+pub struct LsdStream {
+    /// Items collected so far.
+    items: Vec<LsdItem>,
+    /// Number of gaps in the collected items.
+    gaps: usize,
+}
+
+impl LsdStream {
+    /// Create a new, empty `LsdStream`.
+    pub fn new() -> LsdStream {
+        LsdStream {
+            items: vec![],
+            gaps: 0,
+        }
+    }
+
+    /// Decode and add both low-speed data words of the given frame group, packed in the
+    /// same way as for `ldu_frames`. Each word that fails to decode adds a gap.
+    pub fn feed_ldu(&mut self, bits: &[u8]) {
+        let start = LSD_OFFSET / 4;
+
+        for idx in 0..2 {
+            let word = bits.get(start + LSD_BYTES * idx..).unwrap_or(&[]);
+
+            match decode(word) {
+                Ok((data, _)) => self.items.push(LsdItem::Byte(data)),
+                Err(_) => self.push_gap(),
+            }
+        }
+    }
+
+    /// Add the given decoded low-speed data words.
+    pub fn push(&mut self, words: [u8; 2]) {
+        self.items.push(LsdItem::Byte(words[0]));
+        self.items.push(LsdItem::Byte(words[1]));
+    }
+
+    /// Add a gap for a single data word that couldn't be decoded.
+    pub fn push_gap(&mut self) {
+        self.items.push(LsdItem::Gap);
+        self.gaps += 1;
+    }
+
+    /// Items collected so far, in order received.
+    pub fn items(&self) -> &[LsdItem] { &self.items[..] }
+
+    /// Number of gaps in the collected items.
+    pub fn gaps(&self) -> usize { self.gaps }
+
+    /// Whether every collected word was decoded, so the stream has no discontinuities.
+    pub fn is_continuous(&self) -> bool { self.gaps == 0 }
+
+    /// Get the collected bytes if the stream is continuous.
+    pub fn bytes(&self) -> Option<Vec<u8>> {
+        if !self.is_continuous() {
+            return None;
+        }
+
+        Some(self.items.iter().map(|item| match *item {
+            LsdItem::Byte(b) => b,
+            LsdItem::Gap => unreachable!(),
+        }).collect())
+    }
+
+    /// Remove and return all collected items, resetting the stream.
+    pub fn take(&mut self) -> Vec<LsdItem> {
+        self.gaps = 0;
+        std::mem::replace(&mut self.items, vec![])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use coding::cyclic;
+    use voice::frame_group::FRAME_OFFSETS;
+
+    #[test]
+    fn test_decode() {
+        // Known (16, 8, 5) codeword for 0xA5.
+        assert_eq!(cyclic::encode(0xA5), 0xA51C);
+
+        assert_eq!(decode(&[0xA5, 0x1C]), Ok((0xA5, 0)));
+        assert_eq!(decode(&[0xA5, 0x1C, 0xFF]), Ok((0xA5, 0)));
+        assert_eq!(decode(&[0xA4, 0x1C]), Ok((0xA5, 1)));
+        assert_eq!(decode(&[0x25, 0x1D]), Ok((0xA5, 2)));
+        assert_eq!(decode(&[0xA5]), Err(P25Error::TooShort { needed: 2, got: 1 }));
+        assert_eq!(decode(&[]), Err(P25Error::TooShort { needed: 2, got: 0 }));
+    }
+
+    #[test]
+    fn test_decode_ldu() {
+        assert_eq!(LSD_OFFSET, 696);
+        assert_eq!(LSD_OFFSET + DATA_FRAG_DIBITS * 2, FRAME_OFFSETS[8]);
+
+        let mut bits = [0; 196];
+        bits[174..176].copy_from_slice(&cyclic::encode_bytes(0x12));
+        bits[176..178].copy_from_slice(&cyclic::encode_bytes(0xEF));
+
+        assert_eq!(decode_ldu(&bits), Ok(([0x12, 0xEF], 0)));
+
+        bits[174] ^= 0b1000;
+        bits[177] ^= 0b1;
+        assert_eq!(decode_ldu(&bits), Ok(([0x12, 0xEF], 2)));
+
+        assert_eq!(decode_ldu(&bits[..177]),
+                   Err(P25Error::TooShort { needed: 178, got: 177 }));
+    }
+
+    #[test]
+    fn test_stream() {
+        let ldu = |a, b| {
+            let mut bits = [0; 196];
+            bits[174..176].copy_from_slice(&cyclic::encode_bytes(a));
+            bits[176..178].copy_from_slice(&cyclic::encode_bytes(b));
+            bits
+        };
+
+        let mut s = LsdStream::new();
+
+        s.feed_ldu(&ldu(0x12, 0x34));
+        s.push([0x56, 0x78]);
+        assert!(s.is_continuous());
+        assert_eq!(s.bytes(), Some(vec![0x12, 0x34, 0x56, 0x78]));
+
+        // Second word of the next LDU is unrecoverable.
+        let mut bad = ldu(0x9A, 0xBC);
+        bad[177] ^= 0b1011;
+        assert!(decode_ldu(&bad).is_err());
+
+        s.feed_ldu(&bad);
+        s.feed_ldu(&ldu(0xDE, 0xF0));
+
+        assert!(!s.is_continuous());
+        assert_eq!(s.gaps(), 1);
+        assert_eq!(s.bytes(), None);
+        assert_eq!(s.items(), &[
+            LsdItem::Byte(0x12), LsdItem::Byte(0x34), LsdItem::Byte(0x56),
+            LsdItem::Byte(0x78), LsdItem::Byte(0x9A), LsdItem::Gap,
+            LsdItem::Byte(0xDE), LsdItem::Byte(0xF0),
+        ]);
+
+        // Truncated frame group loses both words.
+        s.feed_ldu(&bad[..175]);
+        assert_eq!(s.gaps(), 3);
+
+        assert_eq!(s.take().len(), 10);
+        assert!(s.is_continuous());
+        assert_eq!(s.bytes(), Some(vec![]));
+    }
+}
@@ -6,5 +6,15 @@ pub mod descramble;
 pub mod frame;
 pub mod frame_group;
 pub mod header;
+pub mod lsd;
 pub mod rand;
+pub mod superframe;
 pub mod term;
+pub mod vocoder;
+
+pub use self::descramble::{interleave, deinterleave};
+pub use self::frame::{encode_frame, to_imbe_frame};
+pub use self::frame_group::{decode_ldu, ldu_frames, Ldu, LduExtra};
+pub use self::lsd::LsdStream;
+pub use self::superframe::Superframe;
+pub use self::vocoder::Vocoder;
@@ -34,7 +34,7 @@ impl PseudoRand {
     }
 
     /// Step the generator and retrieve the next random bit.
-    fn advance(&mut self) -> u16 {
+    pub fn advance(&mut self) -> u16 {
         self.state = self.next_state();
         self.next_bit()
     }
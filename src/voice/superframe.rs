@@ -0,0 +1,333 @@
+//! Assemble LDU1 and LDU2 frame groups into voice superframes.
+//!
+//! A superframe is an LDU1 followed by an LDU2, together carrying 18 voice frames along
+//! with the link control word from the LDU1 and the crypto control word from the LDU2.
+
+use collect_slice::CollectSlice;
+
+use baseband::sync::SYNC_GENERATOR;
+use bits::{DibitBytes, Dibits};
+use consts::{NID_DIBITS, SYNC_SYMBOLS};
+use message::nid::{DataUnit, NetworkAccessCode, NetworkId};
+use message::status::{StatusCode, StatusInterleaver};
+use voice::control::LinkControlFields;
+use voice::crypto::CryptoControlFields;
+use voice::frame::VoiceFrame;
+use voice::frame_group::{self, CryptoControlExtra, LinkControlExtra, GROUP_BYTES,
+                         GROUP_FRAMES};
+
+/// Number of bytes in a transmitted frame group, including the frame sync, NID, and
+/// status symbols.
+pub const LDU_BYTES: usize = (SYNC_SYMBOLS + NID_DIBITS + GROUP_BYTES * 4) * 36 / 35 / 4;
+
+/// Accumulates the contents of an LDU1/LDU2 pair.
+pub struct Superframe {
+    /// Voice frames received so far.
+    frames: Vec<VoiceFrame>,
+    /// Link control word from the LDU1.
+    lc: Option<LinkControlFields>,
+    /// Crypto control word from the LDU2.
+    cc: Option<CryptoControlFields>,
+}
+
+impl Superframe {
+    /// Create a new, empty `Superframe`.
+    pub fn new() -> Superframe {
+        Superframe {
+            frames: Vec::with_capacity(GROUP_FRAMES * 2),
+            lc: None,
+            cc: None,
+        }
+    }
+
+    /// Start a new superframe with the voice frames and link control word of an LDU1,
+    /// discarding any previous contents. Return `false` if the LDU1 didn't have a full
+    /// set of voice frames, in which case the superframe is left empty.
+    pub fn push_ldu1(&mut self, frames: Vec<VoiceFrame>, lc: LinkControlFields) -> bool {
+        self.clear();
+
+        if frames.len() != GROUP_FRAMES {
+            return false;
+        }
+
+        self.frames = frames;
+        self.lc = Some(lc);
+
+        true
+    }
+
+    /// Complete the superframe with the voice frames and crypto control word of an LDU2.
+    /// Return `true` if the superframe is now complete, and `false` if the LDU2 didn't
+    /// directly follow an LDU1 or didn't have a full set of voice frames, in which case
+    /// the superframe is discarded.
+    pub fn push_ldu2(&mut self, frames: Vec<VoiceFrame>, cc: CryptoControlFields)
+        -> bool
+    {
+        if !self.pending() || frames.len() != GROUP_FRAMES {
+            self.clear();
+            return false;
+        }
+
+        self.frames.extend(frames);
+        self.cc = Some(cc);
+
+        true
+    }
+
+    /// Whether both frame groups have been received.
+    pub fn complete(&self) -> bool { self.lc.is_some() && self.cc.is_some() }
+
+    /// Retrieve the 18 voice frames, if the superframe is complete.
+    pub fn frames(&self) -> Option<&[VoiceFrame]> {
+        if self.complete() { Some(&self.frames[..]) } else { None }
+    }
+
+    /// Retrieve the link control word, if the superframe is complete.
+    pub fn link_control(&self) -> Option<&LinkControlFields> {
+        if self.complete() { self.lc.as_ref() } else { None }
+    }
+
+    /// Retrieve the crypto control word, if the superframe is complete.
+    pub fn crypto_control(&self) -> Option<&CryptoControlFields> {
+        if self.complete() { self.cc.as_ref() } else { None }
+    }
+
+    /// Encode the superframe, if complete, into a transmittable LDU1 and LDU2 with the
+    /// given NAC, status symbol, and low-speed data words, where the first two words are
+    /// carried in the LDU1 and the last two in the LDU2. Each frame group is packed 4
+    /// dibits per byte, MSB first, starting with the frame sync.
+    pub fn encode(&self, nac: NetworkAccessCode, status: StatusCode, lsd: [u8; 4])
+        -> Option<([u8; LDU_BYTES], [u8; LDU_BYTES])>
+    {
+        let (lc, cc) = match (self.link_control(), self.crypto_control()) {
+            (Some(lc), Some(cc)) => (lc, cc),
+            _ => return None,
+        };
+
+        let mut chunks = [[0; 8]; GROUP_FRAMES * 2];
+
+        for (dest, frame) in chunks.iter_mut().zip(self.frames.iter()) {
+            for (d, &c) in dest.iter_mut().zip(frame.chunks.iter()) {
+                *d = c as u16;
+            }
+        }
+
+        let (first, second) = chunks.split_at(GROUP_FRAMES);
+
+        let mut frames = [[0; 8]; GROUP_FRAMES];
+        frames.copy_from_slice(first);
+        let ldu1 = frame_group::encode_group::<LinkControlExtra>(&frames, lc,
+                                                                 [lsd[0], lsd[1]]);
+
+        frames.copy_from_slice(second);
+        let ldu2 = frame_group::encode_group::<CryptoControlExtra>(&frames, cc,
+                                                                  [lsd[2], lsd[3]]);
+
+        Some((
+            transmit(NetworkId::new(nac, DataUnit::VoiceLCFrameGroup), &ldu1, status),
+            transmit(NetworkId::new(nac, DataUnit::VoiceCCFrameGroup), &ldu2, status),
+        ))
+    }
+
+    /// Whether an LDU1 has been received and is waiting for its LDU2.
+    fn pending(&self) -> bool { self.lc.is_some() && self.cc.is_none() }
+
+    /// Discard all contents.
+    fn clear(&mut self) {
+        self.frames.clear();
+        self.lc = None;
+        self.cc = None;
+    }
+}
+
+/// Prefix the given frame group data symbols with the frame sync and given NID, and
+/// interleave the given status symbol throughout.
+fn transmit(nid: NetworkId, group: &[u8; GROUP_BYTES], status: StatusCode)
+    -> [u8; LDU_BYTES]
+{
+    let nid = nid.encode();
+
+    let dibits = Dibits::new(SYNC_GENERATOR.iter()
+        .chain(nid.iter())
+        .chain(group.iter())
+        .cloned());
+
+    let mut buf = [0; LDU_BYTES];
+    DibitBytes::new(StatusInterleaver::new(dibits, status))
+        .collect_slice_checked(&mut buf[..]);
+
+    buf
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bits::Dibit;
+    use message::nid;
+    use message::status::{StatusDeinterleaver, StreamSymbol};
+    use voice::control::LinkControlOpcode;
+    use voice::crypto::CryptoAlgorithm;
+    use voice::frame_group::{Extra, FrameGroupEvent, FrameGroupReceiver};
+
+    fn frames(start: u32) -> Vec<VoiceFrame> {
+        (start..start + 9).map(|i| VoiceFrame {
+            chunks: [i; 8],
+            errors: [0; 7],
+        }).collect()
+    }
+
+    fn lc() -> LinkControlFields {
+        LinkControlFields::new([0, 0, 0, 0, 0, 0, 0, 0, 0])
+    }
+
+    fn cc() -> CryptoControlFields {
+        CryptoControlFields::new([1, 2, 3, 4, 5, 6, 7, 8, 9, 0x80, 0, 0])
+    }
+
+    #[test]
+    fn test_superframe() {
+        let mut sf = Superframe::new();
+
+        assert!(!sf.complete());
+        assert!(sf.push_ldu1(frames(0), lc()));
+        assert!(!sf.complete());
+        assert!(sf.frames().is_none());
+        assert!(sf.link_control().is_none());
+
+        assert!(sf.push_ldu2(frames(9), cc()));
+        assert!(sf.complete());
+
+        let f = sf.frames().unwrap();
+        assert_eq!(f.len(), 18);
+
+        for (i, frame) in f.iter().enumerate() {
+            assert_eq!(frame.chunks[0], i as u32);
+        }
+
+        assert_eq!(sf.link_control().unwrap().opcode(),
+                   Some(LinkControlOpcode::GroupVoiceTraffic));
+        assert_eq!(sf.crypto_control().unwrap().alg(), CryptoAlgorithm::Unencrypted);
+    }
+
+    #[test]
+    fn test_missing() {
+        let mut sf = Superframe::new();
+
+        // LDU2 without an LDU1.
+        assert!(!sf.push_ldu2(frames(9), cc()));
+        assert!(!sf.complete());
+
+        // Repeated LDU2 after a complete superframe.
+        assert!(sf.push_ldu1(frames(0), lc()));
+        assert!(sf.push_ldu2(frames(9), cc()));
+        assert!(!sf.push_ldu2(frames(9), cc()));
+        assert!(!sf.complete());
+        assert!(sf.frames().is_none());
+
+        // Missing LDU2 restarts the superframe.
+        assert!(sf.push_ldu1(frames(0), lc()));
+        assert!(sf.push_ldu1(frames(18), lc()));
+        assert!(sf.push_ldu2(frames(27), cc()));
+        assert_eq!(sf.frames().unwrap()[0].chunks[0], 18);
+
+        // Short frame groups.
+        let mut short = frames(0);
+        short.truncate(8);
+        assert!(!sf.push_ldu1(short, lc()));
+        assert!(!sf.push_ldu2(frames(9), cc()));
+
+        assert!(sf.push_ldu1(frames(0), lc()));
+        let mut short = frames(9);
+        short.pop();
+        assert!(!sf.push_ldu2(short, cc()));
+        assert!(!sf.complete());
+    }
+
+    /// Check the given transmitted frame group and return its NID, voice frames, extra
+    /// packet, and low-speed data.
+    fn receive<E: Extra>(buf: &[u8; LDU_BYTES])
+        -> (NetworkId, Vec<VoiceFrame>, Option<E::Fields>, u32)
+    {
+        let dibits = Dibits::new(buf.iter().cloned()).collect::<Vec<Dibit>>();
+        assert_eq!(dibits.len(), 864);
+
+        assert_eq!(&buf[..6], SYNC_GENERATOR);
+
+        let mut status = StatusDeinterleaver::new();
+        let data = dibits[SYNC_SYMBOLS..].iter().filter_map(|&d| match status.feed(d) {
+            StreamSymbol::Data(d) => Some(d),
+            StreamSymbol::Status(s) => {
+                assert_eq!(s, StatusCode::SubscriberRepeater);
+                None
+            },
+        }).collect::<Vec<Dibit>>();
+
+        assert_eq!(data.len(), NID_DIBITS + GROUP_BYTES * 4);
+
+        let word = data[..NID_DIBITS].iter().fold(0, |w, d| w << 2 | d.bits() as u64);
+        let (nid, err) = nid::decode_word_strict(word).unwrap();
+        assert_eq!(err, 0);
+
+        let mut recv = FrameGroupReceiver::<E>::new();
+        let mut frames = vec![];
+        let mut extra = None;
+        let mut lsd = 0;
+
+        for &d in data[NID_DIBITS..].iter() {
+            match recv.feed(d) {
+                Some(Ok(FrameGroupEvent::VoiceFrame(vf))) => frames.push(vf),
+                Some(Ok(FrameGroupEvent::Extra(e))) => extra = Some(e),
+                Some(Ok(FrameGroupEvent::DataFragment(f))) => lsd = f,
+                Some(Err(e)) => panic!("{:?}", e),
+                None => {},
+            }
+        }
+
+        assert!(recv.done());
+
+        (nid, frames, extra, lsd)
+    }
+
+    #[test]
+    fn test_encode() {
+        let mut sf = Superframe::new();
+        assert!(sf.encode(NetworkAccessCode::Default, StatusCode::SubscriberRepeater,
+                          [0; 4]).is_none());
+
+        let lc = LinkControlFields::new([0x00, 0x00, 0x00, 0x00, 0x12, 0x34,
+                                         0x56, 0x78, 0x9A]);
+        let cc = CryptoControlFields::new([1, 2, 3, 4, 5, 6, 7, 8, 9, 0x84, 0xBE, 0xEF]);
+
+        assert!(sf.push_ldu1(frames(0), lc));
+        assert!(sf.push_ldu2(frames(100), cc));
+
+        let (ldu1, ldu2) = sf.encode(NetworkAccessCode::Other(0x3A1),
+                                     StatusCode::SubscriberRepeater,
+                                     [0xDE, 0xAD, 0xBE, 0xEF]).unwrap();
+
+        let (nid, f, lc, lsd) = receive::<LinkControlExtra>(&ldu1);
+        assert_eq!(nid.access_code, NetworkAccessCode::Other(0x3A1));
+        assert_eq!(nid.data_unit, DataUnit::VoiceLCFrameGroup);
+        assert_eq!(f.len(), 9);
+
+        for (i, frame) in f.iter().enumerate() {
+            assert_eq!(frame.chunks, [i as u32; 8]);
+        }
+
+        assert_eq!(lc.unwrap().bytes(), sf.link_control().unwrap().bytes());
+        assert_eq!(lsd, 0xDEAD);
+
+        let (nid, f, cc, lsd) = receive::<CryptoControlExtra>(&ldu2);
+        assert_eq!(nid.data_unit, DataUnit::VoiceCCFrameGroup);
+        assert_eq!(f.len(), 9);
+
+        for (i, frame) in f.iter().enumerate() {
+            assert_eq!(frame.chunks, [100 + i as u32; 8]);
+        }
+
+        let cc = cc.unwrap();
+        assert_eq!(cc.bytes(), sf.crypto_control().unwrap().bytes());
+        assert_eq!(cc.key(), 0xBEEF);
+        assert_eq!(lsd, 0xBEEF);
+    }
+}
@@ -4,7 +4,8 @@ use collect_slice::CollectSlice;
 
 use bits::{Dibit, Hexbit, HexbitBytes};
 use buffer::{Buffer, VoiceLCTermWordStorage, VoiceExtraStorage};
-use coding::{reed_solomon, golay};
+use coding::reed_solomon;
+use coding::profile::{self, Field};
 use consts::LINK_CONTROL_BYTES;
 use error::Result;
 use voice::control::LinkControlFields;
@@ -37,8 +38,10 @@ impl VoiceLCTerminatorReceiver {
             None => return None,
         };
 
-        let data = match golay::extended::decode(*buf as u32) {
-            Some((data, err)) => data,
+        let code = profile::inner_code(Field::TerminatorLinkControl);
+
+        let data = match code.decode(*buf as u64) {
+            Some((data, err)) => data as u16,
             // Let the following RS code attempt to correct these errors.
             None => 0,
         };
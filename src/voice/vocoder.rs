@@ -0,0 +1,96 @@
+//! Hand recovered voice frames to an external vocoder.
+//!
+//! No vocoder is included in this crate: applications implement `Vocoder` over the IMBE
+//! decoder of their choice, and `VocoderSink` feeds it each voice frame produced by the
+//! message receiver.
+
+use consts::FRAME_DIBITS;
+use message::receiver::MessageEvent;
+use voice::frame::VoiceFrame;
+
+/// Number of bytes in an IMBE frame.
+pub const IMBE_BYTES: usize = FRAME_DIBITS / 4;
+
+/// Decodes IMBE frames into audio samples.
+pub trait Vocoder {
+    /// Decode the given 144-bit IMBE frame, as given by `VoiceFrame::imbe_frame`, into
+    /// audio samples.
+    fn decode_frame(&mut self, imbe: &[u8; IMBE_BYTES]) -> Vec<i16>;
+}
+
+/// Feeds the voice frames in a stream of message events to a vocoder.
+pub struct VocoderSink<V: Vocoder> {
+    /// Wrapped vocoder.
+    vocoder: V,
+}
+
+impl<V: Vocoder> VocoderSink<V> {
+    /// Create a new `VocoderSink` feeding the given vocoder.
+    pub fn new(vocoder: V) -> VocoderSink<V> {
+        VocoderSink {
+            vocoder: vocoder,
+        }
+    }
+
+    /// Decode the given voice frame, returning its audio samples.
+    pub fn feed_frame(&mut self, vf: &VoiceFrame) -> Vec<i16> {
+        self.vocoder.decode_frame(&vf.imbe_frame())
+    }
+
+    /// Decode the given event if it's a voice frame. Return `Some(samples)` for a voice
+    /// frame and `None` for any other event.
+    pub fn feed(&mut self, event: &MessageEvent) -> Option<Vec<i16>> {
+        match *event {
+            MessageEvent::VoiceFrame(ref vf) => Some(self.feed_frame(vf)),
+            _ => None,
+        }
+    }
+
+    /// Get the wrapped vocoder.
+    pub fn vocoder(&mut self) -> &mut V { &mut self.vocoder }
+
+    /// Consume the sink and return the wrapped vocoder.
+    pub fn into_inner(self) -> V { self.vocoder }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use testutil::voice_chunks;
+    use voice::frame::{encode_dibits, to_imbe_frame};
+    use voice::control::LinkControlFields;
+
+    /// Vocoder that records each frame and outputs its first byte as a sample.
+    struct Recorder(Vec<[u8; IMBE_BYTES]>);
+
+    impl Vocoder for Recorder {
+        fn decode_frame(&mut self, imbe: &[u8; IMBE_BYTES]) -> Vec<i16> {
+            self.0.push(*imbe);
+            vec![imbe[0] as i16; 160]
+        }
+    }
+
+    #[test]
+    fn test_vocoder_sink() {
+        let a = voice_chunks(0x321);
+        let b = [0x123, 0xDEF, 0x00F, 0xAAA, 0x2BA, 0x000, 0x7FF, 0x24];
+
+        let mut sink = VocoderSink::new(Recorder(vec![]));
+
+        let events = [
+            MessageEvent::VoiceFrame(VoiceFrame::new(&encode_dibits(&a)).unwrap()),
+            MessageEvent::LinkControl(LinkControlFields::new([0; 9])),
+            MessageEvent::VoiceFrame(VoiceFrame::new(&encode_dibits(&b)).unwrap()),
+        ];
+
+        let audio = events.iter().filter_map(|e| sink.feed(e)).collect::<Vec<_>>();
+
+        assert_eq!(audio.len(), 2);
+        assert_eq!(audio[0], vec![to_imbe_frame(&a)[0] as i16; 160]);
+
+        let frames = sink.into_inner().0;
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0], to_imbe_frame(&a));
+        assert_eq!(frames[1], to_imbe_frame(&b));
+    }
+}